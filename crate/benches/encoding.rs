@@ -0,0 +1,90 @@
+//! Encoder benchmarks
+//!
+//! Run with `cargo bench -p shine-rs`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+const FRAME_SAMPLES: usize = 1152 * 2;
+
+/// One second of interleaved stereo audio at 44.1 kHz
+fn stereo_signal() -> Vec<i16> {
+    (0..44100)
+        .flat_map(|i| {
+            let t = i as f64 / 44100.0;
+            let left = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 16000.0) as i16;
+            let right = ((2.0 * std::f64::consts::PI * 660.0 * t).sin() * 16000.0) as i16;
+            [left, right]
+        })
+        .collect()
+}
+
+/// Compare the cost of taking one frame off the front of a large input buffer
+fn bench_frame_buffering(c: &mut Criterion) {
+    let signal = stereo_signal();
+    let mut group = c.benchmark_group("frame_buffering");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("drain_collect", |b| {
+        b.iter_batched_ref(
+            || VecDeque::from(signal.clone()),
+            |buffer| {
+                let frame: Vec<i16> = buffer.drain(..FRAME_SAMPLES).collect();
+                black_box(frame);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("ring_slices", |b| {
+        let mut scratch = Vec::with_capacity(FRAME_SAMPLES);
+        b.iter_batched_ref(
+            || VecDeque::from(signal.clone()),
+            |buffer| {
+                let (front, back) = buffer.as_slices();
+                let frame: &[i16] = if front.len() >= FRAME_SAMPLES {
+                    &front[..FRAME_SAMPLES]
+                } else {
+                    scratch.clear();
+                    scratch.extend_from_slice(front);
+                    scratch.extend_from_slice(&back[..FRAME_SAMPLES - front.len()]);
+                    &scratch
+                };
+                black_box(frame);
+                buffer.drain(..FRAME_SAMPLES);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+/// Streaming encode of one second of audio fed in small chunks
+fn bench_streaming_encode(c: &mut Criterion) {
+    let signal = stereo_signal();
+    let mut group = c.benchmark_group("streaming_encode");
+    group.throughput(Throughput::Elements((signal.len() / FRAME_SAMPLES) as u64));
+    group.sample_size(20);
+
+    group.bench_function("chunks_of_1000", |b| {
+        b.iter(|| {
+            let config = Mp3EncoderConfig::new()
+                .sample_rate(44100)
+                .bitrate(128)
+                .channels(2);
+            let mut encoder = Mp3Encoder::new(config).unwrap();
+            for chunk in signal.chunks(1000) {
+                black_box(encoder.encode_interleaved(chunk).unwrap());
+            }
+            black_box(encoder.finish().unwrap())
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_buffering, bench_streaming_encode);
+criterion_main!(benches);
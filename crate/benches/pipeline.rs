@@ -0,0 +1,214 @@
+//! Encoding pipeline benchmarks
+//!
+//! Encodes a fixed 10 second stereo tone at 128 kbps / 44.1 kHz and times
+//! the whole pipeline, the same length of digital silence (encoded, and
+//! replayed with `encode_silence_frames`), white noise at each `Effort`,
+//! and each stage on its own: subband analysis
+//! (one time slot per call, and a granule per call as the encoder runs it),
+//! MDCT (`shine_mdct_sub`, which includes the subband filter), quantization
+//! and bitstream formatting. Throughput is reported in samples per channel,
+//! so the realtime factor is the reported rate divided by 44100.
+//!
+//! These numbers are the baseline the reference C shine encoder is compared
+//! against; run it on the same tone and compare the realtime factors.
+//!
+//! Run with `cargo bench -p shine-rs --bench pipeline`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use shine_rs::bitstream::format_bitstream;
+use shine_rs::encoder::{shine_encode_buffer_interleaved_i32, shine_initialise};
+use shine_rs::mdct::shine_mdct_sub;
+use shine_rs::mp3_encoder::{Effort, Mp3Encoder, Mp3EncoderConfig};
+use shine_rs::quantization::shine_iteration_loop;
+use shine_rs::subband::{filter_granule_i32, shine_window_filter_subband_i32};
+use shine_rs::types::{ShineGlobalConfig, SBLIMIT};
+use std::hint::black_box;
+use std::time::Instant;
+
+const SAMPLE_RATE: usize = 44100;
+const SECONDS: usize = 10;
+const FRAME_SAMPLES: usize = 1152;
+
+/// Ten seconds of interleaved stereo audio
+fn stereo_tone() -> Vec<i16> {
+    (0..SAMPLE_RATE * SECONDS)
+        .flat_map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let left = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 16000.0) as i16;
+            let right = ((2.0 * std::f64::consts::PI * 660.0 * t).sin() * 16000.0) as i16;
+            [left, right]
+        })
+        .collect()
+}
+
+/// Ten seconds of interleaved stereo white noise, which keeps the step size
+/// search busy in every granule
+fn stereo_noise() -> Vec<i16> {
+    let mut seed = 12345u32;
+    (0..SAMPLE_RATE * SECONDS * 2)
+        .map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 16) as i16 / 2
+        })
+        .collect()
+}
+
+fn encode_all(pcm: &[i16]) -> usize {
+    encode_all_with(Mp3EncoderConfig::new(), pcm)
+}
+
+fn encode_all_with(config: Mp3EncoderConfig, pcm: &[i16]) -> usize {
+    let mut encoder = Mp3Encoder::new(config).unwrap();
+    let mut bytes = 0;
+    for frame in encoder.encode_interleaved(pcm).unwrap() {
+        bytes += frame.len();
+    }
+    bytes + encoder.finish().unwrap().len()
+}
+
+/// Encoder state right after encoding one frame of the tone
+fn primed_encoder(frame: &[i32]) -> Box<ShineGlobalConfig> {
+    let mut config = shine_initialise(&Mp3EncoderConfig::new().to_shine_config()).unwrap();
+    shine_encode_buffer_interleaved_i32(&mut config, frame).unwrap();
+    config
+}
+
+/// Point the encoder's channel buffers at an interleaved frame
+fn set_buffers(config: &mut ShineGlobalConfig, frame: &[i32]) {
+    for ch in 0..2 {
+        config.buffer[ch] = frame[ch..].as_ptr();
+        config.buffer_len[ch] = frame.len() - ch;
+    }
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let pcm = stereo_tone();
+
+    let start = Instant::now();
+    black_box(encode_all(&pcm));
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "pipeline: {} s of audio in {:.3} s ({:.1}x realtime)",
+        SECONDS,
+        elapsed,
+        SECONDS as f64 / elapsed
+    );
+
+    let mut group = c.benchmark_group("pipeline");
+    group.throughput(Throughput::Elements((SAMPLE_RATE * SECONDS) as u64));
+    group.sample_size(10);
+    group.bench_function("encode_10s_stereo_128k", |b| {
+        b.iter(|| black_box(encode_all(&pcm)))
+    });
+    let silence = vec![0i16; pcm.len()];
+    group.bench_function("encode_10s_silence", |b| {
+        b.iter(|| black_box(encode_all(&silence)))
+    });
+    group.bench_function("replay_10s_silence", |b| {
+        let frames = silence.len() / FRAME_SAMPLES / 2;
+        b.iter(|| {
+            let mut encoder = Mp3Encoder::new(Mp3EncoderConfig::new()).unwrap();
+            let mut output = Vec::new();
+            encoder.encode_silence_frames(frames, &mut output).unwrap();
+            black_box(output.len() + encoder.finish().unwrap().len())
+        })
+    });
+    group.finish();
+}
+
+fn bench_stages(c: &mut Criterion) {
+    let pcm = stereo_tone();
+    let frame: Vec<i32> = pcm[FRAME_SAMPLES * 20..FRAME_SAMPLES * 22]
+        .iter()
+        .map(|&s| (s as i32) << 16)
+        .collect();
+
+    let mut group = c.benchmark_group("stages");
+    group.throughput(Throughput::Elements(FRAME_SAMPLES as u64));
+
+    group.bench_function("subband", |b| {
+        let mut config = primed_encoder(&frame);
+        let mut s = [0i32; SBLIMIT];
+        b.iter(|| {
+            for ch in 0..2 {
+                let mut buffer = &frame[ch..];
+                for _ in 0..FRAME_SAMPLES / 32 {
+                    shine_window_filter_subband_i32(
+                        &mut buffer,
+                        &mut s,
+                        ch,
+                        &mut config.subband,
+                        2,
+                    );
+                }
+            }
+            black_box(&s);
+        })
+    });
+
+    group.bench_function("subband_granule", |b| {
+        let mut config = primed_encoder(&frame);
+        b.iter(|| {
+            for ch in 0..2 {
+                for granule in frame.chunks_exact(2 * 576) {
+                    black_box(filter_granule_i32(
+                        &granule[ch..],
+                        2,
+                        ch,
+                        &mut config.subband,
+                    ));
+                }
+            }
+        })
+    });
+
+    group.bench_function("shine_mdct_sub", |b| {
+        let mut config = primed_encoder(&frame);
+        b.iter(|| {
+            set_buffers(&mut config, &frame);
+            shine_mdct_sub(&mut config, 2);
+            black_box(&config.mdct_freq);
+        })
+    });
+
+    group.bench_function("quantization", |b| {
+        let mut config = primed_encoder(&frame);
+        b.iter(|| {
+            shine_iteration_loop(&mut config);
+            black_box(&config.l3_enc);
+        })
+    });
+
+    group.bench_function("bitstream", |b| {
+        let mut config = primed_encoder(&frame);
+        b.iter(|| {
+            config.bs.data_position = 0;
+            format_bitstream(&mut config).unwrap();
+            black_box(config.bs.data_position);
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_effort(c: &mut Criterion) {
+    let noise = stereo_noise();
+
+    let mut group = c.benchmark_group("effort");
+    group.throughput(Throughput::Elements((SAMPLE_RATE * SECONDS) as u64));
+    group.sample_size(10);
+    for (name, effort) in [
+        ("fast", Effort::Fast),
+        ("standard", Effort::Standard),
+        ("thorough", Effort::Thorough),
+    ] {
+        let config = Mp3EncoderConfig::new().effort(effort);
+        group.bench_function(format!("encode_10s_noise_{name}"), |b| {
+            b.iter(|| black_box(encode_all_with(config.clone(), &noise)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline, bench_stages, bench_effort);
+criterion_main!(benches);
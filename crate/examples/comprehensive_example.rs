@@ -3,7 +3,6 @@
 //! This example demonstrates how to use the high-level Mp3Encoder API
 //! to convert PCM audio data to MP3 format.
 
-use hound;
 use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3Encoder, Mp3EncoderConfig, StereoMode};
 use std::fs::File;
 use std::io::Write;
@@ -111,7 +111,7 @@ fn test_channel_configuration_errors() {
         Err(EncoderError::Config(ConfigError::InvalidStereoMode { mode, channels })) => {
             println!("✓ 正确捕获声道配置错误:");
             println!("  声道数: {}", channels);
-            println!("  立体声模式: {}", mode);
+            println!("  立体声模式: {:?}", mode);
         }
         other => {
             println!("✗ 意外的结果: {:?}", other);
@@ -139,8 +139,13 @@ fn test_input_data_errors() {
     let right_channel = vec![200i16; 500]; // Different length
 
     match encoder.encode_separate_channels(&left_channel, Some(&right_channel)) {
-        Err(EncoderError::InputData(InputDataError::InvalidChannelCount { expected, actual })) => {
+        Err(EncoderError::InputData(InputDataError::InvalidChannelLength {
+            channel,
+            expected,
+            actual,
+        })) => {
             println!("✓ 正确捕获声道数据长度不匹配错误:");
+            println!("  声道: {}", channel);
             println!("  期望长度: {}", expected);
             println!("  实际长度: {}", actual);
         }
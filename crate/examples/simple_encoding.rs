@@ -5,7 +5,6 @@
 //!
 //! Usage: cargo run --example simple_encoding <input.wav> <output.mp3>
 
-use hound;
 use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3EncoderConfig, StereoMode};
 use std::env;
 use std::fs::File;
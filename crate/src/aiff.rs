@@ -0,0 +1,323 @@
+//! AIFF and AIFF-C input for the encoder
+//!
+//! Reads the format of an AIFF or AIFF-C stream from any `std::io::Read`
+//! source and hands out the interleaved 16-bit samples in chunks, with the
+//! same interface as `WavSource`.
+//!
+//! The IFF chunks are walked by id and size; chunks other than `COMM` and
+//! `SSND` are skipped, including the pad byte after an odd-sized chunk. When
+//! `SSND` comes before `COMM` the samples are buffered until the format is
+//! known. Chunks after `SSND` are never read.
+//!
+//! AIFF samples are big-endian. AIFF-C adds a compression type to `COMM`:
+//! `NONE` is big-endian PCM as in AIFF and `sowt` is little-endian PCM;
+//! other (compressed) types are rejected.
+
+use crate::bytes::{read_i16_be, read_u16_be, read_u32_be, read_u64_be};
+use crate::error::{InputDataError, InputResult};
+use crate::pcm::{bytes_to_i16_be, bytes_to_i16_le};
+use std::io::{self, Read};
+
+/// Bytes per sample of 16-bit PCM
+const SAMPLE_BYTES: u64 = 2;
+
+/// Streaming reader over the samples of a 16-bit AIFF or AIFF-C stream
+pub struct AiffSource<R: Read> {
+    reader: R,
+    sample_rate: u32,
+    channels: u16,
+    /// Samples are little-endian (AIFF-C `sowt`)
+    little_endian: bool,
+    /// Bytes of sample data, limited to the frames `COMM` declares
+    data_len: u64,
+    /// Sample data read ahead because `SSND` came before `COMM`
+    buffered: Option<io::Cursor<Vec<u8>>>,
+    /// Bytes of sample data still to be read from `reader`
+    remaining: u64,
+}
+
+/// Fields of the `COMM` chunk the reader needs
+struct AiffFormat {
+    channels: u16,
+    sample_frames: u32,
+    sample_rate: u32,
+    little_endian: bool,
+}
+
+impl<R: Read> AiffSource<R> {
+    /// Parse the AIFF header and position the reader at the first sample
+    pub fn new(mut reader: R) -> InputResult<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).map_err(aiff_error)?;
+        let aifc = &header[8..12] == b"AIFC";
+        if &header[0..4] != b"FORM" || !(aifc || &header[8..12] == b"AIFF") {
+            return Err(InputDataError::InvalidAiff(
+                "not a FORM/AIFF stream".to_string(),
+            ));
+        }
+
+        let mut format = None;
+        let mut buffered: Option<Vec<u8>> = None;
+        let (format, ssnd_len) = loop {
+            let mut chunk = [0u8; 8];
+            if let Err(err) = reader.read_exact(&mut chunk) {
+                let missing = if format.is_none() { "COMM" } else { "SSND" };
+                return Err(InputDataError::InvalidAiff(format!(
+                    "no {} chunk found: {}",
+                    missing, err
+                )));
+            }
+            let size = read_u32_be(&chunk, 4) as u64;
+
+            match &chunk[0..4] {
+                b"COMM" => {
+                    // Read as it arrives: a corrupt size must not be allocated
+                    let mut body = Vec::new();
+                    (&mut reader)
+                        .take(size)
+                        .read_to_end(&mut body)
+                        .map_err(aiff_error)?;
+                    if (body.len() as u64) < size {
+                        return Err(InputDataError::InvalidAiff(
+                            "stream ended inside a chunk".to_string(),
+                        ));
+                    }
+                    skip(&mut reader, size & 1)?;
+                    let parsed = parse_comm(&body, aifc)?;
+                    if let Some(data) = &buffered {
+                        break (parsed, data.len() as u64);
+                    }
+                    format = Some(parsed);
+                }
+                b"SSND" => {
+                    let data_len = skip_ssnd_header(&mut reader, size)?;
+                    match format.take() {
+                        Some(format) => break (format, data_len),
+                        None => {
+                            // The format comes later: keep the samples until it is known
+                            let mut data = Vec::new();
+                            (&mut reader)
+                                .take(data_len)
+                                .read_to_end(&mut data)
+                                .map_err(aiff_error)?;
+                            skip(&mut reader, size & 1)?;
+                            buffered = Some(data);
+                        }
+                    }
+                }
+                _ => skip(&mut reader, size + (size & 1))?,
+            }
+        };
+
+        let declared = format.sample_frames as u64 * format.channels as u64 * SAMPLE_BYTES;
+        let data_len = ssnd_len.min(declared);
+        Ok(Self {
+            reader,
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            little_endian: format.little_endian,
+            data_len,
+            remaining: if buffered.is_some() { 0 } else { data_len },
+            buffered: buffered.map(|mut data| {
+                data.truncate(data_len as usize);
+                io::Cursor::new(data)
+            }),
+        })
+    }
+
+    /// Sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels
+    pub fn channels(&self) -> u8 {
+        self.channels as u8
+    }
+
+    /// True if the samples are stored little-endian (AIFF-C `sowt`)
+    pub fn is_little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    /// Samples per channel in the sound data
+    pub fn duration_samples(&self) -> u64 {
+        self.data_len / self.block_bytes()
+    }
+
+    /// Skip `frames` samples per channel
+    ///
+    /// The source is only `Read`, so the skipped data is read and discarded.
+    /// Returns the number of frames skipped, which is less than `frames` only
+    /// at the end of the data.
+    pub fn skip_frames(&mut self, frames: u64) -> InputResult<u64> {
+        let block = self.block_bytes();
+        let skipped =
+            io::copy(&mut self.data().take(frames * block), &mut io::sink()).map_err(aiff_error)?;
+        Ok(skipped / block)
+    }
+
+    /// Read up to `count` interleaved samples into `buffer`
+    ///
+    /// The buffer is cleared first. Returns the number of samples read,
+    /// which is less than `count` only at the end of the data.
+    pub fn read_samples(&mut self, buffer: &mut Vec<i16>, count: usize) -> InputResult<usize> {
+        let mut bytes = Vec::new();
+        self.data()
+            .take(count as u64 * SAMPLE_BYTES)
+            .read_to_end(&mut bytes)
+            .map_err(aiff_error)?;
+
+        buffer.clear();
+        self.decode(&bytes, buffer)?;
+        Ok(buffer.len())
+    }
+
+    /// Read all remaining interleaved samples
+    pub fn read_to_end(&mut self) -> InputResult<Vec<i16>> {
+        let mut bytes = Vec::new();
+        self.data().read_to_end(&mut bytes).map_err(aiff_error)?;
+        let mut samples = Vec::with_capacity(bytes.len() / SAMPLE_BYTES as usize);
+        self.decode(&bytes, &mut samples)?;
+        Ok(samples)
+    }
+
+    /// Bytes of one sample of every channel
+    fn block_bytes(&self) -> u64 {
+        SAMPLE_BYTES * self.channels as u64
+    }
+
+    /// Append the samples in `bytes`; a stream cut inside a sample loses the
+    /// dangling byte
+    fn decode(&self, bytes: &[u8], samples: &mut Vec<i16>) -> InputResult<()> {
+        let whole = &bytes[..bytes.len() - bytes.len() % SAMPLE_BYTES as usize];
+        if self.little_endian {
+            bytes_to_i16_le(whole, samples)
+        } else {
+            bytes_to_i16_be(whole, samples)
+        }
+    }
+
+    /// The rest of the sample data; a stream that ends early simply ends
+    fn data(&mut self) -> DataReader<'_, R> {
+        DataReader { source: self }
+    }
+}
+
+/// `Read` over the remaining bytes of the sample data
+struct DataReader<'a, R: Read> {
+    source: &'a mut AiffSource<R>,
+}
+
+impl<R: Read> Read for DataReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(data) = &mut self.source.buffered {
+            return data.read(buf);
+        }
+        let limit = (buf.len() as u64).min(self.source.remaining) as usize;
+        let read = self.source.reader.read(&mut buf[..limit])?;
+        self.source.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Read and discard `count` bytes
+fn skip<R: Read>(reader: &mut R, count: u64) -> InputResult<()> {
+    let skipped = io::copy(&mut reader.take(count), &mut io::sink()).map_err(aiff_error)?;
+    if skipped < count {
+        return Err(InputDataError::InvalidAiff(
+            "stream ended inside a chunk".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Read the offset and block size that start an `SSND` chunk of `size`
+/// bytes, skip to the first sample and return the length of the sample data
+fn skip_ssnd_header<R: Read>(reader: &mut R, size: u64) -> InputResult<u64> {
+    let mut header = [0u8; 8];
+    if size < 8 {
+        return Err(InputDataError::InvalidAiff(
+            "SSND chunk too short".to_string(),
+        ));
+    }
+    reader.read_exact(&mut header).map_err(aiff_error)?;
+    let offset = read_u32_be(&header, 0) as u64;
+    if offset > size - 8 {
+        return Err(InputDataError::InvalidAiff(format!(
+            "SSND offset {} beyond the end of the chunk",
+            offset
+        )));
+    }
+    skip(reader, offset)?;
+    Ok(size - 8 - offset)
+}
+
+/// Parse and validate the body of the `COMM` chunk
+fn parse_comm(body: &[u8], aifc: bool) -> InputResult<AiffFormat> {
+    let min_len = if aifc { 22 } else { 18 };
+    if body.len() < min_len {
+        return Err(InputDataError::InvalidAiff(
+            "COMM chunk too short".to_string(),
+        ));
+    }
+    let channels = read_i16_be(body, 0);
+    let sample_frames = read_u32_be(body, 2);
+    let sample_size = read_i16_be(body, 6);
+    let mut rate = [0u8; 10];
+    rate.copy_from_slice(&body[8..18]);
+    let sample_rate = extended_to_u32(&rate)
+        .ok_or_else(|| InputDataError::InvalidAiff("sample rate out of range".to_string()))?;
+
+    let little_endian = match aifc.then(|| &body[18..22]) {
+        None | Some(b"NONE") => false,
+        Some(b"sowt") => true,
+        Some(other) => {
+            return Err(InputDataError::InvalidAiff(format!(
+                "compression type '{}' is not supported, only NONE and sowt",
+                String::from_utf8_lossy(other)
+            )))
+        }
+    };
+    if sample_size != 16 {
+        return Err(InputDataError::InvalidAiff(format!(
+            "only 16-bit samples are supported, got {}-bit",
+            sample_size
+        )));
+    }
+    if !(1..=2).contains(&channels) {
+        return Err(InputDataError::InvalidChannelCount {
+            expected: 2,
+            actual: channels.max(0) as usize,
+        });
+    }
+
+    Ok(AiffFormat {
+        channels: channels as u16,
+        sample_frames,
+        sample_rate,
+        little_endian,
+    })
+}
+
+/// Convert an 80-bit IEEE 754 extended precision number (the `COMM` sample
+/// rate) to the nearest integer
+///
+/// Returns `None` for negative, zero, non-finite or too large values.
+pub fn extended_to_u32(bytes: &[u8; 10]) -> Option<u32> {
+    let sign_exponent = read_u16_be(bytes, 0);
+    let mantissa = read_u64_be(bytes, 2);
+    if sign_exponent & 0x8000 != 0 || sign_exponent == 0x7FFF || mantissa == 0 {
+        return None;
+    }
+
+    // The mantissa has an explicit integer bit: value = mantissa * 2^(e - 16383 - 63)
+    let shift = (sign_exponent as i32) - 16383 - 63;
+    let value = mantissa as f64 * 2f64.powi(shift);
+    let rounded = value.round();
+    (rounded >= 1.0 && rounded <= u32::MAX as f64).then_some(rounded as u32)
+}
+
+fn aiff_error(err: io::Error) -> InputDataError {
+    InputDataError::InvalidAiff(err.to_string())
+}
@@ -0,0 +1,67 @@
+//! Tokio `AsyncWrite` output adapter
+//!
+//! `AsyncMp3Writer` encodes interleaved PCM and writes the frames to an
+//! async sink. Encoding itself stays synchronous: it runs one frame at a
+//! time inside `write_pcm`, which is short enough not to stall an executor.
+//! Only the writes to the sink are awaited.
+//!
+//! All errors surface as `io::Error`. Sink errors are passed through
+//! unchanged; encoder errors are converted with `From<EncoderError>`, which
+//! wraps them with `io::Error::other`, so the original `EncoderError` can be
+//! recovered with `get_ref` and `downcast_ref`.
+
+use crate::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use std::io;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Encodes PCM into an async sink
+#[derive(Debug)]
+pub struct AsyncMp3Writer<W> {
+    writer: W,
+    encoder: Mp3Encoder,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncMp3Writer<W> {
+    /// Create a writer that encodes with `config` into `writer`
+    pub fn new(writer: W, config: Mp3EncoderConfig) -> io::Result<Self> {
+        let encoder = Mp3Encoder::new(config)?;
+        Ok(Self { writer, encoder })
+    }
+
+    /// Encode interleaved samples and write every completed frame
+    ///
+    /// Samples short of a full frame stay buffered until the next call or
+    /// `shutdown`.
+    pub async fn write_pcm(&mut self, pcm: &[i16]) -> io::Result<()> {
+        let frames = self.encoder.encode_interleaved(pcm)?;
+        for frame in frames {
+            self.writer.write_all(&frame).await?;
+        }
+        Ok(())
+    }
+
+    /// Flush the final frame and shut the sink down
+    ///
+    /// The encoder is finished on the first call; calling again only
+    /// shuts the sink down.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        let tail = self.encoder.finish()?;
+        self.writer.write_all(&tail).await?;
+        self.writer.shutdown().await
+    }
+
+    /// The encoder, for statistics and configuration
+    pub fn encoder(&self) -> &Mp3Encoder {
+        &self.encoder
+    }
+
+    /// The underlying sink
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consume the adapter and return the sink
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,81 @@
+//! Parallel encoding of independent WAV files
+//!
+//! Each job reads one WAV file and writes one MP3 file with its own encoder,
+//! so jobs share no mutable state. Encoders are created on the worker thread
+//! that runs the job; only the job description crosses threads.
+
+use crate::error::EncoderError;
+use crate::mp3_encoder::{encode_wav_stream, EncoderStats, Mp3EncoderConfig};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// One file to encode: input WAV path, output MP3 path and encoder settings
+///
+/// Sample rate and channel count come from the WAV header, as in
+/// `encode_wav_to_mp3`; the configuration supplies everything else.
+pub type BatchJob = (PathBuf, PathBuf, Mp3EncoderConfig);
+
+/// Encode several WAV files concurrently
+///
+/// Uses one worker per available core (at most one per job). Results are
+/// returned in the order of `jobs`; a failing job does not affect the others.
+pub fn encode_batch(jobs: Vec<BatchJob>) -> Vec<Result<EncoderStats, EncoderError>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    encode_batch_with_threads(jobs, threads)
+}
+
+/// Encode several WAV files with at most `threads` worker threads
+pub fn encode_batch_with_threads(
+    jobs: Vec<BatchJob>,
+    threads: usize,
+) -> Vec<Result<EncoderStats, EncoderError>> {
+    let workers = threads.clamp(1, jobs.len().max(1));
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<Option<Result<EncoderStats, EncoderError>>> =
+        jobs.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((input, output, config)) = jobs.get(index) else {
+                            break;
+                        };
+                        done.push((index, encode_file(input, output, config.clone())));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let done = handle.join().expect("batch worker panicked");
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every job is run exactly once"))
+        .collect()
+}
+
+/// Encode one WAV file to one MP3 file
+fn encode_file(
+    input: &Path,
+    output: &Path,
+    config: Mp3EncoderConfig,
+) -> Result<EncoderStats, EncoderError> {
+    let reader = BufReader::new(File::open(input)?);
+    let writer = BufWriter::new(File::create(output)?);
+    encode_wav_stream(reader, writer, Some(config), None)
+}
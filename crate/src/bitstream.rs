@@ -4,10 +4,13 @@
 //! in shine's bitstream.c and l3bitstream.c. It provides functions to write
 //! MP3 frame headers, side information, and main data to the output bitstream.
 
-use crate::error::{EncodingError, EncodingResult};
+use crate::constants::{GRANULE_SIZE, SCALEFACTOR_BANDS_LONG};
+use crate::error::{BitstreamError, EncodingError, EncodingResult};
 use crate::huffman::{HuffCodeTab, SHINE_HUFFMAN_TABLE};
 use crate::tables::{SHINE_SCALE_FACT_BAND_INDEX, SHINE_SLEN1_TAB, SHINE_SLEN2_TAB};
-use crate::types::{GrInfo, ShineGlobalConfig, GRANULE_SIZE};
+use crate::types::{GrInfo, ShineGlobalConfig};
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec};
 
 /// Bitstream writer structure (matches shine's bitstream_t exactly)
 /// (ref/shine/src/lib/bitstream.h:4-10)
@@ -23,6 +26,8 @@ pub struct BitstreamWriter {
     pub cache: u32,
     /// Free bits in cache
     pub cache_bits: i32,
+    /// Reject invalid writes in `try_write_bits` instead of ignoring them
+    pub strict: bool,
 }
 
 impl BitstreamWriter {
@@ -35,9 +40,40 @@ impl BitstreamWriter {
             data_position: 0,
             cache: 0,
             cache_bits: 32,
+            strict: false,
         }
     }
 
+    /// Open the bitstream for writing with strict `try_write_bits` checks
+    pub fn new_strict(size: i32) -> Self {
+        Self {
+            strict: true,
+            ..Self::new(size)
+        }
+    }
+
+    /// Write N bits, reporting invalid writes on a strict writer
+    ///
+    /// A strict writer (see `new_strict`) returns an error when `bits` is
+    /// outside 1..=32 or `value` does not fit in `bits` bits. A lenient
+    /// writer ignores invalid bit counts and writes the value as shine
+    /// does, without any checks.
+    pub fn try_write_bits(&mut self, value: u32, bits: i32) -> Result<(), BitstreamError> {
+        if !(1..=32).contains(&bits) {
+            return if self.strict {
+                Err(BitstreamError::InvalidBitCount(bits))
+            } else {
+                Ok(())
+            };
+        }
+        if self.strict && bits < 32 && (value >> bits) != 0 {
+            return Err(BitstreamError::ValueTooWide { value, bits });
+        }
+
+        self.write_bits_unchecked(value, bits);
+        Ok(())
+    }
+
     /// Write N bits into the bit stream (matches shine_putbits exactly)
     /// (ref/shine/src/lib/bitstream.c:30-58)
     ///
@@ -48,22 +84,17 @@ impl BitstreamWriter {
         #[cfg(debug_assertions)]
         {
             if n > 32 {
-                return Err(EncodingError::BitstreamError(
-                    "Cannot write more than 32 bits at a time".to_string(),
-                ));
+                return Err(BitstreamError::InvalidBitCount(n).into());
             }
             if n < 0 {
-                return Err(EncodingError::BitstreamError(
-                    "Cannot write negative number of bits".to_string(),
-                ));
+                return Err(BitstreamError::InvalidBitCount(n).into());
             }
             if n < 32 && (val >> n) != 0 {
-                return Err(EncodingError::BitstreamError(format!(
-                    "Upper bits are not all zeros: val=0x{:X}, n={}, val>>n=0x{:X}",
-                    val,
-                    n,
-                    val >> n
-                )));
+                return Err(BitstreamError::ValueTooWide {
+                    value: val,
+                    bits: n,
+                }
+                .into());
             }
         }
 
@@ -71,21 +102,20 @@ impl BitstreamWriter {
         if n == 0 {
             return Ok(());
         }
+        if !(1..=32).contains(&n) {
+            return Err(BitstreamError::InvalidBitCount(n).into());
+        }
+
+        self.write_bits_unchecked(val, n);
+        Ok(())
+    }
 
+    /// Write 1..=32 bits without any checks (core of shine_putbits)
+    fn write_bits_unchecked(&mut self, val: u32, n: i32) {
         if self.cache_bits > n {
             // Cache has enough space for the new bits
             self.cache_bits -= n;
-
-            // Add safety check to prevent overflow
-            if self.cache_bits >= 0 && self.cache_bits < 32 {
-                let shifted_val = val << self.cache_bits;
-                self.cache |= shifted_val;
-            } else {
-                return Err(EncodingError::BitstreamError(format!(
-                    "Invalid cache_bits: {}",
-                    self.cache_bits
-                )));
-            }
+            self.cache |= val << self.cache_bits;
         } else {
             // Cache doesn't have enough space, need to flush and write to buffer
             // Ensure we have enough space in the buffer
@@ -119,8 +149,6 @@ impl BitstreamWriter {
                 self.cache = 0;
             }
         }
-
-        Ok(())
     }
 
     /// Get the current bit count (matches shine_get_bits_count exactly)
@@ -234,12 +262,60 @@ pub fn format_bitstream(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
         });
     });
 
+    if config.reservoir.is_enabled() {
+        return format_frame_with_reservoir(config);
+    }
+
     encode_side_info(config)?;
     encode_main_data(config)?;
 
     Ok(())
 }
 
+/// Format a frame whose main data may start in earlier frames
+///
+/// The side info and main data are formatted into the reservoir's scratch
+/// writer, queued, and the frames whose slots are now full are written to
+/// the bitstream.
+fn format_frame_with_reservoir(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
+    config.side_info.main_data_begin = config.reservoir.main_data_begin();
+
+    core::mem::swap(&mut config.bs, &mut config.reservoir.scratch);
+    let formatted = encode_side_info(config).and_then(|()| encode_main_data(config));
+    core::mem::swap(&mut config.bs, &mut config.reservoir.scratch);
+    formatted?;
+
+    // Side info and main data are whole bytes, so this only writes out the cache
+    let scratch = &mut config.reservoir.scratch;
+    scratch.flush()?;
+    let bytes = scratch.data[..scratch.data_position as usize].to_vec();
+    scratch.data_position = 0;
+
+    let side_info_len = (config.sideinfo_len / 8) as usize;
+    let slot = (config.mpeg.bits_per_frame - config.sideinfo_len) as usize / 8;
+    config
+        .reservoir
+        .push_frame(&bytes[..side_info_len], &bytes[side_info_len..], slot);
+
+    let mut ready = Vec::new();
+    config.reservoir.pop_ready(&mut ready);
+    ready
+        .iter()
+        .try_for_each(|&byte| config.bs.put_bits(byte as u32, 8))
+}
+
+/// Write the frames still waiting in the bit reservoir to the bitstream
+///
+/// The free end of their main data slots is filled with zeros. Does
+/// nothing without a reservoir.
+pub fn drain_reservoir(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
+    let mut frames = Vec::new();
+    config.reservoir.drain(&mut frames);
+    frames
+        .iter()
+        .try_for_each(|&byte| config.bs.put_bits(byte as u32, 8))
+}
+
 /// Encode the main data section (matches encodeMainData exactly)
 /// (ref/shine/src/lib/l3bitstream.c:46-71)
 fn encode_main_data(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
@@ -271,16 +347,19 @@ fn encode_main_data(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
                 })?;
             }
             if gr == 0 || scfsi[3] == 0 {
-                (16..21).try_for_each(|sfb| {
+                (16..SCALEFACTOR_BANDS_LONG).try_for_each(|sfb| {
                     let sf_val = config.scalefactor.l[gr][ch][sfb];
                     config.bs.put_bits(sf_val as u32, slen2)
                 })?;
             }
 
-            // Copy the granule info to avoid borrowing conflicts
-            let gi = config.side_info.gr[gr].ch[ch].tt.clone();
-            let ix = config.l3_enc[ch][gr];
-            huffman_code_bits(config, &ix, &gi)?;
+            // Disjoint field borrows: no copy of the granule data per frame
+            huffman_code_bits(
+                &mut config.bs,
+                config.mpeg.samplerate_index as usize,
+                &config.l3_enc[ch][gr],
+                &config.side_info.gr[gr].ch[ch].tt,
+            )?;
         }
     }
 
@@ -307,21 +386,22 @@ fn encode_side_info(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
     config.bs.put_bits(config.mpeg.ext as u32, 1)?;
     config.bs.put_bits(config.mpeg.mode as u32, 2)?;
     config.bs.put_bits(config.mpeg.mode_ext as u32, 2)?;
-    config.bs.put_bits(config.mpeg.copyright as u32, 1)?;
-    config.bs.put_bits(config.mpeg.original as u32, 1)?;
+    // Flags are i32 in the public config: any nonzero value sets the bit
+    config.bs.put_bits((config.mpeg.copyright != 0) as u32, 1)?;
+    config.bs.put_bits((config.mpeg.original != 0) as u32, 1)?;
     config.bs.put_bits(config.mpeg.emph as u32, 2)?;
 
     // Write side information
     if config.mpeg.version == 3 {
         // MPEG_I = 3
-        config.bs.put_bits(0, 9)?; // Main data begin
+        config.bs.put_bits(si.main_data_begin, 9)?;
         if config.wave.channels == 2 {
             config.bs.put_bits(si.private_bits, 3)?;
         } else {
             config.bs.put_bits(si.private_bits, 5)?;
         }
     } else {
-        config.bs.put_bits(0, 8)?; // Main data begin
+        config.bs.put_bits(si.main_data_begin, 8)?;
         if config.wave.channels == 2 {
             config.bs.put_bits(si.private_bits, 2)?;
         } else {
@@ -374,12 +454,13 @@ fn encode_side_info(config: &mut ShineGlobalConfig) -> EncodingResult<()> {
 /// Huffman encode the quantized values (matches Huffmancodebits exactly)
 /// (ref/shine/src/lib/l3bitstream.c:123-165)
 fn huffman_code_bits(
-    config: &mut ShineGlobalConfig,
+    bs: &mut BitstreamWriter,
+    samplerate_index: usize,
     ix: &[i32],
     gi: &GrInfo,
 ) -> EncodingResult<()> {
-    let scalefac = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
-    let bits_start = config.bs.get_bits_count();
+    let scalefac = &SHINE_SCALE_FACT_BAND_INDEX[samplerate_index];
+    let bits_start = bs.get_bits_count();
 
     // 1: Write the bigvalues
     let bigvalues = (gi.big_values << 1) as usize;
@@ -400,7 +481,7 @@ fn huffman_code_bits(
             let x = ix[i];
             let y = ix[i + 1];
 
-            huffman_code(&mut config.bs, table_index as usize, x, y)?;
+            huffman_code(bs, table_index as usize, x, y)?;
         }
         i += 2;
     }
@@ -416,12 +497,12 @@ fn huffman_code_bits(
         let x = ix[i + 2];
         let y = ix[i + 3];
 
-        huffman_coder_count1(&mut config.bs, h, v, w, x, y)?;
+        huffman_coder_count1(bs, h, v, w, x, y)?;
         i += 4;
     }
 
     // 3: Pad with stuffing bits if necessary
-    let bits_used = config.bs.get_bits_count() - bits_start;
+    let bits_used = bs.get_bits_count() - bits_start;
     let bits_available = gi.part2_3_length as i32 - gi.part2_length as i32;
     let stuffing_bits = bits_available - bits_used;
 
@@ -431,12 +512,10 @@ fn huffman_code_bits(
 
         // Due to the nature of the Huffman code tables, we will pad with ones
         for _ in 0..stuffing_words {
-            config.bs.put_bits(0xffffffff, 32)?;
+            bs.put_bits(0xffffffff, 32)?;
         }
         if remaining_bits > 0 {
-            config
-                .bs
-                .put_bits((1u32 << remaining_bits) - 1, remaining_bits)?;
+            bs.put_bits((1u32 << remaining_bits) - 1, remaining_bits)?;
         }
     }
 
@@ -579,6 +658,11 @@ fn huffman_code(
 
             bs.put_bits(code, cbits as i32)?;
         }
+    } else {
+        // Writing nothing here would desynchronise every later code
+        return Err(EncodingError::HuffmanTableUnavailable {
+            table: table_select,
+        });
     }
 
     Ok(())
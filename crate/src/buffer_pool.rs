@@ -0,0 +1,93 @@
+//! Recycled output buffers for handing frames to another thread
+//!
+//! `Mp3Encoder` normally allocates a new `Vec<u8>` for every frame it
+//! returns. An encoder given a `BufferPool` takes the buffers from the pool
+//! instead; the consumer hands each buffer back with `recycle` once it has
+//! written it out, so a steady pipeline stops allocating after the pool has
+//! grown to the number of frames in flight.
+//!
+//! Taking a buffer never blocks: if none is idle the pool allocates a new
+//! one and counts it, so an undersized pool shows up in `allocated` rather
+//! than stalling the encoder.
+
+use crate::encoder::MAX_FRAME_SLOTS;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Capacity of new buffers, enough for the longest frame
+pub const DEFAULT_BUFFER_CAPACITY: usize = MAX_FRAME_SLOTS as usize;
+
+#[derive(Debug)]
+struct Shared {
+    idle: Mutex<Vec<Vec<u8>>>,
+    buffer_capacity: usize,
+    allocated: AtomicU64,
+}
+
+/// Shared pool of frame buffers
+///
+/// Clones refer to the same pool, so the encoder and the consumer each hold
+/// one.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    shared: Arc<Shared>,
+}
+
+impl BufferPool {
+    /// Pool with `buffers` idle buffers of `DEFAULT_BUFFER_CAPACITY` bytes
+    pub fn new(buffers: usize) -> Self {
+        Self::with_capacity(buffers, DEFAULT_BUFFER_CAPACITY)
+    }
+
+    /// Pool with `buffers` idle buffers of `buffer_capacity` bytes
+    pub fn with_capacity(buffers: usize, buffer_capacity: usize) -> Self {
+        let idle = (0..buffers)
+            .map(|_| Vec::with_capacity(buffer_capacity))
+            .collect();
+        Self {
+            shared: Arc::new(Shared {
+                idle: Mutex::new(idle),
+                buffer_capacity,
+                allocated: AtomicU64::new(buffers as u64),
+            }),
+        }
+    }
+
+    /// An empty buffer, allocating one if none is idle
+    pub fn take(&self) -> Vec<u8> {
+        if let Some(buffer) = self.lock_idle().pop() {
+            return buffer;
+        }
+        self.shared.allocated.fetch_add(1, Ordering::Relaxed);
+        Vec::with_capacity(self.shared.buffer_capacity)
+    }
+
+    /// Return a buffer for reuse
+    pub fn recycle(&self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        self.lock_idle().push(buffer);
+    }
+
+    /// Number of buffers waiting to be taken
+    pub fn idle(&self) -> usize {
+        self.lock_idle().len()
+    }
+
+    /// Buffers allocated since the pool was created, including the initial ones
+    pub fn allocated(&self) -> u64 {
+        self.shared.allocated.load(Ordering::Relaxed)
+    }
+
+    /// Capacity of the buffers the pool allocates
+    pub fn buffer_capacity(&self) -> usize {
+        self.shared.buffer_capacity
+    }
+
+    /// The idle list; a panic while holding the lock cannot leave it invalid
+    fn lock_idle(&self) -> MutexGuard<'_, Vec<Vec<u8>>> {
+        self.shared
+            .idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
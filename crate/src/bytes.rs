@@ -0,0 +1,58 @@
+//! Fixed-width integers at an offset of a byte slice
+//!
+//! RIFF/WAV stores its fields little-endian, AIFF and the MP3 tags
+//! big-endian. The parsers read every field through these helpers instead of
+//! assembling bytes by hand, so the byte order is named at each call site.
+//!
+//! Like slice indexing, each helper panics if fewer bytes than the width
+//! follow `offset`; callers check the length of the chunk first.
+
+/// `N` bytes starting at `offset`
+#[inline]
+fn array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut array = [0u8; N];
+    array.copy_from_slice(&bytes[offset..offset + N]);
+    array
+}
+
+/// Little-endian `u16` at `offset`
+#[inline]
+pub fn read_u16_le(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(array(bytes, offset))
+}
+
+/// Big-endian `u16` at `offset`
+#[inline]
+pub fn read_u16_be(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes(array(bytes, offset))
+}
+
+/// Big-endian `i16` at `offset`
+#[inline]
+pub fn read_i16_be(bytes: &[u8], offset: usize) -> i16 {
+    i16::from_be_bytes(array(bytes, offset))
+}
+
+/// Little-endian `u32` at `offset`
+#[inline]
+pub fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(array(bytes, offset))
+}
+
+/// Big-endian `u32` at `offset`
+#[inline]
+pub fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(array(bytes, offset))
+}
+
+/// Little-endian `u64` at `offset`
+#[inline]
+pub fn read_u64_le(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(array(bytes, offset))
+}
+
+/// Big-endian `u64` at `offset`
+#[inline]
+pub fn read_u64_be(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(array(bytes, offset))
+}
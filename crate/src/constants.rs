@@ -0,0 +1,19 @@
+//! Frame layout constants of MPEG Layer III
+//!
+//! Sizes that callers otherwise hardcode when they split PCM into frames or
+//! walk the spectrum of a granule. MPEG-2.5 frames have the MPEG-2 layout.
+
+/// Spectral lines (and PCM samples per channel) in one granule
+pub const GRANULE_SIZE: usize = 576;
+
+/// PCM samples per channel in an MPEG-1 frame (two granules)
+pub const SAMPLES_PER_FRAME_MPEG1: usize = 2 * GRANULE_SIZE;
+
+/// PCM samples per channel in an MPEG-2 or MPEG-2.5 frame (one granule)
+pub const SAMPLES_PER_FRAME_MPEG2: usize = GRANULE_SIZE;
+
+/// Subbands of the polyphase filterbank
+pub const SUBBAND_COUNT: usize = 32;
+
+/// Scalefactor bands of a long block
+pub const SCALEFACTOR_BANDS_LONG: usize = 21;
@@ -13,41 +13,11 @@ use std::io::Write;
 use std::sync::Mutex;
 use std::thread;
 
-lazy_static! {
-    /// Thread-local frame counters for debugging consistency across modules
-    static ref THREAD_FRAME_COUNTERS: Mutex<HashMap<std::thread::ThreadId, i32>> = Mutex::new(HashMap::new());
-}
 lazy_static! {
     /// Global test data collector - now supports multiple threads
     static ref TEST_DATA_COLLECTORS: Mutex<HashMap<std::thread::ThreadId, TestDataCollector>> = Mutex::new(HashMap::new());
 }
 
-/// Reset the frame counter for current thread (for testing)
-pub fn reset_frame_counter() {
-    let thread_id = thread::current().id();
-    let mut counters = THREAD_FRAME_COUNTERS.lock().unwrap();
-    counters.insert(thread_id, 0);
-
-    // Also reset TestDataCollector if diagnostics feature is enabled
-    TestDataCollector::reset();
-}
-
-/// Get the current frame number and increment the counter for current thread
-pub fn get_next_frame_number() -> i32 {
-    let thread_id = thread::current().id();
-    let mut counters = THREAD_FRAME_COUNTERS.lock().unwrap();
-    let counter = counters.entry(thread_id).or_insert(0);
-    *counter += 1;
-    *counter
-}
-
-/// Get the current frame number without incrementing for current thread
-pub fn get_current_frame_number() -> i32 {
-    let thread_id = thread::current().id();
-    let counters = THREAD_FRAME_COUNTERS.lock().unwrap();
-    *counters.get(&thread_id).unwrap_or(&0)
-}
-
 /// Frame-specific encoding data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameData {
@@ -96,8 +66,16 @@ pub struct QuantizationData {
 
     /// Global gain
     pub global_gain: u32,
+
+    /// First quantized values (l3_enc) of the granule, as magnitudes: the
+    /// signs are restored when the frame is formatted
+    #[serde(default)]
+    pub l3_enc: Vec<i32>,
 }
 
+/// Number of quantized values captured per frame
+pub const CAPTURED_L3_ENC_VALUES: usize = 32;
+
 /// Bitstream data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitstreamData {
@@ -223,6 +201,7 @@ impl TestDataCollector {
                         part2_3_length: 0,
                         quantizer_step_size: 0,
                         global_gain: 0,
+                        l3_enc: Vec::new(),
                     },
                     bitstream: BitstreamData {
                         padding: 0,
@@ -319,6 +298,7 @@ impl TestDataCollector {
         part2_3_length: u32,
         quantizer_step_size: i32,
         global_gain: u32,
+        l3_enc: &[i32],
     ) {
         let thread_id = thread::current().id();
         let mut guard = TEST_DATA_COLLECTORS.lock().unwrap();
@@ -335,6 +315,7 @@ impl TestDataCollector {
                     frame.quantization.part2_3_length = part2_3_length;
                     frame.quantization.quantizer_step_size = quantizer_step_size;
                     frame.quantization.global_gain = global_gain;
+                    frame.quantization.l3_enc = l3_enc.to_vec();
                 }
             }
         }
@@ -451,6 +432,7 @@ pub fn record_quant_data(
     part2_3_length: u32,
     quantizer_step_size: i32,
     global_gain: u32,
+    l3_enc: &[i32],
 ) {
     if TestDataCollector::is_collecting() {
         TestDataCollector::record_quantization(
@@ -459,6 +441,7 @@ pub fn record_quant_data(
             part2_3_length,
             quantizer_step_size,
             global_gain,
+            l3_enc,
         );
     }
 }
@@ -471,7 +454,7 @@ pub fn record_bitstream_data(padding: i32, bits_per_frame: i32, written: usize,
 
 // High-level encoder interface for integration testing
 use crate::encoder::{
-    shine_encode_buffer_interleaved, shine_initialise, ShineConfig, ShineMpeg, ShineWave,
+    shine_encode_buffer_interleaved_safe, shine_initialise, ShineConfig, ShineMpeg, ShineWave,
 };
 use crate::error::EncodingResult;
 use crate::types::ShineGlobalConfig;
@@ -526,12 +509,9 @@ impl Encoder {
         // Note: Frame collection is started in shine_encode_buffer_interleaved
         // No need to start it here to avoid duplicate calls
 
-        // Prepare sample data
-        let sample_ptr = samples.as_ptr();
-
         // Encode frame and immediately copy the data to avoid borrow issues
         let (frame_data_slice, written) =
-            unsafe { shine_encode_buffer_interleaved(&mut self.config, sample_ptr)? };
+            shine_encode_buffer_interleaved_safe(&mut self.config, samples)?;
         let frame_data = frame_data_slice.to_vec(); // Copy immediately
 
         // Now we can safely access self.config again
@@ -605,6 +585,10 @@ impl Encoder {
             max_bits: self.config.mean_bits, // This is a fallback - should use collected data
             xrmax: self.config.l3loop.xrmax,
             quantizer_step_size: gr_info.quantizer_step_size,
+            l3_enc: self.config.l3_enc[0][0][..CAPTURED_L3_ENC_VALUES]
+                .iter()
+                .map(|value| value.abs())
+                .collect(),
         }
     }
 }
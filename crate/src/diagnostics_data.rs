@@ -0,0 +1,146 @@
+//! Spectrum analysis accessors for encoded frames
+//!
+//! The MDCT coefficients of every frame are captured at the end of
+//! `shine_mdct_sub` and kept in the encoder's own [`SpectrumHistory`], so
+//! the energy distribution across scalefactor bands can be inspected without
+//! a decoder. Frame numbers are those of the encoder, so encoders sharing a
+//! thread do not see each other's frames.
+//!
+//! This module is only available when the "diagnostics" feature is enabled.
+
+use crate::constants::{GRANULE_SIZE, SCALEFACTOR_BANDS_LONG};
+use crate::tables::SHINE_SCALE_FACT_BAND_INDEX;
+use crate::types::{ShineGlobalConfig, MAX_CHANNELS, MAX_GRANULES};
+use std::collections::VecDeque;
+
+/// Number of long block scalefactor bands
+pub const SCALEFACTOR_BANDS: usize = SCALEFACTOR_BANDS_LONG;
+
+/// Maximum number of frames kept per encoder, oldest frames are dropped first
+pub const SPECTRUM_HISTORY: usize = 1024;
+
+/// Band energies indexed as [granule][channel][scalefactor band]
+pub type BandEnergies = [[[f64; SCALEFACTOR_BANDS]; MAX_CHANNELS]; MAX_GRANULES];
+
+/// MDCT coefficients of one frame
+#[derive(Debug, Clone)]
+struct SpectrumSnapshot {
+    frame_number: i32,
+    samplerate_index: usize,
+    channels: usize,
+    granules: usize,
+    /// Coefficients indexed as [granule][channel][line]
+    coefficients: Box<[[[i32; GRANULE_SIZE]; MAX_CHANNELS]; MAX_GRANULES]>,
+}
+
+/// Spectra an encoder captured, at most [`SPECTRUM_HISTORY`] frames
+#[derive(Debug, Default)]
+pub struct SpectrumHistory(VecDeque<SpectrumSnapshot>);
+
+impl SpectrumHistory {
+    /// Drop all captured spectra
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn frame(&self, frame_index: i32) -> Option<&SpectrumSnapshot> {
+        self.0
+            .iter()
+            .find(|frame| frame.frame_number == frame_index)
+    }
+}
+
+/// Capture the MDCT coefficients of the current frame
+///
+/// Called at the end of `shine_mdct_sub`.
+pub fn record_spectrum(config: &mut ShineGlobalConfig) {
+    let mut coefficients = Box::new([[[0i32; GRANULE_SIZE]; MAX_CHANNELS]; MAX_GRANULES]);
+    let channels = config.wave.channels as usize;
+    let granules = config.mpeg.granules_per_frame as usize;
+    for (gr, granule) in coefficients.iter_mut().enumerate().take(granules) {
+        for (ch, lines) in granule.iter_mut().enumerate().take(channels) {
+            lines.copy_from_slice(&config.mdct_freq[ch][gr]);
+        }
+    }
+
+    let snapshot = SpectrumSnapshot {
+        frame_number: config.frame_number,
+        samplerate_index: config.mpeg.samplerate_index as usize,
+        channels,
+        granules,
+        coefficients,
+    };
+
+    let frames = &mut config.spectra.0;
+    frames.retain(|frame| frame.frame_number != snapshot.frame_number);
+    if frames.len() == SPECTRUM_HISTORY {
+        frames.pop_front();
+    }
+    frames.push_back(snapshot);
+}
+
+/// Energy of each scalefactor band of a frame
+///
+/// `frame_index` is the encoder's frame number (starting at 1). Energies
+/// are relative to full scale, so a line at 0x7fffffff contributes 1.0.
+/// Granules and channels that the frame does not contain are zero.
+pub fn band_energies(config: &ShineGlobalConfig, frame_index: i32) -> Option<BandEnergies> {
+    config.spectra.frame(frame_index).map(|frame| {
+        let bands = &SHINE_SCALE_FACT_BAND_INDEX[frame.samplerate_index];
+        let mut energies = [[[0.0; SCALEFACTOR_BANDS]; MAX_CHANNELS]; MAX_GRANULES];
+        let granules = energies.iter_mut().zip(frame.coefficients.iter());
+        for (granule, coefficients) in granules.take(frame.granules) {
+            let channels = granule.iter_mut().zip(coefficients.iter());
+            for (channel, lines) in channels.take(frame.channels) {
+                for (sfb, energy) in channel.iter_mut().enumerate() {
+                    let start = bands[sfb] as usize;
+                    let end = bands[sfb + 1] as usize;
+                    *energy = lines[start..end]
+                        .iter()
+                        .map(|&x| {
+                            let x = x as f64 / 2147483648.0;
+                            x * x
+                        })
+                        .sum();
+                }
+            }
+        }
+        energies
+    })
+}
+
+/// Spectral flatness of each granule and channel of a frame
+///
+/// The ratio of the geometric to the arithmetic mean of the power spectrum,
+/// indexed as [granule][channel]. Values near 1.0 indicate noise-like
+/// content, values near 0.0 tonal content. Silent granules report 0.0.
+pub fn spectral_flatness(
+    config: &ShineGlobalConfig,
+    frame_index: i32,
+) -> Option<[[f64; MAX_CHANNELS]; MAX_GRANULES]> {
+    config.spectra.frame(frame_index).map(|frame| {
+        let mut flatness = [[0.0; MAX_CHANNELS]; MAX_GRANULES];
+        let granules = flatness.iter_mut().zip(frame.coefficients.iter());
+        for (granule, coefficients) in granules.take(frame.granules) {
+            let channels = granule.iter_mut().zip(coefficients.iter());
+            for (channel, lines) in channels.take(frame.channels) {
+                let power: Vec<f64> = lines
+                    .iter()
+                    .map(|&x| {
+                        let x = x as f64 / 2147483648.0;
+                        x * x
+                    })
+                    .collect();
+                let arithmetic = power.iter().sum::<f64>() / GRANULE_SIZE as f64;
+                if arithmetic > 0.0 {
+                    // Floor each line so a single empty line does not zero the product
+                    let floor = arithmetic * 1e-12;
+                    let log_mean =
+                        power.iter().map(|p| p.max(floor).ln()).sum::<f64>() / GRANULE_SIZE as f64;
+                    *channel = log_mean.exp() / arithmetic;
+                }
+            }
+        }
+        flatness
+    })
+}
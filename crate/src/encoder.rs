@@ -5,14 +5,25 @@
 //! including initialization, configuration, and encoding operations.
 
 use crate::bitstream::BitstreamWriter;
-use crate::error::{EncodingError, EncodingResult};
+use crate::constants::{GRANULE_SIZE, SCALEFACTOR_BANDS_LONG};
+use crate::error::{ConfigError, EncodingError, EncodingResult};
+use crate::mp3_encoder::Emphasis;
+use crate::pcm::PcmSample;
+use crate::quantization::NmrStats;
 use crate::tables::{BITRATES, SAMPLERATES};
-use crate::types::{ShineGlobalConfig, ShineSideInfo, GRANULE_SIZE};
+use crate::types::{ShineGlobalConfig, ShineSideInfo, MAX_CHANNELS, MAX_GRANULES};
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
 
 /// Buffer size for bitstream (matches shine BUFFER_SIZE)
 /// (ref/shine/src/lib/bitstream.h:19)
 const BUFFER_SIZE: i32 = 4096;
 
+/// Largest accepted frame size in 8-bit slots
+///
+/// Valid configurations stay below 1441 slots (320 kbps at 32 kHz).
+pub const MAX_FRAME_SLOTS: i32 = 2048;
+
 /// MPEG version constants (matches shine's mpeg_versions enum)
 /// (ref/shine/src/lib/layer3.h:10)
 pub const MPEG_I: i32 = 3;
@@ -26,6 +37,8 @@ pub const LAYER_III: i32 = 1;
 /// Emphasis constants (matches shine's emph enum)
 /// (ref/shine/src/lib/layer3.h:25)
 pub const NONE: i32 = 0;
+pub const MU50_15: i32 = 1;
+pub const CITT: i32 = 3;
 
 /// Granules per frame for different MPEG versions (matches shine's granules_per_frame)
 /// (ref/shine/src/lib/layer3.c:9-14)
@@ -157,14 +170,148 @@ pub fn shine_samples_per_pass(config: &ShineGlobalConfig) -> i32 {
     config.mpeg.granules_per_frame * GRANULE_SIZE as i32
 }
 
+/// Average number of 8-bit slots per frame, as whole slots and the fractional
+/// remainder (the computation in shine_initialise)
+///
+/// A whole number of slots is returned exactly: shine's floating point
+/// product can land just below it (431.99999... at 8 kHz and 48 kbps), which
+/// would pad every frame while writing the padding bit into its header.
+pub fn shine_slots_per_frame(granules_per_frame: i32, samplerate: i32, bitr: i32) -> (i32, f64) {
+    let bits = granules_per_frame as i64 * GRANULE_SIZE as i64 * 1000 * bitr as i64;
+    if samplerate > 0 && bits % (8 * samplerate as i64) == 0 {
+        return ((bits / (8 * samplerate as i64)) as i32, 0.0);
+    }
+
+    let avg_slots_per_frame = (granules_per_frame as f64 * GRANULE_SIZE as f64 / samplerate as f64)
+        * (1000.0 * bitr as f64 / 8.0);
+    let whole = avg_slots_per_frame as i32;
+    (whole, avg_slots_per_frame - whole as f64)
+}
+
+/// Reject a bitrate whose frames would exceed `MAX_FRAME_SLOTS`
+///
+/// Guards the frame size computation against untrusted `bitr` values before
+/// anything is sized from it. Unknown sample rates pass; `shine_check_config`
+/// reports them.
+pub fn shine_check_frame_size(samplerate: i32, bitr: i32) -> Result<(), ConfigError> {
+    let samplerate_index = shine_find_samplerate_index(samplerate);
+    if samplerate_index < 0 {
+        return Ok(());
+    }
+    let granules = shine_granules_per_frame(shine_mpeg_version(samplerate_index));
+    let (slots, _) = shine_slots_per_frame(granules, samplerate, bitr);
+    if slots > MAX_FRAME_SLOTS {
+        return Err(ConfigError::FrameSizeTooLarge {
+            slots,
+            max: MAX_FRAME_SLOTS,
+        });
+    }
+    Ok(())
+}
+
+/// Bytes of side info following the header of every frame
+pub fn shine_side_info_bytes(granules_per_frame: i32, channels: i32) -> i32 {
+    match (granules_per_frame, channels) {
+        // MPEG 1
+        (2, 1) => 17,
+        (2, _) => 32,
+        // MPEG 2
+        (_, 1) => 9,
+        (_, _) => 17,
+    }
+}
+
+/// Bits of the header and side info at the start of every frame
+pub fn shine_sideinfo_len(granules_per_frame: i32, channels: i32) -> i32 {
+    8 * (4 + shine_side_info_bytes(granules_per_frame, channels))
+}
+
+/// Reject a bitrate whose frames leave no room for main data
+///
+/// The unpadded frame must be larger than the header and side info, or the
+/// main data share `bits_per_frame - sideinfo_len` would be zero or
+/// negative. No supported combination gets close (8 kbps stereo at 24 kHz
+/// leaves 24 bits), but the check keeps the subtraction safe for any
+/// `bitr`. Unknown sample rates pass; `shine_check_config` reports them.
+pub fn shine_check_side_info(samplerate: i32, bitr: i32, channels: i32) -> Result<(), ConfigError> {
+    let samplerate_index = shine_find_samplerate_index(samplerate);
+    if samplerate_index < 0 {
+        return Ok(());
+    }
+    let granules = shine_granules_per_frame(shine_mpeg_version(samplerate_index));
+    let (slots, _) = shine_slots_per_frame(granules, samplerate, bitr);
+    let side_info_bits = shine_sideinfo_len(granules, channels);
+    if 8 * slots <= side_info_bits {
+        return Err(ConfigError::FrameTooSmall {
+            frame_bits: 8 * slots,
+            side_info_bits,
+        });
+    }
+    Ok(())
+}
+
+/// Smallest standard frame holding `budget` bits, as its bitrate index and
+/// size in bits
+///
+/// The budget must cover the header and side info and fit the frame of the
+/// highest bitrate of the configured MPEG version.
+pub fn shine_frame_for_budget(
+    config: &ShineGlobalConfig,
+    budget: i32,
+) -> Result<(i32, i32), ConfigError> {
+    let frame_bits = |index: usize| {
+        let bitr = BITRATES[index][config.mpeg.version as usize];
+        let (slots, _) =
+            shine_slots_per_frame(config.mpeg.granules_per_frame, config.wave.samplerate, bitr);
+        (bitr > 0).then_some(8 * slots)
+    };
+
+    let frame = (1..15)
+        .filter_map(|index| Some((index as i32, frame_bits(index)?)))
+        .find(|&(_, bits)| bits >= budget);
+    match frame {
+        Some(frame) if budget >= config.sideinfo_len => Ok(frame),
+        _ => Err(ConfigError::FrameBitBudgetOutOfRange {
+            bits: budget.max(0) as u32,
+            min: config.sideinfo_len as u32,
+            max: (1..15).filter_map(frame_bits).max().unwrap_or(0) as u32,
+        }),
+    }
+}
+
+/// Padding slot of the next frame; advances the slot lag, which starts at
+/// `-frac_slots` (the dynamic padding in shine_encode_buffer_internal)
+pub fn shine_next_padding(slot_lag: &mut f64, frac_slots: f64) -> i32 {
+    let padding = if *slot_lag <= frac_slots - 1.0 { 1 } else { 0 };
+    *slot_lag += padding as f64 - frac_slots;
+    padding
+}
+
+/// Number of granules in a frame of the given MPEG version
+pub fn shine_granules_per_frame(mpeg_version: i32) -> i32 {
+    GRANULES_PER_FRAME[mpeg_version as usize]
+}
+
 /// Compute default encoding values (matches shine_initialise)
 /// (ref/shine/src/lib/layer3.c:75-134)
 pub fn shine_initialise(pub_config: &ShineConfig) -> EncodingResult<Box<ShineGlobalConfig>> {
+    shine_check_frame_size(pub_config.wave.samplerate, pub_config.mpeg.bitr)
+        .map_err(EncodingError::InvalidConfig)?;
     if shine_check_config(pub_config.wave.samplerate, pub_config.mpeg.bitr) < 0 {
-        return Err(EncodingError::ValidationError(
-            "Invalid configuration".to_string(),
-        ));
+        let error = if shine_find_samplerate_index(pub_config.wave.samplerate) < 0 {
+            ConfigError::UnsupportedSampleRate(pub_config.wave.samplerate as u32)
+        } else {
+            ConfigError::UnsupportedBitrate(pub_config.mpeg.bitr as u32)
+        };
+        return Err(EncodingError::InvalidConfig(error));
     }
+    shine_check_side_info(
+        pub_config.wave.samplerate,
+        pub_config.mpeg.bitr,
+        pub_config.wave.channels,
+    )
+    .map_err(EncodingError::InvalidConfig)?;
+    Emphasis::validate(pub_config.mpeg.emph).map_err(EncodingError::InvalidConfig)?;
 
     let mut config = Box::new(ShineGlobalConfig::default());
 
@@ -197,14 +344,13 @@ pub fn shine_initialise(pub_config: &ShineConfig) -> EncodingResult<Box<ShineGlo
     config.mpeg.granules_per_frame = GRANULES_PER_FRAME[config.mpeg.version as usize];
 
     // Figure average number of 'slots' per frame
-    let avg_slots_per_frame = (config.mpeg.granules_per_frame as f64 * GRANULE_SIZE as f64
-        / config.wave.samplerate as f64)
-        * (1000.0 * config.mpeg.bitr as f64 / config.mpeg.bits_per_slot as f64);
-
-    config.mpeg.whole_slots_per_frame = avg_slots_per_frame as i32;
-
-    config.mpeg.frac_slots_per_frame =
-        avg_slots_per_frame - config.mpeg.whole_slots_per_frame as f64;
+    let (whole_slots, frac_slots) = shine_slots_per_frame(
+        config.mpeg.granules_per_frame,
+        config.wave.samplerate,
+        config.mpeg.bitr,
+    );
+    config.mpeg.whole_slots_per_frame = whole_slots;
+    config.mpeg.frac_slots_per_frame = frac_slots;
     config.mpeg.slot_lag = -config.mpeg.frac_slots_per_frame;
 
     if config.mpeg.frac_slots_per_frame == 0.0 {
@@ -213,64 +359,147 @@ pub fn shine_initialise(pub_config: &ShineConfig) -> EncodingResult<Box<ShineGlo
 
     config.bs = BitstreamWriter::new(BUFFER_SIZE);
 
+    // Scaled PCM of one frame; allocated once so encoding never grows it
+    config.pcm_buffer = Vec::with_capacity(
+        config.mpeg.granules_per_frame as usize * GRANULE_SIZE * config.wave.channels as usize,
+    );
+
     // Clear side info (matches memset in shine)
     config.side_info = ShineSideInfo::default();
 
     // Determine the mean bitrate for main data
-    if config.mpeg.granules_per_frame == 2 {
-        // MPEG 1
-        config.sideinfo_len = 8 * if config.wave.channels == 1 {
-            4 + 17
-        } else {
-            4 + 32
-        };
-    } else {
-        // MPEG 2
-        config.sideinfo_len = 8 * if config.wave.channels == 1 {
-            4 + 9
-        } else {
-            4 + 17
-        };
-    }
+    config.sideinfo_len = shine_sideinfo_len(config.mpeg.granules_per_frame, config.wave.channels);
 
     Ok(config)
 }
 
+/// Return an initialised encoder to the state `shine_initialise` left it in
+///
+/// The filterbank, MDCT and quantizer tables depend only on constants and
+/// are kept, as are the allocations and the configuration (including the
+/// lowpass line and the reservoir size `resv_max`); everything that encoding
+/// changes is cleared.
+pub fn shine_reset(config: &mut ShineGlobalConfig) {
+    config.mpeg.padding = 0;
+    config.mpeg.bits_per_frame = 0;
+    config.mpeg.mode_ext = 0;
+    config.mpeg.slot_lag = -config.mpeg.frac_slots_per_frame;
+    config.mean_bits = 0;
+    config.resv_size = 0;
+    config.reservoir.clear();
+    config.frame_number = 0;
+    config.nmr = NmrStats::default();
+    #[cfg(feature = "diagnostics")]
+    config.spectra.clear();
+
+    config.bs.data.fill(0);
+    config.bs.data_position = 0;
+    config.bs.cache = 0;
+    config.bs.cache_bits = 32;
+
+    config.side_info = ShineSideInfo::default();
+    config
+        .ratio
+        .l
+        .iter_mut()
+        .flatten()
+        .for_each(|row| row.fill(0.0));
+    config
+        .scalefactor
+        .l
+        .iter_mut()
+        .flatten()
+        .for_each(|row| row.fill(0));
+    config
+        .scalefactor
+        .s
+        .iter_mut()
+        .flatten()
+        .flatten()
+        .for_each(|row| row.fill(0));
+    config.buffer = [core::ptr::null(); MAX_CHANNELS];
+    config.buffer_len = [0; MAX_CHANNELS];
+    config.pcm_buffer.clear();
+    config.pe.iter_mut().for_each(|row| row.fill(0.0));
+    config
+        .l3_enc
+        .iter_mut()
+        .flatten()
+        .for_each(|row| row.fill(0));
+    config
+        .l3_sb_sample
+        .iter_mut()
+        .flatten()
+        .flatten()
+        .for_each(|row| row.fill(0));
+    config
+        .mdct_freq
+        .iter_mut()
+        .flatten()
+        .for_each(|row| row.fill(0));
+
+    let l3loop = &mut config.l3loop;
+    l3loop.xr = core::ptr::null_mut();
+    l3loop.xrsq.fill(0);
+    l3loop.xrabs.fill(0);
+    l3loop.xr_amp.fill(0);
+    l3loop.xrmax = 0;
+    l3loop.en_tot = [0; MAX_GRANULES];
+    l3loop.en = [[0; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES];
+    l3loop.xm = [[0; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES];
+    l3loop.xrmaxl = [0; MAX_GRANULES];
+
+    config.subband.off = [0; MAX_CHANNELS];
+    config.subband.x.iter_mut().for_each(|row| row.fill(0));
+
+    config.frame_bit_budget = None;
+    config.frame_bit_budget_sticky = false;
+}
+
 /// Internal encoding function (matches shine_encode_buffer_internal)
 /// (ref/shine/src/lib/layer3.c:136-158)
 fn shine_encode_buffer_internal(
     config: &mut ShineGlobalConfig,
     stride: i32,
 ) -> EncodingResult<(&[u8], usize)> {
-    #[cfg(feature = "diagnostics")]
-    let frame_num = crate::get_next_frame_number();
+    // The budget is checked before anything changes, so a rejected budget
+    // leaves the encoder as it was
+    let bitrate_index = config.mpeg.bitrate_index;
+    let budget = config.frame_bit_budget;
+    if let Some(budget) = budget {
+        shine_apply_frame_bit_budget(config, budget)?;
+    }
+
+    config.frame_number += 1;
 
     // Start frame data collection
     #[cfg(feature = "diagnostics")]
-    crate::diagnostics::start_frame_collection(frame_num);
+    crate::diagnostics::start_frame_collection(config.frame_number);
 
-    // Dynamic padding calculation (matches shine exactly)
-    if config.mpeg.frac_slots_per_frame != 0.0 {
-        config.mpeg.padding = if config.mpeg.slot_lag <= (config.mpeg.frac_slots_per_frame - 1.0) {
-            1
-        } else {
-            0
-        };
-        config.mpeg.slot_lag += config.mpeg.padding as f64 - config.mpeg.frac_slots_per_frame;
-    }
+    if budget.is_none() {
+        // Dynamic padding calculation (matches shine exactly)
+        if config.mpeg.frac_slots_per_frame != 0.0 {
+            config.mpeg.padding =
+                shine_next_padding(&mut config.mpeg.slot_lag, config.mpeg.frac_slots_per_frame);
+        }
 
-    config.mpeg.bits_per_frame = 8 * (config.mpeg.whole_slots_per_frame + config.mpeg.padding);
-    config.mean_bits =
-        (config.mpeg.bits_per_frame - config.sideinfo_len) / config.mpeg.granules_per_frame;
+        config.mpeg.bits_per_frame = 8 * (config.mpeg.whole_slots_per_frame + config.mpeg.padding);
+        config.mean_bits =
+            (config.mpeg.bits_per_frame - config.sideinfo_len) / config.mpeg.granules_per_frame;
+    }
 
     // Apply mdct to the polyphase output
     crate::mdct::shine_mdct_sub(config, stride);
+    crate::mdct::shine_spectrum_tap(config);
+    crate::mdct::shine_mdct_lowpass(config);
+    crate::mdct::shine_ms_stereo(config);
 
     // Bit and noise allocation
     crate::quantization::shine_iteration_loop(config);
 
     // Write the frame to the bitstream
     crate::bitstream::format_bitstream(config)?;
+    config.mpeg.bitrate_index = bitrate_index;
 
     // Return data exactly as shine does: return current data_position and reset it
     let written = config.bs.data_position as usize;
@@ -294,15 +523,54 @@ fn shine_encode_buffer_internal(
     Ok((&config.bs.data[..written], written))
 }
 
+/// Size the next frame from `config.frame_bit_budget` instead of the bitrate
+///
+/// The header gets the smallest standard bitrate whose frame holds the
+/// budget, without padding; the padding phase of the CBR frames is left
+/// alone. The granules share the budget the way they share a CBR frame and
+/// the bits the rounded-up frame adds are stuffed.
+fn shine_apply_frame_bit_budget(config: &mut ShineGlobalConfig, budget: i32) -> EncodingResult<()> {
+    let (bitrate_index, frame_bits) =
+        shine_frame_for_budget(config, budget).map_err(EncodingError::InvalidConfig)?;
+    if !config.frame_bit_budget_sticky {
+        config.frame_bit_budget = None;
+    }
+
+    let granules = config.mpeg.granules_per_frame;
+    let channels = config.wave.channels;
+    config.mpeg.padding = 0;
+    config.mpeg.bitrate_index = bitrate_index;
+    config.mpeg.bits_per_frame = frame_bits;
+    // A multiple of the channel count, so the per-channel share is exact
+    config.mean_bits = (budget - config.sideinfo_len) / granules / channels * channels;
+    config.resv_size += frame_bits - config.sideinfo_len - config.mean_bits * granules;
+    Ok(())
+}
+
 /// Encode buffer with separate channel arrays (matches shine_encode_buffer)
 /// (ref/shine/src/lib/layer3.c:160-167)
+///
+/// Like shine, each pointer must reference `shine_samples_per_pass` samples.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn shine_encode_buffer<'a>(
     config: &'a mut ShineGlobalConfig,
     data: &[*const i16],
 ) -> EncodingResult<(&'a [u8], usize)> {
-    config.buffer[0] = data[0] as *mut i16;
-    if config.wave.channels == 2 {
-        config.buffer[1] = data[1] as *mut i16;
+    let samples = shine_samples_per_pass(config) as usize;
+    let channels = config.wave.channels as usize;
+
+    // Scale to the full-scale representation the filterbank works on
+    config.pcm_buffer.clear();
+    for &channel in data.iter().take(channels) {
+        let channel = unsafe { core::slice::from_raw_parts(channel, samples) };
+        config
+            .pcm_buffer
+            .extend(channel.iter().map(|&sample| sample.to_internal()));
+    }
+
+    for ch in 0..channels {
+        config.buffer[ch] = config.pcm_buffer[ch * samples..].as_ptr();
+        config.buffer_len[ch] = samples;
     }
 
     shine_encode_buffer_internal(config, 1)
@@ -316,21 +584,133 @@ pub fn shine_encode_buffer<'a>(
 /// This function is unsafe because it accepts a raw pointer to PCM data.
 /// The caller must ensure that:
 /// - `data` points to valid PCM samples
-/// - The data contains at least `GRANULE_SIZE * channels` samples
+/// - The data contains at least `shine_samples_per_pass * channels` samples
 /// - The data remains valid for the duration of the function call
 /// - The pointer is properly aligned for i16 access
+///
+/// Prefer [`shine_encode_buffer_interleaved_safe`], which takes a slice.
 pub unsafe fn shine_encode_buffer_interleaved(
     config: &mut ShineGlobalConfig,
     data: *const i16,
 ) -> EncodingResult<(&[u8], usize)> {
-    config.buffer[0] = data as *mut i16;
-    if config.wave.channels == 2 {
-        config.buffer[1] = data.offset(1) as *mut i16;
+    let samples = shine_samples_per_pass(config) as usize * config.wave.channels as usize;
+    let data = core::slice::from_raw_parts(data, samples);
+    shine_encode_buffer_interleaved_safe(config, data)
+}
+
+/// Encode one frame of interleaved 16-bit samples
+///
+/// Safe counterpart of [`shine_encode_buffer_interleaved`]: `data` must hold
+/// at least `shine_samples_per_pass * channels` samples, otherwise
+/// `EncodingError::InvalidInputLength` is returned and nothing is encoded.
+pub fn shine_encode_buffer_interleaved_safe<'a>(
+    config: &'a mut ShineGlobalConfig,
+    data: &[i16],
+) -> EncodingResult<(&'a [u8], usize)> {
+    // Scale to the full-scale representation the filterbank works on
+    let mut pcm = core::mem::take(&mut config.pcm_buffer);
+    pcm.clear();
+    pcm.extend(data.iter().map(|&sample| sample.to_internal()));
+
+    let result = set_interleaved_buffers(config, &pcm).map(|_| ());
+    config.pcm_buffer = pcm;
+    result?;
+
+    shine_encode_buffer_internal(config, config.wave.channels)
+}
+
+/// Encode one frame of channels picked out of a wider interleaved buffer
+///
+/// `data` interleaves `total_channels` channels; the encoder's channels are
+/// read from `first_channel` on, walking the buffer with a stride of
+/// `total_channels` as shine's interleaved path does. Nothing is encoded
+/// and `InvalidChannelIndex` is returned when the encoder's channels run
+/// past `total_channels`, `InvalidDataLength` when `data` is not a whole
+/// number of sample frames, and `InvalidInputLength` when it holds fewer
+/// than `shine_samples_per_pass` of them.
+pub fn shine_encode_buffer_interleaved_strided<'a>(
+    config: &'a mut ShineGlobalConfig,
+    data: &[i16],
+    total_channels: usize,
+    first_channel: usize,
+) -> EncodingResult<(&'a [u8], usize)> {
+    check_strided_input(config, data.len(), total_channels, first_channel)?;
+    let samples = shine_samples_per_pass(config) as usize * total_channels;
+
+    // Scale to the full-scale representation the filterbank works on
+    let mut pcm = core::mem::take(&mut config.pcm_buffer);
+    pcm.clear();
+    pcm.extend(data[..samples].iter().map(|&sample| sample.to_internal()));
+    for ch in 0..config.wave.channels as usize {
+        let start = first_channel + ch;
+        config.buffer[ch] = pcm[start..].as_ptr();
+        config.buffer_len[ch] = pcm.len() - start;
+    }
+    config.pcm_buffer = pcm;
+
+    shine_encode_buffer_internal(config, total_channels as i32)
+}
+
+/// Check the layout of a buffer for [`shine_encode_buffer_interleaved_strided`]
+pub(crate) fn check_strided_input(
+    config: &ShineGlobalConfig,
+    len: usize,
+    total_channels: usize,
+    first_channel: usize,
+) -> EncodingResult<()> {
+    let channels = config.wave.channels as usize;
+    if first_channel + channels > total_channels {
+        return Err(EncodingError::InvalidChannelIndex {
+            channel: first_channel + channels - 1,
+            max_channels: total_channels,
+        });
+    }
+    if !len.is_multiple_of(total_channels) {
+        return Err(EncodingError::InvalidDataLength {
+            expected: len.next_multiple_of(total_channels),
+            actual: len,
+        });
+    }
+    let expected = shine_samples_per_pass(config) as usize * total_channels;
+    if len < expected {
+        return Err(EncodingError::InvalidInputLength {
+            expected,
+            actual: len,
+        });
     }
+    Ok(())
+}
 
+/// Encode one frame of interleaved full-scale 32-bit samples
+///
+/// The samples use the scaling of [`PcmSample::to_internal`]; `data` must
+/// hold at least `shine_samples_per_pass * channels` samples.
+pub fn shine_encode_buffer_interleaved_i32<'a>(
+    config: &'a mut ShineGlobalConfig,
+    data: &[i32],
+) -> EncodingResult<(&'a [u8], usize)> {
+    set_interleaved_buffers(config, data)?;
     shine_encode_buffer_internal(config, config.wave.channels)
 }
 
+/// Point the channel buffers at interleaved samples
+fn set_interleaved_buffers(config: &mut ShineGlobalConfig, data: &[i32]) -> EncodingResult<()> {
+    let channels = config.wave.channels as usize;
+    let expected = shine_samples_per_pass(config) as usize * channels;
+    if data.len() < expected {
+        return Err(EncodingError::InvalidInputLength {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    for ch in 0..channels {
+        config.buffer[ch] = data[ch..].as_ptr();
+        config.buffer_len[ch] = data.len() - ch;
+    }
+    Ok(())
+}
+
 /// Flush remaining data (matches shine_flush)
 /// (ref/shine/src/lib/layer3.c:178-183)
 pub fn shine_flush(config: &mut ShineGlobalConfig) -> (&[u8], usize) {
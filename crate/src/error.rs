@@ -2,7 +2,23 @@
 //!
 //! This module defines all error types used throughout the encoder,
 //! providing detailed error information for different failure scenarios.
+//!
+//! Wrapped errors are reachable through `source()`, and every error has a
+//! stable `code()` such as `"config.unsupported_sample_rate"` for logs and
+//! telemetry. Codes name the condition, not the wrapping: a
+//! `ConfigError` has the same code whether it is returned directly or inside
+//! an `EncoderError` or `EncodingError`. Codes are never reused or renamed.
+//!
+//! `EncoderError::is_recoverable` tells callers whether the encoder can be
+//! used after an error. Recoverable errors are detected before the call
+//! changes any encoder state, so the next valid call behaves as if the
+//! failed one had not been made. The other errors mean an internal invariant
+//! was broken part way through a frame; `Mp3Encoder` is then poisoned and
+//! returns `EncoderError::Poisoned` until it is reset.
 
+use crate::mp3_encoder::StereoMode;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
 use thiserror::Error;
 
 /// Main error type for the MP3 encoder
@@ -24,9 +40,96 @@ pub enum EncoderError {
     #[error("Memory allocation error")]
     Memory,
 
-    /// Internal state consistency errors
-    #[error("Internal state error: {0}")]
-    InternalState(String),
+    /// Reading input or writing output failed
+    #[cfg(feature = "std")]
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Encoding after `finish`
+    #[error("Encoder has been finished")]
+    Finished,
+
+    /// Encoding after an unrecoverable error left the encoder in an unknown state
+    #[error("Encoder is poisoned by an earlier unrecoverable error and must be reset")]
+    Poisoned,
+
+    /// Whole-frame call while samples from earlier calls are still buffered
+    #[error("{samples} buffered samples must be encoded before whole frames")]
+    BufferedSamples { samples: usize },
+
+    /// Existing stream is not a sequence of valid frames
+    #[error("Cannot resume the existing stream: {0}")]
+    Resume(#[source] FrameError),
+
+    /// Existing stream was encoded with other parameters than the configuration
+    #[error(
+        "Cannot resume the existing stream: frame at offset {offset} is {version} {bitrate} kbps \
+         {sample_rate} Hz mode {mode}, the configuration is {expected_bitrate} kbps \
+         {expected_sample_rate} Hz mode {expected_mode}"
+    )]
+    ResumeMismatch {
+        offset: u64,
+        version: &'static str,
+        bitrate: u32,
+        sample_rate: u32,
+        mode: u8,
+        expected_bitrate: u32,
+        expected_sample_rate: u32,
+        expected_mode: u8,
+    },
+}
+
+impl EncoderError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            EncoderError::Config(err) => err.code(),
+            EncoderError::InputData(err) => err.code(),
+            EncoderError::Encoding(err) => err.code(),
+            EncoderError::Memory => "memory",
+            #[cfg(feature = "std")]
+            EncoderError::Io(_) => "io",
+            EncoderError::Finished => "state.finished",
+            EncoderError::Poisoned => "state.poisoned",
+            EncoderError::BufferedSamples { .. } => "state.buffered_samples",
+            EncoderError::Resume(_) => "resume.invalid_stream",
+            EncoderError::ResumeMismatch { .. } => "resume.mismatch",
+        }
+    }
+
+    /// Whether the call that returned this error left the encoder unchanged
+    ///
+    /// After a recoverable error the encoder is in the state it was in
+    /// before the call and the next valid call succeeds; a finished encoder
+    /// stays finished. After any other error `Mp3Encoder` is poisoned.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            EncoderError::Config(_) | EncoderError::InputData(_) => true,
+            EncoderError::Encoding(err) => err.is_recoverable(),
+            EncoderError::Memory | EncoderError::Poisoned => false,
+            #[cfg(feature = "std")]
+            EncoderError::Io(_) => true,
+            EncoderError::Finished
+            | EncoderError::BufferedSamples { .. }
+            | EncoderError::Resume(_)
+            | EncoderError::ResumeMismatch { .. } => true,
+        }
+    }
+}
+
+/// Hand the error to `io` callers, such as `Write` adapters
+///
+/// I/O errors are returned unchanged; any other error is wrapped with
+/// `io::Error::other` and can be recovered with `into_inner` and
+/// `downcast`.
+#[cfg(feature = "std")]
+impl From<EncoderError> for std::io::Error {
+    fn from(err: EncoderError) -> Self {
+        match err {
+            EncoderError::Io(err) => err,
+            err => std::io::Error::other(err),
+        }
+    }
 }
 
 /// Configuration validation errors
@@ -40,6 +143,10 @@ pub enum ConfigError {
     #[error("Unsupported bitrate: {0} kbps")]
     UnsupportedBitrate(u32),
 
+    /// MPEG-2.5 sample rate while MPEG-2.5 output is disabled
+    #[error("Sample rate {0} Hz requires MPEG-2.5, which is disabled by allow_mpeg25")]
+    Mpeg25Disabled(u32),
+
     /// Invalid channel configuration
     #[error("Invalid channel configuration")]
     InvalidChannels,
@@ -49,12 +156,61 @@ pub enum ConfigError {
     IncompatibleRateCombination {
         sample_rate: u32,
         bitrate: u32,
-        reason: String,
+        reason: &'static str,
     },
 
     /// Invalid stereo mode for channel count
     #[error("Invalid stereo mode {mode:?} for {channels} channels")]
-    InvalidStereoMode { mode: String, channels: u8 },
+    InvalidStereoMode { mode: StereoMode, channels: u8 },
+
+    /// Lowpass cutoff that would remove the whole signal
+    #[error("Invalid lowpass cutoff: {0} Hz")]
+    InvalidLowpass(u32),
+
+    /// Mid/side threshold outside 0.0..=1.0
+    #[error("Invalid mid/side threshold: {0} (must be between 0.0 and 1.0)")]
+    InvalidMsThreshold(f32),
+
+    /// Reserved or unknown emphasis value
+    #[error("Invalid emphasis value: {0}")]
+    InvalidEmphasis(i32),
+
+    /// Bitrate giving frames larger than any valid MP3 frame
+    #[error("Frame size of {slots} bytes exceeds the maximum of {max} bytes")]
+    FrameSizeTooLarge { slots: i32, max: i32 },
+
+    /// Bitrate giving frames too small for the header and side info
+    #[error(
+        "Frame of {frame_bits} bits cannot hold the {side_info_bits} bits of header and side info"
+    )]
+    FrameTooSmall {
+        frame_bits: i32,
+        side_info_bits: i32,
+    },
+
+    /// Frame bit budget smaller than header and side info or larger than any frame
+    #[error("Frame bit budget of {bits} bits is outside {min}..={max}")]
+    FrameBitBudgetOutOfRange { bits: u32, min: u32, max: u32 },
+}
+
+impl ConfigError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::UnsupportedSampleRate(_) => "config.unsupported_sample_rate",
+            ConfigError::UnsupportedBitrate(_) => "config.unsupported_bitrate",
+            ConfigError::Mpeg25Disabled(_) => "config.mpeg25_disabled",
+            ConfigError::InvalidChannels => "config.invalid_channels",
+            ConfigError::IncompatibleRateCombination { .. } => "config.incompatible_rate",
+            ConfigError::InvalidStereoMode { .. } => "config.invalid_stereo_mode",
+            ConfigError::InvalidLowpass(_) => "config.invalid_lowpass",
+            ConfigError::InvalidMsThreshold(_) => "config.invalid_ms_threshold",
+            ConfigError::InvalidEmphasis(_) => "config.invalid_emphasis",
+            ConfigError::FrameSizeTooLarge { .. } => "config.frame_size_too_large",
+            ConfigError::FrameTooSmall { .. } => "config.frame_too_small",
+            ConfigError::FrameBitBudgetOutOfRange { .. } => "config.frame_bit_budget",
+        }
+    }
 }
 
 /// Input data validation errors
@@ -64,6 +220,18 @@ pub enum InputDataError {
     #[error("Invalid PCM data length: expected {expected} samples, got {actual}")]
     InvalidLength { expected: usize, actual: usize },
 
+    /// One channel's data length differs from the others
+    #[error("Invalid length for channel {channel}: expected {expected} samples, got {actual}")]
+    InvalidChannelLength {
+        channel: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Input covers only part of a frame where a whole frame is required
+    #[error("Partial frame: have {have} samples per channel, need {need}")]
+    PartialFrame { have: usize, need: usize },
+
     /// Invalid channel count in PCM data
     #[error("Invalid channel count in PCM data: expected {expected}, got {actual}")]
     InvalidChannelCount { expected: usize, actual: usize },
@@ -75,6 +243,50 @@ pub enum InputDataError {
     /// Empty input data
     #[error("Empty input data provided")]
     EmptyInput,
+
+    /// Input ended with a partial frame while strict frame alignment is on
+    #[error("Input is not frame aligned: {remaining} samples remain in a partial frame")]
+    UnalignedInput { remaining: usize },
+
+    /// Malformed or unsupported WAV stream
+    #[error("Invalid WAV data: {0}")]
+    InvalidWav(String),
+
+    /// Malformed or unsupported AIFF or AIFF-C stream
+    #[error("Invalid AIFF data: {0}")]
+    InvalidAiff(String),
+
+    /// Raw PCM bytes that do not divide into 16-bit samples
+    #[error("{0} bytes of PCM data is not a whole number of 16-bit samples")]
+    OddByteCount(usize),
+
+    /// Requested range starts at or after the end of the input
+    #[error("Range starts at sample {start} but the input has only {available} samples")]
+    RangeOutOfBounds { start: u64, available: u64 },
+
+    /// Caller-provided output buffer cannot hold the next frame
+    #[error("Output buffer too small: {needed} bytes needed")]
+    BufferTooSmall { needed: usize },
+}
+
+impl InputDataError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            InputDataError::InvalidLength { .. } => "input.invalid_length",
+            InputDataError::InvalidChannelLength { .. } => "input.invalid_channel_length",
+            InputDataError::PartialFrame { .. } => "input.partial_frame",
+            InputDataError::InvalidChannelCount { .. } => "input.invalid_channel_count",
+            InputDataError::InvalidSamples => "input.invalid_samples",
+            InputDataError::EmptyInput => "input.empty",
+            InputDataError::UnalignedInput { .. } => "input.unaligned",
+            InputDataError::InvalidWav(_) => "input.invalid_wav",
+            InputDataError::InvalidAiff(_) => "input.invalid_aiff",
+            InputDataError::OddByteCount(_) => "input.odd_byte_count",
+            InputDataError::RangeOutOfBounds { .. } => "input.range_out_of_bounds",
+            InputDataError::BufferTooSmall { .. } => "input.buffer_too_small",
+        }
+    }
 }
 
 /// Encoding process errors
@@ -84,21 +296,13 @@ pub enum EncodingError {
     #[error("Quantization loop failed to converge within maximum iterations")]
     QuantizationFailed,
 
-    /// Huffman encoding error
-    #[error("Huffman encoding error: {0}")]
-    HuffmanError(String),
+    /// Big values region assigned a Huffman table that has no codes
+    #[error("Huffman table {table} has no codes")]
+    HuffmanTableUnavailable { table: usize },
 
     /// Bitstream writing error
     #[error("Bitstream writing error: {0}")]
-    BitstreamError(String),
-
-    /// MDCT transform error
-    #[error("MDCT transform error: {0}")]
-    MdctError(String),
-
-    /// Subband filter error
-    #[error("Subband filter error: {0}")]
-    SubbandError(String),
+    Bitstream(#[from] BitstreamError),
 
     /// Invalid input length for processing
     #[error("Invalid input length: expected {expected} samples, got {actual}")]
@@ -118,31 +322,106 @@ pub enum EncodingError {
     )]
     BitReservoirOverflow { requested: usize, available: usize },
 
-    /// Validation error for testing and verification
-    #[error("Validation error: {0}")]
-    ValidationError(String),
+    /// Invalid low-level encoder configuration
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(#[source] ConfigError),
+
+    /// Output larger than the requested size, or too close to it for any padding
+    #[error("Cannot pad output of {size} bytes to {target} bytes")]
+    PadOutput { size: u64, target: u64 },
 }
 
-/// Specialized result types for different modules
-pub type ConfigResult<T> = std::result::Result<T, ConfigError>;
-pub type InputResult<T> = std::result::Result<T, InputDataError>;
-pub type EncodingResult<T> = std::result::Result<T, EncodingError>;
+impl EncodingError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            EncodingError::QuantizationFailed => "encoding.quantization_failed",
+            EncodingError::HuffmanTableUnavailable { .. } => "encoding.huffman_table_unavailable",
+            EncodingError::Bitstream(err) => err.code(),
+            EncodingError::InvalidInputLength { .. } => "encoding.invalid_input_length",
+            EncodingError::InvalidDataLength { .. } => "encoding.invalid_data_length",
+            EncodingError::InvalidChannelIndex { .. } => "encoding.invalid_channel_index",
+            EncodingError::BitReservoirOverflow { .. } => "encoding.bit_reservoir_overflow",
+            EncodingError::InvalidConfig(err) => err.code(),
+            EncodingError::PadOutput { .. } => "encoding.pad_output",
+        }
+    }
 
-/// Convert EncoderError to EncodingError for verification purposes
-impl From<EncoderError> for EncodingError {
-    fn from(err: EncoderError) -> Self {
-        match err {
-            EncoderError::Config(config_err) => {
-                EncodingError::ValidationError(format!("Config error: {}", config_err))
-            }
-            EncoderError::InputData(input_err) => {
-                EncodingError::ValidationError(format!("Input error: {}", input_err))
-            }
-            EncoderError::Encoding(encoding_err) => encoding_err,
-            EncoderError::Memory => EncodingError::ValidationError("Memory error".to_string()),
-            EncoderError::InternalState(msg) => {
-                EncodingError::ValidationError(format!("Internal state error: {}", msg))
-            }
+    /// Whether the error was detected before any encoder state changed
+    ///
+    /// Input and configuration checks run before the frame is touched; the
+    /// other errors stop a frame part way through the pipeline.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            EncodingError::InvalidInputLength { .. }
+            | EncodingError::InvalidDataLength { .. }
+            | EncodingError::InvalidChannelIndex { .. }
+            | EncodingError::InvalidConfig(_) => true,
+            EncodingError::QuantizationFailed
+            | EncodingError::HuffmanTableUnavailable { .. }
+            | EncodingError::Bitstream(_)
+            | EncodingError::BitReservoirOverflow { .. }
+            | EncodingError::PadOutput { .. } => false,
+        }
+    }
+}
+
+/// Invalid writes rejected by a strict bitstream writer
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum BitstreamError {
+    /// Bit count outside 1..=32
+    #[error("Invalid bit count {0}: must be between 1 and 32")]
+    InvalidBitCount(i32),
+
+    /// Value has set bits above the requested width
+    #[error("Value 0x{value:X} does not fit in {bits} bits")]
+    ValueTooWide { value: u32, bits: i32 },
+}
+
+impl BitstreamError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            BitstreamError::InvalidBitCount(_) => "bitstream.invalid_bit_count",
+            BitstreamError::ValueTooWide { .. } => "bitstream.value_too_wide",
         }
     }
 }
+
+/// Errors while parsing an encoded MP3 stream
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// No frame sync word at the expected position
+    #[error("No frame sync at offset {offset}")]
+    NoSync { offset: usize },
+
+    /// Header fields that are reserved or unsupported
+    #[error("Invalid frame header at offset {offset}: {reason}")]
+    InvalidHeader { offset: usize, reason: &'static str },
+
+    /// Not enough data for the frame or one of its parts
+    #[error("Truncated frame at offset {offset}: need {needed} bytes, {available} available")]
+    Truncated {
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
+}
+
+impl FrameError {
+    /// Stable identifier of the error condition
+    pub fn code(&self) -> &'static str {
+        match self {
+            FrameError::NoSync { .. } => "frame.no_sync",
+            FrameError::InvalidHeader { .. } => "frame.invalid_header",
+            FrameError::Truncated { .. } => "frame.truncated",
+        }
+    }
+}
+
+/// Specialized result types for different modules
+pub type ConfigResult<T> = core::result::Result<T, ConfigError>;
+pub type InputResult<T> = core::result::Result<T, InputDataError>;
+pub type EncodingResult<T> = core::result::Result<T, EncodingError>;
+pub type BitstreamResult<T> = core::result::Result<T, BitstreamError>;
+pub type FrameResult<T> = core::result::Result<T, FrameError>;
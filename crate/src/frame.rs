@@ -0,0 +1,419 @@
+//! MP3 frame parsing for encoded Layer III streams
+//!
+//! This module walks an encoded stream frame by frame and decodes the
+//! frame header and side information. It is the reading counterpart of
+//! the header and side info writer in bitstream.rs and is used by the
+//! comparison tools and the tests to inspect encoder output.
+
+use crate::constants::GRANULE_SIZE;
+use crate::encoder::{LAYER_III, MPEG_25, MPEG_I, MPEG_II};
+use crate::error::{FrameError, FrameResult};
+use crate::tables::{BITRATES, SAMPLERATES};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Size of the frame header in bytes
+pub const HEADER_SIZE: usize = 4;
+
+/// Decoded MPEG audio frame header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// MPEG version field (MPEG_I, MPEG_II or MPEG_25)
+    pub version: i32,
+    /// Layer field (LAYER_III)
+    pub layer: i32,
+    /// True when a 16 bit CRC follows the header
+    pub protected: bool,
+    /// Bitrate index (1..14)
+    pub bitrate_index: usize,
+    /// Samplerate field of the header (0..2)
+    pub samplerate_field: usize,
+    /// Padding slot present
+    pub padding: bool,
+    /// Private bit
+    pub private: bool,
+    /// Channel mode (0 stereo, 1 joint stereo, 2 dual channel, 3 mono)
+    pub mode: u8,
+    /// Mode extension
+    pub mode_ext: u8,
+    /// Copyright bit
+    pub copyright: bool,
+    /// Original bit
+    pub original: bool,
+    /// Emphasis field
+    pub emphasis: u8,
+}
+
+impl FrameHeader {
+    /// Parse the header at the start of `data`
+    ///
+    /// `offset` is only used for error reporting.
+    pub fn parse(data: &[u8], offset: usize) -> FrameResult<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(FrameError::Truncated {
+                offset,
+                needed: HEADER_SIZE,
+                available: data.len(),
+            });
+        }
+        if data[0] != 0xFF || data[1] & 0xE0 != 0xE0 {
+            return Err(FrameError::NoSync { offset });
+        }
+
+        let version = ((data[1] >> 3) & 0x03) as i32;
+        let layer = ((data[1] >> 1) & 0x03) as i32;
+        let bitrate_index = (data[2] >> 4) as usize;
+        let samplerate_field = ((data[2] >> 2) & 0x03) as usize;
+        let invalid = |reason| FrameError::InvalidHeader { offset, reason };
+
+        if version == 1 {
+            return Err(invalid("reserved MPEG version"));
+        }
+        if layer != LAYER_III {
+            return Err(invalid("not a Layer III frame"));
+        }
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return Err(invalid("free format or reserved bitrate"));
+        }
+        if samplerate_field == 3 {
+            return Err(invalid("reserved sample rate"));
+        }
+        if BITRATES[bitrate_index][version as usize] <= 0 {
+            return Err(invalid("bitrate not allowed for this MPEG version"));
+        }
+
+        Ok(Self {
+            version,
+            layer,
+            protected: data[1] & 0x01 == 0,
+            bitrate_index,
+            samplerate_field,
+            padding: (data[2] >> 1) & 0x01 == 1,
+            private: data[2] & 0x01 == 1,
+            mode: data[3] >> 6,
+            mode_ext: (data[3] >> 4) & 0x03,
+            copyright: (data[3] >> 3) & 0x01 == 1,
+            original: (data[3] >> 2) & 0x01 == 1,
+            emphasis: data[3] & 0x03,
+        })
+    }
+
+    /// Encode the header back into its four bytes
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        [
+            0xFF,
+            0xE0 | (self.version as u8) << 3 | (self.layer as u8) << 1 | !self.protected as u8,
+            (self.bitrate_index as u8) << 4
+                | (self.samplerate_field as u8) << 2
+                | (self.padding as u8) << 1
+                | self.private as u8,
+            self.mode << 6
+                | self.mode_ext << 4
+                | (self.copyright as u8) << 3
+                | (self.original as u8) << 2
+                | self.emphasis,
+        ]
+    }
+
+    /// Index into `SAMPLERATES` (same numbering as the encoder's samplerate_index)
+    pub fn samplerate_index(&self) -> usize {
+        let base = match self.version {
+            MPEG_I => 0,
+            MPEG_II => 3,
+            _ => 6,
+        };
+        base + self.samplerate_field
+    }
+
+    /// Sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        SAMPLERATES[self.samplerate_index()] as u32
+    }
+
+    /// Bitrate in kbps
+    pub fn bitrate(&self) -> u32 {
+        BITRATES[self.bitrate_index][self.version as usize] as u32
+    }
+
+    /// Number of channels coded in the frame
+    pub fn channels(&self) -> usize {
+        if self.mode == 3 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Number of granules per frame
+    pub fn granules(&self) -> usize {
+        if self.version == MPEG_I {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// PCM samples per channel carried by the frame
+    pub fn samples_per_frame(&self) -> usize {
+        GRANULE_SIZE * self.granules()
+    }
+
+    /// Side information length in bytes
+    pub fn side_info_len(&self) -> usize {
+        match (self.version == MPEG_I, self.channels()) {
+            (true, 1) => 17,
+            (true, _) => 32,
+            (false, 1) => 9,
+            (false, _) => 17,
+        }
+    }
+
+    /// Total frame length in bytes including the header
+    pub fn frame_length(&self) -> usize {
+        let coefficient = if self.version == MPEG_I { 144 } else { 72 };
+        (coefficient * self.bitrate() as usize * 1000 / self.sample_rate() as usize)
+            + self.padding as usize
+    }
+
+    /// A frame with this header and all-zero side info and main data
+    ///
+    /// Every granule codes no values, so decoders play the frame as silence.
+    /// The frame is written without CRC whatever `protected` says.
+    pub fn empty_frame(&self) -> Vec<u8> {
+        let header = FrameHeader {
+            protected: false,
+            ..*self
+        };
+        let mut frame = vec![0u8; header.frame_length()];
+        frame[..HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        frame
+    }
+
+    /// Human readable MPEG version
+    pub fn version_name(&self) -> &'static str {
+        match self.version {
+            MPEG_I => "MPEG-1",
+            MPEG_II => "MPEG-2",
+            MPEG_25 => "MPEG-2.5",
+            _ => "reserved",
+        }
+    }
+}
+
+/// One frame of an encoded stream
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    /// Position of the frame in the stream
+    pub index: usize,
+    /// Byte offset of the frame header in the stream
+    pub offset: usize,
+    /// Decoded header
+    pub header: FrameHeader,
+    /// Complete frame bytes, header included
+    pub data: &'a [u8],
+}
+
+impl Frame<'_> {
+    /// Decode the side information of this frame
+    pub fn side_info(&self) -> FrameResult<SideInfo> {
+        parse_side_info(&self.header, self.data, self.offset)
+    }
+}
+
+/// Iterator over the frames of an encoded stream
+///
+/// Iteration stops at the end of the data or at the first position that does
+/// not hold a complete, valid frame; `error` then tells why it stopped.
+#[derive(Debug, Clone)]
+pub struct FrameIter<'a> {
+    data: &'a [u8],
+    offset: usize,
+    index: usize,
+    error: Option<FrameError>,
+}
+
+impl<'a> FrameIter<'a> {
+    /// Byte offset of the next frame
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Why iteration stopped before the end of the data, if it did
+    pub fn error(&self) -> Option<&FrameError> {
+        self.error.as_ref()
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() || self.offset >= self.data.len() {
+            return None;
+        }
+
+        let rest = &self.data[self.offset..];
+        let result = FrameHeader::parse(rest, self.offset).and_then(|header| {
+            let length = header.frame_length();
+            if rest.len() < length {
+                Err(FrameError::Truncated {
+                    offset: self.offset,
+                    needed: length,
+                    available: rest.len(),
+                })
+            } else {
+                Ok((header, length))
+            }
+        });
+
+        match result {
+            Ok((header, length)) => {
+                let frame = Frame {
+                    index: self.index,
+                    offset: self.offset,
+                    header,
+                    data: &rest[..length],
+                };
+                self.offset += length;
+                self.index += 1;
+                Some(frame)
+            }
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+/// Walk the frames of an encoded stream
+pub fn iter_frames(data: &[u8]) -> FrameIter<'_> {
+    FrameIter {
+        data,
+        offset: 0,
+        index: 0,
+        error: None,
+    }
+}
+
+/// Side information of one granule of one channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GranuleSideInfo {
+    pub part2_3_length: u32,
+    pub big_values: u32,
+    pub global_gain: u32,
+    pub scalefac_compress: u32,
+    pub window_switching: bool,
+    pub block_type: u32,
+    pub mixed_block: bool,
+    pub table_select: [u32; 3],
+    pub subblock_gain: [u32; 3],
+    pub region0_count: u32,
+    pub region1_count: u32,
+    pub preflag: u32,
+    pub scalefac_scale: u32,
+    pub count1table_select: u32,
+}
+
+/// Decoded side information of a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SideInfo {
+    pub main_data_begin: u32,
+    pub private_bits: u32,
+    /// Scalefactor selection information [channel][band] (MPEG-1 only)
+    pub scfsi: [[u32; 4]; 2],
+    /// Granule information [granule][channel]
+    pub gr: [[GranuleSideInfo; 2]; 2],
+}
+
+/// MSB-first bit reader over a byte slice
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> u32 {
+        (0..bits).fold(0, |value, _| {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            self.position += 1;
+            (value << 1) | bit as u32
+        })
+    }
+
+    fn flag(&mut self) -> bool {
+        self.read(1) == 1
+    }
+}
+
+/// Decode the side information of a frame
+///
+/// `frame` starts at the frame header; `offset` is only used for error
+/// reporting. Both the MPEG-1 and the MPEG-2/2.5 layouts are supported.
+pub fn parse_side_info(header: &FrameHeader, frame: &[u8], offset: usize) -> FrameResult<SideInfo> {
+    let start = HEADER_SIZE + if header.protected { 2 } else { 0 };
+    let needed = start + header.side_info_len();
+    if frame.len() < needed {
+        return Err(FrameError::Truncated {
+            offset,
+            needed,
+            available: frame.len(),
+        });
+    }
+
+    let mpeg1 = header.version == MPEG_I;
+    let channels = header.channels();
+    let mut bits = BitReader::new(&frame[start..needed]);
+    let mut si = SideInfo::default();
+
+    if mpeg1 {
+        si.main_data_begin = bits.read(9);
+        si.private_bits = bits.read(if channels == 1 { 5 } else { 3 });
+        for scfsi in si.scfsi.iter_mut().take(channels) {
+            for band in scfsi.iter_mut() {
+                *band = bits.read(1);
+            }
+        }
+    } else {
+        si.main_data_begin = bits.read(8);
+        si.private_bits = bits.read(if channels == 1 { 1 } else { 2 });
+    }
+
+    for granule in si.gr.iter_mut().take(header.granules()) {
+        for gi in granule.iter_mut().take(channels) {
+            gi.part2_3_length = bits.read(12);
+            gi.big_values = bits.read(9);
+            gi.global_gain = bits.read(8);
+            gi.scalefac_compress = bits.read(if mpeg1 { 4 } else { 9 });
+            gi.window_switching = bits.flag();
+            if gi.window_switching {
+                gi.block_type = bits.read(2);
+                gi.mixed_block = bits.flag();
+                for table in gi.table_select.iter_mut().take(2) {
+                    *table = bits.read(5);
+                }
+                for gain in gi.subblock_gain.iter_mut() {
+                    *gain = bits.read(3);
+                }
+            } else {
+                for table in gi.table_select.iter_mut() {
+                    *table = bits.read(5);
+                }
+                gi.region0_count = bits.read(4);
+                gi.region1_count = bits.read(3);
+            }
+            if mpeg1 {
+                gi.preflag = bits.read(1);
+            }
+            gi.scalefac_scale = bits.read(1);
+            gi.count1table_select = bits.read(1);
+        }
+    }
+
+    Ok(si)
+}
@@ -7,8 +7,8 @@
 pub type HuffBits = u16;
 
 /// Constants from shine (matches huffman.c exactly)
-pub const DMASK: HuffBits = 1 << (((std::mem::size_of::<HuffBits>()) << 3) - 1);
-pub const HS: u32 = (std::mem::size_of::<HuffBits>() << 3) as u32;
+pub const DMASK: HuffBits = 1 << (((core::mem::size_of::<HuffBits>()) << 3) - 1);
+pub const HS: u32 = (core::mem::size_of::<HuffBits>() << 3) as u32;
 
 /// Huffman table 1 codes (matches shine's t1HB)
 const T1HB: [HuffBits; 4] = [1, 1, 1, 0];
@@ -552,3 +552,15 @@ pub const SHINE_HUFFMAN_TABLE: [HuffCodeTab; HTN] = [
         hlen: Some(&T33L),
     },
 ];
+
+/// Number of tables that can code big values; the rest code count1 quadruples
+pub const BIGV_TABLES: usize = 32;
+
+/// Whether `table` can be selected for a big values region
+///
+/// Table 0 marks a region of zeros and codes nothing. Tables 4 and 14 are
+/// not defined by the standard and have no codes.
+pub fn is_bigv_table_available(table: u32) -> bool {
+    let table = table as usize;
+    table == 0 || (table < BIGV_TABLES && SHINE_HUFFMAN_TABLE[table].hb.is_some())
+}
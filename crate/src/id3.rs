@@ -0,0 +1,97 @@
+//! ID3v2 tag carrying the ReplayGain results
+//!
+//! The gain is only known once the whole input has been analysed, so the tag
+//! has a fixed size: reserve it at the start of the output with
+//! `replaygain_tag(None)`, encode, then rewrite it with
+//! `patch_replaygain_tag`. The results are stored as ID3v2.3 `TXXX` frames
+//! named `REPLAYGAIN_TRACK_GAIN` and `REPLAYGAIN_TRACK_PEAK`, the form most
+//! players read; the rest of the tag is padding.
+//!
+//! A tag holding nothing but padding also serves to fill an output up to a
+//! fixed size, since players skip it.
+
+use crate::replaygain::ReplayGain;
+#[cfg(not(feature = "std"))]
+use alloc::{format, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Size of the tag in bytes, header included
+pub const REPLAYGAIN_TAG_LEN: usize = 128;
+
+/// Size of the tag header and of a frame header
+const HEADER_LEN: usize = 10;
+
+/// Smallest possible tag: a header with no frames and no padding
+pub const MIN_TAG_LEN: usize = HEADER_LEN;
+
+/// Build the tag, empty (padding only) when there is no result yet
+pub fn replaygain_tag(gain: Option<&ReplayGain>) -> Vec<u8> {
+    let mut tag = Vec::with_capacity(REPLAYGAIN_TAG_LEN);
+    tag.extend_from_slice(b"ID3\x03\x00\x00");
+    tag.extend_from_slice(&syncsafe((REPLAYGAIN_TAG_LEN - HEADER_LEN) as u32));
+
+    if let Some(gain) = gain {
+        txxx_frame(
+            &mut tag,
+            "REPLAYGAIN_TRACK_GAIN",
+            &format!("{:+.2} dB", gain.track_gain),
+        );
+        txxx_frame(
+            &mut tag,
+            "REPLAYGAIN_TRACK_PEAK",
+            &format!("{:.6}", gain.track_peak),
+        );
+    }
+
+    // Both values have a bounded length, so the frames always fit
+    debug_assert!(tag.len() <= REPLAYGAIN_TAG_LEN);
+    tag.resize(REPLAYGAIN_TAG_LEN, 0);
+    tag
+}
+
+/// Empty ID3v2.3 tag of `len` bytes, header included, holding only padding
+///
+/// `len` is raised to `MIN_TAG_LEN` if it is smaller.
+pub fn padding_tag(len: usize) -> Vec<u8> {
+    let len = len.max(MIN_TAG_LEN);
+    let mut tag = Vec::with_capacity(len);
+    tag.extend_from_slice(b"ID3\x03\x00\x00");
+    tag.extend_from_slice(&syncsafe((len - HEADER_LEN) as u32));
+    tag.resize(len, 0);
+    tag
+}
+
+/// Rewrite the reserved tag at the start of `writer` with `gain`
+///
+/// The position of `writer` is restored afterwards.
+#[cfg(feature = "std")]
+pub fn patch_replaygain_tag<W: Write + Seek>(writer: &mut W, gain: &ReplayGain) -> io::Result<()> {
+    let position = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(&replaygain_tag(Some(gain)))?;
+    writer.seek(SeekFrom::Start(position))?;
+    Ok(())
+}
+
+/// Append a user defined text frame with an ISO-8859-1 description and value
+fn txxx_frame(tag: &mut Vec<u8>, description: &str, value: &str) {
+    let size = 1 + description.len() + 1 + value.len();
+    tag.extend_from_slice(b"TXXX");
+    tag.extend_from_slice(&(size as u32).to_be_bytes());
+    tag.extend_from_slice(&[0, 0]);
+    tag.push(0);
+    tag.extend_from_slice(description.as_bytes());
+    tag.push(0);
+    tag.extend_from_slice(value.as_bytes());
+}
+
+/// 28-bit size with the top bit of every byte clear
+fn syncsafe(size: u32) -> [u8; 4] {
+    [
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]
+}
@@ -4,50 +4,96 @@
 //! This library provides a complete MP3 Layer III encoding solution with
 //! support for various sample rates, bitrates, and channel configurations.
 //!
+//! ## Thread safety
+//!
+//! Encoders are `Send` but not `Sync`: move one into a worker thread, or put
+//! it in a `Mutex` to share it. `Mp3EncoderPool` is `Sync` and hands out
+//! encoders to many threads. Frame numbers, which label the data the
+//! diagnostics capture, are counted per encoder.
+//!
+//! ## `no_std`
+//!
+//! With `default-features = false, features = ["no_std"]` the core encoder
+//! (`Mp3Encoder` and the shine pipeline underneath it) builds against `core`
+//! and `alloc` only, using `libm` for floating-point maths. The WAV/AIFF
+//! readers, `io` helpers, pools, batch and streaming pipelines need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "no_std")))]
+compile_error!("enable either the `std` (default) or the `no_std` feature of shine-rs");
+
+extern crate alloc;
 
+#[cfg(feature = "std")]
+pub mod aiff;
+#[cfg(feature = "std")]
+pub mod batch;
 pub mod bitstream;
+#[cfg(feature = "std")]
+pub mod buffer_pool;
+pub mod bytes;
+pub mod constants;
 pub mod encoder;
 pub mod error;
+pub mod frame;
 pub mod huffman;
+pub mod id3;
+#[cfg(not(feature = "std"))]
+mod math;
 pub mod mdct;
 pub mod mp3_encoder;
+#[cfg(feature = "std")]
+pub mod parallel;
+pub mod pcm;
+#[cfg(feature = "std")]
+pub mod pool;
 pub mod quantization;
+pub mod replaygain;
 pub mod reservoir;
+#[cfg(feature = "std")]
+pub mod stream;
 pub mod subband;
 pub mod tables;
 pub mod types;
+#[cfg(feature = "std")]
+pub mod wav;
+#[cfg(feature = "std")]
+pub mod xing;
 
+#[cfg(feature = "async")]
+pub mod async_writer;
 #[cfg(feature = "diagnostics")]
 pub mod diagnostics;
-
-// Re-export diagnostics functions for backward compatibility
 #[cfg(feature = "diagnostics")]
-pub use diagnostics::{get_current_frame_number, get_next_frame_number, reset_frame_counter};
-
-// Stub functions when diagnostics feature is not enabled
-#[cfg(not(feature = "diagnostics"))]
-pub fn reset_frame_counter() {}
-
-#[cfg(not(feature = "diagnostics"))]
-pub fn get_next_frame_number() -> i32 {
-    1
-}
-
-#[cfg(not(feature = "diagnostics"))]
-pub fn get_current_frame_number() -> i32 {
-    1
-}
+pub mod diagnostics_data;
 
 // Re-export high-level interface (recommended for most users)
+#[cfg(feature = "std")]
+pub use mp3_encoder::encode_wav_to_mp3;
 pub use mp3_encoder::{
-    encode_pcm_to_mp3, Mp3Encoder, Mp3EncoderConfig, StereoMode, SUPPORTED_BITRATES,
-    SUPPORTED_SAMPLE_RATES,
+    encode_pcm_to_mp3, encode_pcm_to_mp3_with_progress, encoded_duration, estimated_output_bytes,
+    frames_for_samples, mpeg_version_for, supported_bitrates_for, Effort, Emphasis, EncodeOutcome,
+    EncodeRange, EncodeSummary, EncoderStats, FlushMode, MonoSource, Mp3Encoder, Mp3EncoderConfig,
+    Progress, StereoMode, SUPPORTED_BITRATES, SUPPORTED_SAMPLE_RATES,
 };
 
 // Re-export low-level interface (for advanced users)
+#[cfg(feature = "std")]
+pub use buffer_pool::BufferPool;
 pub use encoder::{
-    shine_close, shine_encode_buffer_interleaved, shine_flush, shine_initialise,
-    shine_set_config_mpeg_defaults, ShineConfig, ShineMpeg, ShineWave,
+    shine_close, shine_encode_buffer_interleaved, shine_encode_buffer_interleaved_safe,
+    shine_flush, shine_initialise, shine_set_config_mpeg_defaults, ShineConfig, ShineMpeg,
+    ShineWave,
+};
+pub use error::{
+    BitstreamError, ConfigError, EncoderError, EncodingError, EncodingResult, FrameError,
+    InputDataError,
 };
-pub use error::{ConfigError, EncoderError, EncodingError, EncodingResult, InputDataError};
-pub use types::ShineGlobalConfig;
+#[cfg(feature = "std")]
+pub use pool::{Mp3EncoderPool, PoolMetrics, PooledEncoder};
+pub use replaygain::ReplayGain;
+pub use reservoir::GranuleBits;
+#[cfg(feature = "std")]
+pub use stream::{OverrunPolicy, StreamEncoder, StreamProducer};
+pub use types::{ShineGlobalConfig, SpectrumFrame};
@@ -0,0 +1,61 @@
+//! Floating-point methods for `no_std` builds
+//!
+//! `core` does not provide the transcendental and rounding methods of `f64`;
+//! this trait supplies them from `libm` under the same names, so the encoder
+//! modules import it under `cfg(not(feature = "std"))` and call `x.sqrt()`
+//! either way. With `std` the inherent methods are used and the output is
+//! unchanged.
+
+pub(crate) trait Float: Sized {
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn ln(self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn exp2(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+}
+
+impl Float for f64 {
+    fn floor(self) -> Self {
+        libm::floor(self)
+    }
+    fn ceil(self) -> Self {
+        libm::ceil(self)
+    }
+    fn round(self) -> Self {
+        libm::round(self)
+    }
+    fn sqrt(self) -> Self {
+        libm::sqrt(self)
+    }
+    fn sin(self) -> Self {
+        libm::sin(self)
+    }
+    fn cos(self) -> Self {
+        libm::cos(self)
+    }
+    fn ln(self) -> Self {
+        libm::log(self)
+    }
+    fn log2(self) -> Self {
+        libm::log2(self)
+    }
+    fn log10(self) -> Self {
+        libm::log10(self)
+    }
+    fn exp2(self) -> Self {
+        libm::exp2(self)
+    }
+    fn powf(self, n: Self) -> Self {
+        libm::pow(self, n)
+    }
+    fn powi(self, n: i32) -> Self {
+        libm::pow(self, n as f64)
+    }
+}
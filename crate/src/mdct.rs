@@ -5,12 +5,12 @@
 //! The implementation strictly follows the shine reference implementation
 //! in ref/shine/src/lib/l3mdct.c
 
-use crate::types::{ShineGlobalConfig, GRANULE_SIZE, SBLIMIT};
+use crate::constants::{GRANULE_SIZE, SUBBAND_COUNT};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::tables::{SHINE_MDCT_COS_L, SHINE_SCALE_FACT_BAND_INDEX};
+use crate::types::{ShineGlobalConfig, SpectrumFrame, SBLIMIT};
 use lazy_static::lazy_static;
-use std::f64::consts::PI;
-
-/// PI/36 constant for MDCT calculations (matches shine PI36)
-const PI36: f64 = PI / 36.0;
 
 /// Aliasing reduction coefficients (matches shine's MDCT_CA and MDCT_CS macros)
 /// These are table B.9 coefficients for aliasing reduction from the ISO standard
@@ -91,23 +91,34 @@ fn cmuls(are: i32, aim: i32, bre: i32, bim: i32) -> (i32, i32) {
 /// Initialize MDCT coefficients
 /// Corresponds to shine_mdct_initialise() in l3mdct.c
 ///
-/// Prepares the MDCT coefficients by combining window and MDCT coefficients
-/// into a single table, scaled and converted to fixed point.
-pub fn shine_mdct_initialise(config: &mut ShineGlobalConfig) {
-    // Prepare the MDCT coefficients (matches shine implementation exactly)
-    (0..18).rev().for_each(|m| {
-        // m from 17 down to 0 (matches shine: for (m = 18; m--;))
-        (0..36).rev().for_each(|k| {
-            // k from 35 down to 0 (matches shine: for (k = 36; k--;))
-            // Combine window and MDCT coefficients into a single table
-            // Scale and convert to fixed point before storing
-            // (matches shine formula exactly)
-            config.mdct.cos_l[m][k] = ((PI36 * (k as f64 + 0.5)).sin()
-                * ((PI / 72.0) * (2 * k + 19) as f64 * (2 * m + 1) as f64).cos()
-                * 0x7fffffff as f64) as i32;
-        });
-    });
+/// The windowed cosine table no longer lives in the encoder state: all
+/// encoders share [`SHINE_MDCT_COS_L`], which this computes ahead of the
+/// first frame if no encoder has yet.
+pub fn shine_mdct_initialise(_config: &mut ShineGlobalConfig) {
+    lazy_static::initialize(&SHINE_MDCT_COS_L);
 }
+
+/// Hand the spectra `shine_mdct_sub` just produced to the caller's tap
+///
+/// Does nothing without a tap. The tap only borrows the coefficients, so it
+/// cannot change what is encoded.
+pub fn shine_spectrum_tap(config: &mut ShineGlobalConfig) {
+    let Some(tap) = config.spectrum_tap.as_mut() else {
+        return;
+    };
+    for gr in 0..config.mpeg.granules_per_frame as usize {
+        for ch in 0..config.wave.channels as usize {
+            (tap.0)(SpectrumFrame {
+                frame: config.frame_number,
+                granule: gr,
+                channel: ch,
+                sample_rate: config.wave.samplerate,
+                coefficients: &config.mdct_freq[ch][gr],
+            });
+        }
+    }
+}
+
 /// MDCT subband analysis
 /// Corresponds to shine_mdct_sub() in l3mdct.c
 ///
@@ -117,9 +128,10 @@ pub fn shine_mdct_initialise(config: &mut ShineGlobalConfig) {
 /// 3. Aliasing reduction butterfly operations
 pub fn shine_mdct_sub(config: &mut ShineGlobalConfig, stride: i32) {
     #[cfg(feature = "diagnostics")]
-    let frame_num = crate::get_current_frame_number();
+    let frame_num = config.frame_number;
 
     let mut mdct_in = [0i32; 36];
+    let cos_l = &*SHINE_MDCT_COS_L;
 
     // Process each channel (matches shine: for (ch = config->wave.channels; ch--;))
     for ch in (0..config.wave.channels).rev() {
@@ -129,65 +141,39 @@ pub fn shine_mdct_sub(config: &mut ShineGlobalConfig, stride: i32) {
         for gr in 0..config.mpeg.granules_per_frame {
             let gr_idx = gr as usize;
 
-            // Polyphase filtering (matches shine implementation exactly)
-            // for (k = 0; k < 18; k += 2)
-            for k in (0..18).step_by(2) {
-                // Create a fresh buffer reference for each k iteration
-                // This is critical - we need to track the buffer pointer correctly
-                let buffer_slice =
-                    unsafe { std::slice::from_raw_parts(config.buffer[ch_idx], GRANULE_SIZE) };
-                let mut buffer_ref = buffer_slice;
-
-                // First subband filtering call - directly write to l3_sb_sample
-                // shine_window_filter_subband(&config->buffer[ch], &config->l3_sb_sample[ch][gr + 1][k][0], ch, config, stride);
-                crate::subband::shine_window_filter_subband(
-                    &mut buffer_ref,
-                    &mut config.l3_sb_sample[ch_idx][gr_idx + 1][k],
-                    ch_idx,
-                    &mut config.subband,
-                    stride as usize,
-                );
-
-                // Record l3_sb_sample for test collection (after first subband filtering)
-                #[cfg(feature = "diagnostics")]
-                {
-                    let debug_frames = std::env::var("RUST_MP3_DEBUG_FRAMES")
-                        .unwrap_or_else(|_| "6".to_string())
-                        .parse::<i32>()
-                        .unwrap_or(6);
-                    if frame_num <= debug_frames && ch == 0 && gr == 0 && k == 0 {
-                        let sample_value = config.l3_sb_sample[ch_idx][gr_idx + 1][k][0];
-                        crate::diagnostics::record_sb_sample(ch_idx, sample_value);
-                    }
-                }
-
-                // Second subband filtering call - directly write to l3_sb_sample
-                // CRITICAL: Use the updated buffer_ref from the first call
-                // shine_window_filter_subband(&config->buffer[ch], &config->l3_sb_sample[ch][gr + 1][k + 1][0], ch, config, stride);
-                crate::subband::shine_window_filter_subband(
-                    &mut buffer_ref,
-                    &mut config.l3_sb_sample[ch_idx][gr_idx + 1][k + 1],
-                    ch_idx,
-                    &mut config.subband,
-                    stride as usize,
-                );
-
-                // Update the main buffer pointer to reflect the consumed samples
-                // This is critical - we need to advance the buffer pointer for the next k iteration
-                // In shine, the buffer pointer is automatically advanced by the subband filter calls
-                config.buffer[ch_idx] = buffer_ref.as_ptr() as *mut i16;
-
-                // Compensate for inversion in the analysis filter
-                // (every odd index of band AND k) - matches shine exactly
-                for band in (1..32).step_by(2) {
-                    // band = 1, 3, 5, ..., 31
-                    config.l3_sb_sample[ch_idx][gr_idx + 1][k + 1][band] *= -1;
+            // Polyphase filtering of the granule's 18 time slots, with the
+            // inversion compensated (matches shine: for (k = 0; k < 18; k += 2))
+            let buffer = unsafe {
+                core::slice::from_raw_parts(config.buffer[ch_idx], config.buffer_len[ch_idx])
+            };
+            config.l3_sb_sample[ch_idx][gr_idx + 1] = crate::subband::filter_granule_i32(
+                buffer,
+                stride as usize,
+                ch_idx,
+                &mut config.subband,
+            );
+
+            // Advance the buffer pointer past the granule as shine's filter calls do
+            let consumed = (GRANULE_SIZE * stride as usize).min(buffer.len());
+            config.buffer[ch_idx] = buffer[consumed..].as_ptr();
+            config.buffer_len[ch_idx] = buffer.len() - consumed;
+
+            // Record l3_sb_sample for test collection (first subband sample)
+            #[cfg(feature = "diagnostics")]
+            {
+                let debug_frames = std::env::var("RUST_MP3_DEBUG_FRAMES")
+                    .unwrap_or_else(|_| "6".to_string())
+                    .parse::<i32>()
+                    .unwrap_or(6);
+                if frame_num <= debug_frames && ch == 0 && gr == 0 {
+                    let sample_value = config.l3_sb_sample[ch_idx][gr_idx + 1][0][0];
+                    crate::diagnostics::record_sb_sample(ch_idx, sample_value);
                 }
             }
 
             // Perform IMDCT of 18 previous + 18 current subband samples
             // (matches shine: for (band = 0; band < 32; band++))
-            for band in 0..32 {
+            for band in 0..SUBBAND_COUNT {
                 // Prepare input for MDCT (matches shine exactly)
                 for k in (0..18).rev() {
                     // k from 17 down to 0 (matches shine: for (k = 18; k--;))
@@ -197,51 +183,62 @@ pub fn shine_mdct_sub(config: &mut ShineGlobalConfig, stride: i32) {
 
                 // Debug: Print MDCT input for first band of first frame
 
-                // Calculation of the MDCT
-                // In the case of long blocks (block_type 0,1,3) there are
-                // 36 coefficients in the time domain and 18 in the frequency domain
-                for k in (0..18).rev() {
-                    // k from 17 down to 0 (matches shine: for (k = 18; k--;))
-                    let mut vm: i32;
-
-                    // Start with the last coefficient (matches shine exactly)
-                    vm = mul0(mdct_in[35], config.mdct.cos_l[k][35]);
-
-                    // Process remaining coefficients in groups of 7 (matches shine's unrolled loop exactly)
-                    let mut j = 35;
-                    while j > 0 {
-                        if j >= 7 {
-                            vm = muladd(vm, mdct_in[j - 1], config.mdct.cos_l[k][j - 1]);
-                            vm = muladd(vm, mdct_in[j - 2], config.mdct.cos_l[k][j - 2]);
-                            vm = muladd(vm, mdct_in[j - 3], config.mdct.cos_l[k][j - 3]);
-                            vm = muladd(vm, mdct_in[j - 4], config.mdct.cos_l[k][j - 4]);
-                            vm = muladd(vm, mdct_in[j - 5], config.mdct.cos_l[k][j - 5]);
-                            vm = muladd(vm, mdct_in[j - 6], config.mdct.cos_l[k][j - 6]);
-                            vm = muladd(vm, mdct_in[j - 7], config.mdct.cos_l[k][j - 7]);
-                            j -= 7;
-                        } else {
-                            break;
+                // A silent band transforms to zeros
+                let silent = !cfg!(feature = "diagnostics") && mdct_in.iter().all(|&x| x == 0);
+                if silent {
+                    config.mdct_freq[ch_idx][gr_idx][band * 18..band * 18 + 18].fill(0);
+                } else {
+                    // Calculation of the MDCT
+                    // In the case of long blocks (block_type 0,1,3) there are
+                    // 36 coefficients in the time domain and 18 in the frequency domain
+                    for k in (0..18).rev() {
+                        // k from 17 down to 0 (matches shine: for (k = 18; k--;))
+                        let mut vm: i32;
+
+                        // Start with the last coefficient (matches shine exactly)
+                        vm = mul0(mdct_in[35], cos_l[k][35]);
+
+                        // Process remaining coefficients in groups of 7 (matches shine's unrolled loop exactly)
+                        let mut j = 35;
+                        while j > 0 {
+                            if j >= 7 {
+                                vm = muladd(vm, mdct_in[j - 1], cos_l[k][j - 1]);
+                                vm = muladd(vm, mdct_in[j - 2], cos_l[k][j - 2]);
+                                vm = muladd(vm, mdct_in[j - 3], cos_l[k][j - 3]);
+                                vm = muladd(vm, mdct_in[j - 4], cos_l[k][j - 4]);
+                                vm = muladd(vm, mdct_in[j - 5], cos_l[k][j - 5]);
+                                vm = muladd(vm, mdct_in[j - 6], cos_l[k][j - 6]);
+                                vm = muladd(vm, mdct_in[j - 7], cos_l[k][j - 7]);
+                                j -= 7;
+                            } else {
+                                break;
+                            }
                         }
-                    }
-
-                    vm = mulz(vm);
 
-                    // Store result in mdct_freq array
-                    // Note: shine accesses mdct_freq as mdct_enc[band][k] where mdct_enc = (int32_t(*)[18])config->mdct_freq[ch][gr]
-                    // This means mdct_freq[ch][gr][band*18 + k]
-                    config.mdct_freq[ch_idx][gr_idx][band * 18 + k] = vm;
-
-                    // Print key MDCT coefficients for verification (debug mode only)
-                    #[cfg(feature = "diagnostics")]
-                    {
-                        let debug_frames = std::env::var("RUST_MP3_DEBUG_FRAMES")
-                            .unwrap_or_else(|_| "6".to_string())
-                            .parse::<i32>()
-                            .unwrap_or(6);
-
-                        // Debug: Show all k values for first band
-                        if frame_num <= debug_frames && ch == 0 && gr == 0 && band == 0 && k >= 15 {
-                            crate::diagnostics::record_mdct_coeff_before_aliasing(k, vm);
+                        vm = mulz(vm);
+
+                        // Store result in mdct_freq array
+                        // Note: shine accesses mdct_freq as mdct_enc[band][k] where mdct_enc = (int32_t(*)[18])config->mdct_freq[ch][gr]
+                        // This means mdct_freq[ch][gr][band*18 + k]
+                        config.mdct_freq[ch_idx][gr_idx][band * 18 + k] = vm;
+
+                        // Print key MDCT coefficients for verification (debug mode only)
+                        #[cfg(feature = "diagnostics")]
+                        {
+                            let debug_frames = std::env::var("RUST_MP3_DEBUG_FRAMES")
+                                .unwrap_or_else(|_| "6".to_string())
+                                .parse::<i32>()
+                                .unwrap_or(6);
+
+                            // Debug: Show all k values for first band
+                            if frame_num <= debug_frames
+                                && ch == 0
+                                && gr == 0
+                                && band == 0
+                                && k >= 15
+                            {
+                                crate::diagnostics::record_mdct_coeff_before_aliasing(k, vm);
+                            }
                         }
                     }
                 }
@@ -344,4 +341,96 @@ pub fn shine_mdct_sub(config: &mut ShineGlobalConfig, stride: i32) {
             }
         }
     }
+
+    // Capture the finished spectrum for band energy analysis. The call and
+    // the diagnostics_data module only exist with the "diagnostics" feature,
+    // so normal builds pay nothing for it
+    #[cfg(feature = "diagnostics")]
+    crate::diagnostics_data::record_spectrum(config);
+}
+
+/// First MDCT line above a lowpass cutoff, rounded up to a scalefactor band boundary
+///
+/// Each of the 576 long-block lines spans samplerate / 1152 Hz. The band that
+/// contains the cutoff is kept whole, so the returned line is the start of
+/// the next long-block scalefactor band. Cutoffs at or above the last band
+/// return GRANULE_SIZE, which disables the filter.
+pub fn shine_lowpass_line(samplerate_index: i32, samplerate: i32, cutoff_hz: u32) -> usize {
+    let line = (cutoff_hz as u64 * 2 * GRANULE_SIZE as u64).div_ceil(samplerate as u64) as i32;
+    SHINE_SCALE_FACT_BAND_INDEX[samplerate_index as usize]
+        .iter()
+        .find(|&&boundary| boundary >= line)
+        .map_or(GRANULE_SIZE, |&boundary| boundary as usize)
+}
+
+/// Zero the MDCT coefficients at and above `config.lowpass_line`
+///
+/// Runs between the MDCT and quantization so the removed high frequencies
+/// cost no bits.
+pub fn shine_mdct_lowpass(config: &mut ShineGlobalConfig) {
+    let line = config.lowpass_line;
+    if line >= GRANULE_SIZE {
+        return;
+    }
+
+    for ch in 0..config.wave.channels as usize {
+        for gr in 0..config.mpeg.granules_per_frame as usize {
+            config.mdct_freq[ch][gr][line..].fill(0);
+        }
+    }
+}
+
+/// `mode_ext` of a joint stereo frame coded as mid/side, without intensity stereo
+pub const MODE_EXT_MS: i32 = 0b10;
+
+/// Similarity of the left and right MDCT coefficients of a frame
+///
+/// `2·ΣLR / (ΣL² + ΣR²)` over every granule of the frame: 1 for identical
+/// channels, -1 for inverted ones and 0 for uncorrelated ones or a silent
+/// frame. Unlike a plain correlation it also falls when the channels differ
+/// in level, which is when the side channel stops being cheap.
+pub fn shine_channel_similarity(config: &ShineGlobalConfig) -> f64 {
+    let (mut cross, mut energy) = (0.0f64, 0.0f64);
+    for gr in 0..config.mpeg.granules_per_frame as usize {
+        let (left, right) = (&config.mdct_freq[0][gr], &config.mdct_freq[1][gr]);
+        for (&l, &r) in left.iter().zip(right.iter()) {
+            let (l, r) = (l as f64, r as f64);
+            cross += l * r;
+            energy += l * l + r * r;
+        }
+    }
+    if energy == 0.0 {
+        0.0
+    } else {
+        2.0 * cross / energy
+    }
+}
+
+/// Decide between left/right and mid/side coding for a joint stereo frame
+///
+/// With `config.ms_threshold` set, a two-channel frame whose
+/// [`shine_channel_similarity`] reaches the threshold in magnitude has its
+/// MDCT coefficients replaced by `M = (L + R)/√2` and `S = (L - R)/√2`,
+/// saturating, and is flagged with [`MODE_EXT_MS`]. MP3 signals mid/side
+/// in the frame header, so the decision covers both granules of the
+/// frame. Runs between the MDCT and quantization, after the lowpass.
+pub fn shine_ms_stereo(config: &mut ShineGlobalConfig) {
+    config.mpeg.mode_ext = 0;
+    let Some(threshold) = config.ms_threshold else {
+        return;
+    };
+    if config.wave.channels != 2 || shine_channel_similarity(config).abs() < threshold as f64 {
+        return;
+    }
+
+    let scale = core::f64::consts::FRAC_1_SQRT_2;
+    let (left, right) = config.mdct_freq.split_at_mut(1);
+    for gr in 0..config.mpeg.granules_per_frame as usize {
+        for (l, r) in left[0][gr].iter_mut().zip(right[0][gr].iter_mut()) {
+            let (lf, rf) = (*l as f64, *r as f64);
+            *l = ((lf + rf) * scale) as i32;
+            *r = ((lf - rf) * scale) as i32;
+        }
+    }
+    config.mpeg.mode_ext = MODE_EXT_MS;
 }
@@ -3,13 +3,41 @@
 //! 这个模块提供了一个简单易用的高级接口，封装了底层的shine编码器实现。
 //! 它提供了Rust风格的API，同时保留了对底层低级接口的完全访问。
 
+use crate::bitstream::drain_reservoir;
+#[cfg(feature = "std")]
+use crate::buffer_pool::BufferPool;
+use crate::constants::GRANULE_SIZE;
 use crate::encoder::{
-    shine_encode_buffer_interleaved, shine_flush, shine_initialise, shine_set_config_mpeg_defaults,
-    ShineConfig, ShineMpeg, ShineWave, NONE,
+    check_strided_input, shine_encode_buffer, shine_encode_buffer_interleaved_i32,
+    shine_encode_buffer_interleaved_strided, shine_find_samplerate_index, shine_flush,
+    shine_frame_for_budget, shine_granules_per_frame, shine_initialise, shine_mpeg_version,
+    shine_next_padding, shine_reset, shine_set_config_mpeg_defaults, shine_slots_per_frame,
+    ShineConfig, ShineMpeg, ShineWave, CITT, MPEG_25, MPEG_I, MPEG_II, MU50_15, NONE,
 };
-use crate::error::{ConfigError, EncoderError, InputDataError};
-use crate::types::ShineGlobalConfig;
-use std::collections::VecDeque;
+use crate::error::{ConfigError, EncoderError, EncodingError, InputDataError};
+#[cfg(feature = "std")]
+use crate::frame::HEADER_SIZE;
+use crate::frame::{iter_frames, FrameHeader};
+use crate::id3::{padding_tag, MIN_TAG_LEN};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::mdct::shine_lowpass_line;
+use crate::pcm::{downmix_to_mono, extract_channel, ClipDetector, PcmSample};
+use crate::replaygain::{ReplayGain, ReplayGainAnalyzer};
+use crate::reservoir::{BitReservoir, GranuleBits, ReservoirUsage};
+use crate::types::{
+    PsyRatioFn, PsyRatioProvider, ShineGlobalConfig, ShineSideInfo, SpectrumFrame, SpectrumTap,
+    HAN_SIZE, MAX_CHANNELS, MAX_GRANULES,
+};
+#[cfg(feature = "std")]
+use crate::wav::WavSource;
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::ops::ControlFlow;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom, Write};
 
 /// 支持的采样率 (Hz)
 pub const SUPPORTED_SAMPLE_RATES: &[u32] = &[
@@ -18,11 +46,45 @@ pub const SUPPORTED_SAMPLE_RATES: &[u32] = &[
     32000, 44100, 48000, // MPEG 1
 ];
 
-/// 支持的比特率 (kbps)
+/// 支持的比特率 (kbps)，所有MPEG版本的合集
 pub const SUPPORTED_BITRATES: &[u32] = &[
     8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 192, 224, 256, 320,
 ];
 
+/// MPEG-1（32、44.1、48 kHz）支持的比特率 (kbps)
+pub const MPEG1_BITRATES: &[u32] = &[
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+
+/// MPEG-2（16、22.05、24 kHz）支持的比特率 (kbps)
+pub const MPEG2_BITRATES: &[u32] = &[8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// MPEG-2.5（8、11.025、12 kHz）支持的比特率 (kbps)，shine的比特率表只到64 kbps
+pub const MPEG25_BITRATES: &[u32] = &[8, 16, 24, 32, 40, 48, 56, 64];
+
+/// 采样率对应的MPEG版本（MPEG_I、MPEG_II或MPEG_25），采样率不受支持时返回None
+pub fn mpeg_version_for(sample_rate: u32) -> Option<i32> {
+    let samplerate_index = shine_find_samplerate_index(sample_rate as i32);
+    if samplerate_index < 0 {
+        None
+    } else {
+        Some(shine_mpeg_version(samplerate_index))
+    }
+}
+
+/// 某个采样率可以使用的比特率 (kbps)，按升序排列
+///
+/// 可用的比特率取决于采样率对应的MPEG版本，可以用来生成界面中的选项。
+/// 采样率不受支持时返回空切片。
+pub fn supported_bitrates_for(sample_rate: u32) -> &'static [u32] {
+    match mpeg_version_for(sample_rate) {
+        Some(MPEG_I) => MPEG1_BITRATES,
+        Some(MPEG_II) => MPEG2_BITRATES,
+        Some(MPEG_25) => MPEG25_BITRATES,
+        _ => &[],
+    }
+}
+
 /// 立体声模式
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StereoMode {
@@ -36,6 +98,95 @@ pub enum StereoMode {
     Mono = 3,
 }
 
+/// 去加重（emphasis）标志
+///
+/// 帧头中的值2为保留值，没有对应的变体。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emphasis {
+    /// 无去加重
+    #[default]
+    None = 0,
+    /// 50/15微秒
+    Emphasis50_15 = 1,
+    /// CCITT J.17
+    CcittJ17 = 3,
+}
+
+impl Emphasis {
+    /// 从底层配置的整数值转换，拒绝保留值2和其他无效值
+    pub fn validate(value: i32) -> Result<Self, ConfigError> {
+        match value {
+            NONE => Ok(Emphasis::None),
+            MU50_15 => Ok(Emphasis::Emphasis50_15),
+            CITT => Ok(Emphasis::CcittJ17),
+            _ => Err(ConfigError::InvalidEmphasis(value)),
+        }
+    }
+}
+
+/// 完成编码时对缓冲区中不足一帧的剩余样本的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushMode {
+    /// 用零填充为完整的一帧后编码
+    #[default]
+    PadWithSilence,
+    /// 丢弃剩余样本，不输出不完整的帧
+    DropRemainder,
+    /// 不编码剩余样本，将其退回给调用者
+    ///
+    /// 退回的样本可以放在下一段PCM的开头继续编码，分段编码时段间没有
+    /// 插入的静音。
+    ReturnRemainder,
+}
+
+/// 量化搜索的力度，在编码速度和比特效率之间取舍
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Effort {
+    /// 取第一个能编码该区域的Huffman码表，步长只在上一颗粒的步长附近的
+    /// 16个值中搜索（4次量化，完整搜索为7次），窗口内最粗的步长仍放不下时
+    /// 才向上继续搜索
+    ///
+    /// 相同步长下需要的比特略多，量化略粗。编码的大部分时间不在步长搜索
+    /// 上，噪声输入的编码只比`Standard`快约10%到20%（见`effort`基准测试）。
+    Fast,
+    /// 与shine一致的搜索，输出与shine逐字节相同
+    #[default]
+    Standard,
+    /// 在标准搜索的基础上尝试相邻的大值区域划分，选择比特最少的一种
+    Thorough,
+}
+
+/// 单声道编码立体声输入时使用的声道
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MonoSource {
+    /// 左右声道取平均（[`downmix_to_mono`]）
+    #[default]
+    Mix,
+    /// 只使用左声道
+    Left,
+    /// 只使用右声道
+    Right,
+}
+
+impl MonoSource {
+    /// 将交错的立体声样本转换为单声道，末尾不成对的样本被丢弃
+    pub fn to_mono(self, interleaved: &[i16]) -> Vec<i16> {
+        match self {
+            MonoSource::Mix => downmix_to_mono(interleaved),
+            MonoSource::Left => extract_channel(interleaved, 2, 0),
+            MonoSource::Right => extract_channel(interleaved, 2, 1),
+        }
+    }
+}
+
+impl TryFrom<i32> for Emphasis {
+    type Error = ConfigError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Self::validate(value)
+    }
+}
+
 /// MP3编码器配置
 #[derive(Debug, Clone)]
 pub struct Mp3EncoderConfig {
@@ -47,10 +198,99 @@ pub struct Mp3EncoderConfig {
     pub channels: u8,
     /// 立体声模式
     pub stereo_mode: StereoMode,
+    /// 联合立体声切换到中/侧（MS）编码的声道相似度阈值（0.0–1.0）
+    ///
+    /// 默认为None，不使用中/侧编码：联合立体声的每帧都按左右声道编码
+    /// （mode_ext为0），与shine的输出逐字节一致。这与约0.8的建议默认值
+    /// 不同，需要中/侧编码时用`ms_threshold(Some(0.8))`显式开启。
+    ///
+    /// 只在[`StereoMode::JointStereo`]下生效。每帧计算左右声道MDCT系数的
+    /// 相似度（见[`crate::mdct::shine_channel_similarity`]），其绝对值达到
+    /// 阈值时该帧按中/侧编码。MP3在帧头中标记中/侧编码，因此按帧而不是按
+    /// 颗粒决定。阈值越低越多的帧使用中/侧编码，比特效率更高但立体声
+    /// 宽度损失更多；1.0只对完全相同（或反相）的声道使用，0.0每帧都使用。
+    pub ms_threshold: Option<f32>,
     /// 版权标志
     pub copyright: bool,
     /// 原创标志
     pub original: bool,
+    /// 去加重标志
+    pub emphasis: Emphasis,
+    /// 是否允许输出MPEG-2.5码流（8000/11025/12000 Hz）
+    ///
+    /// MPEG-2.5不是ISO标准的一部分，其版本位为`00`，部分严格的解码器会拒绝它。
+    /// 设为false时这些采样率会在配置验证阶段被拒绝。低采样率编码必须保持为true。
+    pub allow_mpeg25: bool,
+    /// 完成编码时额外追加的静音帧数
+    ///
+    /// 部分硬件解码器会丢弃最后一帧的输出，追加静音帧可以让真实音频完整播放。
+    /// 与shine一致，刷新时最后一帧缓存中未满32位的数据不会输出，因此被截断的
+    /// 将是追加的静音帧而不是音频帧。
+    pub trailing_silence_frames: u8,
+    /// 严格帧对齐模式
+    ///
+    /// 为true时，完成编码时若缓冲区中剩余不足一帧的样本，返回
+    /// `InputDataError::UnalignedInput`错误，而不是用零填充最后一帧。
+    pub strict_frame_alignment: bool,
+    /// 削波警告阈值
+    ///
+    /// 完成编码时，若处于满幅度的输入样本比例超过该值，输出一次`log::warn!`。
+    /// 为None时不输出警告，削波统计仍然可以通过[`Mp3Encoder::stats`]获取。
+    pub clip_warning_ratio: Option<f64>,
+    /// 填充最后一帧时是否警告
+    ///
+    /// 为true时，完成编码时第一次用零填充不完整的最后一帧会输出一次
+    /// `log::warn!`，编码器重置后不再重复。填充的样本数总是记入
+    /// [`EncoderStats::padded_tail_samples`]。
+    pub padding_warning: bool,
+    /// 低通截止频率 (Hz)
+    ///
+    /// 量化前将截止频率所在缩放因子频带以上的MDCT系数置零，低比特率时
+    /// 把比特留给可听频段。为None时不滤波。
+    pub lowpass_hz: Option<u32>,
+    /// 是否进行ReplayGain响度分析
+    ///
+    /// 为true时，输入样本在编码的同时送入ReplayGain 1.0分析，完成编码后
+    /// 可通过[`Mp3Encoder::replaygain`]获取音轨增益和峰值。
+    pub replaygain: bool,
+    /// 内部随机数种子
+    ///
+    /// 预留给抖动等随机处理阶段，这些阶段将由以此为种子的确定性PRNG驱动，
+    /// 固定种子时同一输入的编码结果逐字节相同。目前编码流程与shine一致，
+    /// 没有任何随机阶段，种子不影响输出：任何种子（包括None）下同一输入的
+    /// 编码结果都逐字节相同。
+    pub rng_seed: Option<u64>,
+    /// 量化搜索力度
+    pub effort: Effort,
+    /// 是否在编码的同时累计输出的CRC32
+    ///
+    /// 为true时，每次产生输出都更新一个CRC32，可通过
+    /// [`Mp3Encoder::running_crc32`]随时获取，无需再对整个输出计算一次。
+    pub running_crc32: bool,
+    /// 比特储备库的最大字节数
+    ///
+    /// 与shine一致，默认（None）不使用储备库，每帧只使用自己的比特。
+    /// 启用后，简单的帧省下的比特留在帧尾，供之后较复杂的帧使用，
+    /// 相同比特率下音质更稳定；代价是一帧的主数据可能从前面的帧开始
+    /// （`main_data_begin`），丢失一帧会连带损坏之后引用它的帧，输出也
+    /// 会延迟到后面的帧填满该帧为止。低延迟或容易丢包的流式传输应使用
+    /// 较小的值或0。0等同于None；超过帧头字段上限的值被截断为511字节
+    /// （MPEG-1）或255字节（MPEG-2和MPEG-2.5）。
+    pub max_reservoir_bytes: Option<u16>,
+    /// 输出的目标总字节数
+    ///
+    /// 设置后，完成编码时在最后追加空帧（边信息和主数据全为零，解码为静音），
+    /// 放不下整帧的余数放入只含填充的ID3v2标签，使本编码器输出的总字节数
+    /// 恰好等于该值。最后一帧不再像shine那样截断，而是完整输出。输出已超过
+    /// 该值，或剩余字节少于一个最小的ID3v2标签且无法用空帧补齐时，
+    /// `finish`返回`EncodingError::PadOutput`错误。
+    pub pad_output_to: Option<usize>,
+    /// 单声道配置编码立体声WAV输入时使用的声道
+    ///
+    /// 只影响[`encode_wav_to_mp3`]等自动缩混的路径；直接调用
+    /// [`Mp3Encoder::encode_interleaved`]时数据须已是单声道，可用
+    /// [`MonoSource::to_mono`]转换。
+    pub mono_source: MonoSource,
 }
 
 impl Default for Mp3EncoderConfig {
@@ -60,8 +300,23 @@ impl Default for Mp3EncoderConfig {
             bitrate: 128,
             channels: 2,
             stereo_mode: StereoMode::Stereo,
+            ms_threshold: None,
             copyright: false,
             original: true,
+            emphasis: Emphasis::None,
+            allow_mpeg25: true,
+            trailing_silence_frames: 0,
+            strict_frame_alignment: false,
+            clip_warning_ratio: Some(0.001),
+            padding_warning: true,
+            lowpass_hz: None,
+            replaygain: false,
+            rng_seed: None,
+            effort: Effort::Standard,
+            running_crc32: false,
+            max_reservoir_bytes: None,
+            pad_output_to: None,
+            mono_source: MonoSource::Mix,
         }
     }
 }
@@ -96,6 +351,20 @@ impl Mp3EncoderConfig {
         self
     }
 
+    /// 设置联合立体声切换到中/侧编码的相似度阈值，None关闭中/侧编码
+    pub fn ms_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.ms_threshold = threshold;
+        self
+    }
+
+    /// 编码时实际使用的中/侧编码阈值，非联合立体声时为None
+    ///
+    /// 直接使用底层接口时赋给`ShineGlobalConfig::ms_threshold`。
+    pub fn active_ms_threshold(&self) -> Option<f32> {
+        self.ms_threshold
+            .filter(|_| self.stereo_mode == StereoMode::JointStereo)
+    }
+
     /// 设置版权标志
     pub fn copyright(mut self, copyright: bool) -> Self {
         self.copyright = copyright;
@@ -108,6 +377,169 @@ impl Mp3EncoderConfig {
         self
     }
 
+    /// 设置去加重标志
+    pub fn emphasis(mut self, emphasis: Emphasis) -> Self {
+        self.emphasis = emphasis;
+        self
+    }
+
+    /// 设置是否允许MPEG-2.5采样率
+    pub fn allow_mpeg25(mut self, allow: bool) -> Self {
+        self.allow_mpeg25 = allow;
+        self
+    }
+
+    /// 设置完成编码时追加的静音帧数
+    pub fn trailing_silence_frames(mut self, frames: u8) -> Self {
+        self.trailing_silence_frames = frames;
+        self
+    }
+
+    /// 设置严格帧对齐模式
+    pub fn strict_frame_alignment(mut self, strict: bool) -> Self {
+        self.strict_frame_alignment = strict;
+        self
+    }
+
+    /// 设置削波警告阈值
+    pub fn clip_warning_ratio(mut self, ratio: Option<f64>) -> Self {
+        self.clip_warning_ratio = ratio;
+        self
+    }
+
+    /// 设置填充最后一帧时是否警告
+    pub fn padding_warning(mut self, enabled: bool) -> Self {
+        self.padding_warning = enabled;
+        self
+    }
+
+    /// 设置低通截止频率
+    pub fn lowpass_hz(mut self, cutoff: Option<u32>) -> Self {
+        self.lowpass_hz = cutoff;
+        self
+    }
+
+    /// 设置是否进行ReplayGain分析
+    pub fn replaygain(mut self, enabled: bool) -> Self {
+        self.replaygain = enabled;
+        self
+    }
+
+    /// 设置内部随机数种子，目前不影响输出
+    pub fn rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.rng_seed = seed;
+        self
+    }
+
+    /// 设置量化搜索力度
+    pub fn effort(mut self, effort: Effort) -> Self {
+        self.effort = effort;
+        self
+    }
+
+    /// 设置是否累计输出的CRC32
+    pub fn running_crc32(mut self, enabled: bool) -> Self {
+        self.running_crc32 = enabled;
+        self
+    }
+
+    /// 设置比特储备库的最大字节数
+    pub fn max_reservoir_bytes(mut self, max_bytes: Option<u16>) -> Self {
+        self.max_reservoir_bytes = max_bytes;
+        self
+    }
+
+    /// 设置输出的目标总字节数
+    pub fn pad_output_to(mut self, size: Option<usize>) -> Self {
+        self.pad_output_to = size;
+        self
+    }
+
+    /// 设置单声道编码立体声输入时使用的声道
+    pub fn mono_source(mut self, source: MonoSource) -> Self {
+        self.mono_source = source;
+        self
+    }
+
+    /// 根据输入音频的参数创建配置
+    ///
+    /// 规则与shine一致：单声道输入总是使用单声道模式，忽略请求的立体声模式；
+    /// 立体声输入使用请求的模式。立体声输入请求单声道模式时返回单声道配置，
+    /// 调用者需要先用[`crate::pcm::downmix_to_mono`]将数据缩混为单声道。
+    /// MPEG版本由采样率决定，采样率与比特率的组合会在返回前验证。
+    pub fn for_input(
+        sample_rate: u32,
+        channels: u16,
+        bitrate: u32,
+        preferred_mode: StereoMode,
+    ) -> Result<Self, ConfigError> {
+        if channels == 0 || channels > 2 {
+            return Err(ConfigError::InvalidChannels);
+        }
+
+        let stereo_mode = if channels == 1 {
+            StereoMode::Mono
+        } else {
+            preferred_mode
+        };
+        let channels = if stereo_mode == StereoMode::Mono {
+            1
+        } else {
+            channels
+        };
+
+        let config = Self::new()
+            .sample_rate(sample_rate)
+            .bitrate(bitrate)
+            .channels(channels as u8)
+            .stereo_mode(stereo_mode);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 采样率对应的MPEG版本（MPEG_I、MPEG_II或MPEG_25），采样率不受支持时返回None
+    pub fn mpeg_version(&self) -> Option<i32> {
+        mpeg_version_for(self.sample_rate)
+    }
+
+    /// 每帧边信息的字节数，不含4字节的帧头
+    ///
+    /// MPEG-1单声道和立体声分别为17和32字节，MPEG-2和MPEG-2.5为9和17字节，
+    /// 与编码器内部的`sideinfo_len`（含帧头，以位计）一致。不受支持的采样率
+    /// 按所在的范围（24 kHz以上为MPEG-1）计算。
+    pub fn side_info_bytes(&self) -> usize {
+        let granules = if self.sample_rate > 24000 { 2 } else { 1 };
+        crate::encoder::shine_side_info_bytes(granules, self.channels as i32) as usize
+    }
+
+    /// 转换为底层shine配置
+    pub fn to_shine_config(&self) -> ShineConfig {
+        let mut mpeg = ShineMpeg {
+            mode: self.stereo_mode as i32,
+            bitr: self.bitrate as i32,
+            emph: self.emphasis as i32,
+            copyright: if self.copyright { 1 } else { 0 },
+            original: if self.original { 1 } else { 0 },
+        };
+
+        // 设置默认值
+        shine_set_config_mpeg_defaults(&mut mpeg);
+
+        // 应用用户配置
+        mpeg.mode = self.stereo_mode as i32;
+        mpeg.bitr = self.bitrate as i32;
+        mpeg.emph = self.emphasis as i32;
+        mpeg.copyright = if self.copyright { 1 } else { 0 };
+        mpeg.original = if self.original { 1 } else { 0 };
+
+        let wave = ShineWave {
+            channels: self.channels as i32,
+            samplerate: self.sample_rate as i32,
+        };
+
+        ShineConfig { wave, mpeg }
+    }
+
     /// 验证配置的有效性
     pub fn validate(&self) -> Result<(), ConfigError> {
         // 检查采样率
@@ -115,6 +547,11 @@ impl Mp3EncoderConfig {
             return Err(ConfigError::UnsupportedSampleRate(self.sample_rate));
         }
 
+        // 检查是否允许MPEG-2.5
+        if !self.allow_mpeg25 && self.sample_rate <= 12000 {
+            return Err(ConfigError::Mpeg25Disabled(self.sample_rate));
+        }
+
         // 检查比特率
         if !SUPPORTED_BITRATES.contains(&self.bitrate) {
             return Err(ConfigError::UnsupportedBitrate(self.bitrate));
@@ -125,15 +562,24 @@ impl Mp3EncoderConfig {
             return Err(ConfigError::InvalidChannels);
         }
 
+        // 检查低通截止频率
+        if self.lowpass_hz == Some(0) {
+            return Err(ConfigError::InvalidLowpass(0));
+        }
+
+        // 检查中/侧编码阈值
+        if let Some(threshold) = self.ms_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(ConfigError::InvalidMsThreshold(threshold));
+            }
+        }
+
         // 检查立体声模式与声道数的兼容性
         match (self.channels, self.stereo_mode) {
             (1, StereoMode::Mono) => {}
             (2, StereoMode::Stereo | StereoMode::JointStereo | StereoMode::DualChannel) => {}
             (channels, mode) => {
-                return Err(ConfigError::InvalidStereoMode {
-                    mode: format!("{:?}", mode),
-                    channels,
-                });
+                return Err(ConfigError::InvalidStereoMode { mode, channels });
             }
         }
 
@@ -152,19 +598,10 @@ impl Mp3EncoderConfig {
             };
 
             let reason = match mpeg_version {
-                "MPEG-2.5" => format!(
-                    "MPEG-2.5 ({}Hz) only supports bitrates up to 64 kbps",
-                    self.sample_rate
-                ),
-                "MPEG-2" => format!(
-                    "MPEG-2 ({}Hz) only supports bitrates up to 160 kbps",
-                    self.sample_rate
-                ),
-                "MPEG-1" => format!(
-                    "MPEG-1 ({}Hz) only supports bitrates from 32 to 320 kbps",
-                    self.sample_rate
-                ),
-                _ => "Invalid combination".to_string(),
+                "MPEG-2.5" => "MPEG-2.5 only supports bitrates up to 64 kbps",
+                "MPEG-2" => "MPEG-2 only supports bitrates up to 160 kbps",
+                "MPEG-1" => "MPEG-1 only supports bitrates from 32 to 320 kbps",
+                _ => "Invalid combination",
             };
 
             return Err(ConfigError::IncompatibleRateCombination {
@@ -174,11 +611,102 @@ impl Mp3EncoderConfig {
             });
         }
 
+        // 检查帧能否容纳帧头和边信息
+        crate::encoder::shine_check_side_info(
+            self.sample_rate as i32,
+            self.bitrate as i32,
+            self.channels as i32,
+        )?;
+
         Ok(())
     }
 }
 
+/// 编码统计信息
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EncoderStats {
+    /// 已编码的帧数（包含填充帧和静音帧）
+    pub frames_encoded: u64,
+    /// 每声道实际输入的样本数（不含填充的零样本）
+    pub samples_per_channel: u64,
+    /// 已输出的MP3字节数
+    pub bytes_written: u64,
+    /// 输入音频时长（由输入样本数和采样率计算）
+    pub duration: Duration,
+    /// 实际比特率 (kbps)，由输出字节数和输入时长计算，无输入时为0
+    pub actual_bitrate_kbps: f64,
+    /// 处于满幅度（削波）的输入样本数，所有声道合计
+    pub clipped_samples: u64,
+    /// 单个声道中连续削波样本的最长长度
+    pub max_consecutive_clipped: u64,
+    /// 完成编码时为补全最后一帧填充的零样本数，所有声道合计
+    pub padded_samples: u64,
+    /// 完成编码时为补全最后一帧填充的每声道零样本数，即每帧样本数减去
+    /// 缓冲区中剩余的每声道样本数；输入正好是整数帧时为0。无缝播放需要
+    /// 从解码结果末尾去掉这些样本
+    pub padded_tail_samples: u64,
+    /// 完成编码时未编码而被丢弃或退回的样本数，所有声道合计
+    pub dropped_samples: u64,
+    /// [`Mp3Encoder::flush_at_boundary`]在流中补全帧时填充的零样本数，
+    /// 所有声道合计；不计入`padded_samples`
+    pub boundary_padded_samples: u64,
+    /// 写入帧头的最大`main_data_begin`（字节），即比特储备库的最大用量
+    pub max_main_data_begin: u32,
+    /// 比特储备库当前的比特数，即下一帧可以额外使用的比特
+    pub reservoir_bits: u32,
+    /// 最后一帧每个颗粒、每个声道分配和实际使用的比特数（按`[颗粒][声道]`索引，
+    /// 未使用的颗粒和声道为0）
+    pub granule_bits: [[GranuleBits; MAX_CHANNELS]; MAX_GRANULES],
+    /// 已编码颗粒的平均噪声掩蔽比（dB），由量化误差与允许失真`l3_xmin`计算，
+    /// 负值表示噪声低于掩蔽阈值（透明）。shine没有心理声学模型，`l3_xmin`为0，
+    /// 此时以各频带能量为参考，即噪声信号比；比特率越高值越低。
+    /// 尚未编码含非零值的颗粒时为`None`
+    pub avg_nmr_db: Option<f64>,
+}
+
+/// 决定静音帧输出的编码器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SilenceKey {
+    padding: i32,
+    resv_size: i32,
+    cache: u32,
+    cache_bits: i32,
+}
+
+/// 编码一个静音帧的输出和之后的状态
+#[derive(Debug)]
+struct SilentFrame {
+    key: SilenceKey,
+    data: Vec<u8>,
+    bits_per_frame: i32,
+    mean_bits: i32,
+    resv_size: i32,
+    usage: ReservoirUsage,
+    side_info: ShineSideInfo,
+    cache: u32,
+    cache_bits: i32,
+    /// 每帧子带滤波器环形缓冲区偏移的推进量
+    off_step: [i32; MAX_CHANNELS],
+}
+
 /// 高级MP3编码器
+///
+/// # 线程安全
+///
+/// `Mp3Encoder`实现了`Send`，可以移动到其他线程继续编码：底层状态中的
+/// 裸指针只指向编码器自己的数据或当次调用的输入（见`ShineGlobalConfig`的
+/// `Send`实现）。它没有实现`Sync`，多个线程共享同一个编码器时放在
+/// `Mutex<Mp3Encoder>`中。帧编号（diagnostics特性用来标记采集的数据）由
+/// 每个编码器自己计数，移动到其他线程后继续递增。
+///
+/// # 错误恢复
+///
+/// 输入长度、声道数等检查在改变任何状态之前进行，返回的错误是可恢复的
+/// （[`EncoderError::is_recoverable`]）：编码器保持调用前的状态，下一次
+/// 有效的调用正常编码。帧编码流水线开始后出现的错误说明内部不变量被破坏，
+/// 储备库、填充位和位缓存可能已经只更新了一半，编码器因此中毒，之后的编码
+/// 调用返回`EncoderError::Poisoned`而不是输出损坏的码流，
+/// [`Mp3Encoder::reset`]后恢复可用。
 #[derive(Debug)]
 pub struct Mp3Encoder {
     /// 底层shine配置
@@ -187,10 +715,37 @@ pub struct Mp3Encoder {
     encoder_config: Mp3EncoderConfig,
     /// 每次编码需要的样本数
     samples_per_frame: usize,
-    /// 输入缓冲区
-    input_buffer: VecDeque<i16>,
+    /// 输入缓冲区（环形缓冲区，按帧从头部消费）
+    input_buffer: VecDeque<i32>,
+    /// 帧数据暂存区，仅在一帧数据跨越环形缓冲区末尾时使用
+    frame_buffer: Vec<i32>,
     /// 是否已完成编码
     finished: bool,
+    /// 是否因不可恢复的错误而不能继续使用，重置后清除
+    poisoned: bool,
+    /// 已编码的帧数
+    frames_encoded: u64,
+    /// 实际输入的样本数（交错格式总数）
+    input_samples: u64,
+    /// 已输出的字节数
+    bytes_written: u64,
+    /// 完成编码时填充的零样本数
+    padded_samples: u64,
+    /// 完成编码时丢弃或退回的样本数
+    dropped_samples: u64,
+    /// 在流中切分帧时填充的零样本数
+    boundary_padded_samples: u64,
+    /// 是否已经警告过最后一帧的填充，重置后保持不变
+    padding_warned: bool,
+    /// 输入削波统计
+    clipping: ClipDetector,
+    /// ReplayGain分析器，未启用时为None
+    replaygain: Option<ReplayGainAnalyzer>,
+    /// 已输出字节的CRC32，未启用时为None
+    running_crc: Option<crc32fast::Hasher>,
+    /// 输出帧使用的缓冲区池，未设置时每帧分配新的缓冲区
+    #[cfg(feature = "std")]
+    buffer_pool: Option<BufferPool>,
 }
 
 impl Mp3Encoder {
@@ -200,21 +755,192 @@ impl Mp3Encoder {
         config.validate()?;
 
         // 转换为shine配置
-        let shine_config = Self::create_shine_config(&config)?;
+        let shine_config = config.to_shine_config();
 
         // 初始化shine编码器
-        let global_config = shine_initialise(&shine_config).map_err(EncoderError::Encoding)?;
+        let mut global_config = shine_initialise(&shine_config).map_err(EncoderError::Encoding)?;
+
+        // 设置低通滤波的起始频率线
+        if let Some(cutoff) = config.lowpass_hz {
+            global_config.lowpass_line = shine_lowpass_line(
+                global_config.mpeg.samplerate_index,
+                global_config.wave.samplerate,
+                cutoff,
+            );
+        }
+
+        global_config.effort = config.effort;
+        global_config.ms_threshold = config.active_ms_threshold();
+
+        // 启用比特储备库
+        if let Some(max_bytes) = config.max_reservoir_bytes {
+            global_config.reservoir = BitReservoir::new(max_bytes, global_config.mpeg.version);
+            global_config.resv_max = global_config.reservoir.max_bytes() as i32 * 8;
+        }
 
         // 计算每帧需要的样本数（交错格式的总样本数）
         let samples_per_channel = crate::encoder::shine_samples_per_pass(&global_config) as usize;
         let samples_per_frame = samples_per_channel * config.channels as usize;
 
+        let clipping = ClipDetector::new(config.channels as usize);
+        // 所有支持的采样率都定义了等响度滤波器
+        let replaygain = if config.replaygain {
+            ReplayGainAnalyzer::new(config.sample_rate, config.channels as usize)
+        } else {
+            None
+        };
+
+        let running_crc = config.running_crc32.then(crc32fast::Hasher::new);
+
         Ok(Self {
             config: global_config,
             encoder_config: config,
             samples_per_frame,
-            input_buffer: VecDeque::new(),
+            // 按帧输入时缓冲区最多保存不足一帧的剩余样本加上新的一帧
+            input_buffer: VecDeque::with_capacity(samples_per_frame * 2),
+            frame_buffer: Vec::with_capacity(samples_per_frame),
             finished: false,
+            poisoned: false,
+            frames_encoded: 0,
+            input_samples: 0,
+            padded_samples: 0,
+            dropped_samples: 0,
+            boundary_padded_samples: 0,
+            padding_warned: false,
+            bytes_written: 0,
+            clipping,
+            replaygain,
+            running_crc,
+            #[cfg(feature = "std")]
+            buffer_pool: None,
+        })
+    }
+
+    /// 从缓冲区池中取得输出帧的缓冲区
+    ///
+    /// 设置后[`Mp3Encoder::encode_interleaved`]等方法返回的每一帧都写入从`pool`
+    /// 取出的缓冲区，消费者写出后用[`BufferPool::recycle`]归还，流水线稳定后
+    /// 不再为输出分配内存。池中没有空闲缓冲区时分配新的缓冲区而不会阻塞。
+    /// `reset`后仍使用同一个池。
+    #[cfg(feature = "std")]
+    pub fn with_buffer_pool(mut self, pool: BufferPool) -> Self {
+        self.buffer_pool = Some(pool);
+        self
+    }
+
+    /// 输出帧使用的缓冲区池
+    #[cfg(feature = "std")]
+    pub fn buffer_pool(&self) -> Option<&BufferPool> {
+        self.buffer_pool.as_ref()
+    }
+
+    /// 使用外部心理声学模型
+    ///
+    /// 每个颗粒量化前以(声道, 颗粒, MDCT频谱)调用`provider`，返回的21个
+    /// 长块缩放因子频带的掩蔽比代替shine全为零的掩蔽比送入calc_xmin：频带的
+    /// 允许失真为掩蔽比乘以频带能量，非正数或非有限值表示不限制该频带。
+    /// MPEG-1时据此为每个频带选择缩放因子，允许失真越小的频带放大越多、
+    /// 量化越细，允许失真大的频带量化越粗，且每个颗粒单独发送缩放因子
+    /// （不使用SCFSI）。MPEG-2和MPEG-2.5的缩放因子仍为零，掩蔽比只影响
+    /// [`EncoderStats::avg_nmr_db`]。`reset`后仍使用同一个模型。
+    pub fn set_psy_ratio_provider(&mut self, provider: Box<PsyRatioFn>) {
+        self.config.psy_ratio = Some(PsyRatioProvider(provider));
+    }
+
+    /// 移除外部心理声学模型，恢复与shine逐字节相同的输出
+    pub fn clear_psy_ratio_provider(&mut self) {
+        self.config.psy_ratio = None;
+    }
+
+    /// 观察编码器的MDCT频谱，例如用于电平表或频谱图
+    ///
+    /// 每帧MDCT完成后，对每个颗粒和声道以[`SpectrumFrame`]调用一次`tap`，
+    /// 其中包含帧号、采样率和576个MDCT系数，此时低通滤波、中/侧声道编码和
+    /// 量化尚未修改系数。`tap`只借用系数，不能改变编码结果；未设置时不产生
+    /// 任何开销。设置后[`Mp3Encoder::encode_silence_frames`]逐帧正常编码，
+    /// 使每一帧都经过`tap`。`reset`后仍使用同一个`tap`。
+    pub fn set_spectrum_tap(&mut self, tap: impl FnMut(SpectrumFrame<'_>) + Send + 'static) {
+        self.config.spectrum_tap = Some(SpectrumTap(Box::new(tap)));
+    }
+
+    /// 移除频谱观察回调
+    pub fn clear_spectrum_tap(&mut self) {
+        self.config.spectrum_tap = None;
+    }
+
+    /// 从已有MP3流的末尾继续编码
+    ///
+    /// 逐帧扫描`existing`（跳过开头的ID3v2标签），检查每一帧的MPEG版本、
+    /// 采样率、比特率和声道模式与`config`一致，然后把`existing`定位到最后一个
+    /// 完整帧之后。末尾不完整的帧（例如shine刷新时被截断的最后一帧）被丢弃，
+    /// 调用者需要在返回的位置截断文件（如`File::set_len`）再追加新的数据。
+    /// 填充位的相位（slot_lag）按已有帧数推进，接缝处的帧长与连续编码一致。
+    ///
+    /// 注意：比特储备库从空开始，接缝后的第一帧不使用之前帧留下的储备，
+    /// 与新文件的第一帧相同；已有的ID3标签（如ReplayGain）不会更新。
+    ///
+    /// # 返回值
+    /// 返回编码器和新数据的写入位置（字节偏移）
+    #[cfg(feature = "std")]
+    pub fn resume_from<R: Read + Seek>(
+        existing: &mut R,
+        config: Mp3EncoderConfig,
+    ) -> Result<(Self, u64), EncoderError> {
+        let mut encoder = Self::new(config)?;
+        let end = existing.seek(SeekFrom::End(0))?;
+        existing.seek(SeekFrom::Start(0))?;
+        let mut offset = id3v2_tag_len(existing)?;
+
+        // 只读取帧头，按帧长跳过帧数据
+        let mut frames = 0u64;
+        let mut header = [0u8; HEADER_SIZE];
+        while offset + HEADER_SIZE as u64 <= end {
+            existing.seek(SeekFrom::Start(offset))?;
+            existing.read_exact(&mut header)?;
+            let parsed =
+                FrameHeader::parse(&header, offset as usize).map_err(EncoderError::Resume)?;
+            encoder.check_resumed_header(&parsed, offset)?;
+
+            let length = parsed.frame_length() as u64;
+            if offset + length > end {
+                break;
+            }
+            offset += length;
+            frames += 1;
+        }
+
+        // 按已有帧数推进填充位相位
+        let mpeg = &mut encoder.config.mpeg;
+        if mpeg.frac_slots_per_frame != 0.0 {
+            for _ in 0..frames {
+                shine_next_padding(&mut mpeg.slot_lag, mpeg.frac_slots_per_frame);
+            }
+        }
+
+        existing.seek(SeekFrom::Start(offset))?;
+        Ok((encoder, offset))
+    }
+
+    /// 检查已有帧的参数与编码器配置一致
+    #[cfg(feature = "std")]
+    fn check_resumed_header(&self, header: &FrameHeader, offset: u64) -> Result<(), EncoderError> {
+        let mpeg = &self.config.mpeg;
+        let matches = header.version == mpeg.version
+            && header.samplerate_field == mpeg.samplerate_index as usize % 3
+            && header.bitrate_index == mpeg.bitrate_index as usize
+            && header.mode as i32 == mpeg.mode;
+        if matches {
+            return Ok(());
+        }
+        Err(EncoderError::ResumeMismatch {
+            offset,
+            version: header.version_name(),
+            bitrate: header.bitrate(),
+            sample_rate: header.sample_rate(),
+            mode: header.mode,
+            expected_bitrate: self.encoder_config.bitrate,
+            expected_sample_rate: self.encoder_config.sample_rate,
+            expected_mode: mpeg.mode as u8,
         })
     }
 
@@ -223,6 +949,73 @@ impl Mp3Encoder {
         &self.encoder_config
     }
 
+    /// 修改之后编码的帧头中的去加重标志
+    ///
+    /// 去加重只是帧头中给解码器的标志：编码器不对输入做预加重滤波，
+    /// 因此这里只改变帧头位。新值从下一个被编码的帧开始生效，
+    /// 包括由已缓冲样本组成的帧；已输出的帧不受影响。
+    pub fn set_emphasis(&mut self, emphasis: Emphasis) {
+        self.encoder_config.emphasis = emphasis;
+        self.config.mpeg.emph = emphasis as i32;
+    }
+
+    /// 由外部码率控制指定下一帧最多使用的比特数
+    ///
+    /// 设置后，下一个被编码的帧（包括由已缓冲样本组成的帧）不再按CBR计算
+    /// 帧长：帧头使用能容纳该预算的最小标准比特率，不加填充位，各颗粒按
+    /// shine分配CBR帧的方式分配预算，帧长向上取整多出的比特作为填充数据。
+    /// 编码该帧后预算自动清除，除非用[`Mp3Encoder::set_frame_bit_budget_sticky`]
+    /// 保留。预算小于帧头加边信息的长度或大于最高比特率的帧长时返回
+    /// `ConfigError::FrameBitBudgetOutOfRange`。None取消预算。
+    pub fn set_frame_bit_budget(&mut self, budget: Option<u32>) -> Result<(), EncoderError> {
+        if let Some(bits) = budget {
+            let bits = i32::try_from(bits).unwrap_or(i32::MAX);
+            shine_frame_for_budget(&self.config, bits)?;
+        }
+        self.config.frame_bit_budget = budget.map(|bits| bits as i32);
+        Ok(())
+    }
+
+    /// 设置帧比特预算是否在编码一帧后保留，用于之后的所有帧
+    pub fn set_frame_bit_budget_sticky(&mut self, sticky: bool) {
+        self.config.frame_bit_budget_sticky = sticky;
+    }
+
+    /// 尚未使用的帧比特预算
+    pub fn frame_bit_budget(&self) -> Option<u32> {
+        self.config.frame_bit_budget.map(|bits| bits as u32)
+    }
+
+    /// 将编码器恢复到刚创建时的状态
+    ///
+    /// 清空输入缓冲区、统计信息、削波和ReplayGain分析，底层shine状态通过
+    /// [`crate::encoder::shine_reset`]复位，保留已计算的表和已分配的内存。
+    /// 之后的输出与用当前配置新建的编码器逐字节相同；
+    /// [`Mp3Encoder::set_emphasis`]修改的去加重设置会保留。
+    pub fn reset(&mut self) {
+        shine_reset(&mut self.config);
+        self.input_buffer.clear();
+        self.frame_buffer.clear();
+        self.finished = false;
+        self.poisoned = false;
+        self.frames_encoded = 0;
+        self.input_samples = 0;
+        self.bytes_written = 0;
+        self.padded_samples = 0;
+        self.dropped_samples = 0;
+        self.boundary_padded_samples = 0;
+        self.clipping = ClipDetector::new(self.encoder_config.channels as usize);
+        if self.replaygain.is_some() {
+            self.replaygain = ReplayGainAnalyzer::new(
+                self.encoder_config.sample_rate,
+                self.encoder_config.channels as usize,
+            );
+        }
+        if let Some(crc) = self.running_crc.as_mut() {
+            crc.reset();
+        }
+    }
+
     /// 获取每帧需要的样本数
     pub fn samples_per_frame(&self) -> usize {
         self.samples_per_frame
@@ -235,17 +1028,50 @@ impl Mp3Encoder {
 
     /// 编码PCM音频数据（交错格式）
     ///
+    /// 输入可以是任意长度，不足一帧的样本会缓存到下一次调用（包括跨调用
+    /// 拆开的采样帧），因此每次调用返回零个或多个完整的帧。
+    ///
     /// # 参数
     /// - `pcm_data`: 交错格式的PCM数据 (左右声道交替)
     ///
     /// # 返回值
     /// 返回编码后的MP3数据块的向量
     pub fn encode_interleaved(&mut self, pcm_data: &[i16]) -> Result<Vec<Vec<u8>>, EncoderError> {
-        if self.finished {
-            return Err(EncoderError::InternalState(
-                "Encoder has been finished".to_string(),
-            ));
+        self.encode_samples(pcm_data)
+    }
+
+    /// 编码输入产生的所有完整帧，以独立拥有的缓冲区返回
+    ///
+    /// 与[`Mp3Encoder::encode_interleaved`]相同，不足一帧的余量留在缓冲区中，
+    /// 但空输入不是错误而是返回空向量，便于批处理调用方逐块累积帧。
+    ///
+    /// # 参数
+    /// - `pcm`: 交错格式的PCM数据 (左右声道交替)
+    ///
+    /// # 返回值
+    /// 按顺序返回本次完成的每一帧
+    pub fn drain_frames(&mut self, pcm: &[i16]) -> Result<Vec<Vec<u8>>, EncoderError> {
+        if pcm.is_empty() && !self.finished && !self.poisoned {
+            return Ok(Vec::new());
         }
+        self.encode_samples(pcm)
+    }
+
+    /// 编码任意样本格式的PCM音频数据（交错格式）
+    ///
+    /// 接受任何[`PcmSample`]类型（i16、i32、f32），缩放规则见该trait的文档。
+    /// i16输入与[`Mp3Encoder::encode_interleaved`]的输出完全相同。
+    ///
+    /// # 参数
+    /// - `pcm_data`: 交错格式的PCM数据 (左右声道交替)
+    ///
+    /// # 返回值
+    /// 返回编码后的MP3数据块的向量
+    pub fn encode_samples<S: PcmSample>(
+        &mut self,
+        pcm_data: &[S],
+    ) -> Result<Vec<Vec<u8>>, EncoderError> {
+        self.check_open()?;
 
         // 验证输入数据
         if pcm_data.is_empty() {
@@ -253,61 +1079,504 @@ impl Mp3Encoder {
         }
 
         // 将数据添加到缓冲区
-        self.input_buffer.extend(pcm_data);
+        self.clipping.update_interleaved(pcm_data);
+        if let Some(analyzer) = self.replaygain.as_mut() {
+            analyzer.update_interleaved(pcm_data);
+        }
+        self.input_buffer
+            .extend(pcm_data.iter().map(|&sample| sample.to_internal()));
+        self.input_samples += pcm_data.len() as u64;
 
         let mut output_frames = Vec::new();
 
         // 处理完整的帧
         while self.input_buffer.len() >= self.samples_per_frame {
-            let frame_data: Vec<i16> = self.input_buffer.drain(..self.samples_per_frame).collect();
-
-            // 调用底层编码函数
-            let (mp3_data, written) =
-                unsafe { shine_encode_buffer_interleaved(&mut self.config, frame_data.as_ptr()) }
-                    .map_err(EncoderError::Encoding)?;
-
-            if written > 0 {
-                output_frames.push(mp3_data[..written].to_vec());
+            let mp3_data = self.encode_buffered_frame()?;
+            if !mp3_data.is_empty() {
+                output_frames.push(mp3_data);
+            } else {
+                self.recycle_buffer(mp3_data);
             }
         }
 
         Ok(output_frames)
     }
 
-    /// 编码PCM音频数据（分离声道格式）
-    ///
-    /// # 参数
-    /// - `left_channel`: 左声道数据
-    /// - `right_channel`: 右声道数据（单声道时可为None）
-    ///
-    /// # 返回值
-    /// 返回编码后的MP3数据块的向量
-    pub fn encode_separate_channels(
-        &mut self,
-        left_channel: &[i16],
-        right_channel: Option<&[i16]>,
-    ) -> Result<Vec<Vec<u8>>, EncoderError> {
+    /// 编码器中毒或已完成时返回对应的错误
+    fn check_open(&self) -> Result<(), EncoderError> {
+        if self.poisoned {
+            return Err(EncoderError::Poisoned);
+        }
         if self.finished {
-            return Err(EncoderError::InternalState(
-                "Encoder has been finished".to_string(),
-            ));
+            return Err(EncoderError::Finished);
         }
+        Ok(())
+    }
 
-        // 验证输入数据
-        if left_channel.is_empty() {
-            return Err(EncoderError::InputData(InputDataError::EmptyInput));
-        }
+    /// 从输入缓冲区头部编码一帧
+    ///
+    /// 帧数据连续时直接从环形缓冲区读取，否则拷贝到复用的暂存区，
+    /// 消费一帧的开销与缓冲区中剩余的样本数无关。
+    fn encode_buffered_frame(&mut self) -> Result<Vec<u8>, EncoderError> {
+        let samples = self.samples_per_frame;
+        let (front, back) = self.input_buffer.as_slices();
+        let frame_data: &[i32] = if front.len() >= samples {
+            &front[..samples]
+        } else {
+            self.frame_buffer.clear();
+            self.frame_buffer.extend_from_slice(front);
+            self.frame_buffer
+                .extend_from_slice(&back[..samples - front.len()]);
+            &self.frame_buffer
+        };
 
-        // 验证声道数据一致性
-        match (self.encoder_config.channels, right_channel) {
-            (1, None) => {
-                // 单声道，只使用左声道
-                self.encode_interleaved(left_channel)
+        // 调用底层编码函数
+        let (mp3_data, written) = shine_encode_buffer_interleaved_i32(&mut self.config, frame_data)
+            .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        #[cfg(feature = "std")]
+        let mp3_data = match &self.buffer_pool {
+            Some(pool) => {
+                let mut buffer = pool.take();
+                buffer.extend_from_slice(&mp3_data[..written]);
+                buffer
             }
-            (2, Some(right)) => {
+            None => mp3_data[..written].to_vec(),
+        };
+        #[cfg(not(feature = "std"))]
+        let mp3_data = mp3_data[..written].to_vec();
+
+        self.input_buffer.drain(..samples);
+        self.frames_encoded += 1;
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &mp3_data);
+        Ok(mp3_data)
+    }
+
+    /// 把不再需要的输出缓冲区还给缓冲区池（如果有）
+    fn recycle_buffer(&self, buffer: Vec<u8>) {
+        #[cfg(feature = "std")]
+        if let Some(pool) = &self.buffer_pool {
+            pool.recycle(buffer);
+        }
+        #[cfg(not(feature = "std"))]
+        drop(buffer);
+    }
+
+    /// 编码`count`帧静音并追加到`out`
+    ///
+    /// 输出与用[`Mp3Encoder::encode_interleaved`]编码`count * samples_per_frame()`
+    /// 个零样本逐字节相同，包括填充位的交替和比特储备库的状态。滤波器历史
+    /// 衰减为零后，每种状态（填充位、储备库和位缓存）下的静音帧只编码一次，
+    /// 之后直接重放，比正常编码快数倍。缓冲区中有不足一帧的样本、
+    /// 启用ReplayGain分析、设置了频谱观察回调或启用diagnostics特性时逐帧正常编码。
+    pub fn encode_silence_frames(
+        &mut self,
+        count: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<(), EncoderError> {
+        self.check_open()?;
+
+        let zeros = vec![0i16; self.samples_per_frame];
+        let mut replay: Vec<SilentFrame> = Vec::new();
+        for _ in 0..count {
+            let key = self.silence_key();
+            if let Some(frame) = key.and_then(|key| replay.iter().find(|frame| frame.key == key)) {
+                self.replay_silent_frame(frame, out);
+                continue;
+            }
+
+            let off_before = self.config.subband.off;
+            let frames = self.encode_interleaved(&zeros)?;
+            let data = frames.concat();
+            frames
+                .into_iter()
+                .for_each(|buffer| self.recycle_buffer(buffer));
+            out.extend_from_slice(&data);
+            if let Some(key) = key {
+                let config = &self.config;
+                let mut off_step = [0; MAX_CHANNELS];
+                for ch in 0..MAX_CHANNELS {
+                    off_step[ch] =
+                        (config.subband.off[ch] - off_before[ch]).rem_euclid(HAN_SIZE as i32);
+                }
+                replay.push(SilentFrame {
+                    key,
+                    data,
+                    bits_per_frame: config.mpeg.bits_per_frame,
+                    mean_bits: config.mean_bits,
+                    resv_size: config.resv_size,
+                    usage: config.reservoir.usage,
+                    side_info: config.side_info.clone(),
+                    cache: config.bs.cache,
+                    cache_bits: config.bs.cache_bits,
+                    off_step,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 静音帧的重放键，滤波器历史不为零或不能重放时为None
+    fn silence_key(&self) -> Option<SilenceKey> {
+        if !self.input_buffer.is_empty()
+            || self.replaygain.is_some()
+            || self.config.frame_bit_budget.is_some()
+            || self.config.spectrum_tap.is_some()
+            || self.config.reservoir.is_enabled()
+            || cfg!(feature = "diagnostics")
+        {
+            return None;
+        }
+
+        let config = &self.config;
+        let channels = config.wave.channels as usize;
+        let quiet = config.subband.x[..channels]
+            .iter()
+            .all(|x| x.iter().all(|&sample| sample == 0))
+            && config.l3_sb_sample[..channels]
+                .iter()
+                .flatten()
+                .flatten()
+                .flatten()
+                .all(|&sample| sample == 0);
+        if !quiet {
+            return None;
+        }
+
+        // 与编码时相同的填充位计算，这里只预览不推进slot_lag
+        let mut slot_lag = config.mpeg.slot_lag;
+        let padding = if config.mpeg.frac_slots_per_frame != 0.0 {
+            shine_next_padding(&mut slot_lag, config.mpeg.frac_slots_per_frame)
+        } else {
+            config.mpeg.padding
+        };
+        Some(SilenceKey {
+            padding,
+            resv_size: config.resv_size,
+            cache: config.bs.cache,
+            cache_bits: config.bs.cache_bits,
+        })
+    }
+
+    /// 重放一个已编码的静音帧，按正常编码的方式更新所有状态
+    fn replay_silent_frame(&mut self, frame: &SilentFrame, out: &mut Vec<u8>) {
+        let config = &mut self.config;
+        if config.mpeg.frac_slots_per_frame != 0.0 {
+            config.mpeg.padding =
+                shine_next_padding(&mut config.mpeg.slot_lag, config.mpeg.frac_slots_per_frame);
+        }
+        config.mpeg.bits_per_frame = frame.bits_per_frame;
+        config.mean_bits = frame.mean_bits;
+        config.resv_size = frame.resv_size;
+        config.reservoir.usage = frame.usage;
+        config.frame_number += 1;
+        config.side_info.clone_from(&frame.side_info);
+        config.bs.cache = frame.cache;
+        config.bs.cache_bits = frame.cache_bits;
+        for ch in 0..MAX_CHANNELS {
+            config.subband.off[ch] =
+                (config.subband.off[ch] + frame.off_step[ch]) % HAN_SIZE as i32;
+        }
+
+        out.extend_from_slice(&frame.data);
+        self.clipping.update_silence(self.samples_per_frame);
+        self.input_samples += self.samples_per_frame as u64;
+        self.frames_encoded += 1;
+        self.bytes_written += frame.data.len() as u64;
+        update_crc(&mut self.running_crc, &frame.data);
+    }
+
+    /// 直接编码一帧平面格式（每声道一个切片）的PCM数据
+    ///
+    /// 每个切片必须正好包含一帧的每声道样本数（否则返回`PartialFrame`，
+    /// 声道长度不一致时返回`InvalidChannelLength`），声道数据直接送入编码器，
+    /// 不经过交错和输入缓冲区。缓冲区中仍有未编码的样本时返回错误，
+    /// 以免打乱样本顺序。
+    ///
+    /// # 参数
+    /// - `channels`: 每声道一个切片
+    ///
+    /// # 返回值
+    /// 返回这一帧输出的MP3数据
+    pub fn encode_planar(&mut self, channels: &[&[i16]]) -> Result<&[u8], EncoderError> {
+        self.check_open()?;
+        if !self.input_buffer.is_empty() {
+            return Err(EncoderError::BufferedSamples {
+                samples: self.input_buffer.len(),
+            });
+        }
+
+        let samples_per_channel = self.samples_per_frame / self.encoder_config.channels as usize;
+        self.validate_planar(channels, Some(samples_per_channel))?;
+
+        let pointers: Vec<*const i16> = channels.iter().map(|channel| channel.as_ptr()).collect();
+        let (mp3_data, written) = shine_encode_buffer(&mut self.config, &pointers)
+            .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        self.clipping.update_planar(channels);
+        if let Some(analyzer) = self.replaygain.as_mut() {
+            analyzer.update_planar(channels);
+        }
+
+        self.input_samples += self.samples_per_frame as u64;
+        self.frames_encoded += 1;
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &mp3_data[..written]);
+        Ok(&mp3_data[..written])
+    }
+
+    /// 从多声道交错数据中选取相邻的声道直接编码一帧
+    ///
+    /// `pcm`交错了`total_channels`个声道，编码器的1或2个声道从第
+    /// `first_channel`个声道开始，以`total_channels`为步长读取，无需先把
+    /// 这些声道拷贝出来，例如从8声道采集数据中编码第3、4声道。`pcm`必须
+    /// 正好包含一帧的采样帧：声道超出`total_channels`时返回
+    /// `EncodingError::InvalidChannelIndex`，长度不是`total_channels`的整数倍时
+    /// 返回`EncodingError::InvalidDataLength`，采样帧数不等于一帧时返回
+    /// `PartialFrame`。与[`Mp3Encoder::encode_planar`]一样，缓冲区中仍有未编码
+    /// 的样本时返回错误。
+    ///
+    /// # 参数
+    /// - `pcm`: `total_channels`个声道的交错PCM数据
+    /// - `total_channels`: `pcm`中的声道数
+    /// - `first_channel`: 编码的第一个声道的序号（从0开始）
+    ///
+    /// # 返回值
+    /// 返回这一帧输出的MP3数据
+    pub fn encode_frame_interleaved_strided(
+        &mut self,
+        pcm: &[i16],
+        total_channels: usize,
+        first_channel: usize,
+    ) -> Result<&[u8], EncoderError> {
+        self.check_open()?;
+        if !self.input_buffer.is_empty() {
+            return Err(EncoderError::BufferedSamples {
+                samples: self.input_buffer.len(),
+            });
+        }
+
+        check_strided_input(&self.config, pcm.len(), total_channels, first_channel)
+            .or_else(|error| match error {
+                // Short input is a partial frame, reported below
+                EncodingError::InvalidInputLength { .. } => Ok(()),
+                error => Err(error),
+            })
+            .map_err(EncoderError::Encoding)?;
+        let channels = self.encoder_config.channels as usize;
+        let samples_per_channel = self.samples_per_frame / channels;
+        if pcm.len() / total_channels != samples_per_channel {
+            return Err(EncoderError::InputData(InputDataError::PartialFrame {
+                have: pcm.len() / total_channels,
+                need: samples_per_channel,
+            }));
+        }
+
+        let selected: Vec<i16> = pcm
+            .chunks_exact(total_channels)
+            .flat_map(|frame| &frame[first_channel..first_channel + channels])
+            .copied()
+            .collect();
+        let (mp3_data, written) = shine_encode_buffer_interleaved_strided(
+            &mut self.config,
+            pcm,
+            total_channels,
+            first_channel,
+        )
+        .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        self.clipping.update_interleaved(&selected);
+        if let Some(analyzer) = self.replaygain.as_mut() {
+            analyzer.update_interleaved(&selected);
+        }
+
+        self.input_samples += self.samples_per_frame as u64;
+        self.frames_encoded += 1;
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &mp3_data[..written]);
+        Ok(&mp3_data[..written])
+    }
+
+    /// 把一帧交错格式的PCM数据直接编码到调用方提供的缓冲区
+    ///
+    /// 适用于不希望每帧分配堆内存的嵌入式场景：`pcm`必须正好包含一帧的
+    /// 样本（否则返回`PartialFrame`），输出写入`out`的开头。`out`小于
+    /// [`Mp3Encoder::max_frame_bytes`]时在编码前返回`BufferTooSmall`，
+    /// 其中`needed`即为该值，编码器状态不变。与[`Mp3Encoder::encode_planar`]
+    /// 一样，缓冲区中仍有未编码的样本时返回错误。
+    ///
+    /// # 参数
+    /// - `pcm`: 一帧交错格式的PCM数据
+    /// - `out`: 输出缓冲区
+    ///
+    /// # 返回值
+    /// 返回写入`out`的字节数；启用比特储备库时帧可能推迟输出，此时为0
+    pub fn encode_frame_to_slice(
+        &mut self,
+        pcm: &[i16],
+        out: &mut [u8],
+    ) -> Result<usize, EncoderError> {
+        self.check_open()?;
+        if !self.input_buffer.is_empty() {
+            return Err(EncoderError::BufferedSamples {
+                samples: self.input_buffer.len(),
+            });
+        }
+
+        let channels = self.encoder_config.channels as usize;
+        if pcm.len() != self.samples_per_frame {
+            return Err(EncoderError::InputData(InputDataError::PartialFrame {
+                have: pcm.len() / channels,
+                need: self.samples_per_frame / channels,
+            }));
+        }
+        let needed = self.max_frame_bytes();
+        if out.len() < needed {
+            return Err(EncoderError::InputData(InputDataError::BufferTooSmall {
+                needed,
+            }));
+        }
+
+        // 复用暂存区，第一帧之后不再分配
+        self.frame_buffer.clear();
+        self.frame_buffer
+            .extend(pcm.iter().map(|&sample| sample.to_internal()));
+        let (mp3_data, written) =
+            shine_encode_buffer_interleaved_i32(&mut self.config, &self.frame_buffer)
+                .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        out[..written].copy_from_slice(&mp3_data[..written]);
+        self.clipping.update_interleaved(pcm);
+        if let Some(analyzer) = self.replaygain.as_mut() {
+            analyzer.update_interleaved(pcm);
+        }
+
+        self.input_samples += pcm.len() as u64;
+        self.frames_encoded += 1;
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &out[..written]);
+        Ok(written)
+    }
+
+    /// 编码下一帧最多输出的字节数
+    ///
+    /// 由码率决定：固定码率时为帧长，码率需要填充位时加一字节；设置了帧
+    /// 比特预算时为预算选定的帧长。启用比特储备库时再加上等待输出的帧。
+    /// 输出与shine一样按32位字写出，上一帧留在位缓存中的最多3个字节会随
+    /// 这一帧输出，因此结果可能比帧长多几个字节。
+    pub fn max_frame_bytes(&self) -> usize {
+        let config = &self.config;
+        let padding = (config.mpeg.frac_slots_per_frame != 0.0) as i32;
+        let cbr = (config.mpeg.whole_slots_per_frame + padding) as usize;
+        let frame = config
+            .frame_bit_budget
+            .and_then(|budget| shine_frame_for_budget(config, budget).ok())
+            .map_or(cbr, |(_, bits)| bits as usize / 8);
+        let cached_bits = (32 - config.bs.cache_bits) as usize;
+        (cached_bits + 8 * (frame + config.reservoir.waiting_bytes())) / 32 * 4
+    }
+
+    /// 编码任意长度的平面格式PCM数据
+    ///
+    /// 所有声道切片长度必须相同。数据进入输入缓冲区，按完整帧编码，
+    /// 与[`Mp3Encoder::encode_samples`]行为一致。
+    ///
+    /// # 参数
+    /// - `channels`: 每声道一个切片
+    ///
+    /// # 返回值
+    /// 返回编码后的MP3数据块的向量
+    pub fn encode_samples_planar<S: PcmSample>(
+        &mut self,
+        channels: &[&[S]],
+    ) -> Result<Vec<Vec<u8>>, EncoderError> {
+        self.validate_planar(channels, None)?;
+
+        let length = channels[0].len();
+        let mut interleaved = Vec::with_capacity(length * channels.len());
+        for i in 0..length {
+            interleaved.extend(channels.iter().map(|channel| channel[i]));
+        }
+
+        self.encode_samples(&interleaved)
+    }
+
+    /// 验证平面数据的声道数和每声道长度
+    ///
+    /// 各声道长度必须与第一个声道一致；`frame_length`不为None时，
+    /// 第一个声道还必须正好包含这么多样本。
+    fn validate_planar<S>(
+        &self,
+        channels: &[&[S]],
+        frame_length: Option<usize>,
+    ) -> Result<(), EncoderError> {
+        let expected_channels = self.encoder_config.channels as usize;
+        if channels.len() != expected_channels {
+            return Err(EncoderError::InputData(
+                InputDataError::InvalidChannelCount {
+                    expected: expected_channels,
+                    actual: channels.len(),
+                },
+            ));
+        }
+
+        let expected = channels[0].len();
+        if let Some((channel, data)) = channels
+            .iter()
+            .enumerate()
+            .find(|(_, data)| data.len() != expected)
+        {
+            return Err(EncoderError::InputData(
+                InputDataError::InvalidChannelLength {
+                    channel,
+                    expected,
+                    actual: data.len(),
+                },
+            ));
+        }
+
+        if expected == 0 {
+            return Err(EncoderError::InputData(InputDataError::EmptyInput));
+        }
+        match frame_length {
+            Some(need) if need != expected => {
+                Err(EncoderError::InputData(InputDataError::PartialFrame {
+                    have: expected,
+                    need,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 编码PCM音频数据（分离声道格式）
+    ///
+    /// # 参数
+    /// - `left_channel`: 左声道数据
+    /// - `right_channel`: 右声道数据（单声道时可为None）
+    ///
+    /// # 返回值
+    /// 返回编码后的MP3数据块的向量
+    pub fn encode_separate_channels(
+        &mut self,
+        left_channel: &[i16],
+        right_channel: Option<&[i16]>,
+    ) -> Result<Vec<Vec<u8>>, EncoderError> {
+        self.check_open()?;
+
+        // 验证输入数据
+        if left_channel.is_empty() {
+            return Err(EncoderError::InputData(InputDataError::EmptyInput));
+        }
+
+        // 验证声道数据一致性
+        match (self.encoder_config.channels, right_channel) {
+            (1, None) => {
+                // 单声道，只使用左声道
+                self.encode_samples(left_channel)
+            }
+            (2, Some(right)) => {
                 if left_channel.len() != right.len() {
                     return Err(EncoderError::InputData(
-                        InputDataError::InvalidChannelCount {
+                        InputDataError::InvalidChannelLength {
+                            channel: 1,
                             expected: left_channel.len(),
                             actual: right.len(),
                         },
@@ -321,7 +1590,7 @@ impl Mp3Encoder {
                     interleaved.push(*r);
                 }
 
-                self.encode_interleaved(&interleaved)
+                self.encode_samples(&interleaved)
             }
             (1, Some(_)) => Err(EncoderError::InputData(
                 InputDataError::InvalidChannelCount {
@@ -339,44 +1608,272 @@ impl Mp3Encoder {
         }
     }
 
+    /// 立即把缓冲区中的样本补零编码为完整的一帧并返回
+    ///
+    /// 用于在指定的样本位置切分片段（例如每100毫秒）：之后输入的样本从新的
+    /// 一帧开始。位缓存中这一帧的最后几个字节一并输出，返回的数据正好在帧的
+    /// 末尾结束。与[`Mp3Encoder::finish`]不同，可以在流中反复调用，不追加静音帧，
+    /// 编码器保持可用；输出与在缓冲的样本后输入相同数量的零样本逐字节相同。
+    /// 缓冲区为空时不编码，返回空数据。填充的零样本数记入
+    /// [`EncoderStats::boundary_padded_samples`]。启用比特储备库时帧在储备库中
+    /// 等待，数据随之后的帧输出。
+    ///
+    /// # 返回值
+    /// 返回补全的这一帧的MP3数据
+    pub fn flush_at_boundary(&mut self) -> Result<Vec<u8>, EncoderError> {
+        self.check_open()?;
+        if self.input_buffer.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let padded = self.samples_per_frame - self.input_buffer.len();
+        self.input_buffer.resize(self.samples_per_frame, 0);
+        let mut mp3_data = self.encode_buffered_frame()?;
+        self.boundary_padded_samples += padded as u64;
+
+        // 帧以整字节结束，位缓存中只有这一帧的完整字节
+        self.config
+            .bs
+            .flush()
+            .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        let (tail, written) = shine_flush(&mut self.config);
+        mp3_data.extend_from_slice(&tail[..written]);
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &tail[..written]);
+        Ok(mp3_data)
+    }
+
     /// 完成编码并获取剩余数据
     ///
+    /// 不完整的最后一帧用零填充（严格帧对齐模式下返回错误），随后追加配置的静音帧数。
+    ///
     /// # 返回值
     /// 返回最后的MP3数据块
     pub fn finish(&mut self) -> Result<Vec<u8>, EncoderError> {
+        self.finish_with(FlushMode::PadWithSilence)
+            .map(|(output, _)| output)
+    }
+
+    /// 一次调用编码整段PCM数据，返回完整的码流和统计信息
+    ///
+    /// 编码`pcm`（连同缓冲区中之前剩余的样本），按[`Mp3Encoder::finish`]完成
+    /// 编码，取得统计信息后[`Mp3Encoder::reset`]，编码器可以直接用于下一段
+    /// 数据。`pcm`可以为空。出错时编码器保持出错时的状态，需要时由调用者重置。
+    ///
+    /// # 参数
+    /// - `pcm`: 交错格式的PCM数据
+    ///
+    /// # 返回值
+    /// 返回整个MP3码流和完成编码时的统计信息
+    pub fn encode_complete(
+        &mut self,
+        pcm: &[i16],
+    ) -> Result<(Vec<u8>, EncoderStats), EncoderError> {
+        self.check_open()?;
+
+        let mut mp3_data = Vec::new();
+        if !pcm.is_empty() {
+            for frame in self.encode_samples(pcm)? {
+                mp3_data.extend_from_slice(&frame);
+                self.recycle_buffer(frame);
+            }
+        }
+        mp3_data.extend(self.finish()?);
+
+        let stats = self.stats();
+        self.reset();
+        Ok((mp3_data, stats))
+    }
+
+    /// 按指定方式处理剩余样本并完成编码
+    ///
+    /// `FlushMode::PadWithSilence`与[`Mp3Encoder::finish`]相同。另外两种方式
+    /// 不编码不完整的帧，严格帧对齐模式对它们不起作用；丢弃或退回的样本数
+    /// 记入[`EncoderStats::dropped_samples`]。配置的静音帧在所有方式下都会追加。
+    ///
+    /// # 返回值
+    /// 返回最后的MP3数据块和退回的样本。退回的样本为编码器内部的32位满幅度
+    /// 格式，可以直接交给[`Mp3Encoder::encode_samples`]；其他方式下为空。
+    pub fn finish_with(&mut self, mode: FlushMode) -> Result<(Vec<u8>, Vec<i32>), EncoderError> {
+        if self.poisoned {
+            return Err(EncoderError::Poisoned);
+        }
         if self.finished {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        // 严格模式下不填充不完整的帧，编码器保持可用以便继续输入
+        if mode == FlushMode::PadWithSilence
+            && self.encoder_config.strict_frame_alignment
+            && !self.input_buffer.is_empty()
+        {
+            return Err(EncoderError::InputData(InputDataError::UnalignedInput {
+                remaining: self.input_buffer.len(),
+            }));
         }
 
         self.finished = true;
+        self.warn_on_clipping();
+        self.flush_remaining(mode)
+            .map_err(|error| poison_on(&mut self.poisoned, error))
+    }
+
+    /// 按`mode`处理缓冲区中剩余的样本，追加静音帧并刷新编码器
+    fn flush_remaining(&mut self, mode: FlushMode) -> Result<(Vec<u8>, Vec<i32>), EncoderError> {
+        let mut remainder = Vec::new();
+        if mode != FlushMode::PadWithSilence {
+            log::debug!(
+                "Not encoding {} samples of a partial final frame ({:?})",
+                self.input_buffer.len(),
+                mode
+            );
+            self.dropped_samples += self.input_buffer.len() as u64;
+            if mode == FlushMode::ReturnRemainder {
+                remainder.extend(self.input_buffer.drain(..));
+            } else {
+                self.input_buffer.clear();
+            }
+        }
 
         // 处理剩余的不完整帧（用零填充）
         let mut final_output = Vec::new();
 
         if !self.input_buffer.is_empty() {
             // 用零填充到完整帧大小
-            while self.input_buffer.len() < self.samples_per_frame {
-                self.input_buffer.push_back(0);
+            log::debug!(
+                "Padding final frame with {} zero samples ({} of {} samples present)",
+                self.samples_per_frame - self.input_buffer.len(),
+                self.input_buffer.len(),
+                self.samples_per_frame
+            );
+            let padded = self.samples_per_frame - self.input_buffer.len();
+            self.padded_samples += padded as u64;
+            if self.encoder_config.padding_warning && !self.padding_warned {
+                self.padding_warned = true;
+                log::warn!(
+                    "Final frame padded with {} samples of silence per channel",
+                    padded / self.encoder_config.channels as usize
+                );
             }
+            self.input_buffer.resize(self.samples_per_frame, 0);
 
-            let frame_data: Vec<i16> = self.input_buffer.drain(..).collect();
-
-            let (mp3_data, written) =
-                unsafe { shine_encode_buffer_interleaved(&mut self.config, frame_data.as_ptr()) }
-                    .map_err(EncoderError::Encoding)?;
+            let mp3_data = self.encode_buffered_frame()?;
+            final_output.extend_from_slice(&mp3_data);
+            self.recycle_buffer(mp3_data);
+        }
 
-            if written > 0 {
-                final_output.extend_from_slice(&mp3_data[..written]);
-            }
+        // 追加静音帧
+        for _ in 0..self.encoder_config.trailing_silence_frames {
+            self.input_buffer.resize(self.samples_per_frame, 0);
+            let mp3_data = self.encode_buffered_frame()?;
+            final_output.extend_from_slice(&mp3_data);
+            self.recycle_buffer(mp3_data);
         }
 
-        // 刷新编码器缓冲区
+        // 写出在比特储备库中等待的帧，然后刷新编码器缓冲区
+        drain_reservoir(&mut self.config)?;
+        if self.encoder_config.pad_output_to.is_some() {
+            // 补齐前最后一帧必须完整
+            self.config.bs.flush()?;
+        }
         let (flush_data, flush_written) = shine_flush(&mut self.config);
         if flush_written > 0 {
             final_output.extend_from_slice(&flush_data[..flush_written]);
         }
+        self.bytes_written += flush_written as u64;
+        update_crc(&mut self.running_crc, &flush_data[..flush_written]);
+
+        if let Some(target) = self.encoder_config.pad_output_to {
+            let padding = output_padding(self.frame_header(), self.bytes_written, target as u64)?;
+            self.bytes_written += padding.len() as u64;
+            update_crc(&mut self.running_crc, &padding);
+            final_output.extend(padding);
+        }
+
+        Ok((final_output, remainder))
+    }
 
-        Ok(final_output)
+    /// 按当前配置写出的帧头，不含填充位和CRC
+    fn frame_header(&self) -> FrameHeader {
+        let mpeg = &self.config.mpeg;
+        FrameHeader {
+            version: mpeg.version,
+            layer: mpeg.layer,
+            protected: false,
+            bitrate_index: mpeg.bitrate_index as usize,
+            samplerate_field: mpeg.samplerate_index as usize % 3,
+            padding: false,
+            private: mpeg.ext != 0,
+            mode: mpeg.mode as u8,
+            mode_ext: 0,
+            copyright: mpeg.copyright != 0,
+            original: mpeg.original != 0,
+            emphasis: mpeg.emph as u8,
+        }
+    }
+
+    /// 停止编码并取出已编码帧的剩余数据
+    ///
+    /// 缓冲区中不完整的帧被丢弃，位缓存中的最后几位按字节补齐后输出，
+    /// 不追加静音帧。之后编码器处于完成状态。
+    fn cancel(&mut self) -> Result<Vec<u8>, EncoderError> {
+        self.finished = true;
+        self.input_buffer.clear();
+        drain_reservoir(&mut self.config)
+            .and_then(|_| self.config.bs.flush())
+            .map_err(|error| poison_on(&mut self.poisoned, error.into()))?;
+        let (data, written) = shine_flush(&mut self.config);
+        self.bytes_written += written as u64;
+        update_crc(&mut self.running_crc, &data[..written]);
+        Ok(data[..written].to_vec())
+    }
+
+    /// 削波比例超过配置的阈值时输出警告
+    fn warn_on_clipping(&self) {
+        let Some(threshold) = self.encoder_config.clip_warning_ratio else {
+            return;
+        };
+        let ratio = self.clipping.clipped_ratio();
+        if ratio > threshold {
+            log::warn!(
+                "Input is clipped: {} of {} samples ({:.2}%) at full scale, longest run {} samples",
+                self.clipping.clipped_samples(),
+                self.clipping.samples(),
+                ratio * 100.0,
+                self.clipping.max_consecutive_clipped()
+            );
+        }
+    }
+
+    /// 到目前为止输出的所有字节的CRC32
+    ///
+    /// 与对返回的全部数据按顺序拼接后计算`crc32fast::hash`的结果相同。
+    /// 需要启用[`Mp3EncoderConfig::running_crc32`]，否则始终返回空数据的CRC32（0）。
+    pub fn running_crc32(&self) -> u32 {
+        self.running_crc
+            .clone()
+            .map_or(0, crc32fast::Hasher::finalize)
+    }
+
+    /// 完成编码后的ReplayGain分析结果
+    ///
+    /// 未启用分析、尚未调用[`Mp3Encoder::finish`]或输入不足一个50毫秒的
+    /// 分析窗口时返回None。补零的样本和追加的静音帧不参与分析。
+    pub fn replaygain(&self) -> Option<ReplayGain> {
+        if !self.finished {
+            return None;
+        }
+        self.replaygain
+            .as_ref()
+            .and_then(ReplayGainAnalyzer::result)
+    }
+
+    /// 输入缓冲区和帧暂存区的总容量（样本数）
+    ///
+    /// 两者在创建编码器时按最大需要分配，每次输入不超过一帧时稳定编码
+    /// 不会再增长。
+    pub fn scratch_capacity(&self) -> usize {
+        self.input_buffer.capacity() + self.frame_buffer.capacity()
     }
 
     /// 获取缓冲区中剩余的样本数
@@ -389,31 +1886,77 @@ impl Mp3Encoder {
         self.finished
     }
 
-    /// 创建shine配置
-    fn create_shine_config(config: &Mp3EncoderConfig) -> Result<ShineConfig, ConfigError> {
-        let mut mpeg = ShineMpeg {
-            mode: config.stereo_mode as i32,
-            bitr: config.bitrate as i32,
-            emph: NONE,
-            copyright: if config.copyright { 1 } else { 0 },
-            original: if config.original { 1 } else { 0 },
-        };
+    /// 检查编码器是否因不可恢复的错误而不能继续使用
+    ///
+    /// 见[`EncoderError::is_recoverable`]。中毒后编码和完成编码的调用都返回
+    /// `EncoderError::Poisoned`，直到调用[`Mp3Encoder::reset`]。
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
 
-        // 设置默认值
-        shine_set_config_mpeg_defaults(&mut mpeg);
+    /// 按已编码帧数估算的音频时长（秒）
+    ///
+    /// 等于`已编码帧数 * 每帧每声道样本数 / 采样率`，包括补零的最后一帧
+    /// 和追加的静音帧，适用于总长度未知的流式输入。
+    pub fn encoded_duration(&self) -> f64 {
+        let samples_per_channel = self.samples_per_frame / self.encoder_config.channels as usize;
+        self.frames_encoded as f64 * samples_per_channel as f64
+            / self.encoder_config.sample_rate as f64
+    }
 
-        // 应用用户配置
-        mpeg.mode = config.stereo_mode as i32;
-        mpeg.bitr = config.bitrate as i32;
-        mpeg.copyright = if config.copyright { 1 } else { 0 };
-        mpeg.original = if config.original { 1 } else { 0 };
+    /// 最后开始编码的帧的编号（从1开始，尚未编码时为0）
+    ///
+    /// 每个编码器单独计数，`reset`后从头开始；诊断功能用它标记采集的数据。
+    pub fn get_current_frame_number(&self) -> i32 {
+        self.config.frame_number
+    }
 
-        let wave = ShineWave {
-            channels: config.channels as i32,
-            samplerate: config.sample_rate as i32,
+    /// 下一帧将得到的编号
+    pub fn get_next_frame_number(&self) -> i32 {
+        self.config.frame_number + 1
+    }
+
+    /// 获取编码统计信息
+    pub fn stats(&self) -> EncoderStats {
+        let samples_per_channel = self.input_samples / self.encoder_config.channels as u64;
+        let duration_secs = samples_per_channel as f64 / self.encoder_config.sample_rate as f64;
+        let actual_bitrate_kbps = if duration_secs > 0.0 {
+            self.bytes_written as f64 * 8.0 / duration_secs / 1000.0
+        } else {
+            0.0
         };
 
-        Ok(ShineConfig { wave, mpeg })
+        EncoderStats {
+            frames_encoded: self.frames_encoded,
+            samples_per_channel,
+            bytes_written: self.bytes_written,
+            duration: Duration::from_secs_f64(duration_secs),
+            actual_bitrate_kbps,
+            clipped_samples: self.clipping.clipped_samples(),
+            max_consecutive_clipped: self.clipping.max_consecutive_clipped(),
+            padded_samples: self.padded_samples,
+            padded_tail_samples: self.padded_samples / self.encoder_config.channels as u64,
+            dropped_samples: self.dropped_samples,
+            boundary_padded_samples: self.boundary_padded_samples,
+            max_main_data_begin: self.config.reservoir.peak_bytes(),
+            reservoir_bits: self.config.reservoir.available_bits(),
+            granule_bits: self.config.reservoir.usage.granules,
+            avg_nmr_db: self.config.nmr.average_db(),
+        }
+    }
+
+    /// 比特储备库的填充比例（0.0到1.0）
+    ///
+    /// 在每个颗粒分配比特之后和每帧结束时更新（与shine更新`ResvSize`的位置相同），
+    /// 因此反映下一帧编码时看到的状态。比例持续下降说明音频需要的比特多于
+    /// 比特率能提供的，自适应应用可以据此在储备耗尽前提高比特率。
+    /// 未启用比特储备库时返回0.0。
+    pub fn reservoir_fill_ratio(&self) -> f64 {
+        let reservoir = &self.config.reservoir;
+        if reservoir.capacity_bits() == 0 {
+            return 0.0;
+        }
+        (reservoir.available_bits() as f64 / reservoir.capacity_bits() as f64).min(1.0)
     }
 }
 
@@ -442,7 +1985,7 @@ pub fn encode_pcm_to_mp3(
     let mut mp3_data = Vec::new();
 
     // 编码所有数据
-    let frames = encoder.encode_interleaved(pcm_data)?;
+    let frames = encoder.encode_samples(pcm_data)?;
     for frame in frames {
         mp3_data.extend(frame);
     }
@@ -453,3 +1996,398 @@ pub fn encode_pcm_to_mp3(
 
     Ok(mp3_data)
 }
+
+/// 流开头ID3v2标签的总长度（含头部和尾部），没有标签时为0
+#[cfg(feature = "std")]
+fn id3v2_tag_len<R: Read>(reader: &mut R) -> Result<u64, EncoderError> {
+    let mut header = [0u8; 10];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..])? {
+            0 => return Ok(0),
+            read => filled += read,
+        }
+    }
+    if &header[..3] != b"ID3" {
+        return Ok(0);
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |size, &byte| (size << 7) | (byte & 0x7F) as u64);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Ok(10 + size + footer)
+}
+
+/// 把`size`字节的输出补齐到`target`字节的数据
+///
+/// 尽量使用空帧，帧数足够时用填充位吸收余数；否则余数连同必要的空帧
+/// 放入一个只含填充的ID3v2标签，标签放在最后。
+fn output_padding(header: FrameHeader, size: u64, target: u64) -> Result<Vec<u8>, EncoderError> {
+    let unreachable = || EncoderError::Encoding(EncodingError::PadOutput { size, target });
+    let missing = target.checked_sub(size).ok_or_else(unreachable)?;
+    let frame_len = header.frame_length() as u64;
+
+    let (frames, padded, tag_len) = if missing % frame_len <= missing / frame_len {
+        (missing / frame_len, missing % frame_len, 0)
+    } else if missing >= MIN_TAG_LEN as u64 {
+        let frames = (missing - MIN_TAG_LEN as u64) / frame_len;
+        (frames, 0, missing - frames * frame_len)
+    } else {
+        return Err(unreachable());
+    };
+
+    let mut padding = Vec::with_capacity(missing as usize);
+    for index in 0..frames {
+        let header = FrameHeader {
+            padding: index < padded,
+            ..header
+        };
+        padding.extend(header.empty_frame());
+    }
+    if tag_len > 0 {
+        padding.extend(padding_tag(tag_len as usize));
+    }
+    debug_assert_eq!(padding.len() as u64, missing);
+    Ok(padding)
+}
+
+/// 不可恢复的错误使编码器中毒，返回原来的错误
+fn poison_on(poisoned: &mut bool, error: EncoderError) -> EncoderError {
+    if !error.is_recoverable() {
+        *poisoned = true;
+    }
+    error
+}
+
+/// 启用时用输出的数据更新累计的CRC32
+fn update_crc(crc: &mut Option<crc32fast::Hasher>, data: &[u8]) {
+    if let Some(crc) = crc {
+        crc.update(data);
+    }
+}
+
+/// 每声道样本数对应的MP3帧数
+///
+/// 不完整的最后一帧按一帧计（编码时补零），并包括配置的追加静音帧。
+pub fn frames_for_samples(
+    samples_per_channel: u64,
+    config: &Mp3EncoderConfig,
+) -> Result<u64, ConfigError> {
+    config.validate()?;
+    let frame_samples = samples_per_frame_for_rate(config.sample_rate)?;
+    Ok(samples_per_channel.div_ceil(frame_samples) + config.trailing_silence_frames as u64)
+}
+
+/// 不运行编码器估算CBR输出的字节数
+///
+/// 按编码器的每帧slot数计算，并逐帧重现填充位（padding）的节奏，
+/// 因此对整帧的输入与实际编码输出的字节数完全一致。与shine相同，
+/// 结束时位缓存中不足32位的数据不会输出，总字节数向下取整到4的倍数。
+pub fn estimated_output_bytes(
+    samples_per_channel: u64,
+    config: &Mp3EncoderConfig,
+) -> Result<u64, ConfigError> {
+    let frames = frames_for_samples(samples_per_channel, config)?;
+    let version = config
+        .mpeg_version()
+        .ok_or(ConfigError::UnsupportedSampleRate(config.sample_rate))?;
+    let (whole_slots, frac_slots) = shine_slots_per_frame(
+        shine_granules_per_frame(version),
+        config.sample_rate as i32,
+        config.bitrate as i32,
+    );
+
+    let padding: u64 = if frac_slots == 0.0 {
+        0
+    } else {
+        let mut slot_lag = -frac_slots;
+        (0..frames)
+            .map(|_| shine_next_padding(&mut slot_lag, frac_slots) as u64)
+            .sum()
+    };
+    let bytes = frames * whole_slots as u64 + padding;
+    Ok(bytes - bytes % 4)
+}
+
+/// 编码后的音频时长（秒）：输入补齐到整帧后的时长
+pub fn encoded_duration(samples_per_channel: u64, sample_rate: u32) -> Result<f64, ConfigError> {
+    let frame_samples = samples_per_frame_for_rate(sample_rate)?;
+    let frames = samples_per_channel.div_ceil(frame_samples);
+    Ok((frames * frame_samples) as f64 / sample_rate as f64)
+}
+
+/// 某采样率下每帧每声道的样本数
+fn samples_per_frame_for_rate(sample_rate: u32) -> Result<u64, ConfigError> {
+    let samplerate_index = shine_find_samplerate_index(sample_rate as i32);
+    if samplerate_index < 0 {
+        return Err(ConfigError::UnsupportedSampleRate(sample_rate));
+    }
+    let granules = shine_granules_per_frame(shine_mpeg_version(samplerate_index));
+    Ok(granules as u64 * GRANULE_SIZE as u64)
+}
+
+/// [`encode_pcm_to_mp3_with_progress`]报告进度的间隔帧数
+pub const PROGRESS_INTERVAL_FRAMES: u64 = 10;
+
+/// 编码进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// 已编码的帧数
+    pub frames_done: u64,
+    /// 总帧数（包括补零的最后一帧和追加的静音帧）
+    pub total_frames: u64,
+    /// 已输出的MP3字节数
+    pub bytes_out: u64,
+}
+
+/// 可取消编码的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeOutcome {
+    /// 全部数据编码完成
+    Complete(Vec<u8>),
+    /// 进度回调取消了编码，包含取消前已完整编码的帧
+    Cancelled(Vec<u8>),
+}
+
+impl EncodeOutcome {
+    /// 编码得到的MP3数据
+    pub fn data(&self) -> &[u8] {
+        match self {
+            EncodeOutcome::Complete(data) | EncodeOutcome::Cancelled(data) => data,
+        }
+    }
+
+    /// 编码是否被取消
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, EncodeOutcome::Cancelled(_))
+    }
+}
+
+/// 便利函数：一次性编码整个PCM数据并报告进度
+///
+/// 每编码[`PROGRESS_INTERVAL_FRAMES`]帧调用一次`progress`，编码结束时再调用
+/// 一次。回调返回`ControlFlow::Break`时停止编码，返回
+/// [`EncodeOutcome::Cancelled`]，其中只包含已完整写出的帧，不会有半帧数据。
+/// 编码完成时的最后一次回调的返回值被忽略。
+pub fn encode_pcm_to_mp3_with_progress<F>(
+    config: Mp3EncoderConfig,
+    pcm_data: &[i16],
+    mut progress: F,
+) -> Result<EncodeOutcome, EncoderError>
+where
+    F: FnMut(Progress) -> ControlFlow<()>,
+{
+    let mut encoder = Mp3Encoder::new(config)?;
+    let frame_len = encoder.samples_per_frame();
+    let total_frames =
+        pcm_data.len().div_ceil(frame_len) as u64 + encoder.config().trailing_silence_frames as u64;
+
+    let mut mp3_data = Vec::new();
+    for chunk in pcm_data.chunks(frame_len) {
+        for frame in encoder.encode_samples(chunk)? {
+            mp3_data.extend(frame);
+        }
+
+        // 不完整的最后一块留在缓冲区中，由finish编码
+        let frames_done = encoder.frames_encoded;
+        if chunk.len() < frame_len || frames_done % PROGRESS_INTERVAL_FRAMES != 0 {
+            continue;
+        }
+        let report = Progress {
+            frames_done,
+            total_frames,
+            bytes_out: mp3_data.len() as u64,
+        };
+        if progress(report).is_break() {
+            mp3_data.extend(encoder.cancel()?);
+            let complete = {
+                let mut frames = iter_frames(&mp3_data);
+                frames.by_ref().count();
+                frames.offset()
+            };
+            mp3_data.truncate(complete);
+            return Ok(EncodeOutcome::Cancelled(mp3_data));
+        }
+    }
+
+    mp3_data.extend(encoder.finish()?);
+    let _ = progress(Progress {
+        frames_done: encoder.frames_encoded,
+        total_frames,
+        bytes_out: mp3_data.len() as u64,
+    });
+
+    Ok(EncodeOutcome::Complete(mp3_data))
+}
+
+/// 输入的编码范围，以每声道样本数计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeRange {
+    /// 起始样本
+    pub start_sample: u64,
+    /// 编码的样本数，为None时编码到输入结束
+    pub len_samples: Option<u64>,
+}
+
+impl EncodeRange {
+    /// 根据起始时间和时长创建范围，时间按采样率四舍五入到最近的样本
+    pub fn from_time(start: Duration, duration: Option<Duration>, sample_rate: u32) -> Self {
+        let to_samples = |time: Duration| (time.as_secs_f64() * sample_rate as f64).round() as u64;
+        Self {
+            start_sample: to_samples(start),
+            len_samples: duration.map(to_samples),
+        }
+    }
+
+    /// 根据输入的样本数确定实际编码的样本数
+    ///
+    /// 起始位置不在输入范围内时返回`RangeOutOfBounds`错误；
+    /// 范围超出输入结尾时截断到结尾并输出警告。
+    pub fn resolve(&self, available: u64) -> Result<u64, InputDataError> {
+        if self.start_sample >= available {
+            return Err(InputDataError::RangeOutOfBounds {
+                start: self.start_sample,
+                available,
+            });
+        }
+
+        let remaining = available - self.start_sample;
+        match self.len_samples {
+            Some(len) if len > remaining => {
+                log::warn!(
+                    "Range of {} samples from sample {} extends past the end of the input, truncating to {} samples",
+                    len,
+                    self.start_sample,
+                    remaining
+                );
+                Ok(remaining)
+            }
+            Some(len) => Ok(len),
+            None => Ok(remaining),
+        }
+    }
+}
+
+/// 流式编码的统计信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeSummary {
+    /// 输入音频时长
+    pub duration: Duration,
+    /// 每声道的输入样本数
+    pub samples_per_channel: u64,
+    /// 编码的MP3帧数（包含追加的静音帧）
+    pub frames: usize,
+    /// 写入的MP3字节数
+    pub bytes: usize,
+}
+
+/// 便利函数：从WAV数据流直接编码到MP3输出
+///
+/// 声道数和采样率从WAV头中读取，并按[`Mp3EncoderConfig::for_input`]的规则
+/// 确定立体声模式。`overrides`提供其余的编码参数（比特率、首选立体声模式、
+/// 标志位等），为`None`时使用默认配置。若配置要求单声道（声道数为1或立体声
+/// 模式为单声道）而WAV为立体声，左右声道取平均后送入单声道编码器。
+/// 数据按帧大小分块送入编码器，不会一次性读入整个文件。
+///
+/// `range`不为None时只编码该范围内的样本（见[`EncodeRange::resolve`]），
+/// 之前的数据被跳过，最后一帧照常用零填充。
+///
+/// # 参数
+/// - `reader`: WAV数据源
+/// - `writer`: MP3数据输出
+/// - `overrides`: 可选的编码器配置
+/// - `range`: 可选的编码范围
+///
+/// # 返回值
+/// 返回编码统计信息
+#[cfg(feature = "std")]
+pub fn encode_wav_to_mp3<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    overrides: Option<Mp3EncoderConfig>,
+    range: Option<EncodeRange>,
+) -> Result<EncodeSummary, EncoderError> {
+    let stats = encode_wav_stream(reader, writer, overrides, range)?;
+    Ok(EncodeSummary {
+        duration: stats.duration,
+        samples_per_channel: stats.samples_per_channel,
+        frames: stats.frames_encoded as usize,
+        bytes: stats.bytes_written as usize,
+    })
+}
+
+/// [`encode_wav_to_mp3`]的实现，返回编码器的完整统计信息
+#[cfg(feature = "std")]
+pub(crate) fn encode_wav_stream<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    overrides: Option<Mp3EncoderConfig>,
+    range: Option<EncodeRange>,
+) -> Result<EncoderStats, EncoderError> {
+    let mut source = WavSource::new(reader)?;
+    let channels = source.channels() as usize;
+
+    // 剩余需要读取的交错样本数，None表示读到结尾
+    let mut remaining = match range {
+        Some(range) => {
+            let len = range.resolve(source.duration_samples())?;
+            source.skip_frames(range.start_sample)?;
+            Some(len as usize * channels)
+        }
+        None => None,
+    };
+
+    let base = overrides.unwrap_or_default();
+    let preferred_mode = if base.channels == 1 {
+        StereoMode::Mono
+    } else {
+        base.stereo_mode
+    };
+    let derived = Mp3EncoderConfig::for_input(
+        source.sample_rate(),
+        source.channels() as u16,
+        base.bitrate,
+        preferred_mode,
+    )?;
+    let config = Mp3EncoderConfig {
+        sample_rate: derived.sample_rate,
+        channels: derived.channels,
+        stereo_mode: derived.stereo_mode,
+        ..base
+    };
+
+    let downmix = config.channels < source.channels();
+    let mono_source = config.mono_source;
+    if downmix {
+        log::info!(
+            "Downmixing {}-channel WAV input to mono for a mono encoder configuration ({:?})",
+            source.channels(),
+            mono_source
+        );
+    }
+
+    let mut encoder = Mp3Encoder::new(config)?;
+    let chunk_len = encoder.samples_per_frame() * channels;
+
+    let mut chunk = Vec::with_capacity(chunk_len);
+    loop {
+        let count = remaining.map_or(chunk_len, |remaining| remaining.min(chunk_len));
+        if count == 0 || source.read_samples(&mut chunk, count)? == 0 {
+            break;
+        }
+        if let Some(remaining) = remaining.as_mut() {
+            *remaining -= chunk.len();
+        }
+        if downmix {
+            chunk = mono_source.to_mono(&chunk);
+        }
+        for frame in encoder.encode_interleaved(&chunk)? {
+            writer.write_all(&frame)?;
+        }
+    }
+
+    writer.write_all(&encoder.finish()?)?;
+    writer.flush()?;
+
+    Ok(encoder.stats())
+}
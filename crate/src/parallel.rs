@@ -0,0 +1,150 @@
+//! Segment-parallel encoding of one PCM buffer
+//!
+//! The input is cut into segments of whole frames, one per worker thread,
+//! and each segment is encoded by its own encoder; the outputs are joined in
+//! input order. Every segment starts with an empty bit reservoir and
+//! filterbank history, so the result is a valid stream with the same frames
+//! as a serial encode but not byte-identical to it: the first granule of
+//! each segment is encoded without the overlap from the one before it.
+//! With a single thread the input is encoded serially, exactly as
+//! `encode_pcm_to_mp3` does.
+
+use crate::error::EncoderError;
+use crate::frame::{iter_frames, FrameHeader};
+use crate::mp3_encoder::{
+    encode_pcm_to_mp3, Mp3Encoder, Mp3EncoderConfig, Progress, PROGRESS_INTERVAL_FRAMES,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+/// Worker count for a requested thread count, 0 meaning one per core
+pub fn resolve_threads(threads: usize) -> usize {
+    if threads == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        threads
+    }
+}
+
+/// Encode interleaved PCM with up to `threads` workers (0: one per core)
+pub fn encode_pcm_parallel(
+    config: Mp3EncoderConfig,
+    pcm_data: &[i16],
+    threads: usize,
+) -> Result<Vec<u8>, EncoderError> {
+    encode_pcm_parallel_with_progress(config, pcm_data, threads, |_| {})
+}
+
+/// Encode interleaved PCM with up to `threads` workers and report progress
+///
+/// `progress` is called from the workers every `PROGRESS_INTERVAL_FRAMES`
+/// frames a worker encodes, and once at the end. Each report counts the
+/// frames and bytes of all workers together, so `frames_done` grows towards
+/// `total_frames` as in `encode_pcm_to_mp3_with_progress`, although reports
+/// from different workers may arrive out of order.
+pub fn encode_pcm_parallel_with_progress<F>(
+    config: Mp3EncoderConfig,
+    pcm_data: &[i16],
+    threads: usize,
+    progress: F,
+) -> Result<Vec<u8>, EncoderError>
+where
+    F: Fn(Progress) + Sync,
+{
+    let frame_len = Mp3Encoder::new(config.clone())?.samples_per_frame();
+    let trailing = config.trailing_silence_frames as u64;
+    let total_frames = pcm_data.len().div_ceil(frame_len) as u64 + trailing;
+
+    let workers = resolve_threads(threads).clamp(1, pcm_data.len().div_ceil(frame_len).max(1));
+    if workers == 1 {
+        let mp3_data = encode_pcm_to_mp3(config, pcm_data)?;
+        progress(Progress {
+            frames_done: total_frames,
+            total_frames,
+            bytes_out: mp3_data.len() as u64,
+        });
+        return Ok(mp3_data);
+    }
+
+    let segment_frames = pcm_data.len().div_ceil(frame_len).div_ceil(workers);
+    let segments: Vec<&[i16]> = pcm_data.chunks(segment_frames * frame_len).collect();
+    let frames_done = AtomicU64::new(0);
+    let bytes_out = AtomicU64::new(0);
+
+    let results: Vec<Result<Vec<u8>, EncoderError>> = thread::scope(|scope| {
+        let handles: Vec<_> = segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                // Silence is only appended after the end of the input
+                let last = index + 1 == segments.len();
+                let config =
+                    config
+                        .clone()
+                        .trailing_silence_frames(if last { trailing as u8 } else { 0 });
+                let (frames_done, bytes_out, progress) = (&frames_done, &bytes_out, &progress);
+                scope.spawn(move || {
+                    let mut encoder = Mp3Encoder::new(config)?;
+                    let mut mp3_data = Vec::new();
+                    for (frame_index, chunk) in segment.chunks(frame_len).enumerate() {
+                        for frame in encoder.encode_samples(chunk)? {
+                            bytes_out.fetch_add(frame.len() as u64, Ordering::Relaxed);
+                            mp3_data.extend(frame);
+                        }
+                        let done = frames_done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if (frame_index as u64 + 1).is_multiple_of(PROGRESS_INTERVAL_FRAMES) {
+                            progress(Progress {
+                                frames_done: done,
+                                total_frames,
+                                bytes_out: bytes_out.load(Ordering::Relaxed),
+                            });
+                        }
+                    }
+
+                    let tail = encoder.finish()?;
+                    bytes_out.fetch_add(tail.len() as u64, Ordering::Relaxed);
+                    mp3_data.extend(tail);
+                    if !last {
+                        let padding = complete_last_frame(&mut mp3_data);
+                        bytes_out.fetch_add(padding as u64, Ordering::Relaxed);
+                    }
+                    Ok(mp3_data)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("parallel encoding worker panicked"))
+            .collect()
+    });
+
+    let mut mp3_data = Vec::new();
+    for result in results {
+        mp3_data.extend(result?);
+    }
+    progress(Progress {
+        frames_done: total_frames,
+        total_frames,
+        bytes_out: mp3_data.len() as u64,
+    });
+    Ok(mp3_data)
+}
+
+/// Zero-fill the final frame of a segment to the length its header declares
+///
+/// The encoder leaves out the last partial word of the bitstream when it is
+/// flushed, which only shortens the stuffing at the end of the stream; in
+/// the middle of the stream the next frame must start where the header says.
+/// Returns the number of bytes added.
+fn complete_last_frame(mp3_data: &mut Vec<u8>) -> usize {
+    let mut frames = iter_frames(mp3_data);
+    frames.by_ref().count();
+    let end = frames.offset();
+    let Ok(header) = FrameHeader::parse(&mp3_data[end..], end) else {
+        return 0;
+    };
+    let padding = (end + header.frame_length()).saturating_sub(mp3_data.len());
+    mp3_data.resize(mp3_data.len() + padding, 0);
+    padding
+}
@@ -0,0 +1,418 @@
+//! PCM sample formats accepted by the encoder
+//!
+//! The encoder works on full-scale 32-bit fractions internally: the
+//! subband filter expects each sample as a signed value where `i32::MIN`
+//! is -1.0. Every input format is converted to that representation before
+//! it reaches the filterbank.
+//!
+//! Raw 16-bit PCM in byte buffers (network payloads, WAV data chunks) is
+//! converted with `bytes_to_i16_le`/`bytes_to_i16_be` and written back with
+//! `i16_to_bytes_le`. G.711 telephony data (µ-law and A-law, one byte per
+//! sample) is expanded to 16-bit PCM with `decode_mulaw` and `decode_alaw`.
+//!
+//! `analyze` and `PcmAnalyzer` measure the peak, RMS and DC offset of 16-bit
+//! PCM in one pass, to judge how hot a source is before choosing a bitrate.
+
+use crate::error::{InputDataError, InputResult};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::types::MAX_CHANNELS;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::time::Duration;
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for i16 {}
+    impl Sealed for i32 {}
+    impl Sealed for f32 {}
+}
+
+/// A PCM sample type the encoder can consume
+///
+/// Scaling conventions of `to_internal`:
+///
+/// - `i16`: shifted left by 16, so `i16::MIN` maps to `i32::MIN` and
+///   the result is identical to what shine feeds its filterbank.
+/// - `i32`: used as is, with `i32::MIN` as -1.0. 24-bit samples must be
+///   left-aligned (shifted left by 8) before they are passed in.
+/// - `f32`: -1.0..=1.0 maps onto the 16-bit full scale, so -1.0 gives
+///   `i16::MIN << 16` and 1.0 gives `i16::MAX << 16`. Values outside the
+///   range are clamped and NaN is treated as silence.
+///
+/// A sample is reported as clipped by `is_clipped` when it sits at the
+/// 16-bit full scale: `i16::MIN`/`i16::MAX`, an `i32` whose upper 16 bits
+/// are one of those, or an `f32` that reaches the clamp bounds (this covers
+/// every value outside -1.0..=1.0).
+///
+/// The trait is sealed; it cannot be implemented outside this crate.
+pub trait PcmSample: Copy + private::Sealed {
+    /// Convert to the encoder's internal full-scale 32-bit representation
+    fn to_internal(self) -> i32;
+
+    /// True if the sample is at (or had to be clamped to) full scale
+    fn is_clipped(self) -> bool;
+}
+
+impl PcmSample for i16 {
+    #[inline]
+    fn to_internal(self) -> i32 {
+        (self as i32) << 16
+    }
+
+    #[inline]
+    fn is_clipped(self) -> bool {
+        self == i16::MIN || self == i16::MAX
+    }
+}
+
+impl PcmSample for i32 {
+    #[inline]
+    fn to_internal(self) -> i32 {
+        self
+    }
+
+    #[inline]
+    fn is_clipped(self) -> bool {
+        ((self >> 16) as i16).is_clipped()
+    }
+}
+
+const F32_MIN: f64 = ((i16::MIN as i32) << 16) as f64;
+const F32_MAX: f64 = ((i16::MAX as i32) << 16) as f64;
+
+impl PcmSample for f32 {
+    #[inline]
+    fn to_internal(self) -> i32 {
+        if self.is_nan() {
+            return 0;
+        }
+        (self as f64 * 2147483648.0).round().clamp(F32_MIN, F32_MAX) as i32
+    }
+
+    #[inline]
+    fn is_clipped(self) -> bool {
+        let scaled = (self as f64 * 2147483648.0).round();
+        scaled <= F32_MIN || scaled >= F32_MAX
+    }
+}
+
+/// Size of a 16-bit sample in bytes
+const I16_BYTES: usize = 2;
+
+/// Append the little-endian 16-bit samples in `bytes` to `samples`
+///
+/// Fails without appending anything if `bytes` holds an odd number of
+/// bytes; a streaming caller converts the even prefix and keeps the last
+/// byte for the next buffer.
+pub fn bytes_to_i16_le(bytes: &[u8], samples: &mut Vec<i16>) -> InputResult<()> {
+    convert_bytes(bytes, samples, i16::from_le_bytes)
+}
+
+/// Append the big-endian 16-bit samples in `bytes` to `samples`
+///
+/// Fails like `bytes_to_i16_le` on an odd number of bytes.
+pub fn bytes_to_i16_be(bytes: &[u8], samples: &mut Vec<i16>) -> InputResult<()> {
+    convert_bytes(bytes, samples, i16::from_be_bytes)
+}
+
+/// Append `samples` to `bytes` as little-endian 16-bit PCM
+pub fn i16_to_bytes_le(samples: &[i16], bytes: &mut Vec<u8>) {
+    bytes.reserve(samples.len() * I16_BYTES);
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+}
+
+/// Expand G.711 µ-law codewords to 16-bit PCM
+pub fn decode_mulaw(codes: &[u8]) -> Vec<i16> {
+    codes.iter().map(|&code| mulaw_to_i16(code)).collect()
+}
+
+/// Expand G.711 A-law codewords to 16-bit PCM
+pub fn decode_alaw(codes: &[u8]) -> Vec<i16> {
+    codes.iter().map(|&code| alaw_to_i16(code)).collect()
+}
+
+/// µ-law codeword to its 14-bit linear value, scaled to 16 bits
+///
+/// Codewords are stored inverted; the biased magnitude is the mantissa
+/// with its implicit leading one, shifted by the segment.
+fn mulaw_to_i16(code: u8) -> i16 {
+    const BIAS: i32 = 0x84;
+    let code = !code;
+    let magnitude = ((((code & 0x0F) as i32) << 3) + BIAS) << ((code & 0x70) >> 4);
+    if code & 0x80 != 0 {
+        (BIAS - magnitude) as i16
+    } else {
+        (magnitude - BIAS) as i16
+    }
+}
+
+/// A-law codeword to its 13-bit linear value, scaled to 16 bits
+///
+/// Even bits are stored inverted; segment 0 has no implicit leading one.
+fn alaw_to_i16(code: u8) -> i16 {
+    let code = code ^ 0x55;
+    let mantissa = ((code & 0x0F) as i32) << 4;
+    let magnitude = match (code & 0x70) >> 4 {
+        0 => mantissa + 8,
+        segment => (mantissa + 0x108) << (segment - 1),
+    };
+    if code & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+fn convert_bytes(
+    bytes: &[u8],
+    samples: &mut Vec<i16>,
+    from_bytes: fn([u8; I16_BYTES]) -> i16,
+) -> InputResult<()> {
+    if !bytes.len().is_multiple_of(I16_BYTES) {
+        return Err(InputDataError::OddByteCount(bytes.len()));
+    }
+    samples.extend(
+        bytes
+            .chunks_exact(I16_BYTES)
+            .map(|pair| from_bytes([pair[0], pair[1]])),
+    );
+    Ok(())
+}
+
+/// Downmix interleaved stereo samples to mono by averaging each L/R pair
+///
+/// A trailing unpaired sample is dropped.
+pub fn downmix_to_mono(interleaved: &[i16]) -> Vec<i16> {
+    interleaved
+        .chunks_exact(2)
+        .map(|pair| ((pair[0] as i32 + pair[1] as i32) / 2) as i16)
+        .collect()
+}
+
+/// Take one channel out of interleaved samples
+///
+/// Returns every `channels`-th sample starting at `index`. A trailing
+/// partial frame is dropped.
+///
+/// # Panics
+///
+/// Panics if `index` is not less than `channels`.
+pub fn extract_channel(interleaved: &[i16], channels: usize, index: usize) -> Vec<i16> {
+    assert!(
+        index < channels,
+        "channel {} out of range for {} channels",
+        index,
+        channels
+    );
+    interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame[index])
+        .collect()
+}
+
+/// Running count of clipped input samples
+///
+/// Tracks how many samples sit at full scale and the longest run of
+/// consecutive clipped samples within one channel. Interleaved input may be
+/// fed in arbitrary chunks; the channel position carries over between calls.
+#[derive(Debug, Clone)]
+pub struct ClipDetector {
+    channels: usize,
+    position: usize,
+    runs: [u64; MAX_CHANNELS],
+    samples: u64,
+    clipped: u64,
+    max_run: u64,
+}
+
+impl ClipDetector {
+    /// Create a detector for `channels` interleaved channels (1 or 2)
+    pub fn new(channels: usize) -> Self {
+        Self {
+            channels: channels.clamp(1, MAX_CHANNELS),
+            position: 0,
+            runs: [0; MAX_CHANNELS],
+            samples: 0,
+            clipped: 0,
+            max_run: 0,
+        }
+    }
+
+    /// Scan interleaved samples
+    pub fn update_interleaved<S: PcmSample>(&mut self, interleaved: &[S]) {
+        for &sample in interleaved {
+            self.record(self.position, sample.is_clipped());
+            self.position = (self.position + 1) % self.channels;
+        }
+    }
+
+    /// Scan one slice per channel
+    pub fn update_planar<S: PcmSample>(&mut self, channels: &[&[S]]) {
+        for (channel, data) in channels.iter().enumerate().take(self.channels) {
+            for &sample in data.iter() {
+                self.record(channel, sample.is_clipped());
+            }
+        }
+    }
+
+    /// Account for `count` interleaved zero samples without scanning them
+    pub fn update_silence(&mut self, count: usize) {
+        self.samples += count as u64;
+        self.runs = [0; MAX_CHANNELS];
+        self.position = (self.position + count) % self.channels;
+    }
+
+    fn record(&mut self, channel: usize, clipped: bool) {
+        self.samples += 1;
+        if clipped {
+            self.clipped += 1;
+            self.runs[channel] += 1;
+            self.max_run = self.max_run.max(self.runs[channel]);
+        } else {
+            self.runs[channel] = 0;
+        }
+    }
+
+    /// Number of samples scanned, all channels together
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Number of clipped samples, all channels together
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped
+    }
+
+    /// Longest run of consecutive clipped samples in a single channel
+    pub fn max_consecutive_clipped(&self) -> u64 {
+        self.max_run
+    }
+
+    /// Fraction of scanned samples that were clipped, 0.0 when nothing was scanned
+    pub fn clipped_ratio(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.clipped as f64 / self.samples as f64
+        }
+    }
+}
+
+/// Full scale of 16-bit PCM: `i16::MIN` is -1.0
+const FULL_SCALE: f64 = 32768.0;
+
+/// DC offset, as a fraction of full scale, above which a warning is due:
+/// the encoder spends bits on the constant that carry no sound
+pub const DC_OFFSET_WARNING: f64 = 0.01;
+
+/// Levels of a stretch of 16-bit PCM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PcmStats {
+    /// Largest sample magnitude in dBFS, negative infinity for silence
+    pub peak_dbfs: f64,
+    /// RMS level over all channels in dBFS (a full-scale sine is -3 dBFS),
+    /// negative infinity for silence
+    pub rms_dbfs: f64,
+    /// Mean of the channel with the largest offset, as a signed fraction of
+    /// full scale
+    pub dc_offset: f64,
+    /// Length of the audio
+    pub duration: Duration,
+}
+
+impl PcmStats {
+    /// True if the DC offset is large enough to waste encoder bits
+    pub fn has_dc_offset(&self) -> bool {
+        self.dc_offset.abs() > DC_OFFSET_WARNING
+    }
+}
+
+/// Streaming peak, RMS and DC offset measurement
+///
+/// Interleaved input may be fed in arbitrary chunks; the channel position
+/// carries over between calls.
+#[derive(Debug, Clone)]
+pub struct PcmAnalyzer {
+    channels: usize,
+    sample_rate: u32,
+    position: usize,
+    samples: u64,
+    peak: u32,
+    sum_squares: f64,
+    sums: [i64; MAX_CHANNELS],
+}
+
+impl PcmAnalyzer {
+    /// Create an analyzer for `channels` interleaved channels (1 or 2)
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        Self {
+            channels: channels.clamp(1, MAX_CHANNELS),
+            sample_rate,
+            position: 0,
+            samples: 0,
+            peak: 0,
+            sum_squares: 0.0,
+            sums: [0; MAX_CHANNELS],
+        }
+    }
+
+    /// Scan interleaved samples
+    pub fn feed(&mut self, interleaved: &[i16]) {
+        for &sample in interleaved {
+            self.peak = self.peak.max(sample.unsigned_abs() as u32);
+            self.sum_squares += sample as f64 * sample as f64;
+            self.sums[self.position] += sample as i64;
+            self.position = (self.position + 1) % self.channels;
+        }
+        self.samples += interleaved.len() as u64;
+    }
+
+    /// Levels of everything fed so far
+    pub fn finish(&self) -> PcmStats {
+        let frames = self.samples / self.channels as u64;
+        let to_dbfs = |level: f64| 20.0 * (level / FULL_SCALE).log10();
+        let (rms_dbfs, dc_offset) = if self.samples == 0 {
+            (f64::NEG_INFINITY, 0.0)
+        } else {
+            let rms = (self.sum_squares / self.samples as f64).sqrt();
+            let dc = self.sums[..self.channels]
+                .iter()
+                .enumerate()
+                .map(|(channel, &sum)| {
+                    // Channels fed an extra sample of a partial frame count it
+                    let count =
+                        frames + (channel < (self.samples % self.channels as u64) as usize) as u64;
+                    sum as f64 / count.max(1) as f64 / FULL_SCALE
+                })
+                .fold(0.0, |largest: f64, mean| {
+                    if mean.abs() > largest.abs() {
+                        mean
+                    } else {
+                        largest
+                    }
+                });
+            (to_dbfs(rms), dc)
+        };
+        PcmStats {
+            peak_dbfs: to_dbfs(self.peak as f64),
+            rms_dbfs,
+            dc_offset,
+            duration: if self.sample_rate > 0 {
+                Duration::from_secs_f64(frames as f64 / self.sample_rate as f64)
+            } else {
+                Duration::ZERO
+            },
+        }
+    }
+}
+
+/// Peak, RMS and DC offset of interleaved 16-bit PCM in one pass
+pub fn analyze(pcm: &[i16], channels: usize, sample_rate: u32) -> PcmStats {
+    let mut analyzer = PcmAnalyzer::new(channels, sample_rate);
+    analyzer.feed(pcm);
+    analyzer.finish()
+}
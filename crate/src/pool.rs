@@ -0,0 +1,166 @@
+//! Pool of reusable encoders for many short encodes
+//!
+//! Creating an encoder computes the filterbank, MDCT and quantizer tables
+//! and allocates the encoder state. A pool keeps finished encoders and hands
+//! them out again after `Mp3Encoder::reset`, so a service encoding many
+//! short clips with the same settings pays that cost only once per pooled
+//! encoder. Every pool serves a single configuration; use one pool per set
+//! of settings.
+
+use crate::error::EncoderError;
+use crate::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Counters of a pool since its creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Requests served by an idle encoder
+    pub hits: u64,
+    /// Requests that found no idle encoder
+    pub misses: u64,
+    /// Encoders created by the pool
+    pub created: u64,
+    /// Encoders dropped on return because the pool was full
+    pub discarded: u64,
+}
+
+/// Thread-safe pool of encoders with one configuration
+#[derive(Debug)]
+pub struct Mp3EncoderPool {
+    config: Mp3EncoderConfig,
+    max_idle: usize,
+    idle: Mutex<Vec<Mp3Encoder>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    created: AtomicU64,
+    discarded: AtomicU64,
+}
+
+impl Mp3EncoderPool {
+    /// Create an empty pool keeping at most `max_idle` idle encoders
+    ///
+    /// The configuration is validated here, so `get` only fails if an
+    /// encoder cannot be created for other reasons.
+    pub fn new(config: Mp3EncoderConfig, max_idle: usize) -> Result<Self, EncoderError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            max_idle,
+            idle: Mutex::new(Vec::with_capacity(max_idle)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            created: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        })
+    }
+
+    /// Take an encoder in its initial state, creating one if none is idle
+    ///
+    /// The encoder goes back to the pool when the guard is dropped.
+    pub fn get(&self) -> Result<PooledEncoder<'_>, EncoderError> {
+        let idle = self.lock_idle().pop();
+        let encoder = match idle {
+            Some(encoder) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                encoder
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                let encoder = Mp3Encoder::new(self.config.clone())?;
+                self.created.fetch_add(1, Ordering::Relaxed);
+                encoder
+            }
+        };
+        Ok(PooledEncoder {
+            pool: self,
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Configuration of the pooled encoders
+    pub fn config(&self) -> &Mp3EncoderConfig {
+        &self.config
+    }
+
+    /// Largest number of idle encoders kept
+    pub fn max_idle(&self) -> usize {
+        self.max_idle
+    }
+
+    /// Number of encoders waiting in the pool
+    pub fn idle(&self) -> usize {
+        self.lock_idle().len()
+    }
+
+    /// Counters since the pool was created
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            created: self.created.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset an encoder and keep it if there is room
+    fn release(&self, mut encoder: Mp3Encoder) {
+        encoder.reset();
+        // Undo a runtime emphasis change so the next user gets the pool's settings
+        if encoder.config().emphasis != self.config.emphasis {
+            encoder.set_emphasis(self.config.emphasis);
+        }
+
+        let mut idle = self.lock_idle();
+        if idle.len() < self.max_idle {
+            idle.push(encoder);
+        } else {
+            drop(idle);
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The idle list; a panic while holding the lock cannot leave it invalid
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<Mp3Encoder>> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Encoder borrowed from a `Mp3EncoderPool`
+///
+/// Dereferences to the `Mp3Encoder`; dropping the guard resets the encoder
+/// and returns it to the pool.
+#[derive(Debug)]
+pub struct PooledEncoder<'a> {
+    pool: &'a Mp3EncoderPool,
+    encoder: Option<Mp3Encoder>,
+}
+
+impl Deref for PooledEncoder<'_> {
+    type Target = Mp3Encoder;
+
+    fn deref(&self) -> &Mp3Encoder {
+        self.encoder
+            .as_ref()
+            .expect("encoder is present until drop")
+    }
+}
+
+impl DerefMut for PooledEncoder<'_> {
+    fn deref_mut(&mut self) -> &mut Mp3Encoder {
+        self.encoder
+            .as_mut()
+            .expect("encoder is present until drop")
+    }
+}
+
+impl Drop for PooledEncoder<'_> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            self.pool.release(encoder);
+        }
+    }
+}
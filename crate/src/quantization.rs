@@ -7,19 +7,41 @@
 //! The implementation strictly follows the shine reference implementation
 //! in ref/shine/src/lib/l3loop.c
 
-use crate::huffman::SHINE_HUFFMAN_TABLE;
-use crate::tables::{SHINE_SCALE_FACT_BAND_INDEX, SHINE_SLEN1_TAB, SHINE_SLEN2_TAB};
-use crate::types::{GrInfo, ShineGlobalConfig, ShinePsyXmin, GRANULE_SIZE};
-use std::f64::consts::LN_2;
+use crate::constants::{GRANULE_SIZE, SCALEFACTOR_BANDS_LONG};
+use crate::encoder::{shine_initialise, ShineConfig};
+use crate::error::EncodingResult;
+use crate::huffman::{is_bigv_table_available, SHINE_HUFFMAN_TABLE};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::mp3_encoder::Effort;
+use crate::tables::{
+    get_sample_rate_index, SHINE_PRETAB, SHINE_SCALE_FACT_BAND_INDEX, SHINE_SLEN1_TAB,
+    SHINE_SLEN2_TAB,
+};
+use crate::types::{GrInfo, ShineGlobalConfig, ShinePsyXmin};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::f64::consts::LN_2;
+use lazy_static::lazy_static;
 
 /// Constants from shine (matches l3loop.c exactly)
 #[allow(dead_code)] // May be used in future implementations
 const CBLIMIT: usize = 21;
-const SFB_LMAX: usize = 22;
+const SFB_LMAX: usize = SCALEFACTOR_BANDS_LONG + 1;
 const EN_TOT_KRIT: i32 = 10;
 const EN_DIF_KRIT: i32 = 100;
 const EN_SCFSI_BAND_KRIT: i32 = 10;
 const XM_SCFSI_BAND_KRIT: i32 = 10;
+
+/// Largest quantized value the Huffman tables can code (within table range)
+pub const IX_MAX_VALUE: i32 = 8192;
+/// Returned by quantize when the step size is too small to stay within table range
+pub const QUANTIZE_OVERFLOW: i32 = 16384;
+/// Largest quantizer step size, global_gain = step + 210 is an 8 bit field
+pub const MAX_QUANTIZER_STEP: i32 = 255 - 210;
+/// Step sizes searched around the previous granule's step with `Effort::Fast`,
+/// four quantizations instead of the seven of the full range
+const FAST_SEARCH_WINDOW: i32 = 16;
 /// Multiplication macros matching shine's mult_noarch_gcc.h
 /// These implement fixed-point arithmetic operations
 ///
@@ -36,9 +58,12 @@ pub fn mulr(a: i32, b: i32) -> i32 {
 }
 
 /// Absolute value function (matches shine labs)
+///
+/// `i32::MIN`, which the MDCT or a saturated mid/side sum can produce,
+/// saturates to `i32::MAX` instead of overflowing as C's labs does.
 #[inline]
 pub fn labs(x: i32) -> i32 {
-    x.abs()
+    x.saturating_abs()
 }
 
 /// Inner loop: find optimal quantization step size for given scalefactors
@@ -75,7 +100,7 @@ pub fn shine_inner_loop(
 
         loop {
             quantizer_step_size += 1;
-            if quantize(ix, quantizer_step_size, config) <= 8192 {
+            if quantize(ix, quantizer_step_size, config) <= IX_MAX_VALUE {
                 break;
             }
         }
@@ -104,20 +129,17 @@ pub fn shine_inner_loop(
 
         // Subdivide and select tables - use temporary variables to avoid borrowing conflicts
         {
-            let samplerate = config.wave.samplerate;
-            let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
-            subdivide_with_samplerate(cod_info, samplerate);
-        }
-
-        {
+            let samplerate_index = config.mpeg.samplerate_index as usize;
+            let effort = config.effort;
             let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
-            bigv_tab_select(ix, cod_info); // codebook selection
-            bvbits = bigv_bitcount(ix, cod_info); // bit count
+            bvbits = select_bigv_tables(ix, cod_info, samplerate_index, effort);
         }
 
         bits += bvbits;
 
-        if bits <= max_bits {
+        // The largest step size quantizes everything to zero, so stop there
+        // even if max_bits cannot be met (shine would index past its tables)
+        if bits <= max_bits || quantizer_step_size >= MAX_QUANTIZER_STEP {
             break;
         }
     }
@@ -138,16 +160,43 @@ pub fn shine_outer_loop(
     ch: i32,
     config: &mut ShineGlobalConfig,
 ) -> i32 {
-    // Extract samplerate to avoid borrowing conflicts
-    let samplerate = config.wave.samplerate;
+    // Extract samplerate index to avoid borrowing conflicts
+    let samplerate_index = config.mpeg.samplerate_index as usize;
+
+    // Fold the scalefactors into the spectrum. Without a psychoacoustic model
+    // every scalefactor is zero and the spectrum is quantized unchanged.
+    if config.mpeg.version == 3 {
+        // MPEG_I
+        let (scalefac_l, cod_info) = (
+            &mut config.scalefactor.l[gr as usize][ch as usize],
+            &mut config.side_info.gr[gr as usize].ch[ch as usize].tt,
+        );
+        shine_preemphasis(scalefac_l, cod_info);
+    }
+    if let Some(scalefac_compress) = shine_select_scalefac_compress(gr, ch, config) {
+        config.side_info.gr[gr as usize].ch[ch as usize]
+            .tt
+            .scalefac_compress = scalefac_compress;
+    }
+    shine_amplify_scalefac_bands(gr, ch, config);
+
+    // The previous granule of this channel; for the first granule its slot
+    // still holds the last granule of the previous frame
+    let granules = config.mpeg.granules_per_frame;
+    let previous_step = config.side_info.gr[((gr + granules - 1) % granules) as usize].ch
+        [ch as usize]
+        .tt
+        .quantizer_step_size;
 
     // Direct access to cod_info without cloning - major performance improvement
     let quantizer_step_size = bin_search_step_size_with_samplerate(
         max_bits,
         ix,
         &mut config.side_info.gr[gr as usize].ch[ch as usize].tt,
-        samplerate,
+        samplerate_index,
         &mut config.l3loop,
+        config.effort,
+        previous_step,
     );
 
     let part2_length = part2_length(gr, ch, config) as u32;
@@ -169,11 +218,136 @@ pub fn shine_outer_loop(
     cod_info.part2_3_length as i32
 }
 
+/// Point the loop state at the MDCT coefficients of one granule
+///
+/// Precalculates the square, abs, and maximum, for use later on.
+fn load_granule_spectrum(config: &mut ShineGlobalConfig, ch: i32, gr: i32) {
+    config.l3loop.xr = config.mdct_freq[ch as usize][gr as usize].as_ptr() as *mut i32;
+
+    config.l3loop.xrmax = 0;
+    for i in (0..GRANULE_SIZE).rev() {
+        let xr_val = unsafe { *config.l3loop.xr.add(i) };
+        config.l3loop.xrsq[i] = mulsr(xr_val, xr_val);
+        config.l3loop.xrabs[i] = labs(xr_val);
+        if config.l3loop.xrabs[i] > config.l3loop.xrmax {
+            config.l3loop.xrmax = config.l3loop.xrabs[i];
+        }
+    }
+}
+
+/// Quantize a single granule of MDCT coefficients on its own
+///
+/// Runs the outer and inner loops on a scratch encoder state built from
+/// `config`, so no frame, reservoir or bitstream state is involved. The
+/// granule is treated as the first granule of channel 0: scalefactors are
+/// always sent (no SCFSI) and `max_bits` is used as given. Returns the
+/// granule info, with `global_gain` set as in a real frame, and the
+/// quantized coefficients.
+pub fn encode_granule(
+    mdct_coeffs: &[i32; GRANULE_SIZE],
+    config: &ShineConfig,
+    max_bits: i32,
+) -> EncodingResult<(GrInfo, Vec<i32>)> {
+    let mut state = shine_initialise(config)?;
+    state.mdct_freq[0][0] = *mdct_coeffs;
+    load_granule_spectrum(&mut state, 0, 0);
+
+    let mut l3_xmin = ShinePsyXmin::default();
+    state.side_info.gr[0].ch[0].tt.sfb_lmax = (SFB_LMAX - 1) as u32;
+    calc_xmin(&state, &mut l3_xmin, 0, 0);
+
+    let mut ix = vec![0i32; GRANULE_SIZE];
+    if state.l3loop.xrmax != 0 {
+        let length = shine_outer_loop(max_bits, &mut l3_xmin, &mut ix, 0, 0, &mut state);
+        state.side_info.gr[0].ch[0].tt.part2_3_length = length as u32;
+    }
+
+    let mut cod_info = state.side_info.gr[0].ch[0].tt.clone();
+    cod_info.global_gain = (cod_info.quantizer_step_size + 210) as u32;
+    Ok((cod_info, ix))
+}
+
+/// Running noise-to-mask ratio of the encoded granules
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NmrStats {
+    /// Sum of the granule NMRs in dB
+    pub sum_db: f64,
+    /// Granules that coded any values
+    pub granules: u64,
+}
+
+impl NmrStats {
+    /// Average NMR in dB over the granules, `None` before the first one
+    pub fn average_db(&self) -> Option<f64> {
+        (self.granules > 0).then(|| self.sum_db / self.granules as f64)
+    }
+}
+
+/// Lowest NMR a band can report, for bands quantized without error
+const NMR_FLOOR_DB: f64 = -100.0;
+
+/// Largest quantized magnitude: big_values table 15 plus 13 linbits
+const MAX_QUANTIZED: usize = 15 + 8191;
+
+lazy_static! {
+    /// |ix|^(4/3) for every quantized magnitude
+    static ref POW43: Vec<f64> = (0..=MAX_QUANTIZED)
+        .map(|ix| (ix as f64).powf(4.0 / 3.0))
+        .collect();
+}
+
+/// Noise-to-mask ratio of a quantized granule in dB
+///
+/// The quantization noise of each scalefactor band is the energy of the
+/// difference between the MDCT values and what a decoder reconstructs from
+/// `l3_enc`. It is compared with the allowed distortion in `l3_xmin`; shine
+/// has no psychoacoustic model and leaves that at zero, in which case the
+/// band energy is the reference, so the result is the noise-to-signal
+/// ratio. The granule NMR is the average over the bands that carry signal.
+/// Returns `None` for a silent granule.
+fn granule_nmr_db(
+    config: &ShineGlobalConfig,
+    l3_xmin: &ShinePsyXmin,
+    gr: usize,
+    ch: usize,
+) -> Option<f64> {
+    let cod_info = &config.side_info.gr[gr].ch[ch].tt;
+    let scalefac_l = &config.scalefactor.l[gr][ch];
+    let bands = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
+    let xr = &config.mdct_freq[ch][gr];
+    let ix = &config.l3_enc[ch][gr];
+    let global_gain = (cod_info.quantizer_step_size + 210) as u32;
+
+    let mut sum_db = 0.0;
+    let mut count = 0;
+    for sfb in 0..=SCALEFACTOR_BANDS_LONG {
+        let scalefac = effective_scalefac(scalefac_l, cod_info, sfb);
+        // shine_dequantize with the power taken from the table
+        let gain = shine_dequantize(1, global_gain, scalefac, cod_info.scalefac_scale);
+        let (mut signal, mut noise) = (0.0, 0.0);
+        for i in bands[sfb] as usize..bands[sfb + 1] as usize {
+            let value = (xr[i] as f64 / 2147483648.0).abs();
+            let magnitude = (ix[i].unsigned_abs() as usize).min(MAX_QUANTIZED);
+            let decoded = POW43[magnitude] * gain;
+            signal += value * value;
+            noise += (value - decoded) * (value - decoded);
+        }
+        if signal == 0.0 {
+            continue;
+        }
+        let xmin = l3_xmin.l[gr][ch].get(sfb).copied().unwrap_or(0.0);
+        let mask = if xmin > 0.0 { xmin } else { signal };
+        sum_db += (10.0 * (noise / mask).log10()).max(NMR_FLOOR_DB);
+        count += 1;
+    }
+    (count > 0).then(|| sum_db / count as f64)
+}
+
 /// Main iteration loop for encoding
 /// Corresponds to shine_iteration_loop() in l3loop.c
 pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
     #[cfg(feature = "diagnostics")]
-    let frame_num = crate::get_current_frame_number();
+    let frame_num = config.frame_number;
 
     let mut l3_xmin = ShinePsyXmin::default();
     let mut ix: *mut i32;
@@ -187,25 +361,12 @@ pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
         for gr in 0..config.mpeg.granules_per_frame {
             // setup pointers
             ix = config.l3_enc[ch as usize][gr as usize].as_mut_ptr();
-            config.l3loop.xr = config.mdct_freq[ch as usize][gr as usize].as_ptr() as *mut i32;
-
-            // Precalculate the square, abs, and maximum, for use later on.
-            config.l3loop.xrmax = 0;
-            for i in (0..GRANULE_SIZE).rev() {
-                let xr_val = unsafe { *config.l3loop.xr.add(i) };
-                config.l3loop.xrsq[i] = mulsr(xr_val, xr_val);
-                config.l3loop.xrabs[i] = labs(xr_val);
-                if config.l3loop.xrabs[i] > config.l3loop.xrmax {
-                    config.l3loop.xrmax = config.l3loop.xrabs[i];
-                }
-            }
+            load_granule_spectrum(config, ch, gr);
 
             // Set sfb_lmax and calculate xmin
-            {
-                let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
-                cod_info.sfb_lmax = (SFB_LMAX - 1) as u32; // gr_deco
-                calc_xmin(&config.ratio, cod_info, &mut l3_xmin, gr, ch);
-            }
+            config.side_info.gr[gr as usize].ch[ch as usize].tt.sfb_lmax = (SFB_LMAX - 1) as u32; // gr_deco
+            let psy_model = load_psy_ratio(config, gr, ch);
+            calc_xmin(config, &mut l3_xmin, gr, ch);
 
             if config.mpeg.version == 3 {
                 // MPEG_I = 3
@@ -236,6 +397,12 @@ pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
                 .iter_mut()
                 .for_each(|row| row.fill(0));
 
+            // Scalefactors from the caller's model; each granule sends its own
+            if psy_model && config.mpeg.version == 3 {
+                config.side_info.scfsi[ch as usize] = [0; 4];
+                shine_psy_scalefactors(&l3_xmin, gr, ch, config);
+            }
+
             // Reset cod_info values
             {
                 let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
@@ -258,13 +425,17 @@ pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
 
             // all spectral values zero ?
             let _part2_3_length = if config.l3loop.xrmax != 0 {
-                let ix_slice = unsafe { std::slice::from_raw_parts_mut(ix, GRANULE_SIZE) };
+                let ix_slice = unsafe { core::slice::from_raw_parts_mut(ix, GRANULE_SIZE) };
                 let length =
                     shine_outer_loop(max_bits, &mut l3_xmin, ix_slice, gr, ch, config) as u32;
 
                 // Update part2_3_length after outer loop
                 let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
                 cod_info.part2_3_length = length;
+                if let Some(nmr) = granule_nmr_db(config, &l3_xmin, gr as usize, ch as usize) {
+                    config.nmr.sum_db += nmr;
+                    config.nmr.granules += 1;
+                }
                 length
             } else {
                 0u32
@@ -287,6 +458,12 @@ pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
 
                 // Manual reservoir adjustment to avoid borrowing conflicts
                 config.resv_size += (mean_bits / channels) - part2_3_length as i32;
+                config.reservoir.usage.granules[gr as usize][ch as usize] =
+                    crate::reservoir::GranuleBits {
+                        allocated: max_bits as u32,
+                        used: part2_3_length,
+                    };
+                config.reservoir.set_available_bits(config.resv_size);
 
                 // Set global gain AFTER reservoir adjustment (matches Shine)
                 let cod_info = &mut config.side_info.gr[gr as usize].ch[ch as usize].tt;
@@ -332,6 +509,7 @@ pub fn shine_iteration_loop(config: &mut ShineGlobalConfig) {
                 cod_info.part2_3_length, // Final value after all reservoir adjustments
                 cod_info.quantizer_step_size,
                 cod_info.global_gain,
+                &config.l3_enc[0][0][..crate::diagnostics::CAPTURED_L3_ENC_VALUES],
             );
         }
     }
@@ -346,20 +524,7 @@ fn calc_scfsi(l3_xmin: &mut ShinePsyXmin, ch: i32, gr: i32, config: &mut ShineGl
     let mut condition = 0;
     let mut _temp: i32;
 
-    let samplerate_index = match config.wave.samplerate {
-        44100 => 0,
-        48000 => 1,
-        32000 => 2,
-        22050 => 3,
-        24000 => 4,
-        16000 => 5,
-        11025 => 6,
-        12000 => 7,
-        8000 => 8,
-        _ => 0,
-    };
-
-    let scalefac_band_long = &SHINE_SCALE_FACT_BAND_INDEX[samplerate_index];
+    let scalefac_band_long = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
 
     config.l3loop.xrmaxl[gr as usize] = config.l3loop.xrmax;
 
@@ -376,7 +541,7 @@ fn calc_scfsi(l3_xmin: &mut ShinePsyXmin, ch: i32, gr: i32, config: &mut ShineGl
 
     // the energy of each scalefactor band, en
     // the allowed distortion of each scalefactor band, xm
-    for sfb in (0..21).rev() {
+    for sfb in (0..SCALEFACTOR_BANDS_LONG).rev() {
         let start = scalefac_band_long[sfb] as usize;
         let end = scalefac_band_long[sfb + 1] as usize;
 
@@ -410,7 +575,7 @@ fn calc_scfsi(l3_xmin: &mut ShinePsyXmin, ch: i32, gr: i32, config: &mut ShineGl
             condition += 1;
         }
         let mut tp = 0;
-        for sfb in (0..21).rev() {
+        for sfb in (0..SCALEFACTOR_BANDS_LONG).rev() {
             tp += (config.l3loop.en[0][sfb] - config.l3loop.en[1][sfb]).abs();
         }
         if tp < EN_DIF_KRIT {
@@ -445,6 +610,10 @@ fn calc_scfsi(l3_xmin: &mut ShinePsyXmin, ch: i32, gr: i32, config: &mut ShineGl
 
 /// Calculate part2 length (scalefactors)
 /// Corresponds to part2_length() in l3loop.c
+///
+/// Bands that granule 1 shares with granule 0 through SCFSI are not sent
+/// again and cost no bits; the result feeds the Huffman budget and
+/// part2_3_length in `shine_outer_loop`.
 pub fn part2_length(gr: i32, ch: i32, config: &mut ShineGlobalConfig) -> i32 {
     let mut bits = 0;
     let gi = &config.side_info.gr[gr as usize].ch[ch as usize].tt;
@@ -471,19 +640,225 @@ pub fn part2_length(gr: i32, ch: i32, config: &mut ShineGlobalConfig) -> i32 {
     bits
 }
 
+/// Scalefactor that is effectively applied to a long block band, including
+/// the implied preemphasis when preflag is set
+#[inline]
+pub fn effective_scalefac(scalefac_l: &[i32; 22], cod_info: &GrInfo, sfb: usize) -> i32 {
+    let pretab = if cod_info.preflag != 0 && sfb < SHINE_PRETAB.len() {
+        SHINE_PRETAB[sfb]
+    } else {
+        0
+    };
+    scalefac_l[sfb] + pretab
+}
+
+/// Switch on preemphasis when every upper band has been amplified
+///
+/// This is the ISO 11172-3 condition: if each scalefactor of bands 11..20 is
+/// at least the pretab value, preflag is set and the pretab is subtracted
+/// from the transmitted scalefactors. The effective amplification of every
+/// band is unchanged, but fewer explicit scalefactor bits are needed.
+/// Returns true when preflag was switched on.
+pub fn shine_preemphasis(scalefac_l: &mut [i32; 22], cod_info: &mut GrInfo) -> bool {
+    if cod_info.preflag != 0 {
+        return false;
+    }
+
+    let amplified = (11..SHINE_PRETAB.len()).all(|sfb| scalefac_l[sfb] >= SHINE_PRETAB[sfb]);
+    if !amplified {
+        return false;
+    }
+
+    cod_info.preflag = 1;
+    for sfb in 11..SHINE_PRETAB.len() {
+        scalefac_l[sfb] -= SHINE_PRETAB[sfb];
+    }
+    true
+}
+
+/// Choose the cheapest scalefac_compress able to carry the granule's scalefactors
+///
+/// Returns None when all scalefactors are zero (scalefac_compress 0 is kept)
+/// or when they are too large to be transmitted.
+pub fn shine_select_scalefac_compress(gr: i32, ch: i32, config: &ShineGlobalConfig) -> Option<u32> {
+    let scalefac_l = &config.scalefactor.l[gr as usize][ch as usize];
+    if scalefac_l[..SCALEFACTOR_BANDS_LONG]
+        .iter()
+        .all(|&sf| sf == 0)
+    {
+        return None;
+    }
+
+    let scfsi = &config.side_info.scfsi[ch as usize];
+    let sends = |band: usize| gr == 0 || scfsi[band] == 0;
+    let max1 = scalefac_l[..11].iter().copied().max().unwrap_or(0);
+    let max2 = scalefac_l[11..SCALEFACTOR_BANDS_LONG]
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0);
+
+    (0..SHINE_SLEN1_TAB.len())
+        .filter(|&compress| {
+            max1 < (1 << SHINE_SLEN1_TAB[compress]) && max2 < (1 << SHINE_SLEN2_TAB[compress])
+        })
+        .min_by_key(|&compress| {
+            let (slen1, slen2) = (SHINE_SLEN1_TAB[compress], SHINE_SLEN2_TAB[compress]);
+            [
+                (0, 6 * slen1),
+                (1, 5 * slen1),
+                (2, 5 * slen2),
+                (3, 5 * slen2),
+            ]
+            .iter()
+            .filter(|(band, _)| sends(*band))
+            .map(|(_, bits)| bits)
+            .sum::<i32>()
+        })
+        .map(|compress| compress as u32)
+}
+
+/// Amplify the spectrum by the granule's effective scalefactors
+///
+/// Each band is scaled by 2^((1 + scalefac_scale) / 2 * scalefac), which the
+/// decoder undoes when it applies the same scalefactors. The amplified copy
+/// is kept in `l3loop.xr_amp` and `l3loop.xr` is pointed at it. Returns
+/// false, leaving the loop state untouched, when no band is amplified.
+pub fn shine_amplify_scalefac_bands(gr: i32, ch: i32, config: &mut ShineGlobalConfig) -> bool {
+    let scalefac_l = &config.scalefactor.l[gr as usize][ch as usize];
+    let cod_info = &config.side_info.gr[gr as usize].ch[ch as usize].tt;
+    if (0..SCALEFACTOR_BANDS_LONG).all(|sfb| effective_scalefac(scalefac_l, cod_info, sfb) == 0) {
+        return false;
+    }
+
+    let scalefac_band_long = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
+    let ifqstep = 0.5 * (1 + cod_info.scalefac_scale) as f64;
+    let xr = &config.mdct_freq[ch as usize][gr as usize];
+    let l3loop = &mut config.l3loop;
+
+    l3loop.xr_amp.copy_from_slice(&xr[..]);
+    for sfb in 0..SCALEFACTOR_BANDS_LONG {
+        let gain = (ifqstep * effective_scalefac(scalefac_l, cod_info, sfb) as f64).exp2();
+        let start = scalefac_band_long[sfb] as usize;
+        let end = scalefac_band_long[sfb + 1] as usize;
+        for value in &mut l3loop.xr_amp[start..end] {
+            *value = (*value as f64 * gain).clamp(-(i32::MAX as f64), i32::MAX as f64) as i32;
+        }
+    }
+
+    l3loop.xrmax = 0;
+    for (abs, &value) in l3loop.xrabs.iter_mut().zip(l3loop.xr_amp.iter()) {
+        *abs = labs(value);
+        l3loop.xrmax = l3loop.xrmax.max(*abs);
+    }
+    l3loop.xr = l3loop.xr_amp.as_mut_ptr();
+    true
+}
+
+/// Reconstruct the magnitude of a quantized value as a decoder would
+///
+/// The result is relative to full scale (1.0 corresponds to an MDCT value of
+/// 0x7fffffff) and undoes the global gain and the effective scalefactor.
+pub fn shine_dequantize(ix: i32, global_gain: u32, scalefac: i32, scalefac_scale: u32) -> f64 {
+    let ifqstep = 0.5 * (1 + scalefac_scale) as f64;
+    (ix as f64).powf(4.0 / 3.0)
+        * ((global_gain as f64 - 210.0) / 4.0).exp2()
+        * (-ifqstep * scalefac as f64).exp2()
+}
+
 /// Calculate allowed distortion for each scalefactor band
 /// Corresponds to calc_xmin() in l3loop.c
-fn calc_xmin(
-    _ratio: &crate::types::ShinePsyRatio,
-    cod_info: &mut GrInfo,
-    l3_xmin: &mut ShinePsyXmin,
+///
+/// The allowed distortion is the band's masking ratio times its energy,
+/// relative to full scale. Without a psychoacoustic model every ratio, and
+/// so every xmin, is zero; a [`crate::types::PsyRatioProvider`] supplies them.
+fn calc_xmin(config: &ShineGlobalConfig, l3_xmin: &mut ShinePsyXmin, gr: i32, ch: i32) {
+    let (gr, ch) = (gr as usize, ch as usize);
+    let sfb_lmax = config.side_info.gr[gr].ch[ch].tt.sfb_lmax as usize;
+    let bands = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
+    let xr = &config.mdct_freq[ch][gr];
+    for sfb in (0..sfb_lmax).rev() {
+        let ratio = config.ratio.l[gr][ch][sfb];
+        l3_xmin.l[gr][ch][sfb] = if ratio > 0.0 {
+            let energy: f64 = xr[bands[sfb] as usize..bands[sfb + 1] as usize]
+                .iter()
+                .map(|&value| (value as f64 / 2147483648.0).powi(2))
+                .sum();
+            ratio * energy
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Ask the caller's psychoacoustic model for the ratios of a granule
+///
+/// Returns false, with shine's zero ratios, when no provider is set.
+fn load_psy_ratio(config: &mut ShineGlobalConfig, gr: i32, ch: i32) -> bool {
+    let (gr, ch) = (gr as usize, ch as usize);
+    let Some(provider) = config.psy_ratio.as_mut() else {
+        config.ratio.l[gr][ch].fill(0.0);
+        return false;
+    };
+    let ratio = (provider.0)(ch, gr, &config.mdct_freq[ch][gr]);
+    for (dst, &ratio) in config.ratio.l[gr][ch].iter_mut().zip(&ratio) {
+        *dst = if ratio.is_finite() { ratio as f64 } else { 0.0 };
+    }
+    true
+}
+
+/// Largest scalefactor slen1 (bands 0-10) and slen2 (bands 11-20) can carry
+const MAX_SCALEFAC: [i32; 2] = [15, 7];
+
+/// Shape the quantization noise after the allowed distortion
+///
+/// Every band is amplified relative to the band that allows the most noise
+/// per line, by one scalefactor step for each halving of its allowance: at
+/// scalefac_scale 0 a step doubles the band's signal-to-noise power ratio
+/// for the same global gain. Bands without an allowed distortion keep
+/// scalefactor 0. Scalefactors are limited to what slen can carry and so
+/// that the amplified band stays within the fixed-point range. Returns
+/// false when all of them are zero.
+pub fn shine_psy_scalefactors(
+    l3_xmin: &ShinePsyXmin,
     gr: i32,
     ch: i32,
-) {
-    for sfb in (0..cod_info.sfb_lmax as usize).rev() {
-        // note. xmin will always be zero with no psychoacoustic model
-        l3_xmin.l[gr as usize][ch as usize][sfb] = 0.0;
+    config: &mut ShineGlobalConfig,
+) -> bool {
+    let (gr, ch) = (gr as usize, ch as usize);
+    let bands = &SHINE_SCALE_FACT_BAND_INDEX[config.mpeg.samplerate_index as usize];
+    let xr = &config.mdct_freq[ch][gr];
+    let allowance = |sfb: usize| {
+        let width = (bands[sfb + 1] - bands[sfb]) as f64;
+        l3_xmin.l[gr][ch][sfb] / width
+    };
+    let max_allowance = (0..SCALEFACTOR_BANDS_LONG)
+        .map(allowance)
+        .fold(0.0, f64::max);
+    if max_allowance <= 0.0 {
+        return false;
     }
+
+    let scalefac_l = &mut config.scalefactor.l[gr][ch];
+    for sfb in 0..SCALEFACTOR_BANDS_LONG {
+        let allowed = allowance(sfb);
+        if allowed <= 0.0 {
+            continue;
+        }
+        let peak = xr[bands[sfb] as usize..bands[sfb + 1] as usize]
+            .iter()
+            .map(|value| value.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        let headroom = if peak > 0 {
+            (2.0 * (i32::MAX as f64 / peak as f64).log2()).floor() as i32
+        } else {
+            0
+        };
+        let limit = MAX_SCALEFAC[(sfb >= 11) as usize].min(headroom);
+        scalefac_l[sfb] = ((max_allowance / allowed).log2().round() as i32).clamp(0, limit.max(0));
+    }
+    scalefac_l.iter().any(|&sf| sf != 0)
 }
 
 /// Initialize quantization loop tables
@@ -525,16 +900,15 @@ pub fn quantize_with_l3loop(
     l3loop: &mut crate::types::L3Loop,
 ) -> i32 {
     let mut max = 0;
-    let mut scale: f64;
     let mut dbl: f64;
 
-    let scalei = l3loop.steptabi[(stepsize + 127).clamp(0, 127) as usize]; // 2**(-stepsize/4)
+    let (scalei, scale) = step_scale(stepsize, l3loop); // 2**(-stepsize/4)
 
     // a quick check to see if ixmax will be less than 8192
     // this speeds up the early calls to bin_search_StepSize
     if mulr(l3loop.xrmax, scalei) > 165140 {
         // 8192**(4/3)
-        max = 16384; // no point in continuing, stepsize not big enough
+        max = QUANTIZE_OVERFLOW; // no point in continuing, stepsize not big enough
     } else {
         for (i, ix_val) in ix.iter_mut().enumerate().take(GRANULE_SIZE) {
             // This calculation is very sensitive. The multiply must round its
@@ -546,7 +920,6 @@ pub fn quantize_with_l3loop(
                 *ix_val = l3loop.int2idx[ln as usize]; // quick look up method
             } else {
                 // outside table range so have to do it using floats
-                scale = l3loop.steptab[(stepsize + 127).clamp(0, 127) as usize]; // 2**(-stepsize/4)
                 dbl = (l3loop.xrabs[i] as f64) * scale * 4.656612875e-10; // 0x7fffffff
                 *ix_val = (dbl.sqrt().sqrt() * dbl.sqrt()) as i32; // dbl**(3/4)
             }
@@ -562,6 +935,22 @@ pub fn quantize_with_l3loop(
     max
 }
 
+/// Integer and float versions of 2**(-stepsize/4)
+///
+/// The tables cover step sizes -127..0 like shine. Larger step sizes are only
+/// reached by the inner loop at very low bitrates and are computed directly,
+/// so the quantization keeps getting coarser instead of reading past the table.
+#[inline]
+fn step_scale(stepsize: i32, l3loop: &crate::types::L3Loop) -> (i32, f64) {
+    if stepsize <= 0 {
+        let index = (stepsize + 127).max(0) as usize;
+        (l3loop.steptabi[index], l3loop.steptab[index])
+    } else {
+        let scale = (-stepsize as f64 / 4.0).exp2();
+        ((scale * 2.0 + 0.5) as i32, scale)
+    }
+}
+
 /// Calculate maximum value in range
 #[inline]
 pub fn ix_max(ix: &[i32], begin: u32, end: u32) -> i32 {
@@ -571,35 +960,38 @@ pub fn ix_max(ix: &[i32], begin: u32, end: u32) -> i32 {
     ix[start..end].iter().max().copied().unwrap_or(0)
 }
 
-/// Calculate run length encoding information
-/// Corresponds to calc_runlen() in l3loop.c
-pub fn calc_runlen(ix: &mut [i32], cod_info: &mut GrInfo) {
+/// Split a granule into its big values, count1 and zero regions
+///
+/// Returns `(rzero, count1, big_values)` counted as shine's calc_runlen does:
+/// `rzero` zero pairs at the end, `count1` quadruples before them whose four
+/// values are each at most 1 (all the count1 tables can code), and
+/// `big_values` pairs covering the rest. A quadruple such as `[0, 0, 2, 1]`
+/// ends the count1 region even though its sum is small.
+pub fn run_length(ix: &[i32]) -> (u32, u32, u32) {
     let mut i = GRANULE_SIZE;
-    let mut _rzero = 0;
 
     // Count trailing zero pairs
-    while i > 1 {
+    let mut rzero = 0;
+    while i > 1 && ix[i - 2] == 0 && ix[i - 1] == 0 {
+        rzero += 1;
         i -= 2;
-        if ix[i] == 0 && ix[i + 1] == 0 {
-            _rzero += 1;
-        } else {
-            i += 2;
-            break;
-        }
     }
 
-    cod_info.count1 = 0;
-    while i > 3 {
+    let mut count1 = 0;
+    while i > 3 && ix[i - 4..i].iter().all(|&value| value <= 1) {
+        count1 += 1;
         i -= 4;
-        if ix[i] <= 1 && ix[i + 1] <= 1 && ix[i + 2] <= 1 && ix[i + 3] <= 1 {
-            cod_info.count1 += 1;
-        } else {
-            i += 4;
-            break;
-        }
     }
 
-    cod_info.big_values = (i >> 1) as u32;
+    (rzero, count1, (i >> 1) as u32)
+}
+
+/// Calculate run length encoding information
+/// Corresponds to calc_runlen() in l3loop.c
+pub fn calc_runlen(ix: &mut [i32], cod_info: &mut GrInfo) {
+    let (_rzero, count1, big_values) = run_length(ix);
+    cod_info.count1 = count1;
+    cod_info.big_values = big_values;
 }
 
 /// Count bits for count1 region
@@ -672,11 +1064,29 @@ pub fn count1_bitcount(ix: &[i32], cod_info: &mut GrInfo) -> i32 {
 /// Subdivide big values region into regions for different Huffman tables
 /// Corresponds to subdivide() in l3loop.c
 pub fn subdivide(cod_info: &mut GrInfo, config: &mut ShineGlobalConfig) {
-    subdivide_with_samplerate(cod_info, config.wave.samplerate);
+    subdivide_with_samplerate_index(cod_info, config.mpeg.samplerate_index as usize);
 }
 
 /// Helper function to subdivide without borrowing conflicts
+///
+/// Looks up the scalefactor band row for `samplerate`. Unsupported rates
+/// have no band table, so the big values region is left undivided.
 pub fn subdivide_with_samplerate(cod_info: &mut GrInfo, samplerate: i32) {
+    match get_sample_rate_index(samplerate) {
+        Some(samplerate_index) => subdivide_with_samplerate_index(cod_info, samplerate_index),
+        None => {
+            cod_info.region0_count = 0;
+            cod_info.region1_count = 0;
+            cod_info.address1 = 2 * cod_info.big_values;
+            cod_info.address2 = 2 * cod_info.big_values;
+            cod_info.address3 = 2 * cod_info.big_values;
+        }
+    }
+}
+
+/// Subdivide using the row of `SHINE_SCALE_FACT_BAND_INDEX` for `samplerate_index`
+/// (the same index that is written to the frame header)
+pub fn subdivide_with_samplerate_index(cod_info: &mut GrInfo, samplerate_index: usize) {
     // Subdivision table from shine (matches exactly)
     const SUBDV_TABLE: [(u32, u32); 23] = [
         (0, 0), // 0 bands
@@ -709,18 +1119,6 @@ pub fn subdivide_with_samplerate(cod_info: &mut GrInfo, samplerate: i32) {
         cod_info.region0_count = 0;
         cod_info.region1_count = 0;
     } else {
-        let samplerate_index = match samplerate {
-            44100 => 0,
-            48000 => 1,
-            32000 => 2,
-            22050 => 3,
-            24000 => 4,
-            16000 => 5,
-            11025 => 6,
-            12000 => 7,
-            8000 => 8,
-            _ => 0,
-        };
         let scalefac_band_long = &SHINE_SCALE_FACT_BAND_INDEX[samplerate_index];
 
         let bigvalues_region = 2 * cod_info.big_values;
@@ -744,14 +1142,14 @@ pub fn subdivide_with_samplerate(cod_info: &mut GrInfo, samplerate: i32) {
         let mut thiscount = SUBDV_TABLE[scfb_anz].1;
         while thiscount > 0 {
             let idx = (cod_info.region0_count + 1 + thiscount) as usize;
-            if idx < 22 && scalefac_band_long[idx + 1] <= bigvalues_region as i32 {
+            if idx < SFB_LMAX && scalefac_band_long[idx + 1] <= bigvalues_region as i32 {
                 break;
             }
             thiscount -= 1;
         }
         cod_info.region1_count = thiscount;
         let idx = (cod_info.region0_count + 1 + thiscount) as usize;
-        if idx + 1 < 22 {
+        if idx + 1 < SFB_LMAX {
             cod_info.address2 = scalefac_band_long[idx + 1] as u32;
         } else {
             cod_info.address2 = bigvalues_region;
@@ -764,27 +1162,131 @@ pub fn subdivide_with_samplerate(cod_info: &mut GrInfo, samplerate: i32) {
 /// Select Huffman code tables for bigvalues regions
 /// Corresponds to bigv_tab_select() in l3loop.c
 pub fn bigv_tab_select(ix: &[i32], cod_info: &mut GrInfo) {
+    bigv_tab_select_with_effort(ix, cod_info, Effort::Standard);
+}
+
+/// Select Huffman code tables for bigvalues regions with the given effort
+///
+/// `Effort::Fast` takes the first table that can code each region instead
+/// of comparing it with the alternatives of the same size.
+pub fn bigv_tab_select_with_effort(ix: &[i32], cod_info: &mut GrInfo, effort: Effort) {
     cod_info.table_select[0] = 0;
     cod_info.table_select[1] = 0;
     cod_info.table_select[2] = 0;
 
     if cod_info.address1 > 0 {
-        cod_info.table_select[0] = new_choose_table(ix, 0, cod_info.address1);
+        cod_info.table_select[0] = new_choose_table(ix, 0, cod_info.address1, effort);
     }
 
     if cod_info.address2 > cod_info.address1 {
-        cod_info.table_select[1] = new_choose_table(ix, cod_info.address1, cod_info.address2);
+        cod_info.table_select[1] =
+            new_choose_table(ix, cod_info.address1, cod_info.address2, effort);
     }
 
     if (cod_info.big_values << 1) > cod_info.address2 {
         cod_info.table_select[2] =
-            new_choose_table(ix, cod_info.address2, cod_info.big_values << 1);
+            new_choose_table(ix, cod_info.address2, cod_info.big_values << 1, effort);
+    }
+
+    // A missing table would count as zero bits and fail when formatting
+    debug_assert!(
+        cod_info
+            .table_select
+            .iter()
+            .all(|&table| is_bigv_table_available(table)),
+        "unavailable Huffman table selected: {:?}",
+        cod_info.table_select
+    );
+}
+
+/// Subdivide the bigvalues region, select its tables and count its bits
+///
+/// `Effort::Thorough` also tries moving each region boundary by one
+/// scalefactor band and keeps the division that needs the fewest bits.
+fn select_bigv_tables(
+    ix: &[i32],
+    cod_info: &mut GrInfo,
+    samplerate_index: usize,
+    effort: Effort,
+) -> i32 {
+    subdivide_with_samplerate_index(cod_info, samplerate_index); // bigvalues sfb division
+    bigv_tab_select_with_effort(ix, cod_info, effort); // codebook selection
+    let bits = bigv_bitcount(ix, cod_info); // bit count
+
+    if effort == Effort::Thorough && cod_info.big_values > 0 {
+        search_neighbour_subdivisions(ix, cod_info, samplerate_index, bits)
+    } else {
+        bits
+    }
+}
+
+/// Try the region counts next to the ones `subdivide` chose
+///
+/// Candidates keep region0_count and region1_count within their 4 and 3 bit
+/// fields and both region boundaries inside the bigvalues region, so the
+/// decoder derives the same boundaries from the counts. Returns the bits of
+/// the best division, which is left in `cod_info`.
+fn search_neighbour_subdivisions(
+    ix: &[i32],
+    cod_info: &mut GrInfo,
+    samplerate_index: usize,
+    bits: i32,
+) -> i32 {
+    let scalefac_band_long = &SHINE_SCALE_FACT_BAND_INDEX[samplerate_index];
+    let bigvalues_region = 2 * cod_info.big_values;
+    let (region0, region1) = (cod_info.region0_count as i32, cod_info.region1_count as i32);
+
+    let mut best = (
+        bits,
+        cod_info.region0_count,
+        cod_info.region1_count,
+        cod_info.address1,
+        cod_info.address2,
+        cod_info.table_select,
+    );
+    for (d0, d1) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, 1), (1, -1)] {
+        let (r0, r1) = (region0 + d0, region1 + d1);
+        if !(0..=15).contains(&r0) || !(0..=7).contains(&r1) || r0 + r1 + 2 > 22 {
+            continue;
+        }
+        let address1 = scalefac_band_long[(r0 + 1) as usize] as u32;
+        let address2 = scalefac_band_long[(r0 + r1 + 2) as usize] as u32;
+        if address2 > bigvalues_region {
+            continue;
+        }
+
+        cod_info.region0_count = r0 as u32;
+        cod_info.region1_count = r1 as u32;
+        cod_info.address1 = address1;
+        cod_info.address2 = address2;
+        bigv_tab_select_with_effort(ix, cod_info, Effort::Standard);
+        let candidate = bigv_bitcount(ix, cod_info);
+        if candidate < best.0 {
+            best = (
+                candidate,
+                r0 as u32,
+                r1 as u32,
+                address1,
+                address2,
+                cod_info.table_select,
+            );
+        }
     }
+
+    (
+        _,
+        cod_info.region0_count,
+        cod_info.region1_count,
+        cod_info.address1,
+        cod_info.address2,
+        cod_info.table_select,
+    ) = best;
+    best.0
 }
 
 /// Choose the Huffman table that will encode ix[begin..end] with the fewest bits
 /// Corresponds to new_choose_table() in l3loop.c
-fn new_choose_table(ix: &[i32], begin: u32, end: u32) -> u32 {
+fn new_choose_table(ix: &[i32], begin: u32, end: u32, effort: Effort) -> u32 {
     let max = ix_max(ix, begin, end);
     if max == 0 {
         return 0;
@@ -803,6 +1305,9 @@ fn new_choose_table(ix: &[i32], begin: u32, end: u32) -> u32 {
                     .is_some_and(|table| table.xlen > max as u32)
             })
             .unwrap_or(0) as u32;
+        if effort == Effort::Fast {
+            return choice[0];
+        }
 
         sum[0] = count_bit(ix, begin, end, choice[0]);
 
@@ -860,6 +1365,9 @@ fn new_choose_table(ix: &[i32], begin: u32, end: u32) -> u32 {
                     .is_some_and(|table| table.linmax >= max_linbits as u32)
             })
             .unwrap_or(15) as u32;
+        if effort == Effort::Fast {
+            return choice[0];
+        }
 
         choice[1] = (24..32)
             .find(|&i| {
@@ -904,6 +1412,11 @@ pub fn count_bit(ix: &[i32], start: u32, end: u32, table: u32) -> i32 {
     if table == 0 {
         return 0;
     }
+    debug_assert!(
+        is_bigv_table_available(table),
+        "no codes in Huffman table {}",
+        table
+    );
 
     let table_idx = table as usize;
     if table_idx >= SHINE_HUFFMAN_TABLE.len() {
@@ -989,27 +1502,79 @@ pub fn count_bit(ix: &[i32], start: u32, end: u32, table: u32) -> i32 {
 
 /// Binary search for optimal quantizer step size
 /// Corresponds to bin_search_StepSize() in l3loop.c
+///
+/// With `Effort::Fast` only `FAST_SEARCH_WINDOW` step sizes around
+/// `previous_step` are searched. When even the coarsest of them does not
+/// fit, the search continues above the window; a granule that would fit
+/// with a finer step than the window holds keeps the finest step of the
+/// window, costing quality but not validity.
 fn bin_search_step_size_with_samplerate(
     desired_rate: i32,
     ix: &mut [i32],
     cod_info: &mut GrInfo,
-    samplerate: i32,
+    samplerate_index: usize,
+    l3loop: &mut crate::types::L3Loop,
+    effort: Effort,
+    previous_step: i32,
+) -> i32 {
+    if effort == Effort::Fast {
+        let low = (previous_step - FAST_SEARCH_WINDOW / 2).clamp(-120, -FAST_SEARCH_WINDOW);
+        let step = bin_search_step_range(
+            low..low + FAST_SEARCH_WINDOW,
+            desired_rate,
+            ix,
+            cod_info,
+            samplerate_index,
+            l3loop,
+            effort,
+        );
+        if step < low + FAST_SEARCH_WINDOW - 1 {
+            return step;
+        }
+        return bin_search_step_range(
+            step..0,
+            desired_rate,
+            ix,
+            cod_info,
+            samplerate_index,
+            l3loop,
+            effort,
+        );
+    }
+
+    bin_search_step_range(
+        -120..0,
+        desired_rate,
+        ix,
+        cod_info,
+        samplerate_index,
+        l3loop,
+        effort,
+    )
+}
+
+/// Binary search for the smallest step size in `steps` that fits `desired_rate`
+fn bin_search_step_range(
+    steps: core::ops::Range<i32>,
+    desired_rate: i32,
+    ix: &mut [i32],
+    cod_info: &mut GrInfo,
+    samplerate_index: usize,
     l3loop: &mut crate::types::L3Loop,
+    effort: Effort,
 ) -> i32 {
-    let mut next = -120;
-    let mut count = 120;
+    let mut next = steps.start;
+    let mut count = steps.end - steps.start;
 
     loop {
         let half = count / 2;
 
-        let bit = if quantize_with_l3loop(ix, next + half, l3loop) > 8192 {
+        let bit = if quantize_with_l3loop(ix, next + half, l3loop) > IX_MAX_VALUE {
             100000 // fail
         } else {
             calc_runlen(ix, cod_info); // rzero,count1,big_values
             let mut bit = count1_bitcount(ix, cod_info); // count1_table selection
-            subdivide_with_samplerate(cod_info, samplerate); // bigvalues sfb division
-            bigv_tab_select(ix, cod_info); // codebook selection
-            bit += bigv_bitcount(ix, cod_info); // bit count
+            bit += select_bigv_tables(ix, cod_info, samplerate_index, effort);
             bit
         };
 
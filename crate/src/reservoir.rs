@@ -3,7 +3,159 @@
 //! Layer3 bit reservoir: Described in C.1.5.4.2.2 of the IS
 //! This module implements shine's reservoir.c functions exactly
 
-use crate::types::{GrInfo, ShineGlobalConfig};
+use crate::bitstream::BitstreamWriter;
+use crate::encoder::MPEG_I;
+use crate::types::{GrInfo, ShineGlobalConfig, MAX_CHANNELS, MAX_GRANULES};
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Largest main_data_begin of MPEG-1 frames in bytes (9 bit field)
+pub const MPEG1_MAX_RESERVOIR_BYTES: u32 = 511;
+/// Largest main_data_begin of MPEG-2 and MPEG-2.5 frames in bytes (8 bit field)
+pub const MPEG2_MAX_RESERVOIR_BYTES: u32 = 255;
+
+/// Bits one granule of one channel was allowed and used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GranuleBits {
+    /// Bits the quantizer was allowed, from `shine_max_reservoir_bits`
+    pub allocated: u32,
+    /// Bits of Huffman data and scale factors, before stuffing
+    pub used: u32,
+}
+
+/// Reservoir level and the allocation of the last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReservoirUsage {
+    /// Bits in the reservoir (`resv_size`)
+    pub available_bits: u32,
+    /// Allocation per granule and channel of the last frame; unused granules
+    /// and channels are zero
+    pub granules: [[GranuleBits; MAX_CHANNELS]; MAX_GRANULES],
+}
+
+/// Frame assembly for the bit reservoir
+///
+/// shine never uses the reservoir: `resv_max` is 0 and every frame carries
+/// exactly its own main data. With a reservoir, bits a frame leaves unused
+/// stay free at the end of its main data slot and the next frames' main
+/// data starts there, `main_data_begin` bytes before their header. A frame
+/// can only be written once later main data has filled its slot, so frames
+/// wait here and output lags the encoder by up to `max_bytes`.
+#[derive(Debug, Default)]
+pub struct BitReservoir {
+    /// Largest main_data_begin in bytes; 0 disables the reservoir
+    max_bytes: u32,
+    /// Header and side info of frames whose main data slot is not yet full,
+    /// with the slot size in bytes
+    frames: VecDeque<(Vec<u8>, usize)>,
+    /// Main data not yet placed in a slot
+    main_data: VecDeque<u8>,
+    /// Largest main_data_begin written so far
+    peak_bytes: u32,
+    /// Level seen by the next granule
+    pub usage: ReservoirUsage,
+    /// Writer the side info and main data of one frame are formatted into
+    pub scratch: BitstreamWriter,
+}
+
+impl BitReservoir {
+    /// Reservoir of at most `max_bytes`, clamped to the main_data_begin
+    /// field of the MPEG version
+    pub fn new(max_bytes: u16, mpeg_version: i32) -> Self {
+        let limit = if mpeg_version == MPEG_I {
+            MPEG1_MAX_RESERVOIR_BYTES
+        } else {
+            MPEG2_MAX_RESERVOIR_BYTES
+        };
+        Self {
+            max_bytes: (max_bytes as u32).min(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Whether frames may take main data from earlier frames
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// Largest main_data_begin in bytes
+    pub fn max_bytes(&self) -> u32 {
+        self.max_bytes
+    }
+
+    /// Largest main_data_begin written so far
+    pub fn peak_bytes(&self) -> u32 {
+        self.peak_bytes
+    }
+
+    /// Bits in the reservoir, as of the last reservoir adjustment
+    pub fn available_bits(&self) -> u32 {
+        self.usage.available_bits
+    }
+
+    /// Largest number of bits the reservoir keeps between frames
+    pub fn capacity_bits(&self) -> u32 {
+        self.max_bytes * 8
+    }
+
+    /// Record the reservoir size after an adjustment
+    pub fn set_available_bits(&mut self, resv_size: i32) {
+        self.usage.available_bits = resv_size.max(0) as u32;
+    }
+
+    /// Free bytes at the end of the waiting frames' slots, where the main
+    /// data of the next frame starts
+    pub fn main_data_begin(&self) -> u32 {
+        let slots: usize = self.frames.iter().map(|(_, slot)| slot).sum();
+        slots.saturating_sub(self.main_data.len()) as u32
+    }
+
+    /// Bytes of the waiting frames, the most `pop_ready` can release before
+    /// another frame is queued
+    pub fn waiting_bytes(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|(side_info, slot)| side_info.len() + slot)
+            .sum()
+    }
+
+    /// Queue a frame: its header and side info, the main data it produced
+    /// and the size of its own main data slot
+    pub fn push_frame(&mut self, side_info: &[u8], main_data: &[u8], slot: usize) {
+        self.peak_bytes = self.peak_bytes.max(self.main_data_begin());
+        self.frames.push_back((side_info.to_vec(), slot));
+        self.main_data.extend(main_data);
+    }
+
+    /// Remove the frames whose slots are full and append their bytes to `out`
+    pub fn pop_ready(&mut self, out: &mut Vec<u8>) {
+        while let Some((_, slot)) = self.frames.front() {
+            if self.main_data.len() < *slot {
+                break;
+            }
+            let (side_info, slot) = self.frames.pop_front().expect("checked above");
+            out.extend_from_slice(&side_info);
+            out.extend(self.main_data.drain(..slot));
+        }
+    }
+
+    /// Remove all waiting frames, filling the free end of the last slots
+    /// with zeros (ancillary data)
+    pub fn drain(&mut self, out: &mut Vec<u8>) {
+        let free = self.main_data_begin() as usize;
+        self.main_data.extend(core::iter::repeat_n(0, free));
+        self.pop_ready(out);
+    }
+
+    /// Drop waiting frames and statistics, keeping the size
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.main_data.clear();
+        self.peak_bytes = 0;
+        self.usage = ReservoirUsage::default();
+    }
+}
 
 /// Get maximum reservoir bits for current granule
 /// Corresponds to shine_max_reservoir_bits() in reservoir.c
@@ -56,6 +208,7 @@ pub fn shine_max_reservoir_bits(pe: &f64, config: &ShineGlobalConfig) -> i32 {
 /// the reservoir to reflect the granule's usage.
 pub fn shine_resv_adjust(gi: &GrInfo, config: &mut ShineGlobalConfig) {
     config.resv_size += (config.mean_bits / config.wave.channels) - gi.part2_3_length as i32;
+    config.reservoir.set_available_bits(config.resv_size);
 }
 
 /// Finalize reservoir at frame end
@@ -131,4 +284,6 @@ pub fn shine_resv_frame_end(config: &mut ShineGlobalConfig) {
             l3_side.resv_drain = stuffing_bits;
         }
     }
+
+    config.reservoir.set_available_bits(config.resv_size);
 }
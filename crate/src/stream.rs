@@ -0,0 +1,272 @@
+//! Realtime streaming front end for capture pipelines
+//!
+//! Audio callbacks hand over small chunks and must never block. The
+//! callback side pushes interleaved samples into a fixed-capacity
+//! single-producer single-consumer ring with `StreamProducer::try_push`,
+//! which neither allocates nor takes a lock. A worker thread owns the
+//! `StreamEncoder` and calls `process` to drain the ring through a normal
+//! `Mp3Encoder`.
+//!
+//! When the ring is full the `OverrunPolicy` decides whether the new chunk
+//! is rejected or the oldest samples are dropped to make room. Either way
+//! the event is counted and can be queried from both sides.
+
+use crate::error::EncoderError;
+use crate::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use std::sync::atomic::{fence, AtomicI16, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// What `try_push` does when the chunk does not fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverrunPolicy {
+    /// Refuse the whole chunk and keep the buffered samples
+    #[default]
+    Reject,
+    /// Discard the oldest buffered samples to make room for the chunk
+    DropOldest,
+}
+
+/// The ring buffer has no room for the chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Stream ring buffer is full")]
+pub struct Full;
+
+/// Ring storage shared by the producer and the encoder
+///
+/// `head` and `tail` count samples since the start and only grow; a
+/// sample lives at its count modulo the capacity. The producer owns
+/// `tail`. Normally only the consumer moves `head`, but with
+/// `OverrunPolicy::DropOldest` the producer may move it too, before it
+/// overwrites the dropped slots. The consumer therefore copies samples
+/// first and commits with a compare-exchange on `head`; a failed exchange
+/// means the copy may have been overwritten and is retried.
+#[derive(Debug)]
+struct Ring {
+    samples: Box<[AtomicI16]>,
+    channels: usize,
+    policy: OverrunPolicy,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    overruns: AtomicU64,
+    dropped_samples: AtomicU64,
+}
+
+impl Ring {
+    fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn slot(&self, position: usize) -> &AtomicI16 {
+        &self.samples[position % self.capacity()]
+    }
+
+    fn record_overrun(&self, dropped: usize) {
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+        self.dropped_samples
+            .fetch_add(dropped as u64, Ordering::Relaxed);
+    }
+}
+
+/// Audio-thread handle of a stream: pushes interleaved samples
+///
+/// There is exactly one producer per stream. It can be moved to another
+/// thread but not cloned.
+#[derive(Debug)]
+pub struct StreamProducer {
+    ring: Arc<Ring>,
+}
+
+impl StreamProducer {
+    /// Append interleaved samples without blocking or allocating
+    ///
+    /// Chunks should hold whole sample frames (a multiple of the channel
+    /// count). A chunk larger than the ring capacity is always rejected.
+    pub fn try_push(&mut self, samples: &[i16]) -> Result<(), Full> {
+        let ring = &*self.ring;
+        if samples.is_empty() {
+            return Ok(());
+        }
+        if samples.len() > ring.capacity() {
+            ring.record_overrun(samples.len());
+            return Err(Full);
+        }
+
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let mut head = ring.head.load(Ordering::Acquire);
+        let free = ring.capacity() - (tail - head);
+        if samples.len() > free {
+            match ring.policy {
+                OverrunPolicy::Reject => {
+                    ring.record_overrun(samples.len());
+                    return Err(Full);
+                }
+                OverrunPolicy::DropOldest => {
+                    // Drop whole sample frames so channels stay aligned
+                    loop {
+                        let free = ring.capacity() - (tail - head);
+                        if samples.len() <= free {
+                            break;
+                        }
+                        let drop = (samples.len() - free)
+                            .div_ceil(ring.channels)
+                            .saturating_mul(ring.channels)
+                            .min(tail - head);
+                        match ring.head.compare_exchange(
+                            head,
+                            head + drop,
+                            Ordering::AcqRel,
+                            Ordering::Acquire,
+                        ) {
+                            Ok(_) => {
+                                ring.record_overrun(drop);
+                                break;
+                            }
+                            // The consumer freed space meanwhile
+                            Err(current) => head = current,
+                        }
+                    }
+                    // Order the head update before the overwrites below
+                    fence(Ordering::Release);
+                }
+            }
+        }
+
+        for (i, &sample) in samples.iter().enumerate() {
+            ring.slot(tail + i).store(sample, Ordering::Relaxed);
+        }
+        ring.tail.store(tail + samples.len(), Ordering::Release);
+        Ok(())
+    }
+
+    /// Number of samples the ring can hold
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    /// Number of pushes that overflowed the ring
+    pub fn overruns(&self) -> u64 {
+        self.ring.overruns.load(Ordering::Relaxed)
+    }
+}
+
+/// Worker-thread side of a stream: drains the ring into an encoder
+#[derive(Debug)]
+pub struct StreamEncoder {
+    ring: Arc<Ring>,
+    encoder: Mp3Encoder,
+    scratch: Vec<i16>,
+}
+
+impl StreamEncoder {
+    /// Create a stream with a ring of `capacity` interleaved samples
+    ///
+    /// The capacity is rounded up to whole sample frames. It should cover
+    /// the longest stall of the worker thread at the input rate.
+    pub fn new(
+        config: Mp3EncoderConfig,
+        capacity: usize,
+        policy: OverrunPolicy,
+    ) -> Result<(Self, StreamProducer), EncoderError> {
+        let encoder = Mp3Encoder::new(config)?;
+        let channels = encoder.config().channels as usize;
+        let capacity = capacity.max(1).div_ceil(channels) * channels;
+
+        let ring = Arc::new(Ring {
+            samples: (0..capacity).map(|_| AtomicI16::new(0)).collect(),
+            channels,
+            policy,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overruns: AtomicU64::new(0),
+            dropped_samples: AtomicU64::new(0),
+        });
+        let producer = StreamProducer { ring: ring.clone() };
+        let stream = Self {
+            ring,
+            encoder,
+            scratch: Vec::with_capacity(capacity),
+        };
+        Ok((stream, producer))
+    }
+
+    /// Encode everything pushed so far and return the complete frames
+    ///
+    /// Samples short of a full frame stay buffered in the encoder until
+    /// the next call.
+    pub fn process(&mut self) -> Result<Vec<Vec<u8>>, EncoderError> {
+        self.drain();
+        if self.scratch.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.encoder.encode_interleaved(&self.scratch)
+    }
+
+    /// Encode the remaining samples and flush the encoder
+    ///
+    /// Returns the last complete frames followed by the flushed tail.
+    pub fn finish(&mut self) -> Result<Vec<u8>, EncoderError> {
+        let mut output = self.process()?.concat();
+        output.extend(self.encoder.finish()?);
+        Ok(output)
+    }
+
+    /// Copy the buffered samples into `scratch` and release their slots
+    fn drain(&mut self) {
+        let ring = &*self.ring;
+        loop {
+            self.scratch.clear();
+            let head = ring.head.load(Ordering::Acquire);
+            let tail = ring.tail.load(Ordering::Acquire);
+            if tail - head > ring.capacity() {
+                // The producer dropped samples between the two loads
+                continue;
+            }
+            self.scratch
+                .extend((head..tail).map(|position| ring.slot(position).load(Ordering::Relaxed)));
+
+            // Pairs with the producer's fence: if any copied slot was
+            // overwritten, the head has moved and the exchange fails
+            fence(Ordering::Acquire);
+            if ring
+                .head
+                .compare_exchange(head, tail, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Number of samples waiting in the ring
+    pub fn pending(&self) -> usize {
+        let head = self.ring.head.load(Ordering::Acquire);
+        let tail = self.ring.tail.load(Ordering::Acquire);
+        tail.saturating_sub(head)
+    }
+
+    /// Number of samples the ring can hold
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    /// Overflow behaviour chosen at creation
+    pub fn policy(&self) -> OverrunPolicy {
+        self.ring.policy
+    }
+
+    /// Number of pushes that overflowed the ring
+    pub fn overruns(&self) -> u64 {
+        self.ring.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Number of samples lost to overruns, rejected or dropped
+    pub fn dropped_samples(&self) -> u64 {
+        self.ring.dropped_samples.load(Ordering::Relaxed)
+    }
+
+    /// The encoder fed by this stream
+    pub fn encoder(&self) -> &Mp3Encoder {
+        &self.encoder
+    }
+}
@@ -7,9 +7,12 @@
 //! The implementation strictly follows the shine reference implementation
 //! in ref/shine/src/lib/l3subband.c
 
+use crate::constants::{GRANULE_SIZE, SUBBAND_COUNT};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
 use crate::tables::SHINE_ENWINDOW;
 use crate::types::{Subband, HAN_SIZE, MAX_CHANNELS, SBLIMIT};
-use std::f64::consts::PI;
+use core::f64::consts::PI;
 
 /// Multiplication macros matching shine's mult_noarch_gcc.h
 /// These implement fixed-point arithmetic operations
@@ -28,21 +31,30 @@ pub fn mulr(a: i32, b: i32) -> i32 {
 }
 
 /// Initialize multiplication operation (matches shine mul0 macro)
+///
+/// The products are summed in 64 bits; see [`mulz`].
 #[inline]
-pub fn mul0(a: i32, b: i32) -> i32 {
-    mul(a, b)
+pub fn mul0(a: i32, b: i32) -> i64 {
+    mul(a, b) as i64
 }
 
 /// Multiply and add operation (matches shine muladd macro)
 #[inline]
-fn muladd(acc: i32, a: i32, b: i32) -> i32 {
-    acc + mul(a, b)
+fn muladd(acc: i64, a: i32, b: i32) -> i64 {
+    acc + mul(a, b) as i64
 }
 
-/// Finalize multiplication (matches shine mulz macro - no-op)
+/// Finalize multiplication (matches shine mulz macro)
+///
+/// shine's portable macros add the shifted products in 32 bits. The sums
+/// here are the same, taken in 64 bits and saturated when stored, so an
+/// input the tables cannot produce would clip instead of wrapping around.
+/// With full-scale input of the worst sign pattern the window sums stay
+/// below 2.3% of the i32 range and the filter matrix sums below 43%, so
+/// the output is identical to shine's.
 #[inline]
-fn mulz(value: i32) -> i32 {
-    value
+fn mulz(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
 }
 
 /// Initialize the subband analysis filterbank
@@ -99,12 +111,10 @@ pub fn shine_window_filter_subband(
     subband: &mut Subband,
     stride: usize,
 ) {
-    let mut y = [0i32; 64];
-
     // Replace 32 oldest samples with 32 new samples
     // (matches shine implementation exactly: for (i = 32; i--;))
     let mut ptr_offset = 0;
-    for i in (0..32).rev() {
+    for i in (0..SUBBAND_COUNT).rev() {
         // i from 31 down to 0 (matches shine: for (i = 32; i--;))
         if ptr_offset < buffer.len() {
             subband.x[ch][i + subband.off[ch] as usize] = (buffer[ptr_offset] as i32) << 16;
@@ -113,14 +123,134 @@ pub fn shine_window_filter_subband(
     }
 
     // Advance buffer pointer (matches shine's pointer arithmetic)
-    if buffer.len() >= 32 * stride {
-        *buffer = &buffer[32 * stride..];
+    if buffer.len() >= SUBBAND_COUNT * stride {
+        *buffer = &buffer[SUBBAND_COUNT * stride..];
     }
 
+    window_filter(s, ch, subband);
+}
+
+/// Windowed subband analysis filterbank for 32-bit input
+///
+/// Same as [`shine_window_filter_subband`], but the samples are already
+/// full-scale 32-bit fractions (a 16-bit sample shifted left by 16), so
+/// higher resolution input keeps its extra bits.
+pub fn shine_window_filter_subband_i32(
+    buffer: &mut &[i32],
+    s: &mut [i32; SBLIMIT],
+    ch: usize,
+    subband: &mut Subband,
+    stride: usize,
+) {
+    let mut ptr_offset = 0;
+    for i in (0..SUBBAND_COUNT).rev() {
+        if ptr_offset < buffer.len() {
+            subband.x[ch][i + subband.off[ch] as usize] = buffer[ptr_offset];
+        }
+        ptr_offset += stride;
+    }
+
+    if buffer.len() >= SUBBAND_COUNT * stride {
+        *buffer = &buffer[SUBBAND_COUNT * stride..];
+    }
+
+    window_filter(s, ch, subband);
+}
+
+/// Subband samples of one granule: 18 time slots of 32 bands
+pub type GranuleSubbands = [[i32; SBLIMIT]; 18];
+
+/// Analyse one granule of 16-bit samples of a channel
+///
+/// Runs the 18 time slots of [`shine_window_filter_subband`] in one call
+/// and compensates for the inversion of the analysis filter as shine's
+/// mdct_sub does, negating the odd bands of every odd slot. The output is
+/// what the MDCT takes for one granule.
+pub fn filter_granule(
+    pcm: &[i16; GRANULE_SIZE],
+    ch: usize,
+    subband: &mut Subband,
+) -> GranuleSubbands {
+    let mut out = [[0; SBLIMIT]; 18];
+    for (slot, (s, samples)) in out.iter_mut().zip(pcm.chunks_exact(SBLIMIT)).enumerate() {
+        let off = subband.off[ch] as usize;
+        for (i, &sample) in samples.iter().enumerate() {
+            subband.x[ch][SBLIMIT - 1 - i + off] = (sample as i32) << 16;
+        }
+        window_filter(s, ch, subband);
+        compensate_inversion(s, slot);
+    }
+    out
+}
+
+/// Analyse one granule of full-scale 32-bit samples
+///
+/// Like [`filter_granule`], for the 32-bit samples of
+/// [`shine_window_filter_subband_i32`] read with a stride of `stride`
+/// from an interleaved buffer: `pcm[0]` is the channel's first sample.
+/// Silence with silence in the window history is recognised and only
+/// advances the window offset, since the filter would output zeros and
+/// leave the history zero.
+///
+/// # Panics
+///
+/// Panics when `pcm` holds fewer than `GRANULE_SIZE` samples at that stride.
+pub fn filter_granule_i32(
+    pcm: &[i32],
+    stride: usize,
+    ch: usize,
+    subband: &mut Subband,
+) -> GranuleSubbands {
+    assert!(
+        pcm.len() > (GRANULE_SIZE - 1) * stride,
+        "granule needs {} samples at stride {}, got {}",
+        GRANULE_SIZE,
+        stride,
+        pcm.len()
+    );
+
+    let mut out = [[0; SBLIMIT]; 18];
+    if pcm
+        .iter()
+        .step_by(stride)
+        .take(GRANULE_SIZE)
+        .all(|&x| x == 0)
+        && subband.x[ch].iter().all(|&x| x == 0)
+    {
+        subband.off[ch] = (subband.off[ch] + 18 * 480) & (HAN_SIZE as i32 - 1);
+        return out;
+    }
+    for (slot, s) in out.iter_mut().enumerate() {
+        let samples = &pcm[slot * SBLIMIT * stride..];
+        let off = subband.off[ch] as usize;
+        for i in 0..SBLIMIT {
+            subband.x[ch][SBLIMIT - 1 - i + off] = samples[i * stride];
+        }
+        window_filter(s, ch, subband);
+        compensate_inversion(s, slot);
+    }
+    out
+}
+
+/// Negate the odd bands of odd time slots, undoing the inversion of the
+/// analysis filter (matches shine's l3mdct.c)
+#[inline]
+fn compensate_inversion(s: &mut [i32; SBLIMIT], slot: usize) {
+    if slot % 2 == 1 {
+        for band in (1..SBLIMIT).step_by(2) {
+            s[band] *= -1;
+        }
+    }
+}
+
+/// Window the updated buffer and apply the filter matrix
+fn window_filter(s: &mut [i32; SBLIMIT], ch: usize, subband: &mut Subband) {
+    let mut y = [0i32; 64];
+
     // Apply analysis window (matches shine implementation exactly)
     for i in 0..64 {
         #[allow(unused_assignments)] // s_value is used but compiler doesn't detect it properly
-        let mut s_value = 0i32;
+        let mut s_value = 0i64;
 
         // Windowing operation using shine's exact loop structure (0 << 6 simplified to 0)
         s_value = mul0(
@@ -172,7 +302,7 @@ pub fn shine_window_filter_subband(
     // Apply synthesis filterbank (matches shine implementation exactly)
     for i in (0..SBLIMIT).rev() {
         // i from SBLIMIT-1 down to 0 (matches shine: for (i = SBLIMIT; i--;))
-        let mut s_value: i32;
+        let mut s_value: i64;
 
         // Start with the last coefficient (j=63) (matches shine exactly)
         s_value = mul0(subband.fl[i][63], y[63]);
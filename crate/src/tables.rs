@@ -4,10 +4,21 @@
 //! This module contains all the static lookup tables and constants
 //! required for MP3 encoding, following shine's tables.c exactly.
 
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use core::f64::consts::PI;
+use lazy_static::lazy_static;
+
 /// Scale factor length tables (matches shine's shine_slen1_tab and shine_slen2_tab)
 pub const SHINE_SLEN1_TAB: [i32; 16] = [0, 0, 0, 0, 3, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4];
 pub const SHINE_SLEN2_TAB: [i32; 16] = [0, 1, 2, 3, 0, 1, 2, 3, 1, 2, 3, 1, 2, 3, 2, 3];
 
+/// Preemphasis table for long blocks (ISO 11172-3 Table B.6)
+/// Added to the transmitted scalefactor of each band when preflag is set
+pub const SHINE_PRETAB: [i32; 21] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 3, 2,
+];
+
 /// Valid samplerates (matches shine's samplerates array)
 pub const SAMPLERATES: [i32; 9] = [
     44100, 48000, 32000, // MPEG-I
@@ -664,6 +675,29 @@ pub const SHINE_ENWINDOW: [i32; 512] = [
     shine_ew(0.000000),
 ];
 
+lazy_static! {
+    /// Windowed MDCT coefficients [m][k] (matches the cos_l table
+    /// shine_mdct_initialise() fills in l3mdct.c)
+    ///
+    /// The 36-point sine window combined with the MDCT cosines, scaled to
+    /// Q31. Computed once and shared by every encoder.
+    pub static ref SHINE_MDCT_COS_L: [[i32; 36]; 18] = mdct_cos_l();
+}
+
+/// Compute the windowed MDCT table the way shine_mdct_initialise() does
+fn mdct_cos_l() -> [[i32; 36]; 18] {
+    let pi36 = PI / 36.0;
+    let mut cos_l = [[0; 36]; 18];
+    for (m, row) in cos_l.iter_mut().enumerate() {
+        for (k, value) in row.iter_mut().enumerate() {
+            *value = ((pi36 * (k as f64 + 0.5)).sin()
+                * ((PI / 72.0) * (2 * k + 19) as f64 * (2 * m + 1) as f64).cos()
+                * 0x7fffffff as f64) as i32;
+        }
+    }
+    cos_l
+}
+
 /// Helper function to get sample rate index from sample rate value
 pub fn get_sample_rate_index(sample_rate: i32) -> Option<usize> {
     SAMPLERATES.iter().position(|&sr| sr == sample_rate)
@@ -4,23 +4,29 @@
 //! to shine's types.h, maintaining binary compatibility and data layout.
 
 use crate::bitstream::BitstreamWriter;
+use crate::constants::{SCALEFACTOR_BANDS_LONG, SUBBAND_COUNT};
+use crate::mp3_encoder::Effort;
+use crate::quantization::NmrStats;
+use crate::reservoir::BitReservoir;
 
 /// Constants from shine (matches types.h exactly)
-pub const GRANULE_SIZE: usize = 576;
-pub const PI: f64 = std::f64::consts::PI;
-pub const PI4: f64 = std::f64::consts::FRAC_PI_4;
+pub use crate::constants::GRANULE_SIZE;
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+pub const PI: f64 = core::f64::consts::PI;
+pub const PI4: f64 = core::f64::consts::FRAC_PI_4;
 pub const PI12: f64 = 0.26179938779915;
 pub const PI36: f64 = 0.087266462599717;
 pub const PI64: f64 = 0.049087385212;
-pub const SQRT2: f64 = std::f64::consts::SQRT_2;
-pub const LN2: f64 = std::f64::consts::LN_2;
+pub const SQRT2: f64 = core::f64::consts::SQRT_2;
+pub const LN2: f64 = core::f64::consts::LN_2;
 pub const LN_TO_LOG10: f64 = 0.2302585093;
 pub const BLKSIZE: usize = 1024;
 pub const HAN_SIZE: usize = 512; // for loop unrolling, require that HAN_SIZE%8==0
 pub const SCALE_BLOCK: i32 = 12;
 pub const SCALE_RANGE: i32 = 64;
 pub const SCALE: i32 = 32768;
-pub const SBLIMIT: usize = 32;
+pub const SBLIMIT: usize = SUBBAND_COUNT;
 pub const MAX_CHANNELS: usize = 2;
 pub const MAX_GRANULES: usize = 2;
 
@@ -75,14 +81,17 @@ pub struct L3Loop {
     pub xrsq: Box<[i32; GRANULE_SIZE]>, // Move to heap
     /// xr absolute
     pub xrabs: Box<[i32; GRANULE_SIZE]>, // Move to heap
+    /// Spectral values amplified by the granule's scalefactors
+    /// (only used when a scalefactor or preflag is non-zero)
+    pub xr_amp: Box<[i32; GRANULE_SIZE]>,
     /// Maximum of xrabs array
     pub xrmax: i32,
     /// Total energy per granule
     pub en_tot: [i32; MAX_GRANULES],
     /// Energy per scalefactor band [granule][sfb]
-    pub en: [[i32; 21]; MAX_GRANULES],
+    pub en: [[i32; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES],
     /// Masking threshold per scalefactor band [granule][sfb]
-    pub xm: [[i32; 21]; MAX_GRANULES],
+    pub xm: [[i32; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES],
     /// Maximum per granule
     pub xrmaxl: [i32; MAX_GRANULES],
     /// 2**(-x/4) for x = -127..0
@@ -96,13 +105,14 @@ pub struct L3Loop {
 impl Default for L3Loop {
     fn default() -> Self {
         Self {
-            xr: std::ptr::null_mut(),
+            xr: core::ptr::null_mut(),
             xrsq: Box::new([0; GRANULE_SIZE]),
             xrabs: Box::new([0; GRANULE_SIZE]),
+            xr_amp: Box::new([0; GRANULE_SIZE]),
             xrmax: 0,
             en_tot: [0; MAX_GRANULES],
-            en: [[0; 21]; MAX_GRANULES],
-            xm: [[0; 21]; MAX_GRANULES],
+            en: [[0; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES],
+            xm: [[0; SCALEFACTOR_BANDS_LONG]; MAX_GRANULES],
             xrmaxl: [0; MAX_GRANULES],
             steptab: [0.0; 128],
             steptabi: [0; 128],
@@ -111,22 +121,6 @@ impl Default for L3Loop {
     }
 }
 
-/// MDCT structure (matches mdct_t)
-/// (ref/shine/src/lib/types.h:103-105)
-#[repr(C)]
-#[derive(Debug)]
-pub struct Mdct {
-    pub cos_l: [[i32; 36]; 18],
-}
-
-impl Default for Mdct {
-    fn default() -> Self {
-        Self {
-            cos_l: [[0; 36]; 18],
-        }
-    }
-}
-
 /// Subband structure (matches subband_t)
 /// (ref/shine/src/lib/types.h:107-111)
 #[repr(C)]
@@ -186,7 +180,7 @@ impl Default for GrInfo {
             scalefac_scale: 0,
             count1table_select: 0,
             part2_length: 0,
-            sfb_lmax: 21,
+            sfb_lmax: SCALEFACTOR_BANDS_LONG as u32,
             address1: 0,
             address2: 0,
             address3: 0,
@@ -214,6 +208,8 @@ pub struct Granule {
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct ShineSideInfo {
+    /// Bytes of this frame's main data in earlier frames (0 without a reservoir)
+    pub main_data_begin: u32,
     pub private_bits: u32,
     pub resv_drain: i32, // matches resvDrain in shine
     pub scfsi: [[u32; 4]; MAX_CHANNELS],
@@ -223,6 +219,7 @@ pub struct ShineSideInfo {
 impl Default for ShineSideInfo {
     fn default() -> Self {
         Self {
+            main_data_begin: 0,
             private_bits: 0,
             resv_drain: 0,
             scfsi: [[0; 4]; MAX_CHANNELS],
@@ -236,29 +233,76 @@ impl Default for ShineSideInfo {
 #[repr(C)]
 #[derive(Debug)]
 pub struct ShinePsyRatio {
-    pub l: Box<[[[f64; 21]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
+    pub l: Box<[[[f64; SCALEFACTOR_BANDS_LONG]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
 }
 
 impl Default for ShinePsyRatio {
     fn default() -> Self {
         Self {
-            l: Box::new([[[0.0; 21]; MAX_CHANNELS]; MAX_GRANULES]),
+            l: Box::new([[[0.0; SCALEFACTOR_BANDS_LONG]; MAX_CHANNELS]; MAX_GRANULES]),
         }
     }
 }
 
+/// Psychoacoustic model supplied by the caller
+///
+/// Called with the channel, the granule and the granule's MDCT spectrum,
+/// returns the masking ratio of each long block scalefactor band.
+pub type PsyRatioFn =
+    dyn FnMut(usize, usize, &[i32; GRANULE_SIZE]) -> [f32; SCALEFACTOR_BANDS_LONG] + Send;
+
+/// Boxed [`PsyRatioFn`], replacing shine's missing psychoacoustic model
+pub struct PsyRatioProvider(pub Box<PsyRatioFn>);
+
+impl core::fmt::Debug for PsyRatioProvider {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("PsyRatioProvider")
+    }
+}
+
+/// MDCT spectrum of one granule of one channel, handed to a [`SpectrumTapFn`]
+///
+/// The coefficients are those `shine_mdct_sub` produced, before the lowpass
+/// filter, mid/side coding and quantization change them. Line `k` is centred
+/// on `(k + 0.5) * sample_rate / 1152` Hz.
+#[derive(Debug, Clone, Copy)]
+pub struct SpectrumFrame<'a> {
+    /// Number of the frame, starting at 1 like `ShineGlobalConfig::frame_number`
+    pub frame: i32,
+    /// Granule within the frame
+    pub granule: usize,
+    /// Channel of the input
+    pub channel: usize,
+    /// Sample rate of the input in Hz
+    pub sample_rate: i32,
+    /// The granule's 576 MDCT coefficients
+    pub coefficients: &'a [i32; GRANULE_SIZE],
+}
+
+/// Observer of the MDCT spectrum, called once per granule and channel
+pub type SpectrumTapFn = dyn for<'a> FnMut(SpectrumFrame<'a>) + Send;
+
+/// Boxed [`SpectrumTapFn`]
+pub struct SpectrumTap(pub Box<SpectrumTapFn>);
+
+impl core::fmt::Debug for SpectrumTap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SpectrumTap")
+    }
+}
+
 /// Psychoacoustic minimum structure (matches shine_psy_xmin_t)
 /// (ref/shine/src/lib/types.h:150-152)
 #[repr(C)]
 #[derive(Debug)]
 pub struct ShinePsyXmin {
-    pub l: Box<[[[f64; 21]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
+    pub l: Box<[[[f64; SCALEFACTOR_BANDS_LONG]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
 }
 
 impl Default for ShinePsyXmin {
     fn default() -> Self {
         Self {
-            l: Box::new([[[0.0; 21]; MAX_CHANNELS]; MAX_GRANULES]),
+            l: Box::new([[[0.0; SCALEFACTOR_BANDS_LONG]; MAX_CHANNELS]; MAX_GRANULES]),
         }
     }
 }
@@ -269,7 +313,7 @@ impl Default for ShinePsyXmin {
 #[derive(Debug)]
 pub struct ShineScalefac {
     /// Long block scale factors [granule][channel][scalefactor_band]
-    pub l: Box<[[[i32; 22]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
+    pub l: Box<[[[i32; SCALEFACTOR_BANDS_LONG + 1]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
     /// Short block scale factors [granule][channel][scalefactor_band][window]
     pub s: Box<[[[[i32; 3]; 13]; MAX_CHANNELS]; MAX_GRANULES]>, // Move to heap
 }
@@ -277,7 +321,7 @@ pub struct ShineScalefac {
 impl Default for ShineScalefac {
     fn default() -> Self {
         Self {
-            l: Box::new([[[0; 22]; MAX_CHANNELS]; MAX_GRANULES]),
+            l: Box::new([[[0; SCALEFACTOR_BANDS_LONG + 1]; MAX_CHANNELS]; MAX_GRANULES]),
             s: Box::new([[[[0; 3]; 13]; MAX_CHANNELS]; MAX_GRANULES]),
         }
     }
@@ -295,15 +339,44 @@ pub struct ShineGlobalConfig {
     pub mean_bits: i32,
     pub ratio: ShinePsyRatio,
     pub scalefactor: Box<ShineScalefac>, // Move to heap
-    pub buffer: [*mut i16; MAX_CHANNELS],
+    pub buffer: [*const i32; MAX_CHANNELS],
+    /// Samples left behind each `buffer` pointer
+    pub buffer_len: [usize; MAX_CHANNELS],
+    /// Full-scale copy of 16-bit input handed to the filterbank
+    pub pcm_buffer: Vec<i32>,
     pub pe: Box<[[f64; MAX_GRANULES]; MAX_CHANNELS]>, // Move to heap
     pub l3_enc: Box<[[[i32; GRANULE_SIZE]; MAX_GRANULES]; MAX_CHANNELS]>, // Move to heap
     pub l3_sb_sample: Box<[[[[i32; SBLIMIT]; 18]; MAX_GRANULES + 1]; MAX_CHANNELS]>, // Move to heap
     pub mdct_freq: Box<[[[i32; GRANULE_SIZE]; MAX_GRANULES]; MAX_CHANNELS]>, // Move to heap
     pub resv_size: i32,
     pub resv_max: i32,
+    /// Frames waiting for main data of later frames when `resv_max` is set
+    pub reservoir: BitReservoir,
+    /// First MDCT line zeroed by the lowpass filter (GRANULE_SIZE disables it)
+    pub lowpass_line: usize,
+    /// How hard the quantization loops search for tables and step sizes
+    pub effort: Effort,
+    /// Channel similarity from which a stereo frame is coded as mid/side;
+    /// `None` always codes left/right, as shine does
+    pub ms_threshold: Option<f32>,
+    /// Bits the next frame may use instead of the CBR frame size
+    pub frame_bit_budget: Option<i32>,
+    /// Keep `frame_bit_budget` for the following frames instead of clearing it
+    pub frame_bit_budget_sticky: bool,
+    /// Number of the frame being encoded, starting at 1; labels the data
+    /// the diagnostics capture
+    pub frame_number: i32,
+    /// Noise-to-mask ratio of the granules encoded so far
+    pub nmr: NmrStats,
+    /// Masking ratios from the caller; `None` keeps shine's zero allowed
+    /// distortion
+    pub psy_ratio: Option<PsyRatioProvider>,
+    /// Observer of the MDCT spectra; `None` skips the call
+    pub spectrum_tap: Option<SpectrumTap>,
+    /// MDCT spectra of the last frames, for `diagnostics_data`
+    #[cfg(feature = "diagnostics")]
+    pub spectra: crate::diagnostics_data::SpectrumHistory,
     pub l3loop: L3Loop,
-    pub mdct: Mdct,
     pub subband: Subband,
 }
 
@@ -342,20 +415,42 @@ impl ShineGlobalConfig {
             mean_bits: 0,
             ratio: ShinePsyRatio::default(),
             scalefactor: Box::new(ShineScalefac::default()), // Allocate on heap
-            buffer: [std::ptr::null_mut(); MAX_CHANNELS],
+            buffer: [core::ptr::null(); MAX_CHANNELS],
+            buffer_len: [0; MAX_CHANNELS],
+            pcm_buffer: Vec::new(),
             pe: Box::new([[0.0; MAX_GRANULES]; MAX_CHANNELS]), // Allocate on heap
             l3_enc: Box::new([[[0; GRANULE_SIZE]; MAX_GRANULES]; MAX_CHANNELS]), // Allocate on heap
             l3_sb_sample: Box::new([[[[0; SBLIMIT]; 18]; MAX_GRANULES + 1]; MAX_CHANNELS]), // Allocate on heap
             mdct_freq: Box::new([[[0; GRANULE_SIZE]; MAX_GRANULES]; MAX_CHANNELS]), // Allocate on heap
             resv_size: 0,
             resv_max: 0,
+            reservoir: BitReservoir::default(),
+            lowpass_line: GRANULE_SIZE,
+            effort: Effort::Standard,
+            ms_threshold: None,
+            frame_bit_budget: None,
+            frame_bit_budget_sticky: false,
+            frame_number: 0,
+            nmr: NmrStats::default(),
+            psy_ratio: None,
+            spectrum_tap: None,
+            #[cfg(feature = "diagnostics")]
+            spectra: Default::default(),
             l3loop: L3Loop::default(),
-            mdct: Mdct::default(),
             subband: Subband::default(),
         }
     }
 }
 
+// SAFETY: `l3loop.xr` points either into the boxed `mdct_freq` or into the
+// boxed `l3loop.xr_amp` (see `shine_amplify_scalefac_bands`); both are heap
+// allocations owned by the same value, so they move with it. `buffer` still
+// points at the caller's input after an encode call returns and may dangle
+// from then on, but it is reassigned at the start of every encode call
+// before it is read. No pointer refers to thread-local or shared data, so
+// the state can be moved to another thread.
+unsafe impl Send for ShineGlobalConfig {}
+
 impl Default for ShineGlobalConfig {
     fn default() -> Self {
         Self::new()
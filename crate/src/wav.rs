@@ -0,0 +1,492 @@
+//! WAV input for the encoder
+//!
+//! Reads the format of a WAV stream from any `std::io::Read` source and
+//! hands out the interleaved 16-bit samples in chunks, so callers can feed
+//! the encoder without materializing the whole file in memory.
+//!
+//! The RIFF chunks are walked by id and size, so `LIST`, `fact`, `bext` and
+//! other chunks may appear anywhere; unknown chunks are skipped, including
+//! the pad byte that follows an odd-sized chunk. When `data` comes before
+//! `fmt ` the samples are buffered until the format is known, since the
+//! source cannot seek. Chunks after `data` are never read.
+//!
+//! RF64 streams (WAV files over 4 GiB) start with `RF64` instead of `RIFF`
+//! and a `ds64` chunk holding the 64-bit sizes; their `data` chunk declares
+//! a size of 0xFFFFFFFF and the size from `ds64` is used instead.
+//!
+//! The stream length is not known up front, so a data chunk declared longer
+//! than the stream simply ends where the stream does; `is_truncated` reports
+//! it once the reader got there. The sample count of a `fact` chunk before
+//! `data` is kept for callers to cross-check.
+//!
+//! `write_wav` and `write_wav_file` produce canonical 16-bit PCM WAV
+//! streams (a 16-byte `fmt ` chunk followed by `data`) for test fixtures and
+//! for dumping intermediate PCM; the reader reads them back bit-exactly.
+//!
+//! Besides 16-bit PCM, G.711 µ-law and A-law data (format tags 7 and 6, one
+//! byte per sample, as in telephony recordings) is accepted and expanded to
+//! 16-bit samples as it is read.
+
+use crate::bytes::{read_u16_le, read_u32_le, read_u64_le};
+use crate::error::{InputDataError, InputResult};
+use crate::pcm::{bytes_to_i16_le, decode_alaw, decode_mulaw, i16_to_bytes_le};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+/// WAVE format tags
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_ALAW: u16 = 0x0006;
+const WAVE_FORMAT_MULAW: u16 = 0x0007;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Bytes per sample of 16-bit PCM
+const SAMPLE_BYTES: u64 = 2;
+
+/// 32-bit chunk size of RF64 chunks whose real size is in `ds64`
+const RF64_SIZE_IN_DS64: u64 = 0xFFFF_FFFF;
+
+/// Encoding of the samples in the data chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavEncoding {
+    /// 16-bit little-endian PCM
+    Pcm16,
+    /// G.711 A-law, one byte per sample
+    ALaw,
+    /// G.711 µ-law, one byte per sample
+    MuLaw,
+}
+
+impl WavEncoding {
+    /// Bytes per sample in the data chunk
+    pub fn sample_bytes(self) -> u64 {
+        match self {
+            WavEncoding::Pcm16 => SAMPLE_BYTES,
+            WavEncoding::ALaw | WavEncoding::MuLaw => 1,
+        }
+    }
+
+    /// Append the samples in `bytes` to `samples` as 16-bit PCM; a stream
+    /// cut inside a sample loses the dangling byte
+    fn decode(self, bytes: &[u8], samples: &mut Vec<i16>) -> InputResult<()> {
+        match self {
+            WavEncoding::Pcm16 => {
+                let whole = bytes.len() - bytes.len() % SAMPLE_BYTES as usize;
+                bytes_to_i16_le(&bytes[..whole], samples)
+            }
+            WavEncoding::ALaw => {
+                samples.extend(decode_alaw(bytes));
+                Ok(())
+            }
+            WavEncoding::MuLaw => {
+                samples.extend(decode_mulaw(bytes));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Streaming reader over the samples of a 16-bit PCM or G.711 WAV stream
+///
+/// Samples are always handed out as 16-bit PCM.
+pub struct WavSource<R: Read> {
+    reader: R,
+    sample_rate: u32,
+    channels: u16,
+    encoding: WavEncoding,
+    /// Size of the data chunk in bytes as declared in its header
+    data_len: u64,
+    /// Data chunk read ahead because it came before `fmt `
+    buffered: Option<io::Cursor<Vec<u8>>>,
+    /// Bytes of the data chunk still to be read from `reader`
+    remaining: u64,
+    /// Samples per channel counted by the `fact` chunk
+    fact_samples: Option<u64>,
+    /// The stream ended before the declared end of the data chunk
+    truncated: bool,
+}
+
+/// Fields of the `fmt ` chunk the reader needs
+struct WavFormat {
+    format_tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+    encoding: WavEncoding,
+}
+
+impl<R: Read> WavSource<R> {
+    /// Parse the WAV header and position the reader at the first sample
+    pub fn new(mut reader: R) -> InputResult<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).map_err(wav_error)?;
+        let rf64 = &header[0..4] == b"RF64";
+        if !(rf64 || &header[0..4] == b"RIFF") || &header[8..12] != b"WAVE" {
+            return Err(InputDataError::InvalidWav(
+                "not a RIFF/WAVE stream".to_string(),
+            ));
+        }
+        let ds64_data_len = if rf64 {
+            Some(read_ds64(&mut reader)?)
+        } else {
+            None
+        };
+
+        let mut format = None;
+        let mut fact_samples = None;
+        let mut buffered: Option<io::Cursor<Vec<u8>>> = None;
+        let (format, data_len) = loop {
+            let mut chunk = [0u8; 8];
+            if let Err(err) = reader.read_exact(&mut chunk) {
+                let missing = if format.is_none() { "fmt " } else { "data" };
+                return Err(InputDataError::InvalidWav(format!(
+                    "no {} chunk found: {}",
+                    missing, err
+                )));
+            }
+            let mut size = read_u32_le(&chunk, 4) as u64;
+            if let (b"data", Some(data_len)) = (&chunk[0..4], ds64_data_len) {
+                if size == RF64_SIZE_IN_DS64 {
+                    size = data_len;
+                }
+            }
+
+            match &chunk[0..4] {
+                b"fmt " => {
+                    let body = read_body(&mut reader, size)?;
+                    skip(&mut reader, size & 1)?;
+                    let parsed = parse_format(&body)?;
+                    if let Some(data) = &buffered {
+                        break (parsed, data.get_ref().len() as u64);
+                    }
+                    format = Some(parsed);
+                }
+                b"fact" if size >= 4 => {
+                    let body = read_body(&mut reader, size)?;
+                    skip(&mut reader, size & 1)?;
+                    fact_samples = Some(read_u32_le(&body, 0) as u64);
+                }
+                b"data" => match format.take() {
+                    Some(format) => break (format, size),
+                    None => {
+                        // The format comes later: keep the samples until it is known
+                        let mut data = Vec::new();
+                        (&mut reader)
+                            .take(size)
+                            .read_to_end(&mut data)
+                            .map_err(wav_error)?;
+                        skip(&mut reader, size & 1)?;
+                        buffered = Some(io::Cursor::new(data));
+                    }
+                },
+                _ => skip(&mut reader, size + (size & 1))?,
+            }
+        };
+
+        Ok(Self {
+            reader,
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            encoding: format.encoding,
+            data_len,
+            remaining: if buffered.is_some() { 0 } else { data_len },
+            truncated: buffered
+                .as_ref()
+                .is_some_and(|data| (data.get_ref().len() as u64) < data_len),
+            buffered,
+            fact_samples,
+        })
+    }
+
+    /// Sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels
+    pub fn channels(&self) -> u8 {
+        self.channels as u8
+    }
+
+    /// Encoding of the samples in the data chunk
+    pub fn encoding(&self) -> WavEncoding {
+        self.encoding
+    }
+
+    /// Samples per channel declared by the header
+    pub fn duration_samples(&self) -> u64 {
+        self.data_len / self.block_bytes()
+    }
+
+    /// Samples per channel counted by the `fact` chunk, if one came before
+    /// the data
+    pub fn fact_samples(&self) -> Option<u64> {
+        self.fact_samples
+    }
+
+    /// True once the stream has ended before the end of the data chunk
+    /// declared in the header
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Skip `frames` samples per channel (whole blocks of `block_align` bytes)
+    ///
+    /// The source is only `Read`, so the skipped data is read and discarded.
+    /// Returns the number of frames skipped, which is less than `frames` only
+    /// at the end of the data.
+    pub fn skip_frames(&mut self, frames: u64) -> InputResult<u64> {
+        let block = self.block_bytes();
+        let skipped =
+            io::copy(&mut self.data().take(frames * block), &mut io::sink()).map_err(wav_error)?;
+        Ok(skipped / block)
+    }
+
+    /// Read up to `count` interleaved samples into `buffer`
+    ///
+    /// The buffer is cleared first. Returns the number of samples read,
+    /// which is less than `count` only at the end of the data.
+    pub fn read_samples(&mut self, buffer: &mut Vec<i16>, count: usize) -> InputResult<usize> {
+        let mut bytes = Vec::new();
+        let sample_bytes = self.encoding.sample_bytes();
+        self.data()
+            .take(count as u64 * sample_bytes)
+            .read_to_end(&mut bytes)
+            .map_err(wav_error)?;
+
+        buffer.clear();
+        self.encoding.decode(&bytes, buffer)?;
+        Ok(buffer.len())
+    }
+
+    /// Read all remaining interleaved samples
+    pub fn read_to_end(&mut self) -> InputResult<Vec<i16>> {
+        let mut bytes = Vec::new();
+        self.data().read_to_end(&mut bytes).map_err(wav_error)?;
+        let mut samples = Vec::with_capacity(bytes.len() / self.encoding.sample_bytes() as usize);
+        self.encoding.decode(&bytes, &mut samples)?;
+        Ok(samples)
+    }
+
+    /// Bytes of one sample of every channel
+    fn block_bytes(&self) -> u64 {
+        self.encoding.sample_bytes() * self.channels as u64
+    }
+
+    /// The rest of the data chunk; a stream that ends early simply ends
+    fn data(&mut self) -> DataReader<'_, R> {
+        DataReader { source: self }
+    }
+}
+
+/// `Read` over the remaining bytes of the data chunk
+struct DataReader<'a, R: Read> {
+    source: &'a mut WavSource<R>,
+}
+
+impl<R: Read> Read for DataReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(data) = &mut self.source.buffered {
+            return data.read(buf);
+        }
+        let limit = (buf.len() as u64).min(self.source.remaining) as usize;
+        let read = self.source.reader.read(&mut buf[..limit])?;
+        self.source.remaining -= read as u64;
+        if read == 0 && limit > 0 && !self.source.truncated {
+            self.source.truncated = true;
+            log::warn!(
+                "WAV stream ended {} bytes before the end of its data chunk",
+                self.source.remaining
+            );
+        }
+        Ok(read)
+    }
+}
+
+/// Read and discard `count` bytes
+fn skip<R: Read>(reader: &mut R, count: u64) -> InputResult<()> {
+    let skipped = io::copy(&mut reader.take(count), &mut io::sink()).map_err(wav_error)?;
+    if skipped < count {
+        return Err(InputDataError::InvalidWav(
+            "stream ended inside a chunk".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Read a chunk body of `size` bytes
+///
+/// The body is read as it arrives rather than allocated up front, so a
+/// corrupt size fails at the end of the stream instead of allocating it.
+fn read_body<R: Read>(reader: &mut R, size: u64) -> InputResult<Vec<u8>> {
+    let mut body = Vec::new();
+    reader
+        .take(size)
+        .read_to_end(&mut body)
+        .map_err(wav_error)?;
+    if (body.len() as u64) < size {
+        return Err(InputDataError::InvalidWav(
+            "stream ended inside a chunk".to_string(),
+        ));
+    }
+    Ok(body)
+}
+
+/// Read the `ds64` chunk that starts an RF64 stream and return the size of
+/// the data chunk
+fn read_ds64<R: Read>(reader: &mut R) -> InputResult<u64> {
+    let mut chunk = [0u8; 8];
+    reader.read_exact(&mut chunk).map_err(wav_error)?;
+    let size = read_u32_le(&chunk, 4) as u64;
+    if &chunk[0..4] != b"ds64" || size < 24 {
+        return Err(InputDataError::InvalidWav(
+            "RF64 stream without ds64 chunk".to_string(),
+        ));
+    }
+
+    // RIFF size, data size and sample count, then a table of other chunk sizes
+    let mut sizes = [0u8; 24];
+    reader.read_exact(&mut sizes).map_err(wav_error)?;
+    skip(reader, size - 24 + (size & 1))?;
+    Ok(read_u64_le(&sizes, 8))
+}
+
+/// Parse and validate the body of the `fmt ` chunk
+fn parse_format(body: &[u8]) -> InputResult<WavFormat> {
+    if body.len() < 16 {
+        return Err(InputDataError::InvalidWav(
+            "fmt chunk too short".to_string(),
+        ));
+    }
+    let mut format = WavFormat {
+        format_tag: read_u16_le(body, 0),
+        channels: read_u16_le(body, 2),
+        sample_rate: read_u32_le(body, 4),
+        block_align: read_u16_le(body, 12),
+        bits_per_sample: read_u16_le(body, 14),
+        encoding: WavEncoding::Pcm16,
+    };
+
+    // WAVE_FORMAT_EXTENSIBLE carries the real format in its sub-format GUID
+    if format.format_tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+        format.format_tag = read_u16_le(body, 24);
+    }
+
+    format.encoding = match (format.format_tag, format.bits_per_sample) {
+        (WAVE_FORMAT_PCM, 16) => WavEncoding::Pcm16,
+        (WAVE_FORMAT_ALAW, 8) => WavEncoding::ALaw,
+        (WAVE_FORMAT_MULAW, 8) => WavEncoding::MuLaw,
+        _ => {
+            return Err(InputDataError::InvalidWav(format!(
+            "only 16-bit integer PCM, A-law and mu-law are supported, got {}-bit format 0x{:04X}",
+            format.bits_per_sample, format.format_tag
+        )))
+        }
+    };
+    if format.channels == 0 || format.channels > 2 {
+        return Err(InputDataError::InvalidChannelCount {
+            expected: 2,
+            actual: format.channels as usize,
+        });
+    }
+    // G.711 data is exactly one byte per sample and channel
+    if format.encoding != WavEncoding::Pcm16
+        && format.block_align as u64 != format.channels as u64 * format.encoding.sample_bytes()
+    {
+        return Err(InputDataError::InvalidWav(format!(
+            "block alignment {} does not match {} one-byte channels",
+            format.block_align, format.channels
+        )));
+    }
+    Ok(format)
+}
+
+/// Write interleaved 16-bit samples as a canonical PCM WAV stream
+///
+/// Fails with `InvalidInput` if `channels` is zero, if the samples do not
+/// fill whole frames, or if the data would not fit in a 32-bit chunk size.
+pub fn write_wav<W: Write>(
+    mut writer: W,
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+) -> io::Result<()> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+    if channels == 0 {
+        return Err(invalid("a WAV stream needs at least one channel"));
+    }
+    if !samples.len().is_multiple_of(channels as usize) {
+        return Err(invalid("samples do not fill whole frames"));
+    }
+    let block_align = channels as u32 * SAMPLE_BYTES as u32;
+    let data_len = u32::try_from(samples.len() as u64 * SAMPLE_BYTES)
+        .ok()
+        .filter(|&len| len <= u32::MAX - 36)
+        .ok_or_else(|| invalid("too many samples for a 32-bit WAV stream"))?;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate.wrapping_mul(block_align)).to_le_bytes());
+    header.extend_from_slice(&(block_align as u16).to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    writer.write_all(&header)?;
+
+    let mut bytes = Vec::new();
+    for chunk in samples.chunks(4096) {
+        bytes.clear();
+        i16_to_bytes_le(chunk, &mut bytes);
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Write interleaved 16-bit samples to a canonical PCM WAV file
+///
+/// An existing file is overwritten. See `write_wav` for the errors.
+pub fn write_wav_file<P: AsRef<Path>>(
+    path: P,
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav(&mut writer, samples, sample_rate, channels)?;
+    writer.flush()
+}
+
+/// Write interleaved float samples as a canonical 16-bit PCM WAV stream
+///
+/// Samples are full scale at ±1.0 and are rounded to the nearest 16-bit
+/// value; values beyond full scale are clamped and NaN becomes silence.
+pub fn write_wav_f32<W: Write>(
+    writer: W,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+) -> io::Result<()> {
+    let samples: Vec<i16> = samples.iter().map(|&sample| f32_to_i16(sample)).collect();
+    write_wav(writer, &samples, sample_rate, channels)
+}
+
+/// Round a full-scale float sample to 16 bits
+fn f32_to_i16(sample: f32) -> i16 {
+    if sample.is_nan() {
+        return 0;
+    }
+    (sample as f64 * 32768.0)
+        .round()
+        .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+fn wav_error(err: io::Error) -> InputDataError {
+    InputDataError::InvalidWav(err.to_string())
+}
@@ -0,0 +1,220 @@
+//! Xing/Info header with a seek table
+//!
+//! A Xing (VBR) or Info (CBR) header fills an otherwise silent first frame
+//! and gives players the frame count, the stream size and a 100 entry table
+//! of contents (TOC) for seeking. Entry k of the TOC is the byte position of
+//! k percent of the playing time, scaled to 0..=255 of the stream size.
+//!
+//! The TOC depends on the offset of every frame, so it can only be written
+//! once encoding is done: reserve the first frame with `XingFrame::reserve`,
+//! write the stream while a `TocBuilder` records the frame offsets, then
+//! rewrite the reserved frame with `patch_xing`, at the offset it was
+//! written to.
+//!
+//! Shine only produces CBR streams, where seeking by bitrate is already
+//! exact, so `encode_wav_to_mp3` and the CLI do not reserve a frame.
+
+use crate::bytes::read_u32_be;
+use crate::error::{FrameError, FrameResult};
+use crate::frame::{iter_frames, FrameHeader, HEADER_SIZE};
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Number of entries in the table of contents
+pub const TOC_ENTRIES: usize = 100;
+
+/// Flags of the fields present after the tag
+const FLAG_FRAMES: u32 = 0x0001;
+const FLAG_BYTES: u32 = 0x0002;
+const FLAG_TOC: u32 = 0x0004;
+
+/// Tag, flags, frame count, byte count and TOC
+const PAYLOAD_LEN: usize = 16 + TOC_ENTRIES;
+
+/// Records the offset of each audio frame to build the TOC
+#[derive(Debug, Clone, Default)]
+pub struct TocBuilder {
+    offsets: Vec<u64>,
+}
+
+impl TocBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the offsets of all frames of an encoded stream
+    ///
+    /// `base` is the position of `data` in the output, usually the length
+    /// of the reserved Xing frame.
+    pub fn from_stream(data: &[u8], base: u64) -> Self {
+        let mut builder = Self::new();
+        for frame in iter_frames(data) {
+            builder.record(frame.index, base + frame.offset as u64);
+        }
+        builder
+    }
+
+    /// Record the byte offset of audio frame `frame_index`
+    ///
+    /// Frames must be recorded in order, starting at 0.
+    pub fn record(&mut self, frame_index: usize, byte_offset: u64) {
+        assert_eq!(
+            frame_index,
+            self.offsets.len(),
+            "frames must be recorded in order"
+        );
+        debug_assert!(self.offsets.last() <= Some(&byte_offset));
+        self.offsets.push(byte_offset);
+    }
+
+    /// Number of frames recorded
+    pub fn frames(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Byte offsets of the recorded frames
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// Table of contents for a stream of `total_bytes`
+    ///
+    /// Every frame has the same duration, so k percent of the playing time
+    /// starts at frame k * frames / 100. Without frames the table is linear.
+    pub fn toc(&self, total_bytes: u64) -> [u8; TOC_ENTRIES] {
+        let mut toc = [0u8; TOC_ENTRIES];
+        for (k, entry) in toc.iter_mut().enumerate() {
+            let position = if self.offsets.is_empty() || total_bytes == 0 {
+                k as u64 * 256 / TOC_ENTRIES as u64
+            } else {
+                let frame = k * self.offsets.len() / TOC_ENTRIES;
+                self.offsets[frame] * 256 / total_bytes
+            };
+            *entry = position.min(255) as u8;
+        }
+        toc
+    }
+}
+
+/// Contents of a Xing/Info frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XingFrame {
+    /// Header of the frame; matches the stream it describes
+    pub header: FrameHeader,
+    /// Tag the frame as "Xing" (VBR) rather than "Info" (CBR)
+    pub vbr: bool,
+    /// Number of audio frames, the Xing frame not included
+    pub frames: u32,
+    /// Stream size in bytes, the Xing frame included
+    pub bytes: u32,
+    /// Seek table
+    pub toc: [u8; TOC_ENTRIES],
+}
+
+impl XingFrame {
+    /// Placeholder frame for a stream with `header`, to be patched later
+    ///
+    /// The header is taken without padding or CRC so the frame length
+    /// depends only on bitrate and sample rate.
+    pub fn reserve(header: FrameHeader, vbr: bool) -> Self {
+        Self {
+            header: FrameHeader {
+                protected: false,
+                padding: false,
+                mode_ext: 0,
+                ..header
+            },
+            vbr,
+            frames: 0,
+            bytes: 0,
+            toc: TocBuilder::new().toc(0),
+        }
+    }
+
+    /// Length of the frame in bytes
+    pub fn len(&self) -> usize {
+        self.header.frame_length()
+    }
+
+    /// Always false: a Xing frame holds at least its header
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Fill in the counts and the TOC from the recorded frames
+    pub fn update(&mut self, toc: &TocBuilder, total_bytes: u64) {
+        self.frames = toc.frames() as u32;
+        self.bytes = total_bytes.min(u32::MAX as u64) as u32;
+        self.toc = toc.toc(total_bytes);
+    }
+
+    /// Encode the frame: header, zero side info and the Xing payload
+    pub fn to_bytes(&self) -> FrameResult<Vec<u8>> {
+        let start = HEADER_SIZE + self.header.side_info_len();
+        let length = self.len();
+        if start + PAYLOAD_LEN > length {
+            return Err(FrameError::InvalidHeader {
+                offset: 0,
+                reason: "frame too short for a Xing header",
+            });
+        }
+
+        let mut frame = vec![0u8; length];
+        frame[..HEADER_SIZE].copy_from_slice(&self.header.to_bytes());
+        let payload = &mut frame[start..start + PAYLOAD_LEN];
+        payload[0..4].copy_from_slice(if self.vbr { b"Xing" } else { b"Info" });
+        payload[4..8].copy_from_slice(&(FLAG_FRAMES | FLAG_BYTES | FLAG_TOC).to_be_bytes());
+        payload[8..12].copy_from_slice(&self.frames.to_be_bytes());
+        payload[12..16].copy_from_slice(&self.bytes.to_be_bytes());
+        payload[16..].copy_from_slice(&self.toc);
+        Ok(frame)
+    }
+
+    /// Decode a Xing/Info frame written by `to_bytes`
+    ///
+    /// Returns `None` if the frame has no Xing/Info tag or lacks any of the
+    /// frame count, byte count and TOC fields.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = FrameHeader::parse(data, 0).ok()?;
+        let start = HEADER_SIZE + if header.protected { 2 } else { 0 } + header.side_info_len();
+        let payload = data.get(start..start + PAYLOAD_LEN)?;
+        let vbr = match &payload[0..4] {
+            b"Xing" => true,
+            b"Info" => false,
+            _ => return None,
+        };
+        let word = |i: usize| read_u32_be(payload, i);
+        if word(4) & (FLAG_FRAMES | FLAG_BYTES | FLAG_TOC) != FLAG_FRAMES | FLAG_BYTES | FLAG_TOC {
+            return None;
+        }
+
+        let mut toc = [0u8; TOC_ENTRIES];
+        toc.copy_from_slice(&payload[16..]);
+        Some(Self {
+            header,
+            vbr,
+            frames: word(8),
+            bytes: word(12),
+            toc,
+        })
+    }
+}
+
+/// Rewrite the reserved frame of `writer` with `xing`
+///
+/// `offset` is the position the reserved frame was written at: 0, or the
+/// length of an ID3v2 tag written ahead of it. The position of `writer` is
+/// restored afterwards.
+pub fn patch_xing<W: Write + Seek>(
+    writer: &mut W,
+    offset: u64,
+    xing: &XingFrame,
+) -> io::Result<()> {
+    let frame = xing
+        .to_bytes()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let position = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(offset))?;
+    writer.write_all(&frame)?;
+    writer.seek(SeekFrom::Start(position))?;
+    Ok(())
+}
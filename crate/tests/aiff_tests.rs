@@ -0,0 +1,272 @@
+//! AIFF reader tests
+//!
+//! Generated AIFF and AIFF-C streams must yield the same samples as the
+//! equivalent WAV stream, wherever their chunks sit.
+
+use shine_rs::aiff::{extended_to_u32, AiffSource};
+use shine_rs::error::InputDataError;
+use shine_rs::wav::WavSource;
+use std::io::Cursor;
+
+/// 80-bit extended precision encoding of an integer sample rate
+fn extended(rate: u32) -> [u8; 10] {
+    let exponent = 31 - rate.leading_zeros();
+    let mut bytes = [0u8; 10];
+    bytes[0..2].copy_from_slice(&(16383 + exponent as u16).to_be_bytes());
+    bytes[2..10].copy_from_slice(&((rate as u64) << (63 - exponent)).to_be_bytes());
+    bytes
+}
+
+/// `COMM` chunk body; AIFF-C bodies carry a compression type and name
+fn comm_body(
+    sample_rate: u32,
+    channels: u16,
+    frames: u32,
+    compression: Option<&[u8; 4]>,
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&channels.to_be_bytes());
+    body.extend_from_slice(&frames.to_be_bytes());
+    body.extend_from_slice(&16u16.to_be_bytes());
+    body.extend_from_slice(&extended(sample_rate));
+    if let Some(compression) = compression {
+        body.extend_from_slice(compression);
+        // Pascal string padded to an even length
+        body.extend_from_slice(b"\x0bnot present");
+    }
+    body
+}
+
+/// `SSND` chunk body with `offset` bytes of alignment before the samples
+fn ssnd_body(samples: &[i16], little_endian: bool, offset: u32) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&offset.to_be_bytes());
+    body.extend_from_slice(&0u32.to_be_bytes());
+    body.resize(8 + offset as usize, 0xAA);
+    for sample in samples {
+        if little_endian {
+            body.extend_from_slice(&sample.to_le_bytes());
+        } else {
+            body.extend_from_slice(&sample.to_be_bytes());
+        }
+    }
+    body
+}
+
+/// FORM stream of `form_type` made of the given chunks, odd-sized ones padded
+fn form(form_type: &[u8; 4], chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = form_type.to_vec();
+    for (id, data) in chunks {
+        body.extend_from_slice(*id);
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    let mut aiff = b"FORM".to_vec();
+    aiff.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    aiff.extend_from_slice(&body);
+    aiff
+}
+
+/// 16-bit PCM WAV stream of the same samples
+fn wav(samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let mut body = b"WAVEfmt \x10\x00\x00\x00".to_vec();
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&sample_rate.to_le_bytes());
+    body.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    body.extend_from_slice(&(channels * 2).to_le_bytes());
+    body.extend_from_slice(&16u16.to_le_bytes());
+    body.extend_from_slice(b"data");
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&data);
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+    wav
+}
+
+fn test_samples() -> Vec<i16> {
+    (0..1152 * 2 * 3)
+        .map(|i| ((i as f64 * 0.03).sin() * 30000.0) as i16)
+        .collect()
+}
+
+fn read_all(aiff: Vec<u8>) -> (u32, u8, u64, Vec<i16>) {
+    let mut source = AiffSource::new(Cursor::new(aiff)).unwrap();
+    let samples = source.read_to_end().unwrap();
+    (
+        source.sample_rate(),
+        source.channels(),
+        source.duration_samples(),
+        samples,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_sample_rates() {
+        assert_eq!(extended(44100), [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+        for rate in [8000, 11025, 22050, 32000, 44100, 48000, 96000] {
+            assert_eq!(extended_to_u32(&extended(rate)), Some(rate));
+        }
+        // 22050.5 rounds to the nearest integer: 22050 has exponent 14, so
+        // the half sits at mantissa bit 63 - 15
+        let mut half = extended(22050);
+        let mantissa = u64::from_be_bytes(half[2..10].try_into().unwrap()) | 1 << 48;
+        half[2..10].copy_from_slice(&mantissa.to_be_bytes());
+        assert_eq!(extended_to_u32(&half), Some(22051));
+
+        let mut negative = extended(44100);
+        negative[0] |= 0x80;
+        assert_eq!(extended_to_u32(&negative), None);
+        assert_eq!(extended_to_u32(&[0; 10]), None);
+        assert_eq!(
+            extended_to_u32(&[0x7F, 0xFF, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_aiff_and_aifc_match_wav() {
+        let samples = test_samples();
+        let frames = samples.len() as u32 / 2;
+        let mut expected = WavSource::new(Cursor::new(wav(&samples, 44100, 2))).unwrap();
+        let expected = expected.read_to_end().unwrap();
+
+        let streams = [
+            form(
+                b"AIFF",
+                &[
+                    (b"COMM", comm_body(44100, 2, frames, None)),
+                    (b"SSND", ssnd_body(&samples, false, 0)),
+                ],
+            ),
+            form(
+                b"AIFC",
+                &[
+                    (b"FVER", 0xA280_5140u32.to_be_bytes().to_vec()),
+                    (b"COMM", comm_body(44100, 2, frames, Some(b"NONE"))),
+                    (b"SSND", ssnd_body(&samples, false, 0)),
+                ],
+            ),
+            form(
+                b"AIFC",
+                &[
+                    (b"FVER", 0xA280_5140u32.to_be_bytes().to_vec()),
+                    (b"COMM", comm_body(44100, 2, frames, Some(b"sowt"))),
+                    (b"SSND", ssnd_body(&samples, true, 0)),
+                ],
+            ),
+        ];
+        for (index, aiff) in streams.into_iter().enumerate() {
+            let mut source = AiffSource::new(Cursor::new(aiff.clone())).unwrap();
+            assert_eq!(source.is_little_endian(), index == 2);
+            let (rate, channels, duration, read) = read_all(aiff);
+            assert_eq!((rate, channels), (44100, 2));
+            assert_eq!(duration, frames as u64);
+            assert_eq!(read, expected);
+            assert_eq!(source.read_to_end().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_chunk_layouts() {
+        let samples = test_samples();
+        let frames = samples.len() as u32;
+        let layouts = [
+            // Odd-sized NAME chunk and an aligned SSND before COMM
+            form(
+                b"AIFF",
+                &[
+                    (b"NAME", b"shine".to_vec()),
+                    (b"SSND", ssnd_body(&samples, false, 6)),
+                    (b"COMM", comm_body(22050, 1, frames, None)),
+                ],
+            ),
+            // Trailing chunk after SSND
+            form(
+                b"AIFF",
+                &[
+                    (b"COMM", comm_body(22050, 1, frames, None)),
+                    (b"SSND", ssnd_body(&samples, false, 4)),
+                    (b"ANNO", vec![0x7F; 33]),
+                ],
+            ),
+        ];
+        for aiff in layouts {
+            let (rate, channels, duration, read) = read_all(aiff.clone());
+            assert_eq!((rate, channels, duration), (22050, 1, frames as u64));
+            assert_eq!(read, samples);
+
+            // Chunked reads stop at the end of the sample data as well
+            let mut source = AiffSource::new(Cursor::new(aiff)).unwrap();
+            assert_eq!(source.skip_frames(100).unwrap(), 100);
+            let mut buffer = Vec::new();
+            let mut total = Vec::new();
+            while source.read_samples(&mut buffer, 1000).unwrap() > 0 {
+                total.extend_from_slice(&buffer);
+            }
+            assert_eq!(total, samples[100..]);
+        }
+
+        // COMM declares fewer frames than SSND holds
+        let aiff = form(
+            b"AIFF",
+            &[
+                (b"COMM", comm_body(22050, 1, 1000, None)),
+                (b"SSND", ssnd_body(&samples, false, 0)),
+            ],
+        );
+        let (_, _, duration, read) = read_all(aiff);
+        assert_eq!(duration, 1000);
+        assert_eq!(read, samples[..1000]);
+    }
+
+    #[test]
+    fn test_unsupported_streams_rejected() {
+        let samples = test_samples();
+        let stream = |comm: Vec<u8>| {
+            let form_type = if comm.len() > 18 { b"AIFC" } else { b"AIFF" };
+            form(
+                form_type,
+                &[(b"COMM", comm), (b"SSND", ssnd_body(&samples, false, 0))],
+            )
+        };
+
+        let compressed = stream(comm_body(44100, 2, 100, Some(b"ima4")));
+        match AiffSource::new(Cursor::new(compressed)) {
+            Err(InputDataError::InvalidAiff(msg)) => assert!(msg.contains("'ima4'")),
+            other => panic!("compressed AIFF-C accepted: {:?}", other.err()),
+        }
+
+        let mut eight_bit = comm_body(44100, 2, 100, None);
+        eight_bit[7] = 8;
+        assert!(matches!(
+            AiffSource::new(Cursor::new(stream(eight_bit))),
+            Err(InputDataError::InvalidAiff(_))
+        ));
+        assert!(matches!(
+            AiffSource::new(Cursor::new(stream(comm_body(44100, 6, 100, None)))),
+            Err(InputDataError::InvalidChannelCount { .. })
+        ));
+
+        for aiff in [
+            form(b"AIFF", &[(b"SSND", ssnd_body(&samples, false, 0))]),
+            form(b"AIFF", &[(b"COMM", comm_body(44100, 2, 100, None))]),
+            form(b"8SVX", &[(b"COMM", comm_body(44100, 2, 100, None))]),
+            wav(&samples, 44100, 2),
+        ] {
+            assert!(matches!(
+                AiffSource::new(Cursor::new(aiff)),
+                Err(InputDataError::InvalidAiff(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Tests for the Tokio output adapter of the async feature
+//!
+//! Run with `cargo test --features async`.
+
+#![cfg(feature = "async")]
+
+use shine_rs::async_writer::AsyncMp3Writer;
+use shine_rs::error::{EncoderError, InputDataError};
+use shine_rs::frame::iter_frames;
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3EncoderConfig};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncReadExt, AsyncWrite};
+
+fn tone(samples: usize) -> Vec<i16> {
+    (0..samples)
+        .map(|i| ((i as f64 * 0.021).sin() * 9000.0) as i16)
+        .collect()
+}
+
+/// Sink whose writes always fail
+struct BrokenSink;
+
+impl AsyncWrite for BrokenSink {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, _: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::Error::from(io::ErrorKind::BrokenPipe)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duplex_receives_all_frames() {
+        let pcm = tone(1152 * 2 * 10 + 500);
+        // Smaller than one frame, so the writer has to wait for the reader
+        let (sink, mut source) = tokio::io::duplex(256);
+
+        let write = async {
+            let mut writer = AsyncMp3Writer::new(sink, Mp3EncoderConfig::new()).unwrap();
+            for chunk in pcm.chunks(1000) {
+                writer.write_pcm(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            writer.encoder().stats().frames_encoded
+        };
+        let read = async {
+            let mut received = Vec::new();
+            source.read_to_end(&mut received).await.unwrap();
+            received
+        };
+        let (frames_encoded, received) = tokio::join!(write, read);
+
+        // As in shine, the last frame loses the bits left in the cache
+        assert_eq!(iter_frames(&received).count() as u64, frames_encoded - 1);
+        assert_eq!(
+            received,
+            encode_pcm_to_mp3(Mp3EncoderConfig::new(), &pcm).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sink_error_is_passed_through() {
+        let mut writer = AsyncMp3Writer::new(BrokenSink, Mp3EncoderConfig::new()).unwrap();
+        let err = writer.write_pcm(&tone(1152 * 2)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[tokio::test]
+    async fn test_encoder_error_is_preserved() {
+        let config = Mp3EncoderConfig::new().strict_frame_alignment(true);
+        let (sink, _source) = tokio::io::duplex(64 * 1024);
+        let mut writer = AsyncMp3Writer::new(sink, config).unwrap();
+        writer.write_pcm(&tone(100)).await.unwrap();
+
+        let err = writer.shutdown().await.unwrap_err();
+        let inner = err.get_ref().and_then(|e| e.downcast_ref::<EncoderError>());
+        assert!(matches!(
+            inner,
+            Some(EncoderError::InputData(InputDataError::UnalignedInput {
+                remaining: 100
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_config_is_an_io_error() {
+        let config = Mp3EncoderConfig::new().bitrate(7);
+        let err = AsyncMp3Writer::new(Vec::<u8>::new(), config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+}
@@ -0,0 +1,111 @@
+//! Batch encoding tests
+//!
+//! Encodes WAV files written to the temp directory in parallel and checks
+//! the outputs and the order of the results.
+
+use shine_rs::batch::{encode_batch, encode_batch_with_threads, BatchJob};
+use shine_rs::error::EncoderError;
+use shine_rs::frame::iter_frames;
+use shine_rs::mp3_encoder::Mp3EncoderConfig;
+use std::fs;
+use std::path::PathBuf;
+
+/// Write a 16-bit WAV file to the temp directory and return its path
+fn write_wav(name: &str, sample_rate: u32, channels: u16, samples: usize) -> PathBuf {
+    let path = temp_path(name);
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..samples * channels as usize {
+        writer
+            .write_sample(((i as f32 * 0.02).sin() * 10000.0) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+    path
+}
+
+/// Per-process file name in the temp directory
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("shine_rs_batch_{}_{}", std::process::id(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_batch_preserves_job_order() {
+        let jobs: Vec<BatchJob> = vec![
+            (
+                write_wav("a.wav", 44100, 2, 44100),
+                temp_path("a.mp3"),
+                Mp3EncoderConfig::new().bitrate(128),
+            ),
+            (
+                write_wav("b.wav", 22050, 1, 11025),
+                temp_path("b.mp3"),
+                Mp3EncoderConfig::new().bitrate(64),
+            ),
+        ];
+        let outputs: Vec<PathBuf> = jobs.iter().map(|job| job.1.clone()).collect();
+
+        let results = encode_batch(jobs);
+        assert_eq!(results.len(), 2);
+
+        let first = results[0].as_ref().unwrap();
+        let second = results[1].as_ref().unwrap();
+        assert_eq!(first.samples_per_channel, 44100);
+        assert_eq!(second.samples_per_channel, 11025);
+
+        for (output, stats, sample_rate, channels) in [
+            (&outputs[0], first, 44100, 2),
+            (&outputs[1], second, 22050, 1),
+        ] {
+            let data = fs::read(output).unwrap();
+            assert_eq!(data.len() as u64, stats.bytes_written);
+
+            let header = iter_frames(&data)
+                .next()
+                .expect("output has no frames")
+                .header;
+            assert_eq!(header.sample_rate(), sample_rate);
+            assert_eq!(header.channels(), channels);
+        }
+
+        for name in ["a.wav", "a.mp3", "b.wav", "b.mp3"] {
+            let _ = fs::remove_file(temp_path(name));
+        }
+    }
+
+    #[test]
+    fn test_encode_batch_reports_failures_per_job() {
+        let jobs: Vec<BatchJob> = vec![
+            (
+                temp_path("missing.wav"),
+                temp_path("missing.mp3"),
+                Mp3EncoderConfig::new(),
+            ),
+            (
+                write_wav("c.wav", 16000, 1, 4000),
+                temp_path("c.mp3"),
+                Mp3EncoderConfig::new().bitrate(32),
+            ),
+        ];
+
+        // A single worker runs the jobs one after the other
+        let results = encode_batch_with_threads(jobs, 1);
+        assert!(matches!(results[0], Err(EncoderError::Io(_))));
+        assert_eq!(results[1].as_ref().unwrap().samples_per_channel, 4000);
+
+        assert!(encode_batch(Vec::new()).is_empty());
+
+        for name in ["c.wav", "c.mp3"] {
+            let _ = fs::remove_file(temp_path(name));
+        }
+    }
+}
@@ -244,7 +244,7 @@ mod tests {
 
         // Verify data is accessible
         let data = bs.get_data();
-        assert!(data.len() > 0, "Should have written data");
+        assert!(!data.is_empty(), "Should have written data");
     }
 
     #[test]
@@ -302,18 +302,24 @@ mod tests {
         const BITS_PER_FRAME: i32 = 3344;
 
         // Validate frame sizes are reasonable for 128kbps MP3
-        assert!(
-            F1_WRITTEN_BYTES > 400 && F1_WRITTEN_BYTES < 450,
-            "Frame 1 size should be reasonable"
-        );
-        assert!(
-            F2_WRITTEN_BYTES > 400 && F2_WRITTEN_BYTES < 450,
-            "Frame 2 size should be reasonable"
-        );
-        assert!(
-            F3_WRITTEN_BYTES > 400 && F3_WRITTEN_BYTES < 450,
-            "Frame 3 size should be reasonable"
-        );
+        const {
+            assert!(
+                F1_WRITTEN_BYTES > 400 && F1_WRITTEN_BYTES < 450,
+                "Frame 1 size should be reasonable"
+            )
+        };
+        const {
+            assert!(
+                F2_WRITTEN_BYTES > 400 && F2_WRITTEN_BYTES < 450,
+                "Frame 2 size should be reasonable"
+            )
+        };
+        const {
+            assert!(
+                F3_WRITTEN_BYTES > 400 && F3_WRITTEN_BYTES < 450,
+                "Frame 3 size should be reasonable"
+            )
+        };
 
         // Total size should be reasonable for 3 frames
         let total_bytes = F1_WRITTEN_BYTES + F2_WRITTEN_BYTES + F3_WRITTEN_BYTES;
@@ -351,7 +357,7 @@ mod tests {
             F3_SLOT_LAG_AFTER,
         ];
         for &lag in &all_slot_lags {
-            assert!(lag >= -1.0 && lag <= 1.0, "Slot lag {} out of range", lag);
+            assert!((-1.0..=1.0).contains(&lag), "Slot lag {} out of range", lag);
         }
 
         // Validate slot lag continuity (each frame's before should match previous frame's after)
@@ -393,7 +399,7 @@ mod tests {
         const BITRATE_128: u32 = 128000;
         let frame_size_128 = (SAMPLES_PER_FRAME * BITRATE_128) / (8 * SAMPLE_RATE);
         assert!(
-            frame_size_128 >= 416 && frame_size_128 <= 418,
+            (416..=418).contains(&frame_size_128),
             "128 kbps frame size should be ~417 bytes"
         );
 
@@ -401,7 +407,7 @@ mod tests {
         const BITRATE_192: u32 = 192000;
         let frame_size_192 = (SAMPLES_PER_FRAME * BITRATE_192) / (8 * SAMPLE_RATE);
         assert!(
-            frame_size_192 >= 625 && frame_size_192 <= 627,
+            (625..=627).contains(&frame_size_192),
             "192 kbps frame size should be ~626 bytes"
         );
     }
@@ -499,3 +505,259 @@ fn test_bitstream_writer_flush_additional() {
     let data = bs.get_data();
     assert!(!data.is_empty());
 }
+
+#[test]
+fn test_strict_writer_rejects_invalid_writes() {
+    use shine_rs::bitstream::BitstreamWriter;
+    use shine_rs::error::BitstreamError;
+
+    let mut bs = BitstreamWriter::new_strict(1024);
+    assert_eq!(
+        bs.try_write_bits(1, 33),
+        Err(BitstreamError::InvalidBitCount(33))
+    );
+    assert_eq!(
+        bs.try_write_bits(0, 0),
+        Err(BitstreamError::InvalidBitCount(0))
+    );
+    assert_eq!(
+        bs.try_write_bits(0xFF, 4),
+        Err(BitstreamError::ValueTooWide {
+            value: 0xFF,
+            bits: 4
+        })
+    );
+    assert_eq!(
+        bs.get_bits_count(),
+        0,
+        "Rejected writes must not change the stream"
+    );
+
+    bs.try_write_bits(0xF, 4).unwrap();
+    bs.try_write_bits(0xDEADBEEF, 32).unwrap();
+    assert_eq!(bs.get_bits_count(), 36);
+}
+
+#[test]
+fn test_lenient_writer_matches_put_bits() {
+    use shine_rs::bitstream::BitstreamWriter;
+
+    let mut lenient = BitstreamWriter::new(1024);
+    let mut reference = BitstreamWriter::new(1024);
+    for (value, bits) in [(0b101, 3), (0x1234, 16), (0xABCDEF01, 32), (0x7F, 7)] {
+        lenient.try_write_bits(value, bits).unwrap();
+        reference.put_bits(value, bits).unwrap();
+    }
+
+    // Invalid bit counts are ignored
+    lenient.try_write_bits(1, 33).unwrap();
+    lenient.try_write_bits(1, 0).unwrap();
+
+    lenient.flush().unwrap();
+    reference.flush().unwrap();
+    assert_eq!(lenient.get_data(), reference.get_data());
+}
+
+#[test]
+fn test_negative_coefficient_sets_huffman_sign_bit() {
+    use shine_rs::bitstream::format_bitstream;
+    use shine_rs::encoder::{shine_initialise, ShineConfig, ShineMpeg, ShineWave, NONE};
+
+    // Quantization stores magnitudes; the sign comes back from mdct_freq
+    let frame = |mdct_value: i32| {
+        let mut config = shine_initialise(&ShineConfig {
+            wave: ShineWave {
+                channels: 1,
+                samplerate: 44100,
+            },
+            mpeg: ShineMpeg {
+                mode: 3,
+                bitr: 128,
+                emph: NONE,
+                copyright: 0,
+                original: 1,
+            },
+        })
+        .unwrap();
+        config.mdct_freq[0][0][0] = mdct_value;
+        config.l3_enc[0][0][0] = 1;
+        let gi = &mut config.side_info.gr[0].ch[0].tt;
+        gi.big_values = 1;
+        gi.table_select = [1, 1, 1];
+        gi.part2_3_length = 16;
+
+        format_bitstream(&mut config).unwrap();
+        let restored = config.l3_enc[0][0][0];
+        config.bs.flush().unwrap();
+        (restored, config.bs.get_data().to_vec())
+    };
+
+    let (positive, positive_bits) = frame(1 << 20);
+    let (negative, negative_bits) = frame(-(1 << 20));
+    assert_eq!(positive, 1);
+    assert_eq!(negative, -1);
+
+    // The streams differ in exactly one bit: the sign of the first value
+    assert_eq!(positive_bits.len(), negative_bits.len());
+    let differing: u32 = positive_bits
+        .iter()
+        .zip(&negative_bits)
+        .map(|(a, b)| (a ^ b).count_ones())
+        .sum();
+    assert_eq!(differing, 1);
+    let (byte, _) = positive_bits
+        .iter()
+        .zip(&negative_bits)
+        .enumerate()
+        .find(|(_, (a, b))| a != b)
+        .map(|(index, (a, b))| (index, a ^ b))
+        .unwrap();
+    let set_in_negative = negative_bits[byte] & !positive_bits[byte];
+    assert_ne!(
+        set_in_negative, 0,
+        "sign bit must be 1 for a negative value"
+    );
+}
+
+/// Reads the bits of a formatted frame back, most significant bit first
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> u32 {
+        (0..bits).fold(0, |value, _| {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            self.position += 1;
+            value << 1 | bit as u32
+        })
+    }
+
+    fn peek(&self, bits: u32) -> u32 {
+        BitReader {
+            data: self.data,
+            position: self.position,
+        }
+        .read(bits)
+    }
+
+    /// Read the Huffman code of `table` and return its index
+    fn read_code(&mut self, table: &shine_rs::huffman::HuffCodeTab, entries: usize) -> usize {
+        let (codes, lengths) = (table.hb.unwrap(), table.hlen.unwrap());
+        let index = (0..entries)
+            .find(|&i| lengths[i] > 0 && self.peek(lengths[i] as u32) == codes[i] as u32)
+            .expect("no Huffman code matches");
+        self.position += lengths[index] as usize;
+        index
+    }
+
+    fn signed(&mut self, magnitude: i32) -> i32 {
+        if magnitude != 0 && self.read(1) == 1 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+/// Decode the big values and count1 regions of one granule
+fn decode_granule(reader: &mut BitReader, gi: &GrInfo, samplerate_index: usize) -> Vec<i32> {
+    use shine_rs::huffman::SHINE_HUFFMAN_TABLE;
+    use shine_rs::tables::SHINE_SCALE_FACT_BAND_INDEX;
+
+    let bands = &SHINE_SCALE_FACT_BAND_INDEX[samplerate_index];
+    let region1_start = bands[gi.region0_count as usize + 1] as usize;
+    let region2_start = bands[(gi.region0_count + gi.region1_count) as usize + 2] as usize;
+    let mut values = Vec::new();
+
+    while values.len() < gi.big_values as usize * 2 {
+        let region =
+            (values.len() >= region1_start) as usize + (values.len() >= region2_start) as usize;
+        let table_index = gi.table_select[region] as usize;
+        if table_index == 0 {
+            values.extend([0, 0]);
+            continue;
+        }
+        let table = &SHINE_HUFFMAN_TABLE[table_index];
+        let index = reader.read_code(table, (table.xlen * table.ylen) as usize);
+        let (mut x, mut y) = (
+            (index / table.ylen as usize) as i32,
+            (index % table.ylen as usize) as i32,
+        );
+        if table_index > 15 && x == 15 {
+            x += reader.read(table.linbits) as i32;
+        }
+        x = reader.signed(x);
+        if table_index > 15 && y == 15 {
+            y += reader.read(table.linbits) as i32;
+        }
+        y = reader.signed(y);
+        values.extend([x, y]);
+    }
+
+    let quad_table = &SHINE_HUFFMAN_TABLE[gi.count1table_select as usize + 32];
+    for _ in 0..gi.count1 {
+        let p = reader.read_code(quad_table, 16) as i32;
+        let quad = [p & 1, p >> 1 & 1, p >> 2 & 1, p >> 3 & 1];
+        values.extend(quad.map(|magnitude| reader.signed(magnitude)));
+    }
+    values
+}
+
+#[test]
+fn test_huffman_values_round_trip_with_signs() {
+    use shine_rs::encoder::{
+        shine_encode_buffer_interleaved_safe, shine_initialise, ShineConfig, ShineMpeg, ShineWave,
+        NONE,
+    };
+
+    let mut config = shine_initialise(&ShineConfig {
+        wave: ShineWave {
+            channels: 1,
+            samplerate: 44100,
+        },
+        mpeg: ShineMpeg {
+            mode: 3,
+            bitr: 128,
+            emph: NONE,
+            copyright: 0,
+            original: 1,
+        },
+    })
+    .unwrap();
+    let mut seed = 0x2545_f491u32;
+    let pcm: Vec<i16> = (0..1152)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 16) as i16 / 4
+        })
+        .collect();
+
+    // The first call leaves the end of the frame in the writer's cache
+    let (data, written) = shine_encode_buffer_interleaved_safe(&mut config, &pcm).unwrap();
+    let mut stream = data[..written].to_vec();
+    let side_info = config.side_info.clone();
+    let l3_enc = config.l3_enc.clone();
+    let samplerate_index = config.mpeg.samplerate_index as usize;
+    let (data, written) = shine_encode_buffer_interleaved_safe(&mut config, &pcm).unwrap();
+    stream.extend_from_slice(&data[..written]);
+
+    // Main data starts after the header and 17 bytes of mono side info
+    let mut reader = BitReader {
+        data: &stream,
+        position: (4 + 17) * 8,
+    };
+    let mut negative = 0;
+    for gr in 0..2 {
+        let gi = &side_info.gr[gr].ch[0].tt;
+        let start = reader.position;
+        reader.position += gi.part2_length as usize;
+        let values = decode_granule(&mut reader, gi, samplerate_index);
+        assert_eq!(values[..], l3_enc[0][gr][..values.len()], "granule {}", gr);
+        assert!(l3_enc[0][gr][values.len()..].iter().all(|&v| v == 0));
+        negative += values.iter().filter(|&&v| v < 0).count();
+        reader.position = start + gi.part2_3_length as usize;
+    }
+    assert!(negative > 100, "only {} negative values", negative);
+}
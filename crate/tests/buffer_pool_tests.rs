@@ -0,0 +1,110 @@
+//! Output buffer pool tests
+//!
+//! Frames written into pooled buffers must match normal output, and a
+//! pipeline that returns its buffers must not allocate new ones.
+
+use shine_rs::buffer_pool::{BufferPool, DEFAULT_BUFFER_CAPACITY};
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3Encoder, Mp3EncoderConfig};
+use std::sync::mpsc;
+use std::thread;
+
+/// Stereo sine of `frames` frames
+fn clip(frames: usize) -> Vec<i16> {
+    (0..frames * 1152 * 2)
+        .map(|i| {
+            let t = (i / 2) as f64 / 44100.0;
+            ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 12000.0) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_and_recycle() {
+        let pool = BufferPool::with_capacity(1, 64);
+        assert_eq!(pool.idle(), 1);
+
+        let mut first = pool.take();
+        assert!(first.capacity() >= 64);
+        first.extend_from_slice(&[1, 2, 3]);
+        let second = pool.take();
+        assert_eq!(pool.allocated(), 2);
+        assert_eq!(pool.idle(), 0);
+
+        pool.recycle(first);
+        pool.recycle(second);
+        assert_eq!(pool.idle(), 2);
+        assert!(pool.take().is_empty());
+        assert_eq!(pool.allocated(), 2);
+        assert_eq!(
+            BufferPool::new(0).buffer_capacity(),
+            DEFAULT_BUFFER_CAPACITY
+        );
+    }
+
+    #[test]
+    fn test_pooled_frames_recycled_across_threads() {
+        let pcm = clip(10);
+        let pool = BufferPool::new(3);
+        let mut encoder = Mp3Encoder::new(Mp3EncoderConfig::new())
+            .unwrap()
+            .with_buffer_pool(pool.clone());
+
+        // At most one frame waits in the channel while the consumer holds
+        // another and the encoder fills a third
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(1);
+        let consumer_pool = pool.clone();
+        let consumer = thread::spawn(move || {
+            let mut output = Vec::new();
+            let mut frames = 0;
+            for frame in receiver {
+                output.extend_from_slice(&frame);
+                consumer_pool.recycle(frame);
+                frames += 1;
+            }
+            (output, frames)
+        });
+
+        for chunk in pcm.chunks(1152 * 2) {
+            for frame in encoder.encode_interleaved(chunk).unwrap() {
+                sender.send(frame).unwrap();
+            }
+        }
+        let tail = encoder.finish().unwrap();
+        drop(sender);
+
+        let (mut output, frames) = consumer.join().unwrap();
+        output.extend(tail);
+        assert_eq!(frames, 10);
+        assert_eq!(pool.allocated(), 3);
+        assert_eq!(pool.idle(), 3);
+        assert_eq!(
+            output,
+            encode_pcm_to_mp3(Mp3EncoderConfig::new(), &pcm).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pool_survives_finish_and_reset() {
+        let pool = BufferPool::new(1);
+        let mut encoder = Mp3Encoder::new(Mp3EncoderConfig::new().trailing_silence_frames(2))
+            .unwrap()
+            .with_buffer_pool(pool.clone());
+
+        for _ in 0..3 {
+            // Flushed frames are copied into the returned data
+            encoder.encode_interleaved(&clip(1)[..1000]).unwrap();
+            encoder.finish().unwrap();
+            let mut silence = Vec::new();
+            encoder.reset();
+            encoder.encode_silence_frames(3, &mut silence).unwrap();
+            encoder.reset();
+        }
+        assert!(encoder.buffer_pool().is_some());
+        assert_eq!(pool.allocated(), 1);
+        assert_eq!(pool.idle(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Byte-order helper tests
+//!
+//! Each reader on known byte patterns, at the start of a slice and at an
+//! offset, including values with the top bit set.
+
+use shine_rs::bytes::{
+    read_i16_be, read_u16_be, read_u16_le, read_u32_be, read_u32_le, read_u64_be, read_u64_le,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u16() {
+        let bytes = [0x34, 0x12, 0x01, 0x80];
+        assert_eq!(read_u16_le(&bytes, 0), 0x1234);
+        assert_eq!(read_u16_be(&bytes, 0), 0x3412);
+        assert_eq!(read_u16_le(&bytes, 2), 0x8001);
+        assert_eq!(read_u16_be(&bytes, 2), 0x0180);
+        assert_eq!(read_u16_le(&bytes, 1), 0x0112);
+    }
+
+    #[test]
+    fn test_i16_be_sign() {
+        assert_eq!(read_i16_be(&[0x80, 0x00], 0), i16::MIN);
+        assert_eq!(read_i16_be(&[0xFF, 0xFF], 0), -1);
+        assert_eq!(read_i16_be(&[0x00, 0x7F, 0xFF], 1), i16::MAX);
+    }
+
+    #[test]
+    fn test_u32() {
+        let bytes = [0x00, 0x78, 0x56, 0x34, 0x12, 0xFE, 0xFF, 0xFF, 0xFF];
+        assert_eq!(read_u32_le(&bytes, 1), 0x1234_5678);
+        assert_eq!(read_u32_be(&bytes, 1), 0x7856_3412);
+        assert_eq!(read_u32_le(&bytes, 5), 0xFFFF_FFFE);
+        assert_eq!(read_u32_be(&bytes, 5), 0xFEFF_FFFF);
+        assert_eq!(read_u32_be(&[0xFF, 0xFF, 0xFF, 0xFE], 0), 0xFFFF_FFFE);
+        assert_eq!(read_u32_le(b"RIFF", 0), 0x4646_4952);
+    }
+
+    #[test]
+    fn test_u64() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 0x88];
+        assert_eq!(read_u64_le(&bytes, 0), 0x8807_0605_0403_0201);
+        assert_eq!(read_u64_be(&bytes, 0), 0x0102_0304_0506_0788);
+
+        let mut padded = vec![0xAA; 3];
+        padded.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(read_u64_le(&padded, 3), u64::MAX);
+        assert_eq!(read_u64_be(&padded, 3), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_short_slice_panics() {
+        read_u32_le(&[1, 2, 3, 4, 5], 2);
+    }
+}
@@ -0,0 +1,184 @@
+//! Stream validity across every encoder configuration
+//!
+//! Encodes a few frames of a tone at every sample rate, bitrate and channel
+//! mode and checks the stream the way a decoder reads it: frames follow each
+//! other at the length their headers declare, the main data of every frame
+//! lies within the bit reservoir and its own frame, and the granules carry
+//! coded values. A stream whose headers are valid but whose main data is
+//! broken fails here.
+//!
+//! No external decoder is run; the checks use the crate's own frame and
+//! side info parser.
+
+use shine_rs::error::FrameError;
+use shine_rs::frame::{iter_frames, HEADER_SIZE};
+use shine_rs::mp3_encoder::{
+    encode_pcm_to_mp3, supported_bitrates_for, Mp3Encoder, Mp3EncoderConfig, StereoMode,
+    SUPPORTED_SAMPLE_RATES,
+};
+
+/// Frames of tone encoded per configuration
+const FRAMES: usize = 4;
+
+/// Configurations known to produce broken main data
+///
+/// A granule codes at most 4095 bits per channel. At these mono rates a
+/// frame holds more main data than its granules can take, and the encoder
+/// drops the stuffing bits it cannot place (shine's `resvDrain`), so the
+/// next frame starts inside the previous one's main data. Remove an entry
+/// once its combination encodes correctly.
+const KNOWN_FAILURES: &[(u32, u32, StereoMode)] = &[
+    (8000, 64, StereoMode::Mono),
+    (16000, 128, StereoMode::Mono),
+    (16000, 144, StereoMode::Mono),
+    (16000, 160, StereoMode::Mono),
+    (32000, 256, StereoMode::Mono),
+    (32000, 320, StereoMode::Mono),
+    (44100, 320, StereoMode::Mono),
+];
+
+/// Interleaved tone of `frames` frames, a different pitch per channel
+fn tone(sample_rate: u32, channels: usize, samples_per_frame: usize, frames: usize) -> Vec<i16> {
+    (0..samples_per_frame * frames)
+        .map(|i| {
+            let t = (i / channels) as f64 / sample_rate as f64;
+            let pitch = if i % channels == 0 { 440.0 } else { 660.0 };
+            ((2.0 * std::f64::consts::PI * pitch * t).sin() * 12000.0) as i16
+        })
+        .collect()
+}
+
+/// Check an encoded stream, returning what is wrong with it
+fn check_stream(mp3: &[u8], config: &Mp3EncoderConfig) -> Result<(), String> {
+    let mut frames = iter_frames(mp3);
+    // Main data bytes of the frames so far, and the bit where the last
+    // frame's main data ended, both counted in the main data stream
+    let mut main_bytes = 0usize;
+    let mut main_end_bits = 0usize;
+    let mut count = 0;
+    let mut coded = false;
+
+    for frame in frames.by_ref() {
+        let header = &frame.header;
+        if header.sample_rate() != config.sample_rate || header.bitrate() != config.bitrate {
+            return Err(format!("frame {}: header {:?}", frame.index, header));
+        }
+        if header.channels() != config.channels as usize {
+            return Err(format!(
+                "frame {}: {} channels",
+                frame.index,
+                header.channels()
+            ));
+        }
+
+        let side_info = frame
+            .side_info()
+            .map_err(|e| format!("frame {}: {}", frame.index, e))?;
+        let side_info_end =
+            HEADER_SIZE + if header.protected { 2 } else { 0 } + header.side_info_len();
+        let own_bytes = frame.data.len() - side_info_end;
+
+        let begin = side_info.main_data_begin as usize;
+        if begin > main_bytes {
+            return Err(format!(
+                "frame {}: main_data_begin {} with only {} bytes before it",
+                frame.index, begin, main_bytes
+            ));
+        }
+        let start_bits = (main_bytes - begin) * 8;
+        // The encoder packs main data back to back, byte aligned per frame
+        if start_bits != main_end_bits.div_ceil(8) * 8 {
+            return Err(format!(
+                "frame {}: main data starts at bit {}, the previous frame ended at bit {}",
+                frame.index, start_bits, main_end_bits
+            ));
+        }
+
+        let mut bits = 0;
+        for granule in side_info.gr.iter().take(header.granules()) {
+            for gi in granule.iter().take(header.channels()) {
+                bits += gi.part2_3_length as usize;
+                coded |= gi.big_values > 0;
+            }
+        }
+        main_bytes += own_bytes;
+        main_end_bits = start_bits + bits;
+        if main_end_bits > main_bytes * 8 {
+            return Err(format!(
+                "frame {}: {} bits of main data run past the end of the frame",
+                frame.index, bits
+            ));
+        }
+        count += 1;
+    }
+
+    // Only the final frame may be cut short when the stream is flushed
+    match frames.error() {
+        None | Some(FrameError::Truncated { .. }) => {}
+        Some(err) => return Err(format!("after {} frames: {}", count, err)),
+    }
+    if count + 1 < FRAMES {
+        return Err(format!("{} frames for {} frames of input", count, FRAMES));
+    }
+    if !coded {
+        return Err("no granule codes any values".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_configuration_encodes_valid_main_data() {
+        let mut failures = Vec::new();
+        let mut unexpected_passes = Vec::new();
+        let mut checked = 0;
+
+        for &sample_rate in SUPPORTED_SAMPLE_RATES {
+            for &bitrate in supported_bitrates_for(sample_rate) {
+                for mode in [
+                    StereoMode::Mono,
+                    StereoMode::Stereo,
+                    StereoMode::JointStereo,
+                    StereoMode::DualChannel,
+                ] {
+                    let channels = if mode == StereoMode::Mono { 1 } else { 2 };
+                    let config = Mp3EncoderConfig::new()
+                        .sample_rate(sample_rate)
+                        .bitrate(bitrate)
+                        .channels(channels)
+                        .stereo_mode(mode);
+                    let samples_per_frame =
+                        Mp3Encoder::new(config.clone()).unwrap().samples_per_frame();
+                    let pcm = tone(sample_rate, channels as usize, samples_per_frame, FRAMES);
+                    let result = encode_pcm_to_mp3(config.clone(), &pcm)
+                        .map_err(|e| e.to_string())
+                        .and_then(|mp3| check_stream(&mp3, &config));
+                    checked += 1;
+
+                    let label = format!("{} Hz {} kbps {:?}", sample_rate, bitrate, mode);
+                    let known = KNOWN_FAILURES.contains(&(sample_rate, bitrate, mode));
+                    match result {
+                        Ok(()) if known => unexpected_passes.push(label),
+                        Err(err) if !known => failures.push(format!("{}: {}", label, err)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        assert!(checked > 400, "only {} configurations checked", checked);
+        assert!(
+            failures.is_empty(),
+            "broken streams:\n{}",
+            failures.join("\n")
+        );
+        assert!(
+            unexpected_passes.is_empty(),
+            "known failures that now pass, remove them from the list:\n{}",
+            unexpected_passes.join("\n")
+        );
+    }
+}
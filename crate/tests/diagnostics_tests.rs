@@ -0,0 +1,125 @@
+//! Tests for the spectrum analysis accessors of the diagnostics feature
+//!
+//! Run with `cargo test --features diagnostics`.
+
+#![cfg(feature = "diagnostics")]
+
+use shine_rs::diagnostics_data::{band_energies, spectral_flatness, SCALEFACTOR_BANDS};
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig, StereoMode};
+
+/// Mono 44.1 kHz / 128 kbps encoder
+fn mono_encoder() -> Mp3Encoder {
+    let config = Mp3EncoderConfig::new()
+        .sample_rate(44100)
+        .bitrate(128)
+        .channels(1)
+        .stereo_mode(StereoMode::Mono);
+    Mp3Encoder::new(config).unwrap()
+}
+
+/// Encode a mono signal, returning the encoder and the number of frames produced
+fn encode_mono(samples: &[i16]) -> (Mp3Encoder, usize) {
+    shine_rs::diagnostics::TestDataCollector::reset();
+
+    let mut encoder = mono_encoder();
+    let frames = encoder.encode_interleaved(samples).unwrap().len();
+    (encoder, frames)
+}
+
+fn sine(frequency: f64, len: usize) -> Vec<i16> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / 44100.0;
+            ((2.0 * std::f64::consts::PI * frequency * t).sin() * 16000.0) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine_energy_concentrates_in_expected_band() {
+        let (mut encoder, frames) = encode_mono(&sine(1000.0, 1152 * 4));
+        assert_eq!(frames, 4);
+
+        // 1 kHz falls on MDCT line 26 (38.3 Hz per line), inside band 6 (lines 24..30)
+        let energies =
+            band_energies(encoder.shine_config(), 3).expect("frame 3 should be captured");
+        for granule in &energies {
+            let bands = &granule[0];
+            let total: f64 = bands.iter().sum();
+            let (peak, peak_energy) =
+                bands.iter().enumerate().fold(
+                    (0, 0.0),
+                    |best, (sfb, &e)| if e > best.1 { (sfb, e) } else { best },
+                );
+            assert_eq!(peak, 6, "energy peak should be in band 6: {:?}", bands);
+            assert!(peak_energy / total > 0.8, "band 6 should dominate");
+
+            // Mono frames leave the second channel empty
+            assert!(granule[1].iter().all(|&e| e == 0.0));
+        }
+    }
+
+    #[test]
+    fn test_spectral_flatness_separates_tone_from_noise() {
+        let (mut encoder, _) = encode_mono(&sine(1000.0, 1152 * 3));
+        let tone = spectral_flatness(encoder.shine_config(), 2).unwrap();
+
+        let mut state = 0x1234_5678u32;
+        let noise: Vec<i16> = (0..1152 * 3)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                ((state >> 16) as i16) / 4
+            })
+            .collect();
+        let (mut encoder, _) = encode_mono(&noise);
+        let noisy = spectral_flatness(encoder.shine_config(), 2).unwrap();
+
+        for gr in 0..2 {
+            assert!(tone[gr][0] < 0.1, "tone flatness {}", tone[gr][0]);
+            assert!(noisy[gr][0] > 0.2, "noise flatness {}", noisy[gr][0]);
+        }
+    }
+
+    #[test]
+    fn test_unknown_frame_is_none() {
+        let (mut encoder, _) = encode_mono(&sine(440.0, 1152));
+        let config = encoder.shine_config();
+        assert!(band_energies(config, 1).is_some());
+        assert!(band_energies(config, 99).is_none());
+        assert!(spectral_flatness(config, 99).is_none());
+        assert_eq!(
+            band_energies(config, 1).unwrap()[0][0].len(),
+            SCALEFACTOR_BANDS
+        );
+
+        encoder.reset();
+        assert!(band_energies(encoder.shine_config(), 1).is_none());
+    }
+
+    #[test]
+    fn test_encoders_on_one_thread_keep_their_own_spectra() {
+        let mut low = mono_encoder();
+        let mut high = mono_encoder();
+        // Interleave the frames so both encoders capture frames 1 and 2 in turn
+        for _ in 0..2 {
+            low.encode_interleaved(&sine(200.0, 1152)).unwrap();
+            high.encode_interleaved(&sine(8000.0, 1152)).unwrap();
+        }
+
+        let peak_band = |encoder: &mut Mp3Encoder, frame| {
+            let energies = band_energies(encoder.shine_config(), frame).unwrap();
+            let bands = &energies[1][0];
+            (0..SCALEFACTOR_BANDS)
+                .max_by(|&a, &b| bands[a].total_cmp(&bands[b]))
+                .unwrap()
+        };
+        for frame in 1..=2 {
+            assert!(peak_band(&mut low, frame) < 4);
+            assert!(peak_band(&mut high, frame) > 12);
+        }
+    }
+}
@@ -4,6 +4,8 @@
 //! initialization, and encoding parameter setup.
 
 use shine_rs::encoder::*;
+use shine_rs::error::{ConfigError, EncodingError};
+use shine_rs::mp3_encoder::Emphasis;
 use shine_rs::types::*;
 
 // Import constants from encoder module
@@ -94,7 +96,7 @@ mod tests {
         let mut config = Box::new(ShineGlobalConfig::default());
         config.mpeg.granules_per_frame = 2; // MPEG-I
 
-        let samples = shine_samples_per_pass(&*config);
+        let samples = shine_samples_per_pass(&config);
         assert_eq!(samples, 2 * GRANULE_SIZE as i32);
     }
 
@@ -124,4 +126,174 @@ mod tests {
         assert_eq!(config.mpeg.layer, LAYER_III);
         assert_eq!(config.mpeg.bits_per_slot, 8);
     }
+
+    #[test]
+    fn test_shine_initialise_rejects_reserved_emphasis() {
+        let mut pub_config = ShineConfig::default();
+        pub_config.mpeg.emph = 2;
+
+        assert!(matches!(
+            shine_initialise(&pub_config),
+            Err(EncodingError::InvalidConfig(ConfigError::InvalidEmphasis(
+                2
+            )))
+        ));
+
+        for (emph, expected) in [
+            (NONE, Emphasis::None),
+            (MU50_15, Emphasis::Emphasis50_15),
+            (CITT, Emphasis::CcittJ17),
+        ] {
+            pub_config.mpeg.emph = emph;
+            assert!(shine_initialise(&pub_config).is_ok());
+            assert_eq!(Emphasis::validate(emph).unwrap(), expected);
+        }
+        assert!(Emphasis::try_from(4).is_err());
+    }
+
+    #[test]
+    fn test_shine_initialise_rejects_oversized_frames() {
+        let mut pub_config = ShineConfig::default();
+        pub_config.mpeg.bitr = 100_000;
+
+        // 1152 samples at 44.1 kHz and 100 Mbps: 326530 slots
+        assert!(matches!(
+            shine_initialise(&pub_config),
+            Err(EncodingError::InvalidConfig(
+                ConfigError::FrameSizeTooLarge {
+                    slots: 326_530,
+                    max: MAX_FRAME_SLOTS,
+                }
+            ))
+        ));
+        assert!(matches!(
+            shine_check_frame_size(44100, i32::MAX),
+            Err(ConfigError::FrameSizeTooLarge { .. })
+        ));
+
+        // The largest valid frames pass the guard
+        assert!(shine_check_frame_size(32000, 320).is_ok());
+        assert!(shine_check_frame_size(8000, 64).is_ok());
+    }
+
+    #[test]
+    fn test_smallest_frames_hold_side_info() {
+        // The lowest bitrate of every sample rate leaves room for main data,
+        // most tightly 8 kbps stereo at 24 kHz: 192 bits against 168
+        for samplerate in [44100, 48000, 32000, 22050, 24000, 16000, 11025, 12000, 8000] {
+            for channels in [1, 2] {
+                let mut pub_config = ShineConfig::default();
+                pub_config.wave.samplerate = samplerate;
+                pub_config.wave.channels = channels;
+                pub_config.mpeg.mode = if channels == 1 { 3 } else { 0 };
+                pub_config.mpeg.bitr = if samplerate >= 32000 { 32 } else { 8 };
+                assert!(shine_check_side_info(samplerate, pub_config.mpeg.bitr, channels).is_ok());
+
+                let mut config = shine_initialise(&pub_config).unwrap();
+                let samples = shine_samples_per_pass(&config) as usize * channels as usize;
+                let pcm: Vec<i16> = (0..samples).map(|i| (i * 7919 % 4001) as i16).collect();
+                for _ in 0..3 {
+                    shine_encode_buffer_interleaved_safe(&mut config, &pcm).unwrap();
+                    assert!(config.mpeg.bits_per_frame > config.sideinfo_len);
+                    assert!(config.mean_bits > 0);
+                }
+            }
+        }
+
+        assert!(matches!(
+            shine_check_side_info(24000, 7, 2),
+            Err(ConfigError::FrameTooSmall {
+                frame_bits: 168,
+                side_info_bits: 168,
+            })
+        ));
+        assert!(shine_check_side_info(24000, 7, 1).is_ok());
+        assert!(shine_check_side_info(96000, 7, 2).is_ok());
+    }
+
+    #[test]
+    fn test_quantized_values_kept_for_formatting() {
+        let mut pub_config = ShineConfig::default();
+        pub_config.wave.channels = 2;
+        pub_config.wave.samplerate = 44100;
+        pub_config.mpeg.bitr = 128;
+        let mut config = shine_initialise(&pub_config).unwrap();
+
+        let pcm: Vec<i16> = (0..1152 * 2)
+            .map(|i| {
+                let t = (i / 2) as f64 / 44100.0;
+                ((2.0 * std::f64::consts::PI * 1000.0 * t).sin() * 12000.0) as i16
+            })
+            .collect();
+        shine_encode_buffer_interleaved_safe(&mut config, &pcm).unwrap();
+
+        for gr in 0..2 {
+            for ch in 0..2 {
+                let gi = &config.side_info.gr[gr].ch[ch].tt;
+                let values = &config.l3_enc[ch][gr];
+                let big_values = gi.big_values as usize * 2;
+                let count1_end = big_values + gi.count1 as usize * 4;
+
+                // The big values region holds what was Huffman coded, the
+                // count1 region only -1, 0 and 1, and the rest is zero
+                assert!(big_values > 0, "granule {} channel {}", gr, ch);
+                assert!(values[..big_values].iter().any(|&v| v.abs() > 1));
+                assert!(values[big_values - 2..big_values].iter().any(|&v| v != 0));
+                assert!(values[big_values..count1_end].iter().all(|&v| v.abs() <= 1));
+                assert!(values[count1_end..].iter().all(|&v| v == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_strided_encode_matches_extracted_channels() {
+        let pub_config = ShineConfig {
+            wave: ShineWave {
+                channels: 2,
+                samplerate: 44100,
+            },
+            mpeg: ShineMpeg {
+                mode: 0,
+                bitr: 128,
+                emph: NONE,
+                copyright: 0,
+                original: 1,
+            },
+        };
+        // Eight channels of a ramp, each around its own constant
+        let total = 8;
+        let pcm: Vec<i16> = (0..1152 * total)
+            .map(|i| ((i % total) as i16 + 1) * 1500 + ((i / total) % 97) as i16 * 40)
+            .collect();
+        let pair: Vec<i16> = pcm
+            .chunks_exact(total)
+            .flat_map(|frame| &frame[2..4])
+            .copied()
+            .collect();
+
+        let mut strided = shine_initialise(&pub_config).unwrap();
+        let mut plain = shine_initialise(&pub_config).unwrap();
+        let (data, written) =
+            shine_encode_buffer_interleaved_strided(&mut strided, &pcm, total, 2).unwrap();
+        let strided_frame = data[..written].to_vec();
+        let (data, written) = shine_encode_buffer_interleaved_safe(&mut plain, &pair).unwrap();
+        assert_eq!(strided_frame, &data[..written]);
+        assert_eq!(strided.mdct_freq, plain.mdct_freq);
+
+        assert!(matches!(
+            shine_encode_buffer_interleaved_strided(&mut strided, &pcm, total, 7),
+            Err(EncodingError::InvalidChannelIndex {
+                channel: 8,
+                max_channels: 8
+            })
+        ));
+        assert!(matches!(
+            shine_encode_buffer_interleaved_strided(&mut strided, &pcm[1..], total, 0),
+            Err(EncodingError::InvalidDataLength { .. })
+        ));
+        assert!(matches!(
+            shine_encode_buffer_interleaved_strided(&mut strided, &pcm[..total * 10], total, 0),
+            Err(EncodingError::InvalidInputLength { .. })
+        ));
+    }
 }
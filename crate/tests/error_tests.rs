@@ -0,0 +1,302 @@
+//! Error hierarchy tests
+//!
+//! Codes are unique across every leaf error and unchanged by wrapping,
+//! Display messages carry the parameters, sources are chained, the
+//! conversion to `io::Error` keeps the original error, and only errors
+//! raised part way through a frame are unrecoverable.
+
+use shine_rs::{
+    BitstreamError, ConfigError, EncoderError, EncodingError, FrameError, InputDataError,
+    StereoMode,
+};
+use std::collections::HashSet;
+use std::error::Error;
+use std::io;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One error of every variant that has a code of its own
+    fn leaf_errors() -> Vec<EncoderError> {
+        let config = [
+            ConfigError::UnsupportedSampleRate(12345),
+            ConfigError::UnsupportedBitrate(123),
+            ConfigError::Mpeg25Disabled(8000),
+            ConfigError::InvalidChannels,
+            ConfigError::IncompatibleRateCombination {
+                sample_rate: 8000,
+                bitrate: 320,
+                reason: "MPEG-2.5 only supports bitrates up to 64 kbps",
+            },
+            ConfigError::InvalidStereoMode {
+                mode: StereoMode::Mono,
+                channels: 2,
+            },
+            ConfigError::InvalidLowpass(0),
+            ConfigError::InvalidMsThreshold(1.5),
+            ConfigError::InvalidEmphasis(2),
+            ConfigError::FrameSizeTooLarge {
+                slots: 3000,
+                max: 2881,
+            },
+            ConfigError::FrameTooSmall {
+                frame_bits: 168,
+                side_info_bits: 168,
+            },
+            ConfigError::FrameBitBudgetOutOfRange {
+                bits: 100,
+                min: 288,
+                max: 23048,
+            },
+        ];
+        let input = [
+            InputDataError::InvalidLength {
+                expected: 2304,
+                actual: 7,
+            },
+            InputDataError::InvalidChannelLength {
+                channel: 1,
+                expected: 1152,
+                actual: 1000,
+            },
+            InputDataError::PartialFrame {
+                have: 100,
+                need: 1152,
+            },
+            InputDataError::InvalidChannelCount {
+                expected: 2,
+                actual: 3,
+            },
+            InputDataError::InvalidSamples,
+            InputDataError::EmptyInput,
+            InputDataError::UnalignedInput { remaining: 17 },
+            InputDataError::InvalidWav("no fmt chunk".to_string()),
+            InputDataError::InvalidAiff("no COMM chunk".to_string()),
+            InputDataError::OddByteCount(3),
+            InputDataError::RangeOutOfBounds {
+                start: 500,
+                available: 400,
+            },
+            InputDataError::BufferTooSmall { needed: 418 },
+        ];
+        let encoding = [
+            EncodingError::QuantizationFailed,
+            EncodingError::HuffmanTableUnavailable { table: 4 },
+            EncodingError::Bitstream(BitstreamError::InvalidBitCount(33)),
+            EncodingError::Bitstream(BitstreamError::ValueTooWide {
+                value: 0x10,
+                bits: 4,
+            }),
+            EncodingError::InvalidInputLength {
+                expected: 2304,
+                actual: 10,
+            },
+            EncodingError::InvalidDataLength {
+                expected: 3,
+                actual: 10,
+            },
+            EncodingError::InvalidChannelIndex {
+                channel: 4,
+                max_channels: 3,
+            },
+            EncodingError::BitReservoirOverflow {
+                requested: 5000,
+                available: 4000,
+            },
+            EncodingError::PadOutput {
+                size: 1000,
+                target: 900,
+            },
+        ];
+
+        let mut errors: Vec<EncoderError> = Vec::new();
+        errors.extend(config.into_iter().map(EncoderError::from));
+        errors.extend(input.into_iter().map(EncoderError::from));
+        errors.extend(encoding.into_iter().map(EncoderError::from));
+        errors.extend([
+            EncoderError::Memory,
+            EncoderError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "eof")),
+            EncoderError::Finished,
+            EncoderError::Poisoned,
+            EncoderError::BufferedSamples { samples: 10 },
+            EncoderError::Resume(FrameError::NoSync { offset: 0 }),
+            EncoderError::ResumeMismatch {
+                offset: 417,
+                version: "MPEG-1",
+                bitrate: 128,
+                sample_rate: 44100,
+                mode: 1,
+                expected_bitrate: 192,
+                expected_sample_rate: 44100,
+                expected_mode: 1,
+            },
+        ]);
+        errors
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let errors = leaf_errors();
+        let codes: HashSet<&str> = errors.iter().map(EncoderError::code).collect();
+        assert_eq!(codes.len(), errors.len(), "duplicate codes in {:?}", codes);
+
+        let frame_codes: HashSet<&str> = [
+            FrameError::NoSync { offset: 0 },
+            FrameError::InvalidHeader {
+                offset: 0,
+                reason: "reserved layer",
+            },
+            FrameError::Truncated {
+                offset: 0,
+                needed: 417,
+                available: 100,
+            },
+        ]
+        .iter()
+        .map(FrameError::code)
+        .collect();
+        assert_eq!(frame_codes.len(), 3);
+        assert!(frame_codes.is_disjoint(&codes));
+
+        for code in codes.iter().chain(&frame_codes) {
+            assert!(
+                code.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_'),
+                "code {:?} is not a lowercase dotted identifier",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_codes_survive_wrapping() {
+        let config = ConfigError::UnsupportedSampleRate(12345);
+        assert_eq!(config.code(), "config.unsupported_sample_rate");
+        assert_eq!(
+            EncoderError::Config(ConfigError::UnsupportedSampleRate(12345)).code(),
+            config.code()
+        );
+        assert_eq!(
+            EncodingError::InvalidConfig(ConfigError::UnsupportedSampleRate(12345)).code(),
+            config.code()
+        );
+        assert_eq!(
+            EncoderError::Encoding(EncodingError::Bitstream(BitstreamError::InvalidBitCount(0)))
+                .code(),
+            "bitstream.invalid_bit_count"
+        );
+    }
+
+    #[test]
+    fn test_display_includes_parameters() {
+        let message = EncoderError::Config(ConfigError::IncompatibleRateCombination {
+            sample_rate: 8000,
+            bitrate: 320,
+            reason: "MPEG-2.5 only supports bitrates up to 64 kbps",
+        })
+        .to_string();
+        assert!(message.contains("8000 Hz"), "{}", message);
+        assert!(message.contains("320 kbps"), "{}", message);
+        assert!(message.contains("64 kbps"), "{}", message);
+
+        let message = EncodingError::HuffmanTableUnavailable { table: 14 }.to_string();
+        assert!(message.contains("14"), "{}", message);
+
+        let message = EncoderError::ResumeMismatch {
+            offset: 417,
+            version: "MPEG-1",
+            bitrate: 128,
+            sample_rate: 44100,
+            mode: 1,
+            expected_bitrate: 192,
+            expected_sample_rate: 48000,
+            expected_mode: 0,
+        }
+        .to_string();
+        for part in [
+            "417", "MPEG-1", "128 kbps", "44100 Hz", "192 kbps", "48000 Hz",
+        ] {
+            assert!(
+                message.contains(part),
+                "{:?} missing from {}",
+                part,
+                message
+            );
+        }
+
+        let message = EncoderError::BufferedSamples { samples: 10 }.to_string();
+        assert!(message.contains("10"), "{}", message);
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let error = EncoderError::Encoding(EncodingError::InvalidConfig(
+            ConfigError::UnsupportedBitrate(123),
+        ));
+        let encoding = error.source().expect("encoding error is the source");
+        assert!(encoding.is::<EncodingError>());
+        let config = encoding.source().expect("config error is the source");
+        assert!(matches!(
+            config.downcast_ref::<ConfigError>(),
+            Some(ConfigError::UnsupportedBitrate(123))
+        ));
+
+        let error = EncoderError::Resume(FrameError::NoSync { offset: 12 });
+        assert!(matches!(
+            error.source().and_then(|s| s.downcast_ref::<FrameError>()),
+            Some(FrameError::NoSync { offset: 12 })
+        ));
+
+        assert!(EncoderError::Finished.source().is_none());
+    }
+
+    #[test]
+    fn test_into_io_error_keeps_original() {
+        let error: io::Error = EncoderError::Config(ConfigError::UnsupportedBitrate(123)).into();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        let inner = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<EncoderError>())
+            .expect("EncoderError is kept");
+        assert_eq!(inner.code(), "config.unsupported_bitrate");
+
+        // I/O errors are handed back as they were
+        let error: io::Error =
+            EncoderError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "eof")).into();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_recoverable_classification() {
+        let unrecoverable: HashSet<&str> = [
+            "encoding.quantization_failed",
+            "encoding.huffman_table_unavailable",
+            "bitstream.invalid_bit_count",
+            "bitstream.value_too_wide",
+            "encoding.bit_reservoir_overflow",
+            "encoding.pad_output",
+            "memory",
+            "state.poisoned",
+        ]
+        .into_iter()
+        .collect();
+        for error in leaf_errors() {
+            assert_eq!(
+                error.is_recoverable(),
+                !unrecoverable.contains(error.code()),
+                "{}",
+                error.code()
+            );
+        }
+
+        // Wrapping does not change the classification
+        let error = EncodingError::InvalidConfig(ConfigError::FrameBitBudgetOutOfRange {
+            bits: 100,
+            min: 288,
+            max: 23048,
+        });
+        assert!(error.is_recoverable());
+        assert!(EncoderError::Encoding(error).is_recoverable());
+    }
+}
@@ -0,0 +1,212 @@
+//! Frame parser tests
+//!
+//! Encoded frames are parsed back and the decoded header and side
+//! information are checked against the encoder state that produced them.
+
+use shine_rs::error::FrameError;
+use shine_rs::frame::{iter_frames, parse_side_info, FrameHeader, HEADER_SIZE};
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig, StereoMode};
+
+/// Generate interleaved PCM with a different tone on each channel
+fn tone(sample_rate: u32, channels: usize, frames: usize) -> Vec<i16> {
+    (0..frames * 1152)
+        .flat_map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            (0..channels).map(move |ch| {
+                let frequency = 440.0 * (ch + 1) as f64;
+                ((2.0 * std::f64::consts::PI * frequency * t).sin() * 10000.0) as i16
+            })
+        })
+        .collect()
+}
+
+fn encoder(channels: u8) -> Mp3Encoder {
+    let mode = if channels == 1 {
+        StereoMode::Mono
+    } else {
+        StereoMode::Stereo
+    };
+    let config = Mp3EncoderConfig::new()
+        .sample_rate(44100)
+        .bitrate(128)
+        .channels(channels)
+        .stereo_mode(mode);
+    Mp3Encoder::new(config).expect("encoder should initialise")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode frames one at a time, keeping the encoder side info of each,
+    /// then parse the complete stream and compare frame by frame
+    fn check_side_info_roundtrip(channels: u8) {
+        let mut encoder = encoder(channels);
+        let pcm = tone(44100, channels as usize, 4);
+
+        let mut stream = Vec::new();
+        let mut expected = Vec::new();
+        for chunk in pcm.chunks(1152 * channels as usize) {
+            for data in encoder.encode_interleaved(chunk).unwrap() {
+                stream.extend_from_slice(&data);
+            }
+            expected.push(encoder.shine_config().side_info.clone());
+        }
+        stream.extend_from_slice(&encoder.finish().unwrap());
+
+        let frames: Vec<_> = iter_frames(&stream).collect();
+        assert_eq!(frames.len(), expected.len());
+        for (frame, expected) in frames.iter().zip(&expected) {
+            let header = frame.header;
+            assert_eq!(header.channels(), channels as usize);
+            assert_eq!(header.side_info_len(), if channels == 1 { 17 } else { 32 });
+
+            let si = parse_side_info(&header, frame.data, frame.offset).unwrap();
+            assert_eq!(si, frame.side_info().unwrap());
+            assert_eq!(si.main_data_begin, 0);
+            assert_eq!(si.private_bits, expected.private_bits);
+            for ch in 0..channels as usize {
+                assert_eq!(si.scfsi[ch], expected.scfsi[ch]);
+            }
+            for gr in 0..2 {
+                for ch in 0..channels as usize {
+                    let parsed = &si.gr[gr][ch];
+                    let tt = &expected.gr[gr].ch[ch].tt;
+                    assert_eq!(parsed.part2_3_length, tt.part2_3_length);
+                    assert_eq!(parsed.big_values, tt.big_values);
+                    assert_eq!(parsed.global_gain, tt.global_gain);
+                    assert_eq!(parsed.scalefac_compress, tt.scalefac_compress);
+                    assert!(!parsed.window_switching);
+                    assert_eq!(parsed.table_select, tt.table_select);
+                    assert_eq!(parsed.region0_count, tt.region0_count);
+                    assert_eq!(parsed.region1_count, tt.region1_count);
+                    assert_eq!(parsed.preflag, tt.preflag);
+                    assert_eq!(parsed.scalefac_scale, tt.scalefac_scale);
+                    assert_eq!(parsed.count1table_select, tt.count1table_select);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_side_info_mpeg1_stereo() {
+        check_side_info_roundtrip(2);
+    }
+
+    #[test]
+    fn test_side_info_mpeg1_mono() {
+        check_side_info_roundtrip(1);
+    }
+
+    #[test]
+    fn test_side_info_slots_follow_channel_content() {
+        // One channel silent, the other noise: the silent channel's
+        // granules code nothing, wherever they sit in the side info
+        for silent in 0..2 {
+            let mut state = 0x1234_5678u32;
+            let pcm: Vec<i16> = (0..1152 * 2 * 4)
+                .map(|i| {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    if i % 2 == silent {
+                        0
+                    } else {
+                        (state >> 16) as i16 / 2
+                    }
+                })
+                .collect();
+            let mut encoder = encoder(2);
+            let mut stream = encoder.encode_interleaved(&pcm).unwrap().concat();
+            stream.extend_from_slice(&encoder.finish().unwrap());
+
+            for frame in iter_frames(&stream) {
+                let si = frame.side_info().unwrap();
+                for gr in 0..2 {
+                    let quiet = &si.gr[gr][silent];
+                    let loud = &si.gr[gr][1 - silent];
+                    assert_eq!(quiet.big_values, 0, "frame {} granule {}", frame.index, gr);
+                    assert!(
+                        loud.part2_3_length > 500,
+                        "frame {} granule {}",
+                        frame.index,
+                        gr
+                    );
+                    // Stuffing bits go to granule 0 of channel 0
+                    if gr == 1 || silent == 1 {
+                        assert_eq!(quiet.part2_3_length, 0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Encode a complete stream including the flushed tail
+    fn encode_stream(frames: usize) -> Vec<u8> {
+        let mut encoder = encoder(2);
+        let mut stream = encoder
+            .encode_interleaved(&tone(44100, 2, frames))
+            .unwrap()
+            .concat();
+        stream.extend_from_slice(&encoder.finish().unwrap());
+        stream
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let stream = encode_stream(2);
+        let header = FrameHeader::parse(&stream, 0).unwrap();
+        assert_eq!(header.version_name(), "MPEG-1");
+        assert_eq!(header.bitrate(), 128);
+        assert_eq!(header.sample_rate(), 44100);
+        assert_eq!(header.channels(), 2);
+        assert_eq!(header.granules(), 2);
+        assert_eq!(header.samples_per_frame(), 1152);
+        // 128 kbps at 44.1 kHz alternates between 417 and 418 byte frames
+        assert_eq!(header.frame_length(), 417 + header.padding as usize);
+    }
+
+    #[test]
+    fn test_iter_frames_walks_stream() {
+        let stream = encode_stream(6);
+
+        let frames: Vec<_> = iter_frames(&stream).collect();
+        assert_eq!(frames.len(), 6);
+        let mut offset = 0;
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.index, i);
+            assert_eq!(frame.offset, offset);
+            assert_eq!(frame.data.len(), frame.header.frame_length());
+            offset += frame.data.len();
+        }
+        assert_eq!(offset, stream.len());
+    }
+
+    #[test]
+    fn test_iter_frames_reports_truncation() {
+        let stream = encode_stream(3);
+        let cut = &stream[..stream.len() - 10];
+
+        let mut iter = iter_frames(cut);
+        assert_eq!(iter.by_ref().count(), 2);
+        assert!(matches!(iter.error(), Some(FrameError::Truncated { .. })));
+    }
+
+    #[test]
+    fn test_header_rejects_garbage() {
+        assert_eq!(
+            FrameHeader::parse(&[0x00, 0x00, 0x00, 0x00], 7),
+            Err(FrameError::NoSync { offset: 7 })
+        );
+        assert!(matches!(
+            FrameHeader::parse(&[0xFF, 0xFB], 0),
+            Err(FrameError::Truncated {
+                needed: HEADER_SIZE,
+                ..
+            })
+        ));
+        // Layer II frame
+        assert!(matches!(
+            FrameHeader::parse(&[0xFF, 0xFD, 0x90, 0x00], 0),
+            Err(FrameError::InvalidHeader { .. })
+        ));
+    }
+}
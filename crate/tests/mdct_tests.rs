@@ -18,15 +18,39 @@ mod tests {
         const PI36: f64 = PI / 36.0;
         const PI72: f64 = PI / 72.0;
 
-        assert!(PI36 > 0.0, "PI36 should be positive");
-        assert!(PI72 > 0.0, "PI72 should be positive");
-        assert!(PI36 > PI72, "PI36 should be larger than PI72");
+        const {
+            assert!(PI36 > 0.0, "PI36 should be positive");
+            assert!(PI72 > 0.0, "PI72 should be positive");
+            assert!(PI36 > PI72, "PI36 should be larger than PI72");
+        };
 
         // Test relationship
         assert!((PI36 - PI / 36.0).abs() < 1e-10, "PI36 should equal PI/36");
         assert!((PI72 - PI / 72.0).abs() < 1e-10, "PI72 should equal PI/72");
     }
 
+    #[test]
+    fn test_shared_cos_table_matches_shine() {
+        use shine_rs::tables::SHINE_MDCT_COS_L;
+        use std::f64::consts::PI;
+
+        // shine_mdct_initialise() in l3mdct.c, loop order included
+        let mut cos_l = [[0i32; 36]; 18];
+        for m in (0..18).rev() {
+            for k in (0..36).rev() {
+                cos_l[m][k] = ((PI / 36.0 * (k as f64 + 0.5)).sin()
+                    * ((PI / 72.0) * (2 * k + 19) as f64 * (2 * m + 1) as f64).cos()
+                    * 0x7fffffff as f64) as i32;
+            }
+        }
+        assert_eq!(*SHINE_MDCT_COS_L, cos_l);
+
+        // Initialising an encoder leaves the shared table alone
+        let mut config = ShineGlobalConfig::new();
+        shine_rs::mdct::shine_mdct_initialise(&mut config);
+        assert_eq!(*SHINE_MDCT_COS_L, cos_l);
+    }
+
     #[test]
     fn test_mdct_structure_sizes() {
         // Test that MDCT-related structures have correct sizes
@@ -156,4 +180,68 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_lowpass_line_rounds_up_to_band_boundary() {
+        use shine_rs::mdct::shine_lowpass_line;
+        use shine_rs::tables::SHINE_SCALE_FACT_BAND_INDEX;
+
+        // 8 kHz at 44.1 kHz is line 209, inside the band 196..238
+        assert_eq!(shine_lowpass_line(0, 44100, 8000), 238);
+        // A cutoff exactly on a boundary keeps that boundary
+        assert_eq!(shine_lowpass_line(0, 44100, 196 * 44100 / 1152), 196);
+        // At or above the last band the filter is disabled
+        assert_eq!(shine_lowpass_line(0, 44100, 22050), GRANULE_SIZE);
+
+        // 22.05 kHz: the result is a band boundary at or above the cutoff line
+        let line = shine_lowpass_line(4, 22050, 5000);
+        let cutoff_line = (5000 * 1152usize).div_ceil(22050) as i32;
+        let bands = &SHINE_SCALE_FACT_BAND_INDEX[4];
+        let position = bands.iter().position(|&b| b as usize == line).unwrap();
+        assert!(bands[position] >= cutoff_line && bands[position - 1] < cutoff_line);
+    }
+
+    #[test]
+    fn test_ms_stereo_transform_and_decision() {
+        use shine_rs::mdct::{shine_channel_similarity, shine_ms_stereo, MODE_EXT_MS};
+
+        let mut config = ShineGlobalConfig::default();
+        config.wave.channels = 2;
+        config.mpeg.granules_per_frame = 2;
+        for gr in 0..2 {
+            for k in 0..GRANULE_SIZE {
+                let value = (k as i32 - 288) * 1000;
+                config.mdct_freq[0][gr][k] = value;
+                config.mdct_freq[1][gr][k] = value;
+            }
+        }
+        assert!((shine_channel_similarity(&config) - 1.0).abs() < 1e-12);
+
+        // Without a threshold the frame stays left/right
+        shine_ms_stereo(&mut config);
+        assert_eq!(config.mpeg.mode_ext, 0);
+        assert_eq!(config.mdct_freq[0][0], config.mdct_freq[1][0]);
+
+        // Identical channels: all the signal moves to mid, side is zero
+        config.ms_threshold = Some(0.8);
+        shine_ms_stereo(&mut config);
+        assert_eq!(config.mpeg.mode_ext, MODE_EXT_MS);
+        for gr in 0..2 {
+            assert!(config.mdct_freq[1][gr].iter().all(|&s| s == 0));
+            let mid = config.mdct_freq[0][gr][0];
+            assert_eq!(mid, (-288_000.0 * std::f64::consts::SQRT_2) as i32);
+        }
+
+        // Unrelated channels stay left/right
+        for k in 0..GRANULE_SIZE {
+            config.mdct_freq[0][0][k] = if k % 2 == 0 { 1000 } else { 0 };
+            config.mdct_freq[1][0][k] = if k % 2 == 0 { 0 } else { 1000 };
+            config.mdct_freq[0][1][k] = 0;
+            config.mdct_freq[1][1][k] = 0;
+        }
+        assert_eq!(shine_channel_similarity(&config), 0.0);
+        shine_ms_stereo(&mut config);
+        assert_eq!(config.mpeg.mode_ext, 0);
+        assert_eq!(config.mdct_freq[0][0][0], 1000);
+    }
 }
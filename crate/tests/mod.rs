@@ -3,10 +3,25 @@
 //! This module contains unit tests for individual components of the MP3 encoder.
 //! Tests are organized by module and functionality.
 
+pub mod aiff_tests;
+pub mod batch_tests;
 pub mod bitstream_tests;
+pub mod buffer_pool_tests;
+pub mod bytes_tests;
+pub mod config_matrix_tests;
 pub mod encoder_tests;
+pub mod error_tests;
+pub mod frame_tests;
 pub mod mdct_tests;
+pub mod parallel_tests;
+pub mod pcm_tests;
+pub mod pool_tests;
 pub mod quantization_tests;
+pub mod replaygain_tests;
+pub mod samplerate_tests;
 pub mod scfsi_tests;
+pub mod stream_tests;
 pub mod subband_tests;
 pub mod types_tests;
+pub mod wav_tests;
+pub mod xing_tests;
@@ -0,0 +1,72 @@
+//! Segment-parallel encoding tests
+//!
+//! Joined segments must form a stream of whole frames, one per input frame,
+//! and a single thread must give the serial output.
+
+use shine_rs::frame::iter_frames;
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3EncoderConfig};
+use shine_rs::parallel::{encode_pcm_parallel, encode_pcm_parallel_with_progress};
+use std::sync::Mutex;
+
+/// Stereo sine of `samples` samples per channel
+fn clip(samples: usize) -> Vec<i16> {
+    (0..samples * 2)
+        .map(|i| {
+            let t = (i / 2) as f64 / 44100.0;
+            ((2.0 * std::f64::consts::PI * 330.0 * t).sin() * 9000.0) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_thread_matches_serial() {
+        let pcm = clip(1152 * 7 + 300);
+        let config = Mp3EncoderConfig::new().bitrate(192);
+        assert_eq!(
+            encode_pcm_parallel(config.clone(), &pcm, 1).unwrap(),
+            encode_pcm_to_mp3(config, &pcm).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_segments_join_into_whole_frames() {
+        let pcm = clip(1152 * 25 + 700);
+        let config = Mp3EncoderConfig::new().trailing_silence_frames(2);
+        let serial = encode_pcm_to_mp3(config.clone(), &pcm).unwrap();
+
+        for threads in [2, 4, 0] {
+            let parallel = encode_pcm_parallel(config.clone(), &pcm, threads).unwrap();
+            // A broken segment boundary would stop the walk early; only the
+            // last frame of the stream may be cut short, as in serial output
+            let mut frames = iter_frames(&parallel);
+            let count = frames.by_ref().count();
+            assert_eq!(count, iter_frames(&serial).count());
+            assert!(count >= 26 + 2 - 1);
+            assert!(parallel.len() - frames.offset() < parallel.len() / count);
+        }
+    }
+
+    #[test]
+    fn test_progress_counts_every_worker() {
+        let pcm = clip(1152 * 40);
+        let reports = Mutex::new(Vec::new());
+        let mp3 = encode_pcm_parallel_with_progress(Mp3EncoderConfig::new(), &pcm, 4, |p| {
+            reports.lock().unwrap().push(p)
+        })
+        .unwrap();
+
+        let reports = reports.into_inner().unwrap();
+        // Each of the four workers reports after its tenth frame, then the total
+        assert_eq!(reports.len(), 5);
+        let last = reports.last().unwrap();
+        assert_eq!((last.frames_done, last.total_frames), (40, 40));
+        assert_eq!(last.bytes_out, mp3.len() as u64);
+        assert!(reports
+            .iter()
+            .all(|p| p.frames_done >= 10 && p.frames_done <= 40));
+    }
+}
@@ -0,0 +1,371 @@
+//! PCM sample format tests
+//!
+//! Checks the scaling of each `PcmSample` type and that every input format
+//! reaches the encoder with the same full-scale representation, and the
+//! level measurement of `analyze`.
+
+use shine_rs::encoder::{
+    shine_encode_buffer_interleaved, shine_flush, shine_initialise, ShineConfig, ShineMpeg,
+    ShineWave,
+};
+use shine_rs::error::InputDataError;
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use shine_rs::pcm::{
+    analyze, bytes_to_i16_be, bytes_to_i16_le, decode_alaw, decode_mulaw, downmix_to_mono,
+    extract_channel, i16_to_bytes_le, ClipDetector, PcmAnalyzer, PcmSample,
+};
+use std::time::Duration;
+
+/// Interleaved stereo test signal
+fn signal(frames: usize) -> Vec<i16> {
+    (0..frames * 1152 * 2)
+        .map(|i| ((i as f32 * 0.031).sin() * 20000.0) as i16)
+        .collect()
+}
+
+/// Encode through the generic high-level path
+fn encode_generic<S: PcmSample>(pcm: &[S]) -> Vec<u8> {
+    let mut encoder = Mp3Encoder::new(Mp3EncoderConfig::new()).unwrap();
+    let mut output = encoder.encode_samples(pcm).unwrap().concat();
+    output.extend(encoder.finish().unwrap());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i16_scaling() {
+        assert_eq!(0i16.to_internal(), 0);
+        assert_eq!(1i16.to_internal(), 1 << 16);
+        assert_eq!(i16::MAX.to_internal(), 0x7FFF_0000);
+        assert_eq!(i16::MIN.to_internal(), i32::MIN);
+    }
+
+    #[test]
+    fn test_i32_is_passed_through() {
+        for value in [i32::MIN, -1, 0, 1, 0x1234_5678, i32::MAX] {
+            assert_eq!(value.to_internal(), value);
+        }
+    }
+
+    #[test]
+    fn test_f32_full_scale_maps_to_i16_full_scale() {
+        assert_eq!(1.0f32.to_internal(), i16::MAX.to_internal());
+        assert_eq!((-1.0f32).to_internal(), i16::MIN.to_internal());
+        assert_eq!(0.0f32.to_internal(), 0);
+        assert_eq!(0.5f32.to_internal(), 1 << 30);
+
+        // Out of range values clamp, NaN is silence
+        assert_eq!(4.0f32.to_internal(), i16::MAX.to_internal());
+        assert_eq!((-4.0f32).to_internal(), i16::MIN.to_internal());
+        assert_eq!(f32::NAN.to_internal(), 0);
+    }
+
+    #[test]
+    fn test_f32_matches_i16_scale() {
+        for sample in [i16::MIN, -12345, -1, 0, 1, 777, i16::MAX] {
+            let float = sample as f32 / 32768.0;
+            assert_eq!(
+                float.to_internal(),
+                sample.to_internal(),
+                "sample {}",
+                sample
+            );
+        }
+    }
+
+    #[test]
+    fn test_generic_i16_matches_legacy_path() {
+        let pcm = signal(5);
+
+        let mut encoder = Mp3Encoder::new(Mp3EncoderConfig::new()).unwrap();
+        let mut legacy = encoder.encode_interleaved(&pcm).unwrap().concat();
+        legacy.extend(encoder.finish().unwrap());
+
+        assert_eq!(encode_generic(&pcm), legacy);
+    }
+
+    #[test]
+    fn test_generic_i16_matches_low_level_api() {
+        let pcm = signal(4);
+
+        let config = ShineConfig {
+            wave: ShineWave {
+                channels: 2,
+                samplerate: 44100,
+            },
+            mpeg: ShineMpeg {
+                mode: 0,
+                bitr: 128,
+                emph: 0,
+                copyright: 0,
+                original: 1,
+            },
+        };
+        let mut shine = shine_initialise(&config).unwrap();
+        let mut low_level = Vec::new();
+        for frame in pcm.chunks(2304) {
+            let (data, written) =
+                unsafe { shine_encode_buffer_interleaved(&mut shine, frame.as_ptr()) }.unwrap();
+            low_level.extend_from_slice(&data[..written]);
+        }
+        let (data, written) = shine_flush(&mut shine);
+        low_level.extend_from_slice(&data[..written]);
+
+        assert_eq!(encode_generic(&pcm), low_level);
+    }
+
+    #[test]
+    fn test_i32_and_f32_inputs_match_i16() {
+        let pcm = signal(3);
+        let expected = encode_generic(&pcm);
+
+        let wide: Vec<i32> = pcm.iter().map(|&s| (s as i32) << 16).collect();
+        assert_eq!(encode_generic(&wide), expected);
+
+        let float: Vec<f32> = pcm.iter().map(|&s| s as f32 / 32768.0).collect();
+        assert_eq!(encode_generic(&float), expected);
+    }
+
+    #[test]
+    fn test_i32_low_bits_reach_the_encoder() {
+        // Quiet content below 16-bit resolution is lost as i16 but not as i32
+        let wide: Vec<i32> = (0..1152 * 2 * 3)
+            .map(|i| ((i as f64 * 0.05).sin() * 30000.0) as i32)
+            .collect();
+        let truncated: Vec<i32> = wide.iter().map(|&s| s & !0xFFFF).collect();
+        assert_ne!(encode_generic(&wide), encode_generic(&truncated));
+    }
+
+    #[test]
+    fn test_downmix_to_mono_averages_pairs() {
+        let stereo = [
+            1000i16,
+            3000,
+            -4000,
+            2000,
+            i16::MAX,
+            i16::MAX,
+            i16::MIN,
+            i16::MIN,
+            7,
+        ];
+        assert_eq!(
+            downmix_to_mono(&stereo),
+            vec![2000, -1000, i16::MAX, i16::MIN]
+        );
+    }
+
+    #[test]
+    fn test_extract_channel_takes_one_lane() {
+        let stereo = [1i16, -1, 2, -2, 3, -3, 4];
+        assert_eq!(extract_channel(&stereo, 2, 0), vec![1, 2, 3]);
+        assert_eq!(extract_channel(&stereo, 2, 1), vec![-1, -2, -3]);
+        assert_eq!(extract_channel(&stereo, 1, 0), stereo.to_vec());
+        assert_eq!(
+            extract_channel(&[10, 20, 30, 11, 21, 31], 3, 2),
+            vec![30, 31]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_extract_channel_rejects_missing_channel() {
+        extract_channel(&[1, 2], 2, 2);
+    }
+
+    #[test]
+    fn test_is_clipped_at_full_scale_only() {
+        assert!(i16::MAX.is_clipped());
+        assert!(i16::MIN.is_clipped());
+        assert!(!(i16::MAX - 1).is_clipped());
+
+        assert!(((i16::MAX as i32) << 16).is_clipped());
+        assert!(i32::MAX.is_clipped());
+        assert!(!((i16::MAX as i32 - 1) << 16).is_clipped());
+
+        assert!(1.0f32.is_clipped());
+        assert!((-1.0f32).is_clipped());
+        assert!(1.5f32.is_clipped());
+        assert!(!0.99f32.is_clipped());
+        assert!(!f32::NAN.is_clipped());
+    }
+
+    #[test]
+    fn test_clip_detector_tracks_runs_per_channel() {
+        let mut detector = ClipDetector::new(2);
+        // Left clips for 3 samples split across two calls, right clips once
+        detector.update_interleaved(&[i16::MAX, 0, i16::MAX, i16::MIN]);
+        detector.update_interleaved(&[i16::MIN, 0, 0, 0]);
+
+        assert_eq!(detector.samples(), 8);
+        assert_eq!(detector.clipped_samples(), 4);
+        assert_eq!(detector.max_consecutive_clipped(), 3);
+        assert_eq!(detector.clipped_ratio(), 0.5);
+    }
+
+    #[test]
+    fn test_bytes_to_i16_both_endiannesses() {
+        let bytes = [0x34, 0x12, 0xFF, 0x7F, 0x00, 0x80, 0xFE, 0xFF];
+        let mut samples = vec![7];
+        bytes_to_i16_le(&bytes, &mut samples).unwrap();
+        assert_eq!(samples, [7, 0x1234, i16::MAX, i16::MIN, -2]);
+
+        samples.clear();
+        bytes_to_i16_be(&bytes, &mut samples).unwrap();
+        assert_eq!(samples, [0x3412, -129, 0x0080, -257]);
+
+        let mut out = Vec::new();
+        i16_to_bytes_le(&[0x1234, i16::MAX, i16::MIN, -2], &mut out);
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn test_bytes_to_i16_rejects_odd_length() {
+        let mut samples = vec![1, 2];
+        for convert in [bytes_to_i16_le, bytes_to_i16_be] {
+            assert!(matches!(
+                convert(&[1, 2, 3], &mut samples),
+                Err(InputDataError::OddByteCount(3))
+            ));
+            assert_eq!(samples, [1, 2]);
+        }
+        bytes_to_i16_le(&[], &mut samples).unwrap();
+        assert_eq!(samples, [1, 2]);
+    }
+
+    #[test]
+    fn test_g711_expansion_reference_values() {
+        // Codewords and linear values from the ITU-T G.711 tables
+        let mulaw = [
+            (0x00, -32124),
+            (0x0F, -16764),
+            (0x7E, -8),
+            (0x7F, 0),
+            (0x80, 32124),
+            (0xFE, 8),
+            (0xFF, 0),
+        ];
+        let (codes, values): (Vec<u8>, Vec<i16>) = mulaw.into_iter().unzip();
+        assert_eq!(decode_mulaw(&codes), values);
+
+        let alaw = [
+            (0x00, -5504),
+            (0x2A, -32256),
+            (0x55, -8),
+            (0x80, 5504),
+            (0xAA, 32256),
+            (0xD5, 8),
+        ];
+        let (codes, values): (Vec<u8>, Vec<i16>) = alaw.into_iter().unzip();
+        assert_eq!(decode_alaw(&codes), values);
+
+        // Each law maps its 256 codewords onto distinct, sign-symmetric levels
+        // (µ-law has both a positive and a negative zero)
+        let all: Vec<u8> = (0..=255).collect();
+        for (decoded, distinct) in [(decode_mulaw(&all), 255), (decode_alaw(&all), 256)] {
+            let mut levels = decoded.clone();
+            levels.sort_unstable();
+            levels.dedup();
+            assert_eq!(levels.len(), distinct);
+            assert!(levels
+                .iter()
+                .all(|level| levels.binary_search(&-level).is_ok()));
+        }
+    }
+
+    #[test]
+    fn test_analyze_square_and_sine_levels() {
+        // Full-scale stereo square, one second at 44.1 kHz
+        let square: Vec<i16> = (0..44100 * 2)
+            .map(|i| {
+                if (i / 2 / 50) % 2 == 0 {
+                    i16::MIN
+                } else {
+                    i16::MAX
+                }
+            })
+            .collect();
+        let stats = analyze(&square, 2, 44100);
+        assert_eq!(stats.peak_dbfs, 0.0);
+        assert!(stats.rms_dbfs.abs() < 0.001, "RMS {}", stats.rms_dbfs);
+        assert!(!stats.has_dc_offset());
+        assert_eq!(stats.duration, Duration::from_secs(1));
+
+        // -6 dBFS mono sine: the RMS of a sine is 3 dB below its peak
+        let amplitude = 32768.0 * 10f64.powf(-6.0 / 20.0);
+        let sine: Vec<i16> = (0..48000)
+            .map(|i| {
+                ((i as f64 * 2.0 * std::f64::consts::PI * 1000.0 / 48000.0).sin() * amplitude)
+                    .round() as i16
+            })
+            .collect();
+        let stats = analyze(&sine, 1, 48000);
+        assert!(
+            (stats.peak_dbfs + 6.0).abs() < 0.01,
+            "peak {}",
+            stats.peak_dbfs
+        );
+        assert!(
+            (stats.rms_dbfs + 9.01).abs() < 0.01,
+            "RMS {}",
+            stats.rms_dbfs
+        );
+        assert!(stats.dc_offset.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_analyzer_chunks_and_dc_offset() {
+        // Left channel sits 1000 above zero, right is a plain tone
+        let pcm: Vec<i16> = (0..4411)
+            .map(|i| {
+                let tone = ((i / 2) as f64 * 0.05).sin() * 3000.0;
+                if i % 2 == 0 {
+                    tone as i16 + 1000
+                } else {
+                    tone as i16
+                }
+            })
+            .collect();
+        let whole = analyze(&pcm, 2, 44100);
+        assert!(whole.has_dc_offset());
+        assert!((whole.dc_offset - 1000.0 / 32768.0).abs() < 0.002);
+
+        // Odd-sized chunks give the same result
+        let mut analyzer = PcmAnalyzer::new(2, 44100);
+        for chunk in pcm.chunks(333) {
+            analyzer.feed(chunk);
+        }
+        assert_eq!(analyzer.finish(), whole);
+
+        let silence = analyze(&[0; 100], 1, 8000);
+        assert_eq!(silence.peak_dbfs, f64::NEG_INFINITY);
+        assert_eq!(silence.rms_dbfs, f64::NEG_INFINITY);
+        assert_eq!(analyze(&[], 2, 8000).duration, Duration::ZERO);
+    }
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_i16_bytes_round_trip(samples in proptest::collection::vec(any::<i16>(), 0..500)) {
+            let mut bytes = Vec::new();
+            i16_to_bytes_le(&samples, &mut bytes);
+            prop_assert_eq!(bytes.len(), samples.len() * 2);
+
+            let mut decoded = Vec::new();
+            bytes_to_i16_le(&bytes, &mut decoded).unwrap();
+            prop_assert_eq!(&decoded, &samples);
+
+            // Swapping each byte pair turns the stream big-endian
+            for pair in bytes.chunks_exact_mut(2) {
+                pair.swap(0, 1);
+            }
+            decoded.clear();
+            bytes_to_i16_be(&bytes, &mut decoded).unwrap();
+            prop_assert_eq!(&decoded, &samples);
+        }
+    }
+}
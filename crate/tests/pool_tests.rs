@@ -0,0 +1,139 @@
+//! Encoder pool tests
+//!
+//! Pooled encoders must behave exactly like fresh ones, whatever they
+//! encoded before, including when the pool is shared by several threads.
+
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Emphasis, Mp3EncoderConfig};
+use shine_rs::pool::{Mp3EncoderPool, PoolMetrics};
+use std::thread;
+
+fn config() -> Mp3EncoderConfig {
+    Mp3EncoderConfig::new()
+        .sample_rate(44100)
+        .bitrate(128)
+        .channels(2)
+}
+
+/// Stereo clip of `frames` frames plus a partial one
+fn clip(frames: usize, frequency: f64) -> Vec<i16> {
+    (0..(frames * 1152 + 300) * 2)
+        .map(|i| {
+            let t = (i / 2) as f64 / 44100.0;
+            ((2.0 * std::f64::consts::PI * frequency * t).sin() * 12000.0) as i16
+        })
+        .collect()
+}
+
+/// Encode a clip with a pooled encoder
+fn encode_pooled(pool: &Mp3EncoderPool, pcm: &[i16]) -> Vec<u8> {
+    let mut encoder = pool.get().unwrap();
+    let mut output = encoder.encode_interleaved(pcm).unwrap().concat();
+    output.extend(encoder.finish().unwrap());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled_output_matches_fresh_encoder() {
+        let pool = Mp3EncoderPool::new(config(), 2).unwrap();
+        let clips = [clip(20, 440.0), clip(13, 1250.0)];
+        let expected: Vec<Vec<u8>> = clips
+            .iter()
+            .map(|pcm| encode_pcm_to_mp3(config(), pcm).unwrap())
+            .collect();
+
+        for round in 0..6 {
+            let index = round % 2;
+            assert_eq!(
+                encode_pooled(&pool, &clips[index]),
+                expected[index],
+                "round {}",
+                round
+            );
+        }
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.created, 1);
+        assert_eq!(metrics.misses, 1);
+        assert_eq!(metrics.hits, 5);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn test_unfinished_encoder_is_reset() {
+        let pool = Mp3EncoderPool::new(config(), 1).unwrap();
+        let pcm = clip(8, 300.0);
+        {
+            // Abandoned mid-stream with buffered samples and changed emphasis
+            let mut encoder = pool.get().unwrap();
+            encoder.encode_interleaved(&clip(5, 900.0)).unwrap();
+            encoder.set_emphasis(Emphasis::Emphasis50_15);
+        }
+
+        let encoder = pool.get().unwrap();
+        assert_eq!(encoder.buffered_samples(), 0);
+        assert_eq!(encoder.stats().frames_encoded, 0);
+        assert_eq!(encoder.config().emphasis, Emphasis::None);
+        drop(encoder);
+        assert_eq!(
+            encode_pooled(&pool, &pcm),
+            encode_pcm_to_mp3(config(), &pcm).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pool_keeps_at_most_max_idle() {
+        let pool = Mp3EncoderPool::new(config(), 1).unwrap();
+        let first = pool.get().unwrap();
+        let second = pool.get().unwrap();
+        drop(first);
+        drop(second);
+
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                hits: 0,
+                misses: 2,
+                created: 2,
+                discarded: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        assert!(Mp3EncoderPool::new(config().bitrate(7), 4).is_err());
+    }
+
+    #[test]
+    fn test_pool_shared_across_threads() {
+        let pool = Mp3EncoderPool::new(config(), 4).unwrap();
+        let clips = [clip(6, 440.0), clip(4, 660.0), clip(9, 880.0)];
+        let expected: Vec<Vec<u8>> = clips
+            .iter()
+            .map(|pcm| encode_pcm_to_mp3(config(), pcm).unwrap())
+            .collect();
+
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let (pool, clips, expected) = (&pool, &clips, &expected);
+                scope.spawn(move || {
+                    for round in 0..10 {
+                        let index = (worker + round) % clips.len();
+                        assert_eq!(encode_pooled(pool, &clips[index]), expected[index]);
+                    }
+                });
+            }
+        });
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.hits + metrics.misses, 40);
+        assert_eq!(metrics.created, metrics.misses);
+        assert!(metrics.created <= 4 + metrics.discarded);
+        assert!(pool.idle() <= 4);
+    }
+}
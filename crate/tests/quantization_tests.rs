@@ -3,8 +3,90 @@
 //! These tests validate quantization parameters, global gain calculation,
 //! and big_values constraints against the Shine reference implementation.
 
+use shine_rs::bitstream::format_bitstream;
+use shine_rs::encoder::{shine_initialise, ShineConfig, ShineMpeg, ShineWave, NONE};
+use shine_rs::huffman::is_bigv_table_available;
+use shine_rs::mp3_encoder::Effort;
+use shine_rs::quantization::{
+    bigv_tab_select_with_effort, calc_runlen, count_bit, effective_scalefac, encode_granule, labs,
+    mulsr, part2_length, quantize_with_l3loop, run_length, shine_amplify_scalefac_bands,
+    shine_dequantize, shine_iteration_loop, shine_outer_loop, shine_preemphasis,
+    shine_select_scalefac_compress, IX_MAX_VALUE, MAX_QUANTIZER_STEP, QUANTIZE_OVERFLOW,
+};
+use shine_rs::tables::{SHINE_PRETAB, SHINE_SCALE_FACT_BAND_INDEX};
 use shine_rs::types::*;
 
+/// Scalefactors that amplify every upper band by at least the pretab value
+const UPPER_BAND_SCALEFACS: [i32; 22] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 1, 1, 3, 2, 4, 3, 3, 2, 0,
+];
+
+/// Create a mono MPEG-I encoder state for driving the quantization helpers
+fn mono_config() -> Box<ShineGlobalConfig> {
+    mono_config_at(44100, 128)
+}
+
+/// Create a mono encoder state for the given sample rate and bitrate
+fn mono_config_at(samplerate: i32, bitr: i32) -> Box<ShineGlobalConfig> {
+    shine_initialise(&mono_shine_config(samplerate, bitr)).expect("valid configuration")
+}
+
+/// Public mono configuration for the given sample rate and bitrate
+fn mono_shine_config(samplerate: i32, bitr: i32) -> ShineConfig {
+    ShineConfig {
+        wave: ShineWave {
+            channels: 1,
+            samplerate,
+        },
+        mpeg: ShineMpeg {
+            mode: 3,
+            bitr,
+            emph: NONE,
+            copyright: 0,
+            original: 1,
+        },
+    }
+}
+
+/// Decaying spectrum with alternating signs, loudest at the low end
+fn decaying_spectrum() -> [i32; GRANULE_SIZE] {
+    std::array::from_fn(|i| {
+        let magnitude = (400_000_000.0 / (1.0 + i as f64 / 8.0)) as i32;
+        if i % 3 == 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    })
+}
+
+/// Fill granule 0 of channel 0 with full-scale coefficients of alternating sign
+/// and prepare the loop state the way the iteration loop does
+fn load_full_scale_granule(config: &mut ShineGlobalConfig) {
+    for (i, value) in config.mdct_freq[0][0].iter_mut().enumerate() {
+        *value = if i % 2 == 0 { i32::MAX } else { -i32::MAX };
+    }
+    config.l3loop.xr = config.mdct_freq[0][0].as_mut_ptr();
+    config.l3loop.xrmax = 0;
+    for i in 0..GRANULE_SIZE {
+        let xr = config.mdct_freq[0][0][i];
+        config.l3loop.xrsq[i] = mulsr(xr, xr);
+        config.l3loop.xrabs[i] = labs(xr);
+        config.l3loop.xrmax = config.l3loop.xrmax.max(config.l3loop.xrabs[i]);
+    }
+}
+
+/// Run `f` on a worker thread and fail the test if it does not finish in time
+fn with_timeout<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(f());
+    });
+    receiver
+        .recv_timeout(std::time::Duration::from_secs(60))
+        .expect("quantization did not converge in time")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -47,12 +129,13 @@ mod tests {
         // Test MP3 standard limits that our implementation must respect
 
         // Test that our granule info structure can hold valid MP3 values
-        let mut gr_info = GrInfo::default();
-
         // Test setting maximum valid values
-        gr_info.part2_3_length = 4095; // 12-bit field maximum
-        gr_info.big_values = 288; // Granule size / 2 maximum
-        gr_info.global_gain = 255; // 8-bit field maximum
+        let gr_info = GrInfo {
+            part2_3_length: 4095, // 12-bit field maximum
+            big_values: 288,      // Granule size / 2 maximum
+            global_gain: 255,     // 8-bit field maximum
+            ..Default::default()
+        };
 
         assert!(
             gr_info.part2_3_length <= 4095,
@@ -67,4 +150,449 @@ mod tests {
             "Global gain should fit in 8 bits"
         );
     }
+
+    #[test]
+    fn test_pretab_table() {
+        assert_eq!(SHINE_PRETAB.len(), 21);
+        assert!(SHINE_PRETAB[..11].iter().all(|&p| p == 0));
+        assert_eq!(&SHINE_PRETAB[11..], &[1, 1, 1, 1, 2, 2, 3, 3, 3, 2]);
+    }
+
+    #[test]
+    fn test_preemphasis_requires_all_upper_bands() {
+        let mut gi = GrInfo::default();
+        let mut scalefac = UPPER_BAND_SCALEFACS;
+        scalefac[19] = 2; // one band below its pretab value
+
+        assert!(!shine_preemphasis(&mut scalefac, &mut gi));
+        assert_eq!(gi.preflag, 0);
+        assert_eq!(scalefac[19], 2, "scalefactors must be untouched");
+    }
+
+    #[test]
+    fn test_preemphasis_keeps_effective_scalefactors() {
+        let mut gi = GrInfo::default();
+        let mut scalefac = UPPER_BAND_SCALEFACS;
+
+        assert!(shine_preemphasis(&mut scalefac, &mut gi));
+        assert_eq!(gi.preflag, 1);
+
+        for (sfb, &expected) in UPPER_BAND_SCALEFACS[..21].iter().enumerate() {
+            assert_eq!(
+                effective_scalefac(&scalefac, &gi, sfb),
+                expected,
+                "band {} amplification changed",
+                sfb
+            );
+        }
+        assert_eq!(&scalefac[11..21], &[1, 1, 0, 0, 1, 0, 1, 0, 0, 0]);
+
+        // Applying it twice must not subtract the pretab again
+        assert!(!shine_preemphasis(&mut scalefac, &mut gi));
+        assert_eq!(&scalefac[11..21], &[1, 1, 0, 0, 1, 0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_preemphasis_reduces_scalefactor_bits() {
+        let mut config = mono_config();
+        config.scalefactor.l[0][0] = UPPER_BAND_SCALEFACS;
+
+        let compress = shine_select_scalefac_compress(0, 0, &config).unwrap();
+        config.side_info.gr[0].ch[0].tt.scalefac_compress = compress;
+        let bits_without = part2_length(0, 0, &mut config);
+        assert_eq!(bits_without, 30, "max scalefactor 4 needs slen2 = 3");
+
+        let (scalefac, gi) = (
+            &mut config.scalefactor.l[0][0],
+            &mut config.side_info.gr[0].ch[0].tt,
+        );
+        assert!(shine_preemphasis(scalefac, gi));
+
+        let compress = shine_select_scalefac_compress(0, 0, &config).unwrap();
+        config.side_info.gr[0].ch[0].tt.scalefac_compress = compress;
+        let bits_with = part2_length(0, 0, &mut config);
+        assert_eq!(bits_with, 10, "explicit scalefactors fit in slen2 = 1");
+        assert!(bits_with < bits_without);
+    }
+
+    #[test]
+    fn test_scfsi_shared_bands_reduce_part2_length() {
+        let mut config = mono_config();
+        // slen1 = 4, slen2 = 3: 6 * 4 + 5 * 4 + 5 * 3 + 5 * 3 bits
+        config.side_info.gr[0].ch[0].tt.scalefac_compress = 15;
+        config.side_info.gr[1].ch[0].tt.scalefac_compress = 15;
+        config.side_info.scfsi[0] = [1, 1, 1, 1];
+
+        // Granule 0 always sends every band
+        assert_eq!(part2_length(0, 0, &mut config), 74);
+        // Granule 1 reuses all four bands from granule 0
+        assert_eq!(part2_length(1, 0, &mut config), 0);
+
+        config.side_info.scfsi[0] = [1, 0, 1, 0];
+        assert_eq!(part2_length(1, 0, &mut config), 74 - 24 - 15);
+        config.side_info.scfsi[0] = [0; 4];
+        assert_eq!(part2_length(1, 0, &mut config), 74);
+    }
+
+    #[test]
+    fn test_encode_granule_fits_budget() {
+        let config = mono_shine_config(44100, 128);
+        let (gi, ix) = encode_granule(&decaying_spectrum(), &config, 1500).unwrap();
+
+        assert!(gi.part2_3_length > 0 && gi.part2_3_length <= 1500);
+        assert!(gi.big_values > 0 && gi.big_values <= 288);
+        assert_eq!(gi.global_gain as i32, gi.quantizer_step_size + 210);
+        // Only the big_values and count1 regions may hold non-zero values
+        let coded = (gi.big_values * 2 + gi.count1 * 4) as usize;
+        assert!(ix[coded..].iter().all(|&value| value == 0));
+        assert!(ix[..coded].iter().all(|&value| value.abs() <= IX_MAX_VALUE));
+
+        // A tighter budget needs a coarser step size
+        let (tight, _) = encode_granule(&decaying_spectrum(), &config, 400).unwrap();
+        assert!(tight.part2_3_length <= 400);
+        assert!(tight.quantizer_step_size > gi.quantizer_step_size);
+    }
+
+    #[test]
+    fn test_encode_granule_is_reproducible() {
+        let config = mono_shine_config(44100, 128);
+        let (first, first_ix) = encode_granule(&decaying_spectrum(), &config, 1000).unwrap();
+        let (second, second_ix) = encode_granule(&decaying_spectrum(), &config, 1000).unwrap();
+
+        assert_eq!(first_ix, second_ix);
+        assert_eq!(first.part2_3_length, second.part2_3_length);
+        assert_eq!(first.big_values, second.big_values);
+        assert_eq!(first.table_select, second.table_select);
+        assert_eq!(first.global_gain, second.global_gain);
+    }
+
+    #[test]
+    fn test_fast_effort_tables_cost_more_bits() {
+        let config = mono_shine_config(44100, 128);
+        let bigv_bits = |ix: &[i32], gi: &GrInfo| {
+            count_bit(ix, 0, gi.address1, gi.table_select[0])
+                + count_bit(ix, gi.address1, gi.address2, gi.table_select[1])
+                + count_bit(ix, gi.address2, gi.address3, gi.table_select[2])
+        };
+
+        let mut more = 0;
+        for budget in [300, 600, 1000, 1500, 2500] {
+            let (gi, ix) = encode_granule(&decaying_spectrum(), &config, budget).unwrap();
+            let mut fast = gi.clone();
+            bigv_tab_select_with_effort(&ix, &mut fast, Effort::Fast);
+
+            // The same quantization costs at least as much with the first fitting tables
+            let (standard_bits, fast_bits) = (bigv_bits(&ix, &gi), bigv_bits(&ix, &fast));
+            assert!(fast_bits >= standard_bits);
+            if fast_bits > standard_bits {
+                more += 1;
+            }
+        }
+        assert!(more > 0);
+    }
+
+    #[test]
+    fn test_table_selection_avoids_missing_tables() {
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |range: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) % range
+        };
+
+        for _ in 0..500 {
+            // Region values from silent to past every table's linbits range
+            let peak = [0, 1, 2, 3, 7, 15, 40, 500, IX_MAX_VALUE as u32][next(9) as usize];
+            let mut ix = [0i32; GRANULE_SIZE];
+            for value in ix
+                .iter_mut()
+                .take(next(GRANULE_SIZE as u32 / 2) as usize * 2)
+            {
+                if next(3) > 0 {
+                    *value = next(peak + 1) as i32;
+                }
+            }
+
+            let mut gi = GrInfo::default();
+            gi.big_values = next(GRANULE_SIZE as u32 / 2 + 1);
+            gi.address2 = next((gi.big_values << 1) + 1);
+            gi.address1 = next(gi.address2 + 1);
+            let regions = [
+                (0, gi.address1),
+                (gi.address1, gi.address2),
+                (gi.address2, gi.big_values << 1),
+            ];
+
+            for effort in [Effort::Fast, Effort::Standard, Effort::Thorough] {
+                gi.table_select = [0; 3];
+                bigv_tab_select_with_effort(&ix, &mut gi, effort);
+                for (&table, &(start, end)) in gi.table_select.iter().zip(&regions) {
+                    assert!(is_bigv_table_available(table), "table {}", table);
+                    // Table 0 only for regions that code nothing
+                    let region = &ix[start as usize..end as usize];
+                    assert_eq!(table == 0, region.iter().all(|&v| v == 0));
+                }
+            }
+        }
+    }
+
+    /// shine's calc_runlen (l3loop.c) as written in C, indexing from the end
+    fn shine_runlen(ix: &[i32]) -> (u32, u32) {
+        let mut i = GRANULE_SIZE;
+        while i > 1 && ix[i - 1] == 0 && ix[i - 2] == 0 {
+            i -= 2;
+        }
+        let mut count1 = 0;
+        while i > 3 && ix[i - 4..i].iter().all(|&v| v <= 1) {
+            count1 += 1;
+            i -= 4;
+        }
+        ((i >> 1) as u32, count1)
+    }
+
+    #[test]
+    fn test_calc_runlen_matches_shine() {
+        let mut seed = 0x1b87_3593_u32;
+        let mut next = |range: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) % range
+        };
+
+        let mut cases = vec![[0i32; GRANULE_SIZE], [1i32; GRANULE_SIZE]];
+        let mut odd_tail = [0i32; GRANULE_SIZE];
+        odd_tail[GRANULE_SIZE - 1] = 1;
+        cases.push(odd_tail);
+        for _ in 0..300 {
+            // Large values first, then ones, then zeros, with stray values
+            // breaking up the regions
+            let (big_end, ones_end) = (next(GRANULE_SIZE as u32), next(GRANULE_SIZE as u32));
+            let mut ix = [0i32; GRANULE_SIZE];
+            for (i, value) in ix.iter_mut().enumerate() {
+                *value = match i as u32 {
+                    i if i < big_end.min(ones_end) => next(20) as i32,
+                    i if i < ones_end => next(2) as i32,
+                    _ if next(50) == 0 => next(3) as i32,
+                    _ => 0,
+                };
+            }
+            cases.push(ix);
+        }
+
+        for mut ix in cases {
+            let mut gi = GrInfo::default();
+            calc_runlen(&mut ix, &mut gi);
+            assert_eq!((gi.big_values, gi.count1), shine_runlen(&ix));
+            assert!(gi.big_values * 2 + gi.count1 * 4 <= GRANULE_SIZE as u32);
+        }
+    }
+
+    #[test]
+    fn test_run_length_regions() {
+        // A final quadruple with a 2 cannot be a count1 quadruple, however
+        // small its sum: the whole spectrum is big values
+        let mut ix = [0i32; GRANULE_SIZE];
+        ix[GRANULE_SIZE - 4..].copy_from_slice(&[0, 0, 2, 1]);
+        assert_eq!(run_length(&ix), (0, 0, 288));
+        assert_eq!(shine_runlen(&ix), (288, 0));
+
+        // Trailing [1, 0, 1, 1] quadruples after a big value are count1, as
+        // are the zero quadruples between them and the big value
+        let mut ix = [0i32; GRANULE_SIZE];
+        ix[0] = 5;
+        ix[3] = 3;
+        ix[16..24].copy_from_slice(&[1, 0, 1, 1, 1, 0, 1, 1]);
+        assert_eq!(run_length(&ix), (276, 5, 2));
+        assert_eq!(shine_runlen(&ix), (2, 5));
+
+        // A 3 just before the ones ends the count1 region there
+        ix[15] = 3;
+        assert_eq!(run_length(&ix), (276, 2, 8));
+        assert_eq!(shine_runlen(&ix), (8, 2));
+
+        assert_eq!(run_length(&[0; GRANULE_SIZE]), (288, 0, 0));
+        assert_eq!(run_length(&[1; GRANULE_SIZE]), (0, 144, 0));
+
+        // calc_runlen stores the same split
+        let mut gi = GrInfo::default();
+        calc_runlen(&mut ix, &mut gi);
+        assert_eq!((gi.count1, gi.big_values), (2, 8));
+    }
+
+    #[test]
+    fn test_quantize_extreme_coefficients() {
+        // i32::MIN has no positive counterpart; the magnitudes saturate
+        assert_eq!(labs(i32::MIN), i32::MAX);
+        assert_eq!(labs(-5), 5);
+
+        let config = mono_shine_config(44100, 128);
+        let mut alternating = [i32::MAX; GRANULE_SIZE];
+        alternating
+            .iter_mut()
+            .step_by(2)
+            .for_each(|v| *v = i32::MIN);
+        let mut low_band = [0; GRANULE_SIZE];
+        low_band[..32].fill(i32::MIN);
+        for coeffs in [[i32::MIN; GRANULE_SIZE], alternating, low_band] {
+            let (gi, ix) = encode_granule(&coeffs, &config, 4000).unwrap();
+            assert!(gi.part2_3_length <= 4000);
+            assert!(ix.iter().all(|&value| value.abs() <= IX_MAX_VALUE));
+            assert!(ix.iter().any(|&value| value != 0));
+        }
+
+        // Through the whole frame: both loops and the bitstream
+        let mut state = shine_initialise(&mono_shine_config(44100, 128)).unwrap();
+        state.mdct_freq[0][0] = [i32::MIN; GRANULE_SIZE];
+        state.mdct_freq[0][1] = alternating;
+        state.mpeg.bits_per_frame = 8 * 417;
+        state.mean_bits = (state.mpeg.bits_per_frame - state.sideinfo_len) / 2;
+        shine_iteration_loop(&mut state);
+        format_bitstream(&mut state).unwrap();
+        assert!(state.bs.get_bits_count() <= state.mpeg.bits_per_frame);
+    }
+
+    #[test]
+    fn test_encode_granule_silence() {
+        let config = mono_shine_config(44100, 128);
+        let (gi, ix) = encode_granule(&[0; GRANULE_SIZE], &config, 1000).unwrap();
+        assert_eq!(gi.part2_3_length, 0);
+        assert_eq!(gi.big_values, 0);
+        assert!(ix.iter().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn test_zero_scalefactors_keep_compress_zero() {
+        let config = mono_config();
+        assert_eq!(shine_select_scalefac_compress(0, 0, &config), None);
+    }
+
+    #[test]
+    fn test_preemphasis_reconstruction_levels() {
+        let mut config = mono_config();
+        for (i, value) in config.mdct_freq[0][0].iter_mut().enumerate() {
+            *value = 0x0100_0000 + (i as i32) * 0x1000;
+        }
+        let original: Vec<i32> = config.mdct_freq[0][0].to_vec();
+
+        // Explicit scalefactors without preemphasis
+        config.scalefactor.l[0][0] = UPPER_BAND_SCALEFACS;
+        assert!(shine_amplify_scalefac_bands(0, 0, &mut config));
+        let amplified_plain = config.l3loop.xr_amp.to_vec();
+
+        // Same amplification expressed through preflag
+        let (scalefac, gi) = (
+            &mut config.scalefactor.l[0][0],
+            &mut config.side_info.gr[0].ch[0].tt,
+        );
+        assert!(shine_preemphasis(scalefac, gi));
+        assert!(shine_amplify_scalefac_bands(0, 0, &mut config));
+        assert_eq!(config.l3loop.xr_amp.to_vec(), amplified_plain);
+
+        let stepsize = -64;
+        let mut ix = vec![0i32; GRANULE_SIZE];
+        let max = quantize_with_l3loop(&mut ix, stepsize, &mut config.l3loop);
+        assert!(max > 0 && max < 8192);
+
+        let gi = &config.side_info.gr[0].ch[0].tt;
+        let scalefac = &config.scalefactor.l[0][0];
+        let bands = &SHINE_SCALE_FACT_BAND_INDEX[0];
+        for sfb in 0..21 {
+            let sf = effective_scalefac(scalefac, gi, sfb);
+            for i in bands[sfb] as usize..bands[sfb + 1] as usize {
+                let expected = original[i] as f64 / 2147483648.0;
+                let decoded = shine_dequantize(ix[i], (stepsize + 210) as u32, sf, 0);
+                let error = (decoded - expected).abs() / expected;
+                assert!(
+                    error < 0.02,
+                    "line {} (sfb {}): decoded {} expected {}",
+                    i,
+                    sfb,
+                    decoded,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_scalefactors_leave_spectrum_alone() {
+        let mut config = mono_config();
+        config.mdct_freq[0][0][10] = 12345;
+        assert!(!shine_amplify_scalefac_bands(0, 0, &mut config));
+        assert!(config.l3loop.xr.is_null());
+    }
+
+    #[test]
+    fn test_quantize_reports_overflow_sentinel() {
+        let mut config = mono_config();
+        load_full_scale_granule(&mut config);
+        let mut ix = vec![0i32; GRANULE_SIZE];
+
+        // Far too fine a step for full-scale input
+        assert_eq!(
+            quantize_with_l3loop(&mut ix, -120, &mut config.l3loop),
+            QUANTIZE_OVERFLOW
+        );
+        const { assert!(QUANTIZE_OVERFLOW > IX_MAX_VALUE) };
+
+        // Coarse enough steps land inside the table range
+        assert!(quantize_with_l3loop(&mut ix, -40, &mut config.l3loop) <= IX_MAX_VALUE);
+    }
+
+    #[test]
+    fn test_quantize_keeps_coarsening_above_step_zero() {
+        let mut config = mono_config();
+        load_full_scale_granule(&mut config);
+        let mut ix = vec![0i32; GRANULE_SIZE];
+
+        let at_zero = quantize_with_l3loop(&mut ix, 0, &mut config.l3loop);
+        let at_max = quantize_with_l3loop(&mut ix, MAX_QUANTIZER_STEP, &mut config.l3loop);
+        assert!(at_zero > 0);
+        assert_eq!(
+            at_max, 0,
+            "the largest step must quantize everything to zero"
+        );
+        assert!(ix.iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_full_scale_search_escalates_step_size() {
+        let (bits, step, ix_max) = with_timeout(|| {
+            // 8 kbps at 8 kHz leaves only a few hundred bits per granule
+            let mut config = mono_config_at(8000, 8);
+            load_full_scale_granule(&mut config);
+            let mut l3_xmin = ShinePsyXmin::default();
+            let mut ix = vec![0i32; GRANULE_SIZE];
+            let max_bits = 200;
+
+            let bits = shine_outer_loop(max_bits, &mut l3_xmin, &mut ix, 0, 0, &mut config);
+            let step = config.side_info.gr[0].ch[0].tt.quantizer_step_size;
+            (bits, step, ix.iter().copied().max().unwrap())
+        });
+
+        assert!(bits <= 200, "granule must fit its budget, used {}", bits);
+        assert!(step > -120, "step size must leave the overflow region");
+        assert!(step <= MAX_QUANTIZER_STEP);
+        assert!(ix_max <= IX_MAX_VALUE);
+    }
+
+    #[test]
+    fn test_full_scale_low_bitrate_encoding_terminates() {
+        let frames = with_timeout(|| {
+            use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig, StereoMode};
+
+            let config = Mp3EncoderConfig::new()
+                .sample_rate(8000)
+                .bitrate(8)
+                .channels(1)
+                .stereo_mode(StereoMode::Mono);
+            let mut encoder = Mp3Encoder::new(config).unwrap();
+            let pcm: Vec<i16> = (0..576 * 8)
+                .map(|i| if i % 2 == 0 { i16::MAX } else { i16::MIN })
+                .collect();
+            let mut frames = encoder.encode_interleaved(&pcm).unwrap();
+            frames.push(encoder.finish().unwrap());
+            frames
+        });
+
+        assert!(frames.len() >= 8);
+    }
 }
@@ -0,0 +1,199 @@
+//! ReplayGain analysis tests
+//!
+//! Gains are compared with the values the reference implementation
+//! (gain_analysis.c) reports for the same signals.
+
+use shine_rs::id3::{patch_replaygain_tag, replaygain_tag, REPLAYGAIN_TAG_LEN};
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig, StereoMode};
+use shine_rs::replaygain::{ReplayGain, ReplayGainAnalyzer};
+use std::io::{Cursor, Write};
+
+/// Allowed difference from the reference gains, in dB
+const TOLERANCE_DB: f64 = 0.5;
+
+/// Interleaved stereo sine with the same signal on both channels
+fn sine(sample_rate: u32, frequency: f64, amplitude: f64, seconds: f64) -> Vec<i16> {
+    (0..(sample_rate as f64 * seconds) as usize)
+        .flat_map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            let sample = ((2.0 * std::f64::consts::PI * frequency * t).sin() * amplitude).round();
+            [sample as i16; 2]
+        })
+        .collect()
+}
+
+/// Interleaved stereo pink noise (white noise through Paul Kellet's
+/// economy filter) scaled to `rms_dbfs`
+fn pink_noise(sample_rate: u32, seconds: f64, rms_dbfs: f64) -> Vec<i16> {
+    let mut state = 0x1234_5678u32;
+    let mut white = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f64 / u32::MAX as f64 * 2.0 - 1.0
+    };
+    let mut poles = [[0.0f64; 3]; 2];
+    let raw: Vec<f64> = (0..(sample_rate as f64 * seconds) as usize * 2)
+        .map(|i| {
+            let w = white();
+            let b = &mut poles[i % 2];
+            b[0] = 0.99765 * b[0] + w * 0.0990460;
+            b[1] = 0.96300 * b[1] + w * 0.2965164;
+            b[2] = 0.57000 * b[2] + w * 1.0526913;
+            b[0] + b[1] + b[2] + w * 0.1848
+        })
+        .collect();
+
+    let rms = (raw.iter().map(|x| x * x).sum::<f64>() / raw.len() as f64).sqrt();
+    let scale = 32768.0 * 10f64.powf(rms_dbfs / 20.0) / rms;
+    raw.iter()
+        .map(|x| (x * scale).round().clamp(-32768.0, 32767.0) as i16)
+        .collect()
+}
+
+fn analyze(sample_rate: u32, channels: usize, pcm: &[i16]) -> ReplayGain {
+    let mut analyzer = ReplayGainAnalyzer::new(sample_rate, channels).unwrap();
+    analyzer.update_interleaved(pcm);
+    analyzer.result().expect("input long enough to analyse")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_scale_sine_matches_reference() {
+        // gain_analysis.c: 1 kHz full-scale sine
+        for (sample_rate, reference) in [(44100, -14.15), (48000, -14.14), (22050, -13.75)] {
+            let gain = analyze(sample_rate, 2, &sine(sample_rate, 1000.0, 32767.0, 5.0));
+            assert!(
+                (gain.track_gain - reference).abs() <= TOLERANCE_DB,
+                "{} Hz: gain {} dB, reference {} dB",
+                sample_rate,
+                gain.track_gain,
+                reference
+            );
+            // No headroom left: the peak is full scale
+            assert!((gain.track_peak - 32767.0 / 32768.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_pink_noise_matches_reference() {
+        // gain_analysis.c: the same pink noise at -20 dBFS RMS
+        for (sample_rate, reference) in [(44100, 3.13), (32000, 3.27), (8000, 4.20)] {
+            let gain = analyze(sample_rate, 2, &pink_noise(sample_rate, 10.0, -20.0));
+            assert!(
+                (gain.track_gain - reference).abs() <= TOLERANCE_DB,
+                "{} Hz: gain {} dB, reference {} dB",
+                sample_rate,
+                gain.track_gain,
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn test_louder_input_gets_lower_gain() {
+        let quiet = analyze(44100, 2, &pink_noise(44100, 5.0, -26.0));
+        let loud = analyze(44100, 2, &pink_noise(44100, 5.0, -20.0));
+        assert!((quiet.track_gain - loud.track_gain - 6.0).abs() < 0.05);
+        assert!(quiet.track_peak < loud.track_peak);
+    }
+
+    #[test]
+    fn test_chunking_and_layout_do_not_change_result() {
+        let pcm = pink_noise(44100, 3.0, -18.0);
+        let whole = analyze(44100, 2, &pcm);
+
+        let mut chunked = ReplayGainAnalyzer::new(44100, 2).unwrap();
+        for chunk in pcm.chunks(1001) {
+            chunked.update_interleaved(chunk);
+        }
+        assert_eq!(chunked.result(), Some(whole));
+
+        let left: Vec<i16> = pcm.iter().step_by(2).copied().collect();
+        let right: Vec<i16> = pcm.iter().skip(1).step_by(2).copied().collect();
+        let mut planar = ReplayGainAnalyzer::new(44100, 2).unwrap();
+        planar.update_planar(&[&left[..], &right[..]]);
+        assert_eq!(planar.result(), Some(whole));
+    }
+
+    #[test]
+    fn test_mono_matches_identical_stereo() {
+        let stereo = sine(44100, 440.0, 12000.0, 2.0);
+        let mono: Vec<i16> = stereo.iter().step_by(2).copied().collect();
+        assert_eq!(analyze(44100, 1, &mono), analyze(44100, 2, &stereo));
+    }
+
+    #[test]
+    fn test_short_input_has_no_result() {
+        // One 50 ms window at 44.1 kHz is 2205 sample frames
+        let mut analyzer = ReplayGainAnalyzer::new(44100, 1).unwrap();
+        analyzer.update_interleaved(&[1000i16; 2204]);
+        assert_eq!(analyzer.result(), None);
+        analyzer.update_interleaved(&[1000i16]);
+        assert!(analyzer.result().is_some());
+    }
+
+    #[test]
+    fn test_unsupported_rate_rejected() {
+        assert!(ReplayGainAnalyzer::new(96000, 2).is_none());
+    }
+
+    #[test]
+    fn test_encoder_reports_replaygain_after_finish() {
+        let pcm = sine(44100, 1000.0, 32767.0, 2.0);
+        let config = Mp3EncoderConfig::new().stereo_mode(StereoMode::Stereo);
+
+        let mut plain = Mp3Encoder::new(config.clone()).unwrap();
+        let mut expected = plain.encode_interleaved(&pcm).unwrap().concat();
+        expected.extend(plain.finish().unwrap());
+        assert_eq!(plain.replaygain(), None);
+
+        let mut encoder = Mp3Encoder::new(config.replaygain(true)).unwrap();
+        let mut output = encoder.encode_interleaved(&pcm).unwrap().concat();
+        assert_eq!(encoder.replaygain(), None);
+        output.extend(encoder.finish().unwrap());
+
+        // Analysis does not touch the encoded stream
+        assert_eq!(output, expected);
+        assert_eq!(encoder.replaygain(), Some(analyze(44100, 2, &pcm)));
+    }
+
+    #[test]
+    fn test_tag_is_reserved_and_patched() {
+        let placeholder = replaygain_tag(None);
+        assert_eq!(placeholder.len(), REPLAYGAIN_TAG_LEN);
+        assert_eq!(&placeholder[..5], b"ID3\x03\x00");
+        // Syncsafe size of everything after the header
+        assert_eq!(
+            placeholder[6..10],
+            [0, 0, 0, (REPLAYGAIN_TAG_LEN - 10) as u8]
+        );
+        assert!(placeholder[10..].iter().all(|&b| b == 0));
+
+        let mut output = Cursor::new(Vec::new());
+        output.write_all(&placeholder).unwrap();
+        output.write_all(b"audio").unwrap();
+
+        let gain = ReplayGain {
+            track_gain: -7.5,
+            track_peak: 0.987654321,
+        };
+        patch_replaygain_tag(&mut output, &gain).unwrap();
+        assert_eq!(output.position(), REPLAYGAIN_TAG_LEN as u64 + 5);
+
+        let data = output.into_inner();
+        assert_eq!(
+            &data[..REPLAYGAIN_TAG_LEN],
+            &replaygain_tag(Some(&gain))[..]
+        );
+        assert_eq!(&data[REPLAYGAIN_TAG_LEN..], b"audio");
+
+        let text = String::from_utf8_lossy(&data[..REPLAYGAIN_TAG_LEN]);
+        assert!(text.contains("TXXX"));
+        assert!(text.contains("REPLAYGAIN_TRACK_GAIN\0-7.50 dB"));
+        assert!(text.contains("REPLAYGAIN_TRACK_PEAK\x000.987654"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Sample rate coverage tests for the MPEG-1 rates other than 44.1 kHz
+//!
+//! The scalefactor band tables and the header samplerate field differ for
+//! 48 kHz and 32 kHz, so these tests make sure the encoder picks the right
+//! row instead of silently falling back to the 44.1 kHz layout.
+
+use shine_rs::mp3_encoder::{Mp3Encoder, Mp3EncoderConfig};
+use shine_rs::quantization::{subdivide_with_samplerate, subdivide_with_samplerate_index};
+use shine_rs::tables::SHINE_SCALE_FACT_BAND_INDEX;
+use shine_rs::types::GrInfo;
+
+/// Generate an interleaved stereo sine tone
+fn stereo_tone(sample_rate: u32, frequency: f64, frames: usize) -> Vec<i16> {
+    (0..frames * 1152)
+        .flat_map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            let sample = ((2.0 * std::f64::consts::PI * frequency * t).sin() * 12000.0) as i16;
+            [sample, sample]
+        })
+        .collect()
+}
+
+/// Encode a tone and return the encoder together with the produced frames
+fn encode_tone(sample_rate: u32) -> (Mp3Encoder, Vec<Vec<u8>>) {
+    let config = Mp3EncoderConfig::new()
+        .sample_rate(sample_rate)
+        .bitrate(128)
+        .channels(2);
+    let mut encoder = Mp3Encoder::new(config).expect("encoder should initialise");
+    let frames = encoder
+        .encode_interleaved(&stereo_tone(sample_rate, 1000.0, 4))
+        .expect("encoding should succeed");
+    (encoder, frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_samplerate_bits(frame: &[u8]) -> u8 {
+        assert_eq!(frame[0], 0xFF, "frame must start with sync");
+        assert_eq!(frame[1] & 0xE0, 0xE0, "frame must start with sync");
+        (frame[2] >> 2) & 0x03
+    }
+
+    fn assert_regions_use_row(encoder: &mut Mp3Encoder, row: usize) {
+        let bands = &SHINE_SCALE_FACT_BAND_INDEX[row];
+        let config = encoder.shine_config();
+        for gr in 0..2 {
+            for ch in 0..2 {
+                let tt = &config.side_info.gr[gr].ch[ch].tt;
+                assert!(tt.big_values > 0, "tone should produce big values");
+                let bigvalues_region = 2 * tt.big_values as i32;
+                for address in [tt.address1, tt.address2] {
+                    assert!(
+                        bands.contains(&(address as i32)) || address as i32 == bigvalues_region,
+                        "region boundary {} is not a band edge of row {}",
+                        address,
+                        row
+                    );
+                }
+                assert_eq!(
+                    tt.address1 as i32,
+                    bands[tt.region0_count as usize + 1],
+                    "region0 must end on a row {} band edge",
+                    row
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_48000_header_samplerate_index() {
+        let (mut encoder, frames) = encode_tone(48000);
+        assert!(!frames.is_empty());
+        for frame in &frames {
+            assert_eq!(header_samplerate_bits(frame), 1);
+        }
+        assert_eq!(encoder.shine_config().mpeg.samplerate_index, 1);
+    }
+
+    #[test]
+    fn test_32000_header_samplerate_index() {
+        let (mut encoder, frames) = encode_tone(32000);
+        assert!(!frames.is_empty());
+        for frame in &frames {
+            assert_eq!(header_samplerate_bits(frame), 2);
+        }
+        assert_eq!(encoder.shine_config().mpeg.samplerate_index, 2);
+    }
+
+    #[test]
+    fn test_48000_subdivide_uses_row_1() {
+        let (mut encoder, _) = encode_tone(48000);
+        assert_regions_use_row(&mut encoder, 1);
+    }
+
+    #[test]
+    fn test_32000_subdivide_uses_row_2() {
+        let (mut encoder, _) = encode_tone(32000);
+        assert_regions_use_row(&mut encoder, 2);
+    }
+
+    #[test]
+    fn test_subdivide_rows_differ_per_rate() {
+        // 200 big value lines split on different band edges for each row
+        let expected = [
+            (44100, 0, 5, 24, 90),
+            (48000, 1, 5, 24, 88),
+            (32000, 2, 4, 20, 82),
+        ];
+
+        for (sample_rate, row, region0_count, address1, address2) in expected {
+            let mut by_index = GrInfo {
+                big_values: 100,
+                ..Default::default()
+            };
+            subdivide_with_samplerate_index(&mut by_index, row);
+            assert_eq!(by_index.region0_count, region0_count, "row {}", row);
+            assert_eq!(by_index.region1_count, 6, "row {}", row);
+            assert_eq!(by_index.address1, address1, "row {}", row);
+            assert_eq!(by_index.address2, address2, "row {}", row);
+            assert_eq!(by_index.address3, 200);
+
+            let mut by_rate = GrInfo {
+                big_values: 100,
+                ..Default::default()
+            };
+            subdivide_with_samplerate(&mut by_rate, sample_rate);
+            assert_eq!(by_rate.address2, address2, "{} Hz", sample_rate);
+        }
+    }
+
+    #[test]
+    fn test_subdivide_unsupported_rate_keeps_single_region() {
+        let mut gi = GrInfo {
+            big_values: 100,
+            ..Default::default()
+        };
+        subdivide_with_samplerate(&mut gi, 96000);
+        assert_eq!(gi.region0_count, 0);
+        assert_eq!(gi.region1_count, 0);
+        assert_eq!(gi.address1, 200);
+        assert_eq!(gi.address3, 200);
+    }
+}
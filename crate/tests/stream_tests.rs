@@ -0,0 +1,168 @@
+//! Realtime stream encoder tests
+//!
+//! Checks the overrun policies of the ring buffer single-threaded, then
+//! runs a producer thread at 48 kHz pace against a slow consumer and
+//! compares the output with a direct encode of the same samples.
+
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3EncoderConfig, StereoMode};
+use shine_rs::stream::{Full, OverrunPolicy, StreamEncoder};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn stereo_48k() -> Mp3EncoderConfig {
+    Mp3EncoderConfig::new()
+        .sample_rate(48000)
+        .bitrate(128)
+        .channels(2)
+        .stereo_mode(StereoMode::Stereo)
+}
+
+/// Interleaved counter pattern: both channels carry the sample frame index
+/// modulo 1000, the right one negated
+fn counter(frames: std::ops::Range<usize>) -> Vec<i16> {
+    frames
+        .map(|i| (i % 1000) as i16)
+        .flat_map(|n| [n, -n])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity_rounds_to_whole_frames() {
+        let (stream, producer) =
+            StreamEncoder::new(stereo_48k(), 1001, OverrunPolicy::Reject).unwrap();
+        assert_eq!(stream.capacity(), 1002);
+        assert_eq!(producer.capacity(), 1002);
+        assert_eq!(stream.policy(), OverrunPolicy::Reject);
+    }
+
+    #[test]
+    fn test_reject_keeps_buffered_samples() {
+        let (mut stream, mut producer) =
+            StreamEncoder::new(stereo_48k(), 1024, OverrunPolicy::Reject).unwrap();
+        let pcm = counter(0..768);
+
+        producer.try_push(&pcm[..1024]).unwrap();
+        assert_eq!(producer.try_push(&pcm[1024..1030]), Err(Full));
+        assert_eq!(producer.try_push(&vec![0; 2048]), Err(Full));
+        assert_eq!(stream.pending(), 1024);
+        assert_eq!(stream.overruns(), 2);
+        assert_eq!(stream.dropped_samples(), 6 + 2048);
+
+        // The rejected chunk can be pushed again once the worker drained
+        assert!(stream.process().unwrap().is_empty());
+        producer.try_push(&pcm[1024..]).unwrap();
+        let output = stream.finish().unwrap();
+        assert_eq!(output, encode_pcm_to_mp3(stereo_48k(), &pcm).unwrap());
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest_samples() {
+        let (mut stream, mut producer) =
+            StreamEncoder::new(stereo_48k(), 4096, OverrunPolicy::DropOldest).unwrap();
+        let pcm = counter(0..4096);
+
+        for chunk in pcm.chunks(256) {
+            producer.try_push(chunk).unwrap();
+        }
+        assert_eq!(stream.pending(), 4096);
+        assert_eq!(producer.overruns(), 16);
+        assert_eq!(stream.dropped_samples(), 4096);
+
+        let output = stream.finish().unwrap();
+        let expected = encode_pcm_to_mp3(stereo_48k(), &pcm[4096..]).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_drop_oldest_drops_whole_sample_frames() {
+        let (stream, mut producer) =
+            StreamEncoder::new(stereo_48k(), 8, OverrunPolicy::DropOldest).unwrap();
+        producer.try_push(&[1, 2, 3, 4, 5, 6]).unwrap();
+        // Three samples more than fit: two whole frames go
+        producer.try_push(&[7, 8, 9, 10, 11]).unwrap();
+        assert_eq!(stream.dropped_samples(), 4);
+        assert_eq!(stream.pending(), 7);
+    }
+
+    #[test]
+    fn test_realtime_producer_with_slow_consumer() {
+        const CHUNK: usize = 256;
+        const CHUNKS: usize = 300;
+        let pcm = counter(0..CHUNK * CHUNKS / 2);
+
+        // 100 ms of stereo audio at 48 kHz
+        let (mut stream, mut producer) =
+            StreamEncoder::new(stereo_48k(), 9600, OverrunPolicy::Reject).unwrap();
+
+        let output = thread::scope(|scope| {
+            let pcm = &pcm;
+            let audio = scope.spawn(move || {
+                // One chunk every 128 / 48000 s, retried if the ring is full
+                let period = Duration::from_secs_f64(CHUNK as f64 / 2.0 / 48000.0);
+                let start = Instant::now();
+                for (index, chunk) in pcm.chunks(CHUNK).enumerate() {
+                    let due = start + period * index as u32;
+                    if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                        thread::sleep(wait);
+                    }
+                    while producer.try_push(chunk).is_err() {
+                        thread::yield_now();
+                    }
+                }
+                producer.overruns()
+            });
+
+            let mut output = Vec::new();
+            while !audio.is_finished() {
+                thread::sleep(Duration::from_millis(20));
+                output.extend(stream.process().unwrap().concat());
+            }
+            let overruns = audio.join().unwrap();
+            output.extend(stream.finish().unwrap());
+            (output, overruns)
+        });
+
+        let (output, overruns) = output;
+        assert_eq!(stream.overruns(), overruns);
+        assert_eq!(
+            stream.encoder().stats().samples_per_channel as usize * 2,
+            pcm.len()
+        );
+        // Rejected chunks were retried, so nothing is missing either way
+        assert_eq!(output, encode_pcm_to_mp3(stereo_48k(), &pcm).unwrap());
+    }
+
+    #[test]
+    fn test_drop_oldest_accounts_for_every_sample() {
+        const CHUNK: usize = 128;
+        let pcm = counter(0..48000);
+
+        // Too small for the consumer's stalls, so samples are dropped
+        let (mut stream, mut producer) =
+            StreamEncoder::new(stereo_48k(), 1024, OverrunPolicy::DropOldest).unwrap();
+
+        thread::scope(|scope| {
+            let pcm = &pcm;
+            let audio = scope.spawn(move || {
+                for chunk in pcm.chunks(CHUNK) {
+                    producer.try_push(chunk).unwrap();
+                }
+            });
+            while !audio.is_finished() {
+                stream.process().unwrap();
+                thread::sleep(Duration::from_micros(200));
+            }
+            audio.join().unwrap();
+        });
+        stream.finish().unwrap();
+
+        let encoded = stream.encoder().stats().samples_per_channel * 2;
+        assert!(stream.dropped_samples() > 0);
+        assert_eq!(encoded + stream.dropped_samples(), pcm.len() as u64);
+        assert_eq!(stream.dropped_samples() % 2, 0);
+    }
+}
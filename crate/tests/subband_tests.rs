@@ -460,7 +460,7 @@ mod tests {
         );
         // Allow for some rounding error in the scaling relationship
         let expected_scaled = energy / 4;
-        let diff = (scaled_energy as i64 - expected_scaled).abs();
+        let diff = (scaled_energy - expected_scaled).abs();
         assert!(
             diff < energy / 10,
             "Energy should scale approximately quadratically"
@@ -552,4 +552,186 @@ mod tests {
             "Each buffer should be HAN_SIZE"
         );
     }
+
+    /// shine's window_filter_subband with the portable 32-bit accumulators,
+    /// checking every addition
+    fn shine_filter(x: &[i32; HAN_SIZE], off: usize, fl: &[[i32; 64]; SBLIMIT]) -> [i32; SBLIMIT] {
+        use shine_rs::tables::SHINE_ENWINDOW;
+
+        let mul = |a: i32, b: i32| ((a as i64 * b as i64) >> 32) as i32;
+        let add = |acc: i32, term: i32| acc.checked_add(term).expect("accumulator overflow");
+        let mut y = [0i32; 64];
+        for (i, y) in y.iter_mut().enumerate() {
+            *y = (0..8).fold(0, |acc, k| {
+                let index = (off + i + (k << 6)) & (HAN_SIZE - 1);
+                add(acc, mul(x[index], SHINE_ENWINDOW[i + (k << 6)]))
+            });
+        }
+        let mut s = [0i32; SBLIMIT];
+        for (i, s) in s.iter_mut().enumerate() {
+            *s = (0..64)
+                .rev()
+                .fold(0, |acc, j| add(acc, mul(fl[i][j], y[j])));
+        }
+        s
+    }
+
+    #[test]
+    fn test_full_scale_input_matches_shine() {
+        use shine_rs::subband::{shine_subband_initialise, shine_window_filter_subband};
+        use shine_rs::types::Subband;
+
+        let frames = 300;
+        let len = frames * 1152;
+        type Signal = fn(usize) -> i16;
+        let signals: [(&str, Signal); 5] = [
+            ("square", |n| if n / 40 % 2 == 0 { 32767 } else { -32768 }),
+            ("alternating", |n| if n % 2 == 0 { 32767 } else { -32768 }),
+            ("positive dc", |_| 32767),
+            ("negative dc", |_| -32767),
+            ("minimum dc", |_| -32768),
+        ];
+
+        for (name, signal) in signals {
+            let pcm: Vec<i16> = (0..len).map(signal).collect();
+            let mut subband = Subband::default();
+            shine_subband_initialise(&mut subband);
+            let mut buffer = pcm.as_slice();
+            let mut peak = 0;
+            for _ in 0..len / SBLIMIT {
+                // shine windows the buffer at the offset before the update
+                let off = subband.off[0] as usize;
+                let mut x = subband.x[0];
+                for (i, &sample) in buffer[..SBLIMIT].iter().enumerate() {
+                    x[SBLIMIT - 1 - i + off] = (sample as i32) << 16;
+                }
+                let expected = shine_filter(&x, off, &subband.fl);
+
+                let mut s = [0i32; SBLIMIT];
+                shine_window_filter_subband(&mut buffer, &mut s, 0, &mut subband, 1);
+                assert_eq!(s, expected, "{}", name);
+                peak = s
+                    .iter()
+                    .fold(peak, |peak: i32, &v| peak.max(v.saturating_abs()));
+            }
+            // Far from the i32 limits, so nothing was clipped either
+            assert!(peak < i32::MAX / 2, "{}: peak {}", name, peak);
+        }
+    }
+
+    /// Per-slot analysis as shine's mdct_sub runs it, inversion compensated
+    fn per_slot_granule(
+        buffer: &mut &[i32],
+        stride: usize,
+        ch: usize,
+        subband: &mut shine_rs::types::Subband,
+    ) -> [[i32; SBLIMIT]; 18] {
+        let mut out = [[0i32; SBLIMIT]; 18];
+        for (slot, s) in out.iter_mut().enumerate() {
+            shine_rs::subband::shine_window_filter_subband_i32(buffer, s, ch, subband, stride);
+            if slot % 2 == 1 {
+                for band in (1..SBLIMIT).step_by(2) {
+                    s[band] *= -1;
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_silent_granules_match_per_slot() {
+        use shine_rs::subband::{filter_granule_i32, shine_subband_initialise};
+        use shine_rs::types::Subband;
+
+        let mut expected_state = Subband::default();
+        shine_subband_initialise(&mut expected_state);
+        let mut state = Subband::default();
+        shine_subband_initialise(&mut state);
+
+        // Silence, a tone burst, silence long enough to flush the window
+        // history, then the tone again
+        let tone = |i: usize| (((i as f64 * 0.07).sin() * 20000.0) as i32) << 16;
+        let mut pcm = vec![0i32; 3 * 576];
+        pcm.extend((0..2 * 576).map(tone));
+        pcm.extend(vec![0; 6 * 576]);
+        pcm.extend((0..576).map(tone));
+
+        let mut silent_outputs = 0;
+        for granule in pcm.chunks_exact(576) {
+            let mut buffer = granule;
+            let expected = per_slot_granule(&mut buffer, 1, 0, &mut expected_state);
+            let output = filter_granule_i32(granule, 1, 0, &mut state);
+            assert_eq!(output, expected);
+            assert_eq!(state.x, expected_state.x);
+            assert_eq!(state.off, expected_state.off);
+            if output.iter().flatten().all(|&x| x == 0) {
+                silent_outputs += 1;
+            }
+        }
+        // The leading silence and the silence after the history drained
+        assert!(
+            silent_outputs >= 3 + 4,
+            "{} silent granules",
+            silent_outputs
+        );
+    }
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 32,
+            failure_persistence: None,
+            ..ProptestConfig::default()
+        })]
+
+        #[test]
+        fn test_filter_granule_matches_per_slot(
+            pcm in proptest::collection::vec(any::<i16>(), 3 * 2 * 576),
+        ) {
+            use shine_rs::subband::{
+                filter_granule, filter_granule_i32, shine_subband_initialise,
+                shine_window_filter_subband,
+            };
+            use shine_rs::types::Subband;
+
+            let mut expected_state = Subband::default();
+            shine_subband_initialise(&mut expected_state);
+            let mut state = Subband::default();
+            shine_subband_initialise(&mut state);
+
+            // Interleaved stereo, read with a stride as the encoder does
+            let full: Vec<i32> = pcm.iter().map(|&sample| (sample as i32) << 16).collect();
+            for granule in full.chunks_exact(2 * 576) {
+                for ch in 0..2 {
+                    let mut buffer = &granule[ch..];
+                    let expected = per_slot_granule(&mut buffer, 2, ch, &mut expected_state);
+                    let output = filter_granule_i32(&granule[ch..], 2, ch, &mut state);
+                    prop_assert_eq!(output, expected);
+                }
+            }
+
+            // A single channel of 16-bit samples
+            let mut expected_state = Subband::default();
+            shine_subband_initialise(&mut expected_state);
+            let mut state = Subband::default();
+            shine_subband_initialise(&mut state);
+            for granule in pcm.chunks_exact(576) {
+                let mut buffer = granule;
+                let mut expected = [[0i32; SBLIMIT]; 18];
+                for (slot, s) in expected.iter_mut().enumerate() {
+                    shine_window_filter_subband(&mut buffer, s, 0, &mut expected_state, 1);
+                    if slot % 2 == 1 {
+                        for band in (1..SBLIMIT).step_by(2) {
+                            s[band] *= -1;
+                        }
+                    }
+                }
+                let output = filter_granule(granule.try_into().unwrap(), 0, &mut state);
+                prop_assert_eq!(output, expected);
+            }
+            prop_assert_eq!(state.x, expected_state.x);
+            prop_assert_eq!(state.off, expected_state.off);
+        }
+    }
 }
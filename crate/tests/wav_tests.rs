@@ -0,0 +1,406 @@
+//! WAV reader tests
+//!
+//! Checks that the `fmt ` and `data` chunks are found wherever they sit in
+//! the RIFF stream and that other chunks are skipped, and that streams
+//! written by `write_wav` read back bit-exactly.
+
+use shine_rs::error::InputDataError;
+use shine_rs::frame::iter_frames;
+use shine_rs::mp3_encoder::{encode_wav_to_mp3, Mp3EncoderConfig};
+use shine_rs::pcm::{decode_alaw, decode_mulaw};
+use shine_rs::wav::{write_wav, write_wav_f32, write_wav_file, WavEncoding, WavSource};
+use std::io::Cursor;
+
+/// 16-bit PCM `fmt ` chunk body
+fn fmt_body(sample_rate: u32, channels: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&sample_rate.to_le_bytes());
+    body.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    body.extend_from_slice(&(channels * 2).to_le_bytes());
+    body.extend_from_slice(&16u16.to_le_bytes());
+    body
+}
+
+/// G.711 `fmt ` chunk body: one byte per sample and channel
+fn g711_fmt_body(format_tag: u16, sample_rate: u32, channels: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&format_tag.to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&sample_rate.to_le_bytes());
+    body.extend_from_slice(&(sample_rate * channels as u32).to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&8u16.to_le_bytes());
+    body
+}
+
+fn data_body(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+/// RIFF/WAVE stream made of the given chunks, odd-sized ones padded
+fn riff(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = b"WAVE".to_vec();
+    for (id, data) in chunks {
+        body.extend_from_slice(*id);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+    wav
+}
+
+/// RF64/WAVE stream: a `ds64` chunk declaring `data_len`, then the given
+/// chunks, with the `data` chunk size left to `ds64`
+fn rf64(data_len: u64, chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut ds64 = Vec::new();
+    ds64.extend_from_slice(&(data_len + 100).to_le_bytes());
+    ds64.extend_from_slice(&data_len.to_le_bytes());
+    ds64.extend_from_slice(&(data_len / 4).to_le_bytes());
+    ds64.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut wav = b"RF64\xff\xff\xff\xffWAVEds64".to_vec();
+    wav.extend_from_slice(&(ds64.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&ds64);
+    for (id, data) in chunks {
+        wav.extend_from_slice(*id);
+        if id == &b"data" {
+            wav.extend_from_slice(&u32::MAX.to_le_bytes());
+        } else {
+            wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        }
+        wav.extend_from_slice(data);
+    }
+    wav
+}
+
+fn test_samples() -> Vec<i16> {
+    (0..1152 * 2 * 3)
+        .map(|i| ((i as f64 * 0.03).sin() * 10000.0) as i16)
+        .collect()
+}
+
+fn read_all(wav: Vec<u8>) -> (u32, u8, u64, Vec<i16>) {
+    let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+    let samples = source.read_to_end().unwrap();
+    (
+        source.sample_rate(),
+        source.channels(),
+        source.duration_samples(),
+        samples,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_chunk_before_data() {
+        let samples = test_samples();
+        // Odd-sized LIST chunk: the pad byte must be skipped too
+        let wav = riff(&[
+            (b"fmt ", fmt_body(44100, 2)),
+            (b"LIST", b"INFOISFT\x05\x00\x00\x00shine".to_vec()),
+            (b"data", data_body(&samples)),
+        ]);
+
+        let (rate, channels, frames, read) = read_all(wav);
+        assert_eq!((rate, channels), (44100, 2));
+        assert_eq!(frames as usize, samples.len() / 2);
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn test_trailing_chunk_after_data() {
+        let samples = test_samples();
+        let wav = riff(&[
+            (b"fmt ", fmt_body(22050, 1)),
+            (b"data", data_body(&samples)),
+            (b"id3 ", vec![0x7F; 33]),
+        ]);
+
+        let (rate, channels, _, read) = read_all(wav.clone());
+        assert_eq!((rate, channels), (22050, 1));
+        assert_eq!(read, samples);
+
+        // Chunked reads stop at the end of the data chunk as well
+        let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+        let mut buffer = Vec::new();
+        let mut total = Vec::new();
+        while source.read_samples(&mut buffer, 1000).unwrap() > 0 {
+            total.extend_from_slice(&buffer);
+        }
+        assert_eq!(total, samples);
+    }
+
+    #[test]
+    fn test_metadata_chunks_and_data_before_fmt() {
+        let samples = test_samples();
+        let canonical = riff(&[
+            (b"fmt ", fmt_body(48000, 2)),
+            (b"data", data_body(&samples)),
+        ]);
+        let layouts = [
+            riff(&[
+                (b"fact", 1234u32.to_le_bytes().to_vec()),
+                (b"bext", vec![1; 101]),
+                (b"fmt ", fmt_body(48000, 2)),
+                (b"LIST", vec![2; 7]),
+                (b"data", data_body(&samples)),
+            ]),
+            riff(&[
+                (b"data", data_body(&samples)),
+                (b"LIST", vec![3; 9]),
+                (b"fmt ", fmt_body(48000, 2)),
+            ]),
+        ];
+
+        let mut expected = Vec::new();
+        encode_wav_to_mp3(Cursor::new(canonical), &mut expected, None, None).unwrap();
+        for wav in layouts {
+            let (rate, channels, _, read) = read_all(wav.clone());
+            assert_eq!((rate, channels), (48000, 2));
+            assert_eq!(read, samples);
+
+            let mut mp3 = Vec::new();
+            encode_wav_to_mp3(Cursor::new(wav), &mut mp3, None, None).unwrap();
+            assert_eq!(mp3, expected);
+        }
+    }
+
+    #[test]
+    fn test_rf64_data_size_from_ds64() {
+        let samples = test_samples();
+        let chunks = [
+            (b"fmt ", fmt_body(44100, 2)),
+            (b"data", data_body(&samples)),
+        ];
+        let wav = rf64(samples.len() as u64 * 2, &chunks);
+        let (rate, channels, frames, read) = read_all(wav);
+        assert_eq!((rate, channels), (44100, 2));
+        assert_eq!(frames as usize, samples.len() / 2);
+        assert_eq!(read, samples);
+
+        // A 6 GiB data chunk: the length survives beyond 32 bits, the
+        // stream simply ends where the short test data does
+        let wav = rf64(6 << 30, &chunks);
+        let (_, _, frames, read) = read_all(wav);
+        assert_eq!(frames, (6 << 30) / 4);
+        assert_eq!(read, samples);
+
+        // ds64 must come first
+        let mut wav = rf64(0, &chunks);
+        wav[12..16].copy_from_slice(b"junk");
+        assert!(matches!(
+            WavSource::new(Cursor::new(wav)),
+            Err(InputDataError::InvalidWav(_))
+        ));
+    }
+
+    #[test]
+    fn test_rf64_huge_sizes_end_at_eof() {
+        let samples = test_samples();
+        // 5 TiB declared, a few frames present
+        let wav = rf64(
+            5 << 40,
+            &[
+                (b"fmt ", fmt_body(44100, 2)),
+                (b"data", data_body(&samples)),
+            ],
+        );
+        let mut source = WavSource::new(Cursor::new(wav.clone())).unwrap();
+        assert_eq!(source.duration_samples(), (5 << 40) / 4);
+
+        let mut buffer = Vec::new();
+        let mut total = Vec::new();
+        while source.read_samples(&mut buffer, 4096).unwrap() > 0 {
+            total.extend_from_slice(&buffer);
+        }
+        assert_eq!(total, samples);
+
+        // Skipping far past the data stops at the end of the stream
+        let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+        assert_eq!(
+            source.skip_frames(u64::MAX / 8).unwrap(),
+            samples.len() as u64 / 2
+        );
+        assert_eq!(source.read_samples(&mut buffer, 4096).unwrap(), 0);
+
+        // A format chunk declaring gigabytes fails at the end of the stream
+        let mut wav = riff(&[(b"fmt ", fmt_body(44100, 2))]);
+        wav[16..20].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+        assert!(matches!(
+            WavSource::new(Cursor::new(wav)),
+            Err(InputDataError::InvalidWav(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_data_and_fact_chunk() {
+        let samples = test_samples();
+        let frames = samples.len() as u64 / 2;
+        let wav = riff(&[
+            (b"fmt ", fmt_body(44100, 2)),
+            (b"fact", (frames as u32).to_le_bytes().to_vec()),
+            (b"data", data_body(&samples)),
+        ]);
+        let mut source = WavSource::new(Cursor::new(wav.clone())).unwrap();
+        assert_eq!(source.fact_samples(), Some(frames));
+        assert_eq!(source.read_to_end().unwrap(), samples);
+        assert!(!source.is_truncated());
+
+        // Cut in the middle of the data: the samples up to the cut are read
+        let cut = wav.len() - samples.len();
+        let mut source = WavSource::new(Cursor::new(wav[..cut].to_vec())).unwrap();
+        assert_eq!(source.duration_samples(), frames);
+        assert_eq!(source.read_to_end().unwrap(), samples[..samples.len() / 2]);
+        assert!(source.is_truncated());
+
+        // A streaming writer's placeholder size: reading stops at the end
+        let mut wav = riff(&[
+            (b"fmt ", fmt_body(44100, 2)),
+            (b"data", data_body(&samples)),
+        ]);
+        wav[40..44].copy_from_slice(&0x7FFF_FFF0u32.to_le_bytes());
+        let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+        assert_eq!(source.fact_samples(), None);
+        assert!(!source.is_truncated());
+        assert_eq!(source.read_to_end().unwrap(), samples);
+        assert!(source.is_truncated());
+    }
+
+    #[test]
+    fn test_g711_data_is_expanded() {
+        // A second of codewords sweeping the whole range, odd length included
+        let codes: Vec<u8> = (0..8001).map(|i| (i * 7 % 256) as u8).collect();
+        for (tag, encoding, expected) in [
+            (7, WavEncoding::MuLaw, decode_mulaw(&codes)),
+            (6, WavEncoding::ALaw, decode_alaw(&codes)),
+        ] {
+            let wav = riff(&[
+                (b"fmt ", g711_fmt_body(tag, 8000, 1)),
+                (b"data", codes.clone()),
+            ]);
+            let mut source = WavSource::new(Cursor::new(wav.clone())).unwrap();
+            assert_eq!(source.encoding(), encoding);
+            assert_eq!(source.duration_samples(), 8001);
+            assert_eq!(source.skip_frames(1).unwrap(), 1);
+            assert_eq!(source.read_to_end().unwrap(), expected[1..]);
+
+            // Encoded as MPEG-2.5 like any 8 kHz input
+            let mut mp3 = Vec::new();
+            let config = Mp3EncoderConfig::new().bitrate(32);
+            let summary =
+                encode_wav_to_mp3(Cursor::new(wav), &mut mp3, Some(config), None).unwrap();
+            assert_eq!(summary.samples_per_channel, 8001);
+            let frames: Vec<_> = iter_frames(&mp3).collect();
+            // Only the final frame may be cut short at flush
+            assert!(frames.len() + 1 >= summary.frames && frames.len() <= summary.frames);
+            assert!(frames
+                .iter()
+                .all(|f| f.header.sample_rate() == 8000 && f.header.bitrate() == 32));
+        }
+
+        // Two channels need a block alignment of two bytes
+        let mut fmt = g711_fmt_body(7, 8000, 2);
+        fmt[12] = 4;
+        let wav = riff(&[(b"fmt ", fmt), (b"data", codes)]);
+        assert!(matches!(
+            WavSource::new(Cursor::new(wav)),
+            Err(InputDataError::InvalidWav(_))
+        ));
+    }
+
+    #[test]
+    fn test_skip_frames_and_missing_chunks() {
+        let samples = test_samples();
+        let wav = riff(&[
+            (b"data", data_body(&samples)),
+            (b"fmt ", fmt_body(44100, 2)),
+        ]);
+        let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+        assert_eq!(source.skip_frames(100).unwrap(), 100);
+        assert_eq!(source.read_to_end().unwrap(), samples[200..]);
+
+        for wav in [
+            riff(&[(b"data", data_body(&samples))]),
+            riff(&[(b"fmt ", fmt_body(44100, 2)), (b"LIST", vec![0; 4])]),
+            b"RIFX\x00\x00\x00\x00WAVE".to_vec(),
+        ] {
+            assert!(matches!(
+                WavSource::new(Cursor::new(wav)),
+                Err(InputDataError::InvalidWav(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_written_header_fields() {
+        let samples = test_samples();
+        let mut wav = Vec::new();
+        write_wav(&mut wav, &samples, 48000, 2).unwrap();
+        assert_eq!(
+            wav,
+            riff(&[
+                (b"fmt ", fmt_body(48000, 2)),
+                (b"data", data_body(&samples)),
+            ])
+        );
+
+        // The file variant writes the same bytes
+        let path = std::env::temp_dir().join("shine_rs_write_wav_file.wav");
+        write_wav_file(&path, &samples, 48000, 2).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(written, wav);
+
+        // Partial frames and zero channels are rejected
+        for (samples, channels) in [(&[1i16, 2, 3][..], 2), (&[][..], 0)] {
+            let err = write_wav(Vec::new(), samples, 8000, channels).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_float_samples_are_rounded_to_16_bits() {
+        let floats = [0.0, 0.5, -0.5, 1.0, -1.0, 2.0, f32::NAN, 1.0 / 65536.0];
+        let mut wav = Vec::new();
+        write_wav_f32(&mut wav, &floats, 44100, 1).unwrap();
+        let (_, _, _, read) = read_all(wav);
+        assert_eq!(read, [0, 16384, -16384, 32767, -32768, 32767, 0, 1]);
+    }
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_written_wav_reads_back_exactly(
+            stereo in any::<bool>(),
+            rate_index in 0..9usize,
+            samples in proptest::collection::vec(any::<i16>(), 0..5000),
+        ) {
+            let channels = if stereo { 2 } else { 1 };
+            let rate = [8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000][rate_index];
+            // Whole frames only, odd frame counts included
+            let samples = &samples[..samples.len() - samples.len() % channels as usize];
+
+            let mut wav = Vec::new();
+            write_wav(&mut wav, samples, rate, channels).unwrap();
+            prop_assert_eq!(wav.len(), 44 + samples.len() * 2);
+
+            let mut source = WavSource::new(Cursor::new(wav)).unwrap();
+            prop_assert_eq!(source.sample_rate(), rate);
+            prop_assert_eq!(source.channels() as u16, channels);
+            prop_assert_eq!(source.duration_samples(), (samples.len() / channels as usize) as u64);
+            prop_assert_eq!(source.read_to_end().unwrap(), samples);
+            prop_assert!(!source.is_truncated());
+        }
+    }
+}
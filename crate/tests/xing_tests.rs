@@ -0,0 +1,162 @@
+//! Xing/Info frame tests
+//!
+//! The TOC is built from real encoder output and checked against the frame
+//! offsets, and a reserved frame is patched in place and parsed back.
+
+use shine_rs::frame::{iter_frames, FrameHeader};
+use shine_rs::id3::padding_tag;
+use shine_rs::mp3_encoder::{encode_pcm_to_mp3, Mp3EncoderConfig, StereoMode};
+use shine_rs::xing::{patch_xing, TocBuilder, XingFrame, TOC_ENTRIES};
+use std::io::{Cursor, Write};
+
+/// Encode `frames` frames of a stereo tone at 44.1 kHz
+fn encode_tone(frames: usize) -> Vec<u8> {
+    let pcm: Vec<i16> = (0..frames * 1152)
+        .flat_map(|i| {
+            let t = i as f64 / 44100.0;
+            let sample = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 10000.0) as i16;
+            [sample, sample / 2]
+        })
+        .collect();
+    let config = Mp3EncoderConfig::new()
+        .sample_rate(44100)
+        .bitrate(128)
+        .channels(2)
+        .stereo_mode(StereoMode::Stereo);
+    encode_pcm_to_mp3(config, &pcm).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_to_bytes_round_trip() {
+        let stream = encode_tone(4);
+        for frame in iter_frames(&stream) {
+            assert_eq!(frame.header.to_bytes(), frame.data[..4]);
+        }
+    }
+
+    #[test]
+    fn test_toc_is_monotonic() {
+        let stream = encode_tone(120);
+        let builder = TocBuilder::from_stream(&stream, 0);
+        assert!(builder.frames() > 100);
+
+        let toc = builder.toc(stream.len() as u64);
+        assert_eq!(toc[0], 0);
+        assert!(toc.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(toc[TOC_ENTRIES - 1] > 240);
+    }
+
+    #[test]
+    fn test_toc_entries_within_one_frame() {
+        let stream = encode_tone(120);
+        let total = stream.len() as u64;
+        let builder = TocBuilder::from_stream(&stream, 0);
+        let frame_length = FrameHeader::parse(&stream, 0).unwrap().frame_length() as u64 + 1;
+        let toc = builder.toc(total);
+
+        for (k, &entry) in toc.iter().enumerate() {
+            let true_offset = builder.offsets()[k * builder.frames() / TOC_ENTRIES];
+            let mapped = entry as u64 * total / 256;
+            assert!(
+                mapped.abs_diff(true_offset) <= frame_length,
+                "entry {} maps to {}, frame starts at {}",
+                k,
+                mapped,
+                true_offset
+            );
+        }
+    }
+
+    #[test]
+    fn test_toc_without_frames_is_linear() {
+        let toc = TocBuilder::new().toc(0);
+        assert_eq!(toc[0], 0);
+        assert_eq!(toc[50], 128);
+        assert_eq!(toc[99], 253);
+    }
+
+    #[test]
+    #[should_panic(expected = "frames must be recorded in order")]
+    fn test_record_out_of_order_panics() {
+        let mut builder = TocBuilder::new();
+        builder.record(1, 0);
+    }
+
+    #[test]
+    fn test_patch_reserved_frame() {
+        let stream = encode_tone(20);
+        let header = FrameHeader::parse(&stream, 0).unwrap();
+        let mut xing = XingFrame::reserve(header, false);
+
+        let mut output = Cursor::new(Vec::new());
+        output.write_all(&xing.to_bytes().unwrap()).unwrap();
+        let base = output.position();
+        output.write_all(&stream).unwrap();
+        let end = output.position();
+
+        let builder = TocBuilder::from_stream(&stream, base);
+        xing.update(&builder, end);
+        patch_xing(&mut output, 0, &xing).unwrap();
+        assert_eq!(output.position(), end);
+
+        let data = output.into_inner();
+        assert_eq!(&data[base as usize..], &stream[..]);
+        let frames: Vec<_> = iter_frames(&data).collect();
+        assert_eq!(frames.len(), builder.frames() + 1);
+
+        let parsed = XingFrame::parse(frames[0].data).expect("Info frame should parse");
+        assert_eq!(parsed, xing);
+        assert!(!parsed.vbr);
+        assert_eq!(parsed.frames as usize, builder.frames());
+        assert_eq!(parsed.bytes as u64, end);
+    }
+
+    #[test]
+    fn test_patch_after_leading_tag() {
+        let stream = encode_tone(20);
+        let header = FrameHeader::parse(&stream, 0).unwrap();
+        let mut xing = XingFrame::reserve(header, false);
+        let tag = padding_tag(1024);
+
+        let mut output = Cursor::new(Vec::new());
+        output.write_all(&tag).unwrap();
+        let offset = output.position();
+        output.write_all(&xing.to_bytes().unwrap()).unwrap();
+        output.write_all(&stream).unwrap();
+        let end = output.position();
+
+        // Offsets and size count from the Xing frame, not the tag
+        let builder = TocBuilder::from_stream(&stream, xing.len() as u64);
+        xing.update(&builder, end - offset);
+        patch_xing(&mut output, offset, &xing).unwrap();
+        assert_eq!(output.position(), end);
+
+        let data = output.into_inner();
+        assert_eq!(&data[..tag.len()], &tag[..]);
+        let audio = &data[offset as usize..];
+        assert_eq!(&audio[xing.len()..], &stream[..]);
+        let parsed = XingFrame::parse(audio).expect("Info frame should parse");
+        assert_eq!(parsed, xing);
+        assert_eq!(iter_frames(audio).count(), builder.frames() + 1);
+    }
+
+    #[test]
+    fn test_frame_too_short_for_xing() {
+        let config = Mp3EncoderConfig::new()
+            .sample_rate(8000)
+            .bitrate(8)
+            .channels(1)
+            .stereo_mode(StereoMode::Mono);
+        let stream = encode_pcm_to_mp3(config, &vec![0i16; 576 * 4]).unwrap();
+        let header = FrameHeader::parse(&stream, 0).unwrap();
+
+        let xing = XingFrame::reserve(header, true);
+        assert!(xing.to_bytes().is_err());
+        let mut output = Cursor::new(vec![0u8; xing.len()]);
+        assert!(patch_xing(&mut output, 0, &xing).is_err());
+    }
+}
@@ -79,13 +79,13 @@ def benchmark_rust(audio_file, bitrate, output_file):
     
     try:
         # 不使用 -q 参数，这样可以读取输出信息
-        cmd = [str(rust_exe), "-b", str(bitrate), audio_file, output_file]
+        cmd = [str(rust_exe), "-f", "-b", str(bitrate), audio_file, output_file]
         
         result = subprocess.run(cmd, capture_output=True, text=True, timeout=60)
         
         if result.returncode == 0:
             # 从Rust输出解析实际倍率
-            rust_reported_ratio = parse_realtime_ratio(result.stdout)
+            rust_reported_ratio = parse_realtime_ratio(result.stdout + result.stderr)
             
             # Clean up output file
             if os.path.exists(output_file):
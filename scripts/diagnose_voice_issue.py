@@ -27,7 +27,7 @@ def run_encoder(encoder_type, input_file, output_file, frame_limit=None):
     input_path = audio_dir / input_file
     
     if encoder_type == "rust":
-        cmd = ["cargo", "run", "--", str(input_path), output_file]
+        cmd = ["cargo", "run", "--", "-f", str(input_path), output_file]
         env = os.environ.copy()
         if frame_limit:
             env["RUST_MP3_MAX_FRAMES"] = str(frame_limit)
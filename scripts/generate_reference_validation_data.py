@@ -31,7 +31,7 @@ def run_shine_encoder(input_file, output_file):
     if not rust_exe.exists():
         raise FileNotFoundError(f"Rust encoder not found. Run 'cargo build' first.")
     
-    cmd = ["cargo", "run", "--", str(input_file), str(output_file)]
+    cmd = ["cargo", "run", "--", "-f", str(input_file), str(output_file)]
     result = subprocess.run(cmd, capture_output=True, text=True)
     
     if result.returncode != 0:
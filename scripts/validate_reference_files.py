@@ -78,7 +78,7 @@ class ReferenceFileValidator:
         if not input_path.exists():
             return False, f"Input file not found: {input_path}"
         
-        cmd = ["cargo", "run", "--", str(input_path), output_file]
+        cmd = ["cargo", "run", "--", "-f", str(input_path), output_file]
         
         # Set up environment
         env = os.environ.copy()
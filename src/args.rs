@@ -0,0 +1,400 @@
+//! Command line argument parsing for `shineenc`
+//!
+//! Every short option of the original shine encoder is kept and has a long
+//! form. Short options can be bundled (`-qj`), values can be attached
+//! (`-b128`, `--bitrate=192`) or given as the next argument, and `--` ends
+//! the options. Options may appear before or after the file names.
+
+use crate::util::parse_time;
+use shine_rs::{MonoSource, StereoMode};
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Bitrates accepted by shine, in kbps
+const SUPPORTED_BITRATES: [i32; 17] = [
+    8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+
+/// A recognised option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opt {
+    Help,
+    Bitrate,
+    Mono,
+    Copyright,
+    JointStereo,
+    MsThreshold,
+    DualChannel,
+    Force,
+    Quiet,
+    Verbose,
+    Start,
+    Duration,
+    ReplayGain,
+    Info,
+    Json,
+    Threads,
+    StrictInput,
+    Channel,
+}
+
+/// Short letter, long name and whether the option takes a value
+const OPTIONS: [(Option<char>, &str, bool, Opt); 18] = [
+    (Some('h'), "help", false, Opt::Help),
+    (Some('b'), "bitrate", true, Opt::Bitrate),
+    (Some('m'), "mono", false, Opt::Mono),
+    (None, "channel", true, Opt::Channel),
+    (Some('c'), "copyright", false, Opt::Copyright),
+    (Some('j'), "joint-stereo", false, Opt::JointStereo),
+    (None, "ms-threshold", true, Opt::MsThreshold),
+    (Some('d'), "dual-channel", false, Opt::DualChannel),
+    (Some('f'), "force", false, Opt::Force),
+    (Some('q'), "quiet", false, Opt::Quiet),
+    (Some('v'), "verbose", false, Opt::Verbose),
+    (None, "start", true, Opt::Start),
+    (None, "duration", true, Opt::Duration),
+    (None, "replaygain", false, Opt::ReplayGain),
+    (None, "info", false, Opt::Info),
+    (None, "json", false, Opt::Json),
+    (None, "threads", true, Opt::Threads),
+    (None, "strict-input", false, Opt::StrictInput),
+];
+
+/// Error type for argument parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    /// Help was requested with -h/--help
+    Help,
+    /// The input file is missing
+    MissingInput,
+    /// Option not recognised, with the closest valid option if there is one
+    UnknownOption {
+        option: String,
+        suggestion: Option<String>,
+    },
+    /// Option requires a value but none was given
+    MissingValue(String),
+    /// Value attached to an option that does not take one
+    UnexpectedValue(String),
+    /// Option value could not be used
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: String,
+    },
+    /// More file names than the mode accepts
+    UnexpectedArgument(String),
+    /// No output name could be derived from the input
+    InvalidOutput(String),
+}
+
+impl ArgsError {
+    /// True if the usage text should be shown instead of a message
+    pub fn shows_usage(&self) -> bool {
+        matches!(self, ArgsError::Help | ArgsError::MissingInput)
+    }
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "help requested"),
+            ArgsError::MissingInput => write!(f, "missing input file"),
+            ArgsError::UnknownOption {
+                option,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unknown option: {} (did you mean {}?)",
+                option, suggestion
+            ),
+            ArgsError::UnknownOption { option, .. } => write!(f, "Unknown option: {}", option),
+            ArgsError::MissingValue(option) => write!(f, "Option {} requires a value", option),
+            ArgsError::UnexpectedValue(option) => {
+                write!(f, "Option {} does not take a value", option)
+            }
+            ArgsError::InvalidValue {
+                option,
+                value,
+                reason,
+            } => write!(f, "Invalid value {} for {}: {}", value, option, reason),
+            ArgsError::UnexpectedArgument(arg) => write!(f, "Unexpected argument: {}", arg),
+            ArgsError::InvalidOutput(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// Command line arguments structure
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub input_file: String,
+    /// Output path, derived from the input when it was not given
+    pub output_file: String,
+    pub bitrate: i32,
+    pub stereo_mode: StereoMode,
+    /// Channel similarity above which joint stereo frames use mid/side
+    pub ms_threshold: Option<f32>,
+    pub force_mono: bool,
+    /// Channel of stereo input that is encoded with --mono
+    pub mono_source: MonoSource,
+    pub copyright: bool,
+    /// Overwrite an existing output file
+    pub force: bool,
+    pub quiet: bool,
+    pub verbose: bool,
+    pub start: Option<Duration>,
+    pub duration: Option<Duration>,
+    /// Analyse the loudness and write a ReplayGain tag
+    pub replaygain: bool,
+    pub info: bool,
+    pub json: bool,
+    /// Worker threads for encoding, 0 for one per core
+    pub threads: usize,
+    /// Reject WAV input whose header sizes disagree with the file
+    pub strict_input: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            input_file: String::new(),
+            output_file: String::new(),
+            bitrate: 128,
+            stereo_mode: StereoMode::Stereo,
+            ms_threshold: None,
+            force_mono: false,
+            mono_source: MonoSource::Mix,
+            copyright: false,
+            force: false,
+            quiet: false,
+            verbose: false,
+            start: None,
+            duration: None,
+            replaygain: false,
+            info: false,
+            json: false,
+            threads: 1,
+            strict_input: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parse the process command line
+    pub fn parse() -> Result<Self, ArgsError> {
+        let args: Vec<String> = env::args().collect();
+        Self::parse_from(&args)
+    }
+
+    /// Parse command line arguments
+    ///
+    /// `args[0]` is the program name. A lone "-" is a file name (standard
+    /// input or output), not an option.
+    pub fn parse_from(args: &[String]) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+        let mut files = Vec::new();
+        let mut rest = args.iter().skip(1);
+        let mut options_done = false;
+
+        while let Some(arg) = rest.next() {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                files.push(arg.clone());
+            } else if arg == "--" {
+                options_done = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                let (name, attached) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let (_, _, takes_value, opt) = OPTIONS
+                    .iter()
+                    .find(|(_, long_name, _, _)| *long_name == name)
+                    .copied()
+                    .ok_or_else(|| ArgsError::UnknownOption {
+                        option: format!("--{}", name),
+                        suggestion: suggest_option(name),
+                    })?;
+
+                let option = format!("--{}", name);
+                let value = match (takes_value, attached) {
+                    (true, Some(value)) => Some(value),
+                    (true, None) => Some(
+                        rest.next()
+                            .cloned()
+                            .ok_or_else(|| ArgsError::MissingValue(option.clone()))?,
+                    ),
+                    (false, Some(_)) => return Err(ArgsError::UnexpectedValue(option)),
+                    (false, None) => None,
+                };
+                parsed.apply(opt, &option, value)?;
+            } else {
+                // Bundle of short options; a value option takes the rest of
+                // the bundle or the next argument
+                let letters = &arg[1..];
+                for (index, letter) in letters.char_indices() {
+                    let (_, _, takes_value, opt) = OPTIONS
+                        .iter()
+                        .find(|(short, _, _, _)| *short == Some(letter))
+                        .copied()
+                        .ok_or_else(|| ArgsError::UnknownOption {
+                            option: format!("-{}", letter),
+                            suggestion: suggest_option(&letters[index..]),
+                        })?;
+
+                    let option = format!("-{}", letter);
+                    if !takes_value {
+                        parsed.apply(opt, &option, None)?;
+                        continue;
+                    }
+
+                    let attached = &letters[index + letter.len_utf8()..];
+                    let value = if attached.is_empty() {
+                        rest.next()
+                            .cloned()
+                            .ok_or_else(|| ArgsError::MissingValue(option.clone()))?
+                    } else {
+                        attached.to_string()
+                    };
+                    parsed.apply(opt, &option, Some(value))?;
+                    break;
+                }
+            }
+        }
+
+        // --info takes a single file and no output
+        let max_files = if parsed.info { 1 } else { 2 };
+        if let Some(extra) = files.get(max_files) {
+            return Err(ArgsError::UnexpectedArgument(extra.clone()));
+        }
+        let mut files = files.into_iter();
+        parsed.input_file = files.next().ok_or(ArgsError::MissingInput)?;
+        if !parsed.info {
+            parsed.output_file = match files.next() {
+                Some(output) => output,
+                None => derive_output_file(&parsed.input_file)?,
+            };
+        }
+
+        Ok(parsed)
+    }
+
+    /// Record one option and its value
+    fn apply(&mut self, opt: Opt, option: &str, value: Option<String>) -> Result<(), ArgsError> {
+        let value = value.unwrap_or_default();
+        let invalid = |reason: String| ArgsError::InvalidValue {
+            option: option.to_string(),
+            value: value.clone(),
+            reason,
+        };
+
+        match opt {
+            Opt::Help => return Err(ArgsError::Help),
+            Opt::Bitrate => {
+                let bitrate = value
+                    .parse::<i32>()
+                    .map_err(|_| invalid("not a number".to_string()))?;
+                // Matches shine's supported bitrates
+                if !SUPPORTED_BITRATES.contains(&bitrate) {
+                    return Err(invalid("supported bitrates are 8-320 kbps".to_string()));
+                }
+                self.bitrate = bitrate;
+            }
+            Opt::Mono => self.force_mono = true,
+            Opt::Channel => {
+                self.mono_source = match value.as_str() {
+                    "left" => MonoSource::Left,
+                    "right" => MonoSource::Right,
+                    "mix" => MonoSource::Mix,
+                    _ => return Err(invalid("expected left, right or mix".to_string())),
+                };
+            }
+            Opt::Copyright => self.copyright = true,
+            Opt::JointStereo => self.stereo_mode = StereoMode::JointStereo,
+            Opt::MsThreshold => {
+                let threshold = value
+                    .parse::<f32>()
+                    .map_err(|_| invalid("not a number".to_string()))?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    return Err(invalid("expected a value between 0 and 1".to_string()));
+                }
+                self.ms_threshold = Some(threshold);
+            }
+            Opt::DualChannel => self.stereo_mode = StereoMode::DualChannel,
+            Opt::Force => self.force = true,
+            Opt::Quiet => {
+                self.quiet = true;
+                self.verbose = false;
+            }
+            Opt::Verbose => {
+                self.verbose = true;
+                self.quiet = false;
+            }
+            Opt::Start | Opt::Duration => {
+                let time = parse_time(&value).map_err(|e| invalid(e.to_string()))?;
+                if opt == Opt::Start {
+                    self.start = Some(time);
+                } else {
+                    self.duration = Some(time);
+                }
+            }
+            Opt::ReplayGain => self.replaygain = true,
+            Opt::Info => self.info = true,
+            Opt::Json => self.json = true,
+            Opt::StrictInput => self.strict_input = true,
+            Opt::Threads => {
+                self.threads = value
+                    .parse::<usize>()
+                    .map_err(|_| invalid("not a number".to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Closest long option to a misspelt name, if any is close enough
+fn suggest_option(name: &str) -> Option<String> {
+    OPTIONS
+        .iter()
+        .map(|(_, long, _, _)| (edit_distance(name, long), *long))
+        .filter(|(distance, long)| *distance <= 2.max(long.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, long)| format!("--{}", long))
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Output path for an input given without one: the input with a .mp3 extension
+pub fn derive_output_file(input_file: &str) -> Result<String, ArgsError> {
+    if input_file == "-" {
+        return Err(ArgsError::InvalidOutput(
+            "An output file is required when reading from standard input".to_string(),
+        ));
+    }
+
+    let output = Path::new(input_file).with_extension("mp3");
+    if output == Path::new(input_file) {
+        return Err(ArgsError::InvalidOutput(format!(
+            "Cannot derive an output name from {}: it would overwrite the input",
+            input_file
+        )));
+    }
+    Ok(output.to_string_lossy().into_owned())
+}
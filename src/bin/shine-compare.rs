@@ -0,0 +1,238 @@
+//! Frame-by-frame comparison of two MP3 files
+//!
+//! Walks both streams with the shine-rs frame parser and reports where they
+//! first diverge: the byte offset, the frame index, the side information
+//! fields that differ and a hexdump of the differing region.
+//!
+//! Exits with 0 only when both files are byte-identical, 1 when they differ
+//! and 2 on usage or I/O errors.
+
+use anyhow::{Context, Result};
+use shine_rs::frame::{iter_frames, Frame, FrameHeader, GranuleSideInfo, SideInfo};
+use std::env;
+use std::fs;
+use std::process;
+
+/// Bytes shown per hexdump row
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Hexdump rows shown around the first difference
+const HEXDUMP_ROWS: usize = 4;
+
+fn print_usage() {
+    eprintln!("Usage: shine-compare <reference.mp3> <candidate.mp3>");
+    eprintln!();
+    eprintln!("Exits with 0 when both files are byte-identical, 1 when they differ.");
+}
+
+/// Index of the first differing byte, or the length of the shorter input
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(position) => Some(position),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+fn diff_headers(a: &FrameHeader, b: &FrameHeader) -> Vec<String> {
+    let mut diffs = Vec::new();
+    let mut field = |name: &str, x: String, y: String| {
+        if x != y {
+            diffs.push(format!("  header.{:<16} {:>8} | {}", name, x, y));
+        }
+    };
+    field("version", a.version_name().into(), b.version_name().into());
+    field(
+        "protected",
+        a.protected.to_string(),
+        b.protected.to_string(),
+    );
+    field("bitrate", a.bitrate().to_string(), b.bitrate().to_string());
+    field(
+        "sample_rate",
+        a.sample_rate().to_string(),
+        b.sample_rate().to_string(),
+    );
+    field("padding", a.padding.to_string(), b.padding.to_string());
+    field("private", a.private.to_string(), b.private.to_string());
+    field("mode", a.mode.to_string(), b.mode.to_string());
+    field("mode_ext", a.mode_ext.to_string(), b.mode_ext.to_string());
+    field(
+        "copyright",
+        a.copyright.to_string(),
+        b.copyright.to_string(),
+    );
+    field("original", a.original.to_string(), b.original.to_string());
+    field("emphasis", a.emphasis.to_string(), b.emphasis.to_string());
+    diffs
+}
+
+fn diff_granule(gr: usize, ch: usize, a: &GranuleSideInfo, b: &GranuleSideInfo) -> Vec<String> {
+    let mut diffs = Vec::new();
+    let mut field = |name: &str, x: u32, y: u32| {
+        if x != y {
+            diffs.push(format!("  gr{} ch{} {:<16} {:>8} | {}", gr, ch, name, x, y));
+        }
+    };
+    field("part2_3_length", a.part2_3_length, b.part2_3_length);
+    field("big_values", a.big_values, b.big_values);
+    field("global_gain", a.global_gain, b.global_gain);
+    field(
+        "scalefac_compress",
+        a.scalefac_compress,
+        b.scalefac_compress,
+    );
+    for (region, (&x, &y)) in a.table_select.iter().zip(&b.table_select).enumerate() {
+        field(&format!("table_select[{}]", region), x, y);
+    }
+    field("region0_count", a.region0_count, b.region0_count);
+    field("region1_count", a.region1_count, b.region1_count);
+    field("count1table", a.count1table_select, b.count1table_select);
+    diffs
+}
+
+fn diff_side_info(header: &FrameHeader, a: &SideInfo, b: &SideInfo) -> Vec<String> {
+    let mut diffs = Vec::new();
+    if a.main_data_begin != b.main_data_begin {
+        diffs.push(format!(
+            "  main_data_begin        {:>8} | {}",
+            a.main_data_begin, b.main_data_begin
+        ));
+    }
+    for gr in 0..header.granules() {
+        for ch in 0..header.channels() {
+            diffs.extend(diff_granule(gr, ch, &a.gr[gr][ch], &b.gr[gr][ch]));
+        }
+    }
+    diffs
+}
+
+fn hexdump_row(data: &[u8], start: usize, other: &[u8]) -> String {
+    (start..start + HEXDUMP_WIDTH)
+        .map(|i| match (data.get(i), other.get(i)) {
+            (Some(x), Some(y)) if x == y => format!(" {:02x}", x),
+            (Some(x), _) => format!("*{:02x}", x),
+            (None, _) => "   ".to_string(),
+        })
+        .collect()
+}
+
+/// Print both inputs side by side around `offset`, marking differing bytes with `*`
+fn print_hexdump(a: &[u8], b: &[u8], offset: usize) {
+    let first_row = offset / HEXDUMP_WIDTH * HEXDUMP_WIDTH;
+    let end = a.len().max(b.len());
+    println!("Hexdump (differing bytes marked with *):");
+    for row in (first_row..end).step_by(HEXDUMP_WIDTH).take(HEXDUMP_ROWS) {
+        println!("  {:08x} a:{}", row, hexdump_row(a, row, b));
+        println!("  {:8} b:{}", "", hexdump_row(b, row, a));
+    }
+}
+
+fn describe_frame(frame: &Frame) -> String {
+    format!(
+        "{} {} kbps {} Hz, {} bytes",
+        frame.header.version_name(),
+        frame.header.bitrate(),
+        frame.header.sample_rate(),
+        frame.data.len()
+    )
+}
+
+/// Compare two streams, returning true when they are byte-identical
+fn compare(a: &[u8], b: &[u8]) -> bool {
+    let frames_a: Vec<Frame> = iter_frames(a).collect();
+    let frames_b: Vec<Frame> = iter_frames(b).collect();
+
+    let offset = match first_difference(a, b) {
+        None => {
+            println!(
+                "Files are identical ({} frames, {} bytes)",
+                frames_a.len(),
+                a.len()
+            );
+            return true;
+        }
+        Some(offset) => offset,
+    };
+
+    println!("Files differ at byte offset {} (0x{:x})", offset, offset);
+    println!("Sizes: {} | {} bytes", a.len(), b.len());
+    println!("Frames: {} | {}", frames_a.len(), frames_b.len());
+
+    let containing =
+        |frames: &[Frame]| frames.iter().position(|f| offset < f.offset + f.data.len());
+    match (containing(&frames_a), containing(&frames_b)) {
+        (Some(i), Some(j)) if i == j && frames_a[i].offset == frames_b[j].offset => {
+            let (fa, fb) = (&frames_a[i], &frames_b[j]);
+            println!(
+                "First differing frame: #{} at offset {} (byte {} of the frame)",
+                i,
+                fa.offset,
+                offset - fa.offset
+            );
+            println!("  a: {}", describe_frame(fa));
+            println!("  b: {}", describe_frame(fb));
+
+            let mut diffs = diff_headers(&fa.header, &fb.header);
+            let same_layout = fa.header.side_info_len() == fb.header.side_info_len()
+                && fa.header.version == fb.header.version;
+            if same_layout {
+                match (fa.side_info(), fb.side_info()) {
+                    (Ok(sa), Ok(sb)) => diffs.extend(diff_side_info(&fa.header, &sa, &sb)),
+                    (Err(err), _) | (_, Err(err)) => println!("  side info unreadable: {}", err),
+                }
+            }
+            if diffs.is_empty() {
+                println!("Header and side info identical, difference is in the main data");
+            } else {
+                println!("Field differences (a | b):");
+                for line in diffs {
+                    println!("{}", line);
+                }
+            }
+        }
+        (Some(i), None) => println!("Frame #{} of a has no counterpart in b", i),
+        (None, Some(j)) => println!("Frame #{} of b has no counterpart in a", j),
+        (Some(i), Some(j)) => println!(
+            "Frame boundaries diverged: frame #{} of a at offset {}, frame #{} of b at offset {}",
+            i, frames_a[i].offset, j, frames_b[j].offset
+        ),
+        (None, None) => println!("Difference is outside of any parsed frame"),
+    }
+
+    for (name, data) in [("a", a), ("b", b)] {
+        let mut frames = iter_frames(data);
+        frames.by_ref().for_each(drop);
+        if let Some(err) = frames.error() {
+            println!("Parsing of {} stopped early: {}", name, err);
+        }
+    }
+
+    print_hexdump(a, b, offset);
+    false
+}
+
+fn run(paths: &[String]) -> Result<bool> {
+    let a = fs::read(&paths[0]).with_context(|| format!("failed to read {}", paths[0]))?;
+    let b = fs::read(&paths[1]).with_context(|| format!("failed to read {}", paths[1]))?;
+    println!("a: {}", paths[0]);
+    println!("b: {}", paths[1]);
+    Ok(compare(&a, &b))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 {
+        print_usage();
+        process::exit(2);
+    }
+
+    match run(&args) {
+        Ok(true) => process::exit(0),
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,530 @@
+//! Command line front end of the `shineenc` tool
+//!
+//! Usage text and the WAV to MP3 conversion driven by the binary; the
+//! arguments are parsed by the `args` module. Only the MP3 stream (or the
+//! `--info` report) is written to stdout; all other messages go to stderr.
+
+use crate::args::Args;
+use crate::info::wav_length_mismatches;
+use crate::input::PcmInput;
+use shine_rs::constants::GRANULE_SIZE;
+use shine_rs::encoder::{shine_samples_per_pass, MPEG_I};
+use shine_rs::frame::iter_frames;
+use shine_rs::id3::{patch_replaygain_tag, replaygain_tag};
+use shine_rs::parallel::{encode_pcm_parallel, resolve_threads};
+use shine_rs::pcm::{ClipDetector, PcmAnalyzer};
+use shine_rs::replaygain::ReplayGainAnalyzer;
+use shine_rs::{
+    shine_close, shine_encode_buffer_interleaved_safe, shine_flush, shine_initialise,
+    supported_bitrates_for, MonoSource, Mp3EncoderConfig,
+};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Print usage information (matches shine's usage format)
+pub fn print_usage() {
+    eprintln!("Usage: shineenc [options] <infile> [outfile]");
+    eprintln!("       shineenc --info [--json] <file>");
+    eprintln!();
+    eprintln!("Use \"-\" for standard input or output.");
+    eprintln!("Without <outfile> the output is <infile> with a .mp3 extension.");
+    eprintln!();
+    eprintln!("Options (short options can be bundled, e.g. -qj; values can be attached,");
+    eprintln!("e.g. -b128 or --bitrate=128; \"--\" ends the options):");
+    eprintln!(" -h, --help              this help message");
+    eprintln!(" -b, --bitrate <bitrate> set the bitrate [8-320], default 128kbit");
+    eprintln!(" -m, --mono              force encoder to operate in mono");
+    eprintln!("     --channel <c>       with --mono, encode the left, right or mix (default)");
+    eprintln!("                         of stereo input");
+    eprintln!(" -c, --copyright         set copyright flag, default off");
+    eprintln!(" -j, --joint-stereo      encode in joint stereo (stereo data only)");
+    eprintln!("     --ms-threshold <s>  with -j, code frames whose channel similarity");
+    eprintln!("                         reaches s [0-1] as mid/side (default off, as shine)");
+    eprintln!(" -d, --dual-channel      encode in dual-channel (stereo data only)");
+    eprintln!(" -f, --force             overwrite an existing output file");
+    eprintln!(" -q, --quiet             quiet mode");
+    eprintln!(" -v, --verbose           verbose mode");
+    eprintln!("     --start <t>         start encoding at time t (seconds or hh:mm:ss.mmm)");
+    eprintln!("     --duration <t>      encode only t of audio (seconds or hh:mm:ss.mmm)");
+    eprintln!("     --replaygain        analyse loudness and write a ReplayGain ID3 tag");
+    eprintln!("     --threads <n>       encode segments on n threads, 0 for one per core");
+    eprintln!("                         (default 1, identical to the serial encoder)");
+    eprintln!(
+        "     --strict-input      reject WAV input whose header sizes disagree with the file"
+    );
+    eprintln!("     --info              print WAV/MP3 stream details of <infile> and exit");
+    eprintln!("     --json              with --info, print the details as JSON");
+}
+
+/// Print program name (matches shine's output)
+pub fn print_name() {
+    eprintln!("shineenc (Rust version)");
+}
+
+/// Convert WAV file to MP3
+///
+/// When the output file is "-" the MP3 stream is written to `stdout`; every
+/// human-readable message goes to stderr so it can never mix with the audio.
+pub fn convert_wav_to_mp3(
+    args: &Args,
+    stdout: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let quiet = args.quiet;
+
+    // Never truncate an existing file unless asked to
+    if args.output_file != "-" && !args.force && Path::new(&args.output_file).exists() {
+        return Err(format!(
+            "Output file {} already exists, use -f to overwrite it",
+            args.output_file
+        )
+        .into());
+    }
+
+    // Print header (matches shine output)
+    if !quiet {
+        print_name();
+    }
+
+    // A header that disagrees with the file is read up to what is there
+    for mismatch in wav_length_mismatches(&args.input_file) {
+        if args.strict_input {
+            return Err(format!("Invalid WAVE file: {}", mismatch).into());
+        }
+        eprintln!("Warning: {}", mismatch);
+    }
+
+    // Open WAV file (memory-mapped with the mmap feature)
+    let input = PcmInput::open(&args.input_file, args.start, args.duration)
+        .map_err(|e| format!("Could not open WAVE file: {}", e))?;
+
+    encode_input(args, &input, stdout)
+}
+
+/// Encode already opened PCM input to the output named in `args`
+///
+/// `stdout` receives the MP3 stream when the output file is "-".
+pub fn encode_input(
+    args: &Args,
+    input: &PcmInput,
+    stdout: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let quiet = args.quiet;
+    let sample_rate = input.sample_rate;
+    let channels = input.channels;
+
+    // Calculate duration (high precision floating point calculation)
+    let data_chunk_length = input.len() * 2; // Convert samples to bytes (16-bit = 2 bytes per sample)
+    let byte_rate = sample_rate * channels as u32 * 2; // fmt_chunk.byte_rate
+    let duration = data_chunk_length as f64 / byte_rate as f64; // High precision calculation
+
+    // The requested range was cut short by the end of the file
+    if let Some(requested) = args.duration {
+        if duration < requested.as_secs_f64() - 0.5 / sample_rate as f64 {
+            eprintln!(
+                "Warning: requested duration {:.3}s extends past the end of the input, encoding {:.3}s",
+                requested.as_secs_f64(),
+                duration
+            );
+        }
+    }
+
+    // Print WAV info (matches shine format - this happens in wave_open)
+    if !quiet {
+        let channel_str = if channels == 1 { "mono" } else { "stereo" };
+        eprintln!(
+            "WAVE PCM Data, {} {}Hz 16bit, duration: {:02}:{:02}:{:02}",
+            channel_str,
+            sample_rate,
+            (duration as u32) / 3600,
+            ((duration as u32) % 3600) / 60,
+            (duration as u32) % 60
+        );
+    }
+
+    // MPEG-2 and MPEG-2.5 stop short of the MPEG-1 bitrates: a bitrate the
+    // sample rate does not have, such as the default for 8 kHz telephony,
+    // becomes the highest one whose granules fit in 4095 bits per channel
+    let mut bitrate = args.bitrate as u32;
+    let bitrates = supported_bitrates_for(sample_rate);
+    let encoded_channels = if args.force_mono { 1 } else { channels as u32 };
+    let max = bitrates
+        .iter()
+        .copied()
+        .filter(|&kbps| kbps * 1000 * GRANULE_SIZE as u32 / sample_rate / encoded_channels <= 4095)
+        .max();
+    if let Some(max) = max {
+        if bitrate > max && !bitrates.contains(&bitrate) {
+            eprintln!(
+                "Warning: {} kbps is not available at {} Hz, encoding at {} kbps",
+                bitrate, sample_rate, max
+            );
+            bitrate = max;
+        }
+    }
+
+    // Create encoder configuration (mono input forces mono mode, as in shine)
+    let input_channels = if args.force_mono { 1 } else { channels };
+    let mut encoder_config =
+        Mp3EncoderConfig::for_input(sample_rate, input_channels, bitrate, args.stereo_mode)?;
+    // There is no option for the original flag: shine always sets it
+    encoder_config.copyright = args.copyright;
+    encoder_config.original = true;
+    encoder_config.mono_source = args.mono_source;
+    encoder_config.ms_threshold = args.ms_threshold;
+    let config = encoder_config.to_shine_config();
+
+    // A mono configuration cannot consume interleaved stereo data: average
+    // L/R or take the channel chosen with --channel first
+    let encoder_channels = encoder_config.channels as u16;
+    let mut samples = input.samples();
+    let mut total_samples = input.len();
+    let mono_source = encoder_config.mono_source;
+    if encoder_channels < channels {
+        log::info!(
+            "Downmixing {}-channel input to mono for the mono encoder configuration ({:?})",
+            channels,
+            mono_source
+        );
+        let mut stereo = samples;
+        samples = Box::new(std::iter::from_fn(move || {
+            let (left, right) = (stereo.next()?, stereo.next()?);
+            Some(match mono_source {
+                MonoSource::Left => left,
+                MonoSource::Right => right,
+                // Same average as shine_rs::pcm::downmix_to_mono
+                MonoSource::Mix => ((left as i32 + right as i32) / 2) as i16,
+            })
+        }));
+        total_samples /= 2;
+    } else if mono_source != MonoSource::Mix {
+        eprintln!("Warning: --channel only applies when stereo input is encoded with --mono");
+    }
+    if encoder_config.ms_threshold.is_some() && encoder_config.active_ms_threshold().is_none() {
+        eprintln!("Warning: --ms-threshold only applies with --joint-stereo");
+    }
+
+    let mut encoder = shine_initialise(&config)?;
+    encoder.ms_threshold = encoder_config.active_ms_threshold();
+
+    // Print some info about the file about to be created (matches shine's check_config)
+    if !quiet {
+        let version_names = ["2.5", "reserved", "II", "I"];
+        let mode_names = ["stereo", "joint-stereo", "dual-channel", "mono"];
+        let demp_names = ["none", "50/15us", "", "CITT"];
+
+        let version = encoder_config.mpeg_version().unwrap_or(MPEG_I);
+        eprintln!(
+            "MPEG-{} layer III, {}  Psychoacoustic Model: Shine",
+            version_names[version as usize], mode_names[config.mpeg.mode as usize]
+        );
+        eprintln!(
+            "Bitrate: {} kbps  De-emphasis: {}   {} {}",
+            config.mpeg.bitr,
+            demp_names[config.mpeg.emph as usize],
+            if config.mpeg.original != 0 {
+                "Original"
+            } else {
+                ""
+            },
+            if config.mpeg.copyright != 0 {
+                "(C)"
+            } else {
+                ""
+            }
+        );
+        eprintln!(
+            "Encoding \"{}\" to \"{}\"",
+            args.input_file, args.output_file
+        );
+    }
+
+    let start_time = std::time::Instant::now();
+
+    // Open output file (matches shine's file handling)
+    let mut file = None;
+    let output_file: &mut dyn Write = if args.output_file == "-" {
+        stdout
+    } else {
+        file.insert(File::create(&args.output_file)?)
+    };
+
+    // The tag is rewritten once the whole input has been analysed, which
+    // needs a seekable output
+    let mut replaygain = None;
+    if args.replaygain {
+        replaygain = ReplayGainAnalyzer::new(sample_rate, encoder_channels as usize);
+        if args.output_file != "-" {
+            output_file.write_all(&replaygain_tag(None))?;
+        } else {
+            eprintln!("Warning: ReplayGain tag is not written to standard output");
+        }
+    }
+
+    // Samples per frame and channel: 1152 for MPEG-1, 576 for MPEG-2 and 2.5
+    let samples_per_frame = shine_samples_per_pass(&encoder) as usize;
+    let frame_size = samples_per_frame * encoder_channels as usize;
+    let mut header_bytes = Vec::new();
+    let mut clipping = ClipDetector::new(encoder_channels as usize);
+    let mut levels = PcmAnalyzer::new(encoder_channels as usize, sample_rate);
+
+    if args.verbose {
+        eprintln!();
+        eprintln!("=== Verbose Mode: Frame-by-Frame Encoding Details ===");
+        eprintln!("Format: [Frame #] PCM samples, MP3 bytes @ hex offset, CRC32 checksum");
+        eprintln!(
+            "-------------------------------------------------------------------------------"
+        );
+    }
+
+    // Segment-parallel encoding needs the whole input in memory; --threads 1
+    // keeps the frame-by-frame shine path below
+    let threads = resolve_threads(args.threads);
+    let (frame_count, mp3_len) = if threads > 1 {
+        let pcm: Vec<i16> = samples.by_ref().take(total_samples).collect();
+        for chunk in pcm.chunks(frame_size) {
+            clipping.update_interleaved(chunk);
+            levels.feed(chunk);
+            if let Some(analyzer) = replaygain.as_mut() {
+                analyzer.update_interleaved(chunk);
+            }
+        }
+        let mp3_data = encode_pcm_parallel(encoder_config.clone(), &pcm, threads)?;
+
+        // Frames come back joined in input order, so they are listed in order
+        let mut frames = iter_frames(&mp3_data);
+        for frame in frames.by_ref() {
+            if args.verbose {
+                let pcm_start = frame.index * frame.header.samples_per_frame();
+                eprintln!(
+                    "[Frame {}] PCM {}-{}, MP3 {} bytes @ 0x{:04X}-0x{:04X}, CRC32: 0x{:08X}",
+                    frame.index + 1,
+                    pcm_start,
+                    pcm_start + frame.header.samples_per_frame() - 1,
+                    frame.data.len(),
+                    frame.offset,
+                    frame.offset + frame.data.len() - 1,
+                    crc32fast::hash(frame.data)
+                );
+            }
+            if header_bytes.is_empty() {
+                header_bytes.extend_from_slice(&frame.data[..4]);
+            }
+        }
+        if args.verbose {
+            eprintln!(
+                "-------------------------------------------------------------------------------"
+            );
+            // The end of the stream is not padded to a whole frame
+            let rest = &mp3_data[frames.offset()..];
+            if !rest.is_empty() {
+                eprintln!(
+                    "[Flush] MP3 {} bytes @ 0x{:04X}-0x{:04X}, CRC32: 0x{:08X}",
+                    rest.len(),
+                    frames.offset(),
+                    mp3_data.len() - 1,
+                    crc32fast::hash(rest)
+                );
+            }
+        }
+
+        output_file.write_all(&mp3_data)?;
+        (total_samples.div_ceil(frame_size), mp3_data.len())
+    } else {
+        // Process complete frames
+        let mut frame_count = 0;
+        let mut mp3_offset = 0;
+        let mut processed_samples = 0;
+
+        // Process all data, including incomplete last frame (matches Shine behavior)
+        let mut frame_buffer = vec![0i16; frame_size];
+        while processed_samples < total_samples {
+            let remaining_samples = total_samples - processed_samples;
+            let current_frame_size = std::cmp::min(frame_size, remaining_samples);
+
+            // Fill the buffer for this frame, pad with zeros if incomplete (matches Shine)
+            frame_buffer.fill(0);
+            for (slot, sample) in frame_buffer[..current_frame_size]
+                .iter_mut()
+                .zip(samples.by_ref())
+            {
+                *slot = sample;
+            }
+            clipping.update_interleaved(&frame_buffer[..current_frame_size]);
+            levels.feed(&frame_buffer[..current_frame_size]);
+            if let Some(analyzer) = replaygain.as_mut() {
+                analyzer.update_interleaved(&frame_buffer[..current_frame_size]);
+            }
+
+            // Calculate PCM range (matches Shine's samples_per_pass calculation)
+            let pcm_start = frame_count * samples_per_frame;
+            let pcm_end = pcm_start + samples_per_frame - 1;
+
+            match shine_encode_buffer_interleaved_safe(&mut encoder, &frame_buffer) {
+                Ok((frame_data, written)) => {
+                    if written > 0 {
+                        // Calculate frame checksum (CRC32)
+                        let frame_checksum = crc32fast::hash(&frame_data[..written]);
+
+                        if args.verbose {
+                            eprintln!("[Frame {}] PCM {}-{}, MP3 {} bytes @ 0x{:04X}-0x{:04X}, CRC32: 0x{:08X}",
+                                     frame_count + 1,
+                                     pcm_start,
+                                     pcm_end,
+                                     written,
+                                     mp3_offset,
+                                     mp3_offset + written - 1,
+                                     frame_checksum);
+                        }
+
+                        output_file.write_all(&frame_data[..written])?;
+                        if header_bytes.len() < 4 {
+                            let needed = (4 - header_bytes.len()).min(written);
+                            header_bytes.extend_from_slice(&frame_data[..needed]);
+                        }
+                        mp3_offset += written;
+                    } else if args.verbose {
+                        eprintln!(
+                            "[Frame {}] PCM {}-{}, MP3 buffered",
+                            frame_count + 1,
+                            pcm_start,
+                            pcm_end
+                        );
+                    }
+
+                    frame_count += 1;
+                    processed_samples += current_frame_size;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if args.verbose {
+            eprintln!(
+                "-------------------------------------------------------------------------------"
+            );
+        }
+
+        // Flush any remaining data
+        let (final_data, final_written) = shine_flush(&mut encoder);
+        if final_written > 0 {
+            if args.verbose {
+                let final_checksum = crc32fast::hash(&final_data[..final_written]);
+                eprintln!(
+                    "[Flush] MP3 {} bytes @ 0x{:04X}-0x{:04X}, CRC32: 0x{:08X}",
+                    final_written,
+                    mp3_offset,
+                    mp3_offset + final_written - 1,
+                    final_checksum
+                );
+            }
+            output_file.write_all(&final_data[..final_written])?;
+            mp3_offset += final_written;
+        }
+        (frame_count, mp3_offset)
+    };
+
+    // Close encoder
+    shine_close(encoder);
+
+    let levels = levels.finish();
+    if levels.has_dc_offset() {
+        eprintln!(
+            "Warning: input has a DC offset of {:+.2}% of full scale, which costs bits without adding sound",
+            levels.dc_offset * 100.0
+        );
+    }
+
+    let gain = replaygain.as_ref().and_then(ReplayGainAnalyzer::result);
+    if let (Some(gain), Some(file)) = (gain, file.as_mut()) {
+        patch_replaygain_tag(file, &gain)?;
+    }
+
+    let elapsed = start_time.elapsed();
+    let realtime_factor = if elapsed.as_secs_f64() > 0.0 {
+        duration / elapsed.as_secs_f64()
+    } else {
+        f64::INFINITY
+    };
+
+    // Print completion message (matches shine format)
+    if !quiet {
+        if realtime_factor.is_infinite() {
+            eprintln!(
+                "Finished in {:02}:{:02}:{:02} (infx realtime)",
+                elapsed.as_secs() / 3600,
+                (elapsed.as_secs() % 3600) / 60,
+                elapsed.as_secs() % 60
+            );
+        } else {
+            eprintln!(
+                "Finished in {:02}:{:02}:{:02} ({:.1}x realtime)",
+                elapsed.as_secs() / 3600,
+                (elapsed.as_secs() % 3600) / 60,
+                elapsed.as_secs() % 60,
+                realtime_factor
+            );
+        }
+    }
+
+    if !quiet && args.replaygain {
+        match gain {
+            Some(gain) => eprintln!(
+                "ReplayGain: track gain {:+.2} dB, peak {:.6}",
+                gain.track_gain, gain.track_peak
+            ),
+            None => eprintln!("ReplayGain: input too short to analyse"),
+        }
+    }
+
+    if args.verbose {
+        eprintln!();
+        eprintln!("=== Additional Statistics ===");
+        eprintln!("Total frames encoded: {}", frame_count);
+        eprintln!("Total MP3 bytes: {} (hex: 0x{:04X})", mp3_len, mp3_len);
+        eprintln!(
+            "Average bytes per frame: {:.1}",
+            mp3_len as f64 / frame_count as f64
+        );
+        // Both paths zero-pad the last frame to a whole frame
+        let padded = (frame_count * frame_size - total_samples) / encoder_channels as usize;
+        if padded > 0 {
+            eprintln!("Final frame padded with {} samples of silence", padded);
+        }
+
+        // Level and clipping summary of the PCM fed to the encoder
+        eprintln!(
+            "Peak: {:.2} dBFS, RMS: {:.2} dBFS, DC offset: {:+.4}%",
+            levels.peak_dbfs,
+            levels.rms_dbfs,
+            levels.dc_offset * 100.0
+        );
+        eprintln!(
+            "Clipped samples: {} ({:.2}%), longest run: {} samples",
+            clipping.clipped_samples(),
+            clipping.clipped_ratio() * 100.0,
+            clipping.max_consecutive_clipped()
+        );
+
+        // Show first few bytes of MP3 data (header info)
+        if header_bytes.len() >= 4 {
+            eprintln!(
+                "MP3 header bytes: {:02X} {:02X} {:02X} {:02X} (at offset 0x0000)",
+                header_bytes[0], header_bytes[1], header_bytes[2], header_bytes[3]
+            );
+        }
+
+        // Calculate compression ratio (use data_chunk_length to match Shine's wave.length)
+        let input_size = data_chunk_length; // This matches wave.length in Shine
+        let compression_ratio = input_size as f64 / mp3_len as f64;
+        eprintln!("Input size:  {} bytes", input_size);
+        eprintln!("Output size: {} bytes", mp3_len);
+        eprintln!("Compression: {:.1}:1", compression_ratio);
+        eprintln!(
+            "Actual bitrate: {:.1} kbps",
+            (mp3_len as f64 * 8.0) / (duration * 1000.0)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+//! Stream inspection for the `--info` mode
+//!
+//! Reports the format of a WAV or MP3 file without constructing an encoder.
+//! WAV files are described from their RIFF chunks and, for the formats the
+//! encoder reads, the peak, RMS and DC offset of their samples; MP3 files by
+//! walking the frames with the library's frame parser.
+
+use crate::util::{walk_riff_chunks, UtilError, UtilResult};
+use serde_json::{json, Value};
+use shine_rs::bytes::{read_u16_le, read_u32_le};
+use shine_rs::frame::{iter_frames, FrameHeader, HEADER_SIZE};
+use shine_rs::pcm::{PcmAnalyzer, PcmStats};
+use shine_rs::wav::WavSource;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+/// WAVE format tags
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_ALAW: u16 = 0x0006;
+const WAVE_FORMAT_MULAW: u16 = 0x0007;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Size of an ID3v1 tag at the end of the file
+const ID3V1_SIZE: usize = 128;
+
+/// Details of a WAV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavInfo {
+    /// Sample format (PCM, IEEE float, ...)
+    pub format: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    /// Samples per channel in the data chunk, up to the end of the file
+    pub samples_per_channel: u64,
+    /// Duration in seconds
+    pub duration: f64,
+    /// Sample count of the `fact` chunk, if the file has one
+    pub fact_samples: Option<u64>,
+    /// Header sizes that disagree with the file
+    pub mismatches: Vec<LengthMismatch>,
+    /// Peak, RMS and DC offset of the samples; only measured by `probe_file`,
+    /// for formats the encoder reads
+    pub levels: Option<PcmStats>,
+}
+
+/// A WAV header size that disagrees with the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthMismatch {
+    /// The data chunk declares more samples than the file holds, as left by
+    /// a truncated upload or a streaming writer that never fixed its header
+    DataTruncated { declared: u64, available: u64 },
+    /// The `fact` chunk counts a different number of samples than the data
+    /// chunk holds
+    FactCount { fact: u64, data: u64 },
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthMismatch::DataTruncated {
+                declared,
+                available,
+            } => write!(
+                f,
+                "data chunk declares {} samples per channel but the file holds {}",
+                declared, available
+            ),
+            LengthMismatch::FactCount { fact, data } => write!(
+                f,
+                "fact chunk declares {} samples per channel but the data chunk holds {}",
+                fact, data
+            ),
+        }
+    }
+}
+
+/// Details of an MP3 file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mp3Info {
+    /// MPEG version name (MPEG-1, MPEG-2, MPEG-2.5)
+    pub version: String,
+    /// Channel mode of the first frame
+    pub mode: String,
+    pub channels: usize,
+    pub sample_rate: u32,
+    /// Number of complete audio frames (a Xing/Info frame is not counted)
+    pub frames: usize,
+    /// "CBR" when every frame has the same bitrate, "VBR" otherwise
+    pub bitrate_mode: String,
+    /// Average bitrate over the audio frames in kbps
+    pub bitrate: u32,
+    /// Duration in seconds
+    pub duration: f64,
+    /// First frame carries a Xing, Info or VBRI header
+    pub xing: bool,
+    pub id3v2: bool,
+    pub id3v1: bool,
+    /// Bytes after the last complete frame that do not form a frame
+    pub trailing_bytes: usize,
+}
+
+/// Details of an inspected file
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamInfo {
+    Wav(WavInfo),
+    Mp3(Mp3Info),
+}
+
+/// Inspect a WAV or MP3 file, detected from its contents
+pub fn probe_file(path: &str) -> UtilResult<StreamInfo> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 12];
+    let read = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if read == 12 && matches!(&magic[0..4], b"RIFF" | b"RF64") && &magic[8..12] == b"WAVE" {
+        let mut info = probe_wav(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        info.levels = measure_levels(file).ok();
+        Ok(StreamInfo::Wav(info))
+    } else {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        probe_mp3(&data).map(StreamInfo::Mp3)
+    }
+}
+
+/// Peak, RMS and DC offset of the samples of a WAV stream, in one pass
+fn measure_levels<R: Read>(reader: R) -> UtilResult<PcmStats> {
+    let mut source =
+        WavSource::new(reader).map_err(|e| UtilError::ValidationError(e.to_string()))?;
+    let mut analyzer = PcmAnalyzer::new(source.channels() as usize, source.sample_rate());
+    let mut buffer = Vec::new();
+    while source
+        .read_samples(&mut buffer, 1 << 16)
+        .map_err(|e| UtilError::ValidationError(e.to_string()))?
+        > 0
+    {
+        analyzer.feed(&buffer);
+    }
+    Ok(analyzer.finish())
+}
+
+/// Header sizes of a WAV file that disagree with the file
+///
+/// Empty for files that are not WAV or cannot be inspected; the reader
+/// reports those when the file is opened.
+pub fn wav_length_mismatches(path: &str) -> Vec<LengthMismatch> {
+    match probe_file(path) {
+        Ok(StreamInfo::Wav(info)) => info.mismatches,
+        _ => Vec::new(),
+    }
+}
+
+/// Describe a WAV stream from its `fmt `, `fact` and `data` chunks
+///
+/// A data chunk declared longer than the file is counted up to the end of
+/// the file, which is where the reader stops, and reported in `mismatches`.
+pub fn probe_wav<R: Read + Seek>(reader: &mut R) -> UtilResult<WavInfo> {
+    let chunks = walk_riff_chunks(reader)?;
+    let invalid = |msg: &str| UtilError::ValidationError(msg.to_string());
+
+    let fmt = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"fmt ")
+        .ok_or_else(|| invalid("missing fmt chunk"))?;
+    let data = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"data")
+        .ok_or_else(|| invalid("missing data chunk"))?;
+    if fmt.size < 16 {
+        return Err(invalid("fmt chunk too short"));
+    }
+
+    let mut body = vec![0u8; fmt.size.min(40) as usize];
+    reader.seek(SeekFrom::Start(fmt.offset))?;
+    reader.read_exact(&mut body)?;
+
+    let mut format_tag = read_u16_le(&body, 0);
+    let channels = read_u16_le(&body, 2);
+    let sample_rate = read_u32_le(&body, 4);
+    let block_align = read_u16_le(&body, 12);
+    let bits_per_sample = read_u16_le(&body, 14);
+
+    // WAVE_FORMAT_EXTENSIBLE carries the real format in its sub-format GUID
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+        format_tag = read_u16_le(&body, 24);
+    }
+    let format = match format_tag {
+        WAVE_FORMAT_PCM => "PCM".to_string(),
+        WAVE_FORMAT_IEEE_FLOAT => "IEEE float".to_string(),
+        WAVE_FORMAT_ALAW => "A-law".to_string(),
+        WAVE_FORMAT_MULAW => "mu-law".to_string(),
+        other => format!("format 0x{:04X}", other),
+    };
+
+    if block_align == 0 || sample_rate == 0 {
+        return Err(invalid("invalid block alignment or sample rate"));
+    }
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let declared = data.size / block_align as u64;
+    let samples_per_channel =
+        declared.min(file_len.saturating_sub(data.offset) / block_align as u64);
+    let mut mismatches = Vec::new();
+    if samples_per_channel < declared {
+        mismatches.push(LengthMismatch::DataTruncated {
+            declared,
+            available: samples_per_channel,
+        });
+    }
+
+    let fact = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"fact" && chunk.size >= 4 && chunk.offset + 4 <= file_len);
+    let fact_samples = match fact {
+        Some(fact) => {
+            let mut count = [0u8; 4];
+            reader.seek(SeekFrom::Start(fact.offset))?;
+            reader.read_exact(&mut count)?;
+            Some(read_u32_le(&count, 0) as u64)
+        }
+        None => None,
+    };
+    if let Some(fact) = fact_samples.filter(|&fact| fact != samples_per_channel) {
+        mismatches.push(LengthMismatch::FactCount {
+            fact,
+            data: samples_per_channel,
+        });
+    }
+
+    Ok(WavInfo {
+        format,
+        channels,
+        sample_rate,
+        bits_per_sample,
+        samples_per_channel,
+        duration: samples_per_channel as f64 / sample_rate as f64,
+        fact_samples,
+        mismatches,
+        levels: None,
+    })
+}
+
+/// Describe an MP3 stream by walking its frames
+pub fn probe_mp3(data: &[u8]) -> UtilResult<Mp3Info> {
+    let id3v2 = data.len() >= 10 && &data[0..3] == b"ID3";
+    let start = if id3v2 { id3v2_size(data) } else { 0 };
+    let id3v1 = data.len() >= start + ID3V1_SIZE && &data[data.len() - ID3V1_SIZE..][..3] == b"TAG";
+    let end = if id3v1 {
+        data.len() - ID3V1_SIZE
+    } else {
+        data.len()
+    };
+    let stream = data.get(start..end).unwrap_or_default();
+
+    let mut frames = iter_frames(stream);
+    let mut first: Option<FrameHeader> = None;
+    let mut xing = false;
+    let mut count = 0;
+    let mut samples = 0u64;
+    let mut bits = 0u64;
+    let mut bitrates = Vec::new();
+
+    for frame in frames.by_ref() {
+        if frame.index == 0 && has_vbr_header(&frame.header, frame.data) {
+            xing = true;
+            first = Some(frame.header);
+            continue;
+        }
+        first.get_or_insert(frame.header);
+        count += 1;
+        samples += frame.header.samples_per_frame() as u64;
+        bits += frame.data.len() as u64 * 8;
+        if !bitrates.contains(&frame.header.bitrate()) {
+            bitrates.push(frame.header.bitrate());
+        }
+    }
+
+    let header = first
+        .ok_or_else(|| UtilError::ValidationError("no MPEG audio frames found".to_string()))?;
+    let duration = samples as f64 / header.sample_rate() as f64;
+
+    Ok(Mp3Info {
+        version: header.version_name().to_string(),
+        mode: ["stereo", "joint-stereo", "dual-channel", "mono"][header.mode as usize].to_string(),
+        channels: header.channels(),
+        sample_rate: header.sample_rate(),
+        frames: count,
+        bitrate_mode: if bitrates.len() > 1 { "VBR" } else { "CBR" }.to_string(),
+        bitrate: if duration > 0.0 {
+            (bits as f64 / duration / 1000.0).round() as u32
+        } else {
+            0
+        },
+        duration,
+        xing,
+        id3v2,
+        id3v1,
+        trailing_bytes: stream.len() - frames.offset(),
+    })
+}
+
+/// Total size of the ID3v2 tag at the start of `data`, header included
+fn id3v2_size(data: &[u8]) -> usize {
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |size, &byte| (size << 7) | (byte & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    (10 + size + footer).min(data.len())
+}
+
+/// True if the frame carries a Xing/Info or VBRI header instead of audio
+fn has_vbr_header(header: &FrameHeader, frame: &[u8]) -> bool {
+    let side_info_end = HEADER_SIZE + if header.protected { 2 } else { 0 } + header.side_info_len();
+    let tag_at = |offset: usize| frame.get(offset..offset + 4);
+    matches!(tag_at(side_info_end), Some(b"Xing") | Some(b"Info"))
+        || tag_at(HEADER_SIZE + 32) == Some(b"VBRI")
+}
+
+impl StreamInfo {
+    /// Line-oriented `key: value` description
+    pub fn to_text(&self, path: &str) -> String {
+        let mut text = String::new();
+        for (key, value) in self.fields(path) {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Bool(true) => "yes".to_string(),
+                Value::Bool(false) => "no".to_string(),
+                Value::Array(items) => items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                other => other.to_string(),
+            };
+            let _ = writeln!(text, "{}: {}", key, value);
+        }
+        text
+    }
+
+    /// JSON object with the same keys as the text output
+    pub fn to_json(&self, path: &str) -> Value {
+        Value::Object(
+            self.fields(path)
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn fields(&self, path: &str) -> Vec<(&'static str, Value)> {
+        let mut fields = vec![("file", json!(path))];
+        match self {
+            StreamInfo::Wav(wav) => {
+                fields.extend([
+                    ("type", json!("wav")),
+                    ("format", json!(wav.format)),
+                    ("channels", json!(wav.channels)),
+                    ("sample_rate", json!(wav.sample_rate)),
+                    ("bits_per_sample", json!(wav.bits_per_sample)),
+                    ("samples_per_channel", json!(wav.samples_per_channel)),
+                    ("duration", json!(round_millis(wav.duration))),
+                ]);
+                // Silence has no level in dB: JSON null, "null" in the text
+                if let Some(levels) = &wav.levels {
+                    fields.extend([
+                        ("peak_dbfs", json!(round_centi(levels.peak_dbfs))),
+                        ("rms_dbfs", json!(round_centi(levels.rms_dbfs))),
+                        ("dc_offset", json!((levels.dc_offset * 1e6).round() / 1e6)),
+                    ]);
+                }
+                // Only files whose header disagrees with them get the key
+                if !wav.mismatches.is_empty() {
+                    let warnings: Vec<String> =
+                        wav.mismatches.iter().map(|m| m.to_string()).collect();
+                    fields.push(("warnings", json!(warnings)));
+                }
+            }
+            StreamInfo::Mp3(mp3) => fields.extend([
+                ("type", json!("mp3")),
+                ("format", json!(format!("{} Layer III", mp3.version))),
+                ("mode", json!(mp3.mode)),
+                ("channels", json!(mp3.channels)),
+                ("sample_rate", json!(mp3.sample_rate)),
+                ("frames", json!(mp3.frames)),
+                ("bitrate_mode", json!(mp3.bitrate_mode)),
+                ("bitrate", json!(mp3.bitrate)),
+                ("duration", json!(round_millis(mp3.duration))),
+                ("xing", json!(mp3.xing)),
+                ("id3v2", json!(mp3.id3v2)),
+                ("id3v1", json!(mp3.id3v1)),
+                ("trailing_bytes", json!(mp3.trailing_bytes)),
+            ]),
+        }
+        fields
+    }
+}
+
+/// Round a level to hundredths of a dB so the output is stable
+fn round_centi(db: f64) -> f64 {
+    (db * 100.0).round() / 100.0
+}
+
+/// Round seconds to milliseconds so the output is stable
+fn round_millis(seconds: f64) -> f64 {
+    (seconds * 1000.0).round() / 1000.0
+}
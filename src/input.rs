@@ -0,0 +1,171 @@
+//! PCM input of the CLI
+//!
+//! The samples to encode either sit in memory (read through the library's
+//! WAV or AIFF reader) or, with the `mmap` feature, are read straight from a
+//! memory-mapped file so multi-gigabyte inputs are never copied into a
+//! buffer. Mapping is used for 16-bit PCM files; anything else falls back to
+//! the in-memory path.
+
+use crate::util::{read_wav_file_range, UtilResult};
+use std::time::Duration;
+
+#[cfg(feature = "mmap")]
+use crate::info::probe_wav;
+#[cfg(feature = "mmap")]
+use crate::util::{walk_riff_chunks, UtilError};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "mmap")]
+use shine_rs::mp3_encoder::EncodeRange;
+#[cfg(feature = "mmap")]
+use std::io::Cursor;
+
+/// Interleaved 16-bit PCM samples to encode
+pub struct PcmInput {
+    pub sample_rate: u32,
+    pub channels: u16,
+    data: PcmData,
+}
+
+enum PcmData {
+    Memory(Vec<i16>),
+    #[cfg(feature = "mmap")]
+    Mapped {
+        map: Mmap,
+        /// Byte range of the samples within the mapping
+        start: usize,
+        end: usize,
+    },
+}
+
+impl PcmInput {
+    /// Wrap samples that are already in memory
+    pub fn from_samples(samples: Vec<i16>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            data: PcmData::Memory(samples),
+        }
+    }
+
+    /// Read a time range of a WAV or AIFF file into memory
+    pub fn read(
+        path: &str,
+        start: Option<Duration>,
+        duration: Option<Duration>,
+    ) -> UtilResult<Self> {
+        let (samples, sample_rate, channels) = read_wav_file_range(path, start, duration)?;
+        Ok(Self::from_samples(
+            samples,
+            sample_rate as u32,
+            channels as u16,
+        ))
+    }
+
+    /// Open a time range of a WAV or AIFF file, mapping it when possible
+    pub fn open(
+        path: &str,
+        start: Option<Duration>,
+        duration: Option<Duration>,
+    ) -> UtilResult<Self> {
+        #[cfg(feature = "mmap")]
+        match Self::map(path, start, duration) {
+            Ok(input) => return Ok(input),
+            Err(err) => log::debug!("Not mapping {}: {}", path, err),
+        }
+        Self::read(path, start, duration)
+    }
+
+    /// Map a time range of a 16-bit PCM WAV file
+    ///
+    /// The header is parsed to locate the data chunk; a data chunk declared
+    /// longer than the file is cut at the end of the file.
+    #[cfg(feature = "mmap")]
+    pub fn map(
+        path: &str,
+        start: Option<Duration>,
+        duration: Option<Duration>,
+    ) -> UtilResult<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the mapping is read-only; the file must not be truncated
+        // while it is being encoded, as with any mapped input
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut cursor = Cursor::new(&map[..]);
+        let info = probe_wav(&mut cursor)?;
+        if info.format != "PCM" || info.bits_per_sample != 16 || info.channels == 0 {
+            return Err(UtilError::ValidationError(format!(
+                "only 16-bit PCM can be mapped, found {} {}-bit",
+                info.format, info.bits_per_sample
+            )));
+        }
+        let data = walk_riff_chunks(&mut cursor)?
+            .into_iter()
+            .find(|chunk| &chunk.id == b"data")
+            .ok_or_else(|| UtilError::ValidationError("missing data chunk".to_string()))?;
+
+        let frame_bytes = info.channels as usize * 2;
+        let data_start = (data.offset as usize).min(map.len());
+        let data_len = (data.size as usize).min(map.len() - data_start);
+        let available = (data_len / frame_bytes) as u64;
+
+        let (first, frames) = if start.is_none() && duration.is_none() {
+            (0, available)
+        } else {
+            let range =
+                EncodeRange::from_time(start.unwrap_or_default(), duration, info.sample_rate);
+            let frames = range
+                .resolve(available)
+                .map_err(|e| UtilError::ValidationError(e.to_string()))?;
+            (range.start_sample, frames)
+        };
+        if frames == 0 {
+            return Err(UtilError::ValidationError(
+                "No audio data found in WAV file".to_string(),
+            ));
+        }
+
+        let start = data_start + first as usize * frame_bytes;
+        Ok(Self {
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            data: PcmData::Mapped {
+                map,
+                start,
+                end: start + frames as usize * frame_bytes,
+            },
+        })
+    }
+
+    /// True if the samples are read from a memory-mapped file
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self.data, PcmData::Memory(_))
+    }
+
+    /// Number of interleaved samples
+    pub fn len(&self) -> usize {
+        match &self.data {
+            PcmData::Memory(samples) => samples.len(),
+            #[cfg(feature = "mmap")]
+            PcmData::Mapped { start, end, .. } => (end - start) / 2,
+        }
+    }
+
+    /// True if there are no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate the interleaved samples
+    pub fn samples(&self) -> Box<dyn Iterator<Item = i16> + '_> {
+        match &self.data {
+            PcmData::Memory(samples) => Box::new(samples.iter().copied()),
+            #[cfg(feature = "mmap")]
+            PcmData::Mapped { map, start, end } => Box::new(
+                map[*start..*end]
+                    .chunks_exact(2)
+                    .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+            ),
+        }
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! This library provides utility functions for the shine-rs command-line tools.
 
+pub mod args;
+pub mod cli;
+pub mod info;
+pub mod input;
 pub mod util;
 
 // Re-export commonly used functions and types
@@ -3,7 +3,14 @@
 //! This module provides common utility functions used by the MP3 encoder,
 //! including PCM audio data processing utilities and error handling.
 
+use shine_rs::aiff::AiffSource;
+use shine_rs::bytes::{read_u32_le, read_u64_le};
+use shine_rs::error::InputResult;
+use shine_rs::mp3_encoder::EncodeRange;
+use shine_rs::wav::WavSource;
 use std::fmt;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::time::Duration;
 
 /// Error type for utility operations
 #[derive(Debug)]
@@ -35,27 +42,199 @@ impl From<std::io::Error> for UtilError {
 pub type UtilResult<T> = std::result::Result<T, UtilError>;
 
 /// Read WAV file and return PCM samples, sample rate, and channel count
-/// Uses hound library for WAV parsing
+/// Uses the library's WAV reader for parsing
 pub fn read_wav_file(file_path: &str) -> UtilResult<(Vec<i16>, i32, i32)> {
-    let mut reader = hound::WavReader::open(file_path)
-        .map_err(|e| UtilError::ValidationError(format!("Failed to open WAV file: {}", e)))?;
+    read_wav_file_range(file_path, None, None)
+}
+
+/// Read a time range of a WAV or AIFF file
+///
+/// The format is detected from the magic bytes. Samples before `start` are
+/// skipped and reading stops after `duration` (or at the end of the file).
+/// A start beyond the end of the file is an error; a duration running past
+/// the end is truncated.
+pub fn read_wav_file_range(
+    file_path: &str,
+    start: Option<Duration>,
+    duration: Option<Duration>,
+) -> UtilResult<(Vec<i16>, i32, i32)> {
+    let file = std::fs::File::open(file_path)?;
+    let mut source = AudioSource::new(BufReader::new(file))?;
+    let kind = source.kind();
 
-    let spec = reader.spec();
-    let sample_rate = spec.sample_rate as i32;
-    let channels = spec.channels as i32;
+    let sample_rate = source.sample_rate() as i32;
+    let channels = source.channels() as i32;
+    let read_error =
+        |e| UtilError::ValidationError(format!("Failed to read {} samples: {}", kind, e));
 
-    // Read all samples
-    let samples: Result<Vec<i16>, _> = reader.samples::<i16>().collect();
-    let samples = samples
-        .map_err(|e| UtilError::ValidationError(format!("Failed to read WAV samples: {}", e)))?;
+    let samples = if start.is_none() && duration.is_none() {
+        // Read all samples
+        source.read_to_end().map_err(read_error)?
+    } else {
+        let range =
+            EncodeRange::from_time(start.unwrap_or_default(), duration, source.sample_rate());
+        let len = range
+            .resolve(source.duration_samples())
+            .map_err(|e| UtilError::ValidationError(e.to_string()))?;
+        source.skip_frames(range.start_sample).map_err(read_error)?;
+
+        let mut samples = Vec::new();
+        source
+            .read_samples(&mut samples, len as usize * channels as usize)
+            .map_err(read_error)?;
+        samples
+    };
 
     if samples.is_empty() {
+        return Err(UtilError::ValidationError(format!(
+            "No audio data found in {} file",
+            kind
+        )));
+    }
+
+    Ok((samples, sample_rate, channels))
+}
+
+/// WAV or AIFF reader, picked from the magic bytes of the stream
+enum AudioSource<R: Read> {
+    Wav(WavSource<R>),
+    Aiff(AiffSource<R>),
+}
+
+impl<R: BufRead> AudioSource<R> {
+    fn new(mut reader: R) -> UtilResult<Self> {
+        let aiff = reader.fill_buf()?.starts_with(b"FORM");
+        let source = if aiff {
+            AiffSource::new(reader).map(AudioSource::Aiff)
+        } else {
+            WavSource::new(reader).map(AudioSource::Wav)
+        };
+        let kind = if aiff { "AIFF" } else { "WAV" };
+        source
+            .map_err(|e| UtilError::ValidationError(format!("Failed to open {} file: {}", kind, e)))
+    }
+}
+
+impl<R: Read> AudioSource<R> {
+    fn kind(&self) -> &'static str {
+        match self {
+            AudioSource::Wav(_) => "WAV",
+            AudioSource::Aiff(_) => "AIFF",
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            AudioSource::Wav(source) => source.sample_rate(),
+            AudioSource::Aiff(source) => source.sample_rate(),
+        }
+    }
+
+    fn channels(&self) -> u8 {
+        match self {
+            AudioSource::Wav(source) => source.channels(),
+            AudioSource::Aiff(source) => source.channels(),
+        }
+    }
+
+    fn duration_samples(&self) -> u64 {
+        match self {
+            AudioSource::Wav(source) => source.duration_samples(),
+            AudioSource::Aiff(source) => source.duration_samples(),
+        }
+    }
+
+    fn skip_frames(&mut self, frames: u64) -> InputResult<u64> {
+        match self {
+            AudioSource::Wav(source) => source.skip_frames(frames),
+            AudioSource::Aiff(source) => source.skip_frames(frames),
+        }
+    }
+
+    fn read_samples(&mut self, buffer: &mut Vec<i16>, count: usize) -> InputResult<usize> {
+        match self {
+            AudioSource::Wav(source) => source.read_samples(buffer, count),
+            AudioSource::Aiff(source) => source.read_samples(buffer, count),
+        }
+    }
+
+    fn read_to_end(&mut self) -> InputResult<Vec<i16>> {
+        match self {
+            AudioSource::Wav(source) => source.read_to_end(),
+            AudioSource::Aiff(source) => source.read_to_end(),
+        }
+    }
+}
+
+/// One chunk of a RIFF file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RiffChunk {
+    /// Four character chunk id
+    pub id: [u8; 4],
+    /// Offset of the chunk body from the start of the file
+    pub offset: u64,
+    /// Size of the chunk body in bytes as declared in its header
+    pub size: u64,
+}
+
+/// List the chunks of a RIFF/WAVE file
+///
+/// Only the 8-byte chunk headers are read; chunk bodies (including the audio
+/// data) are skipped with seeks. Chunks are word aligned, so an odd-sized
+/// body is followed by one pad byte. A chunk running past the end of the
+/// file is still listed and ends the walk.
+///
+/// RF64 files are walked the same way; their `data` chunk, whose 32-bit
+/// size is 0xFFFFFFFF, is listed with the 64-bit size from the `ds64` chunk.
+pub fn walk_riff_chunks<R: Read + Seek>(reader: &mut R) -> UtilResult<Vec<RiffChunk>> {
+    let mut header = [0u8; 12];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    let rf64 = &header[0..4] == b"RF64";
+    if !(rf64 || &header[0..4] == b"RIFF") || &header[8..12] != b"WAVE" {
         return Err(UtilError::ValidationError(
-            "No audio data found in WAV file".to_string(),
+            "Not a RIFF/WAVE file".to_string(),
         ));
     }
 
-    Ok((samples, sample_rate, channels))
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut position = 12;
+    let mut chunks = Vec::new();
+    while position + 8 <= file_len {
+        let mut chunk_header = [0u8; 8];
+        reader.seek(SeekFrom::Start(position))?;
+        reader.read_exact(&mut chunk_header)?;
+
+        let mut chunk = RiffChunk {
+            id: [
+                chunk_header[0],
+                chunk_header[1],
+                chunk_header[2],
+                chunk_header[3],
+            ],
+            offset: position + 8,
+            size: read_u32_le(&chunk_header, 4) as u64,
+        };
+        if rf64 && &chunk.id == b"data" && chunk.size == 0xFFFF_FFFF {
+            chunk.size = ds64_data_size(reader, &chunks)?;
+        }
+        chunks.push(chunk);
+        position = chunk.offset + chunk.size + (chunk.size & 1);
+    }
+
+    Ok(chunks)
+}
+
+/// Data chunk size stored in the `ds64` chunk of an RF64 file
+fn ds64_data_size<R: Read + Seek>(reader: &mut R, chunks: &[RiffChunk]) -> UtilResult<u64> {
+    let ds64 = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"ds64" && chunk.size >= 16)
+        .ok_or_else(|| UtilError::ValidationError("RF64 file without ds64 chunk".to_string()))?;
+    let mut size = [0u8; 8];
+    reader.seek(SeekFrom::Start(ds64.offset + 8))?;
+    reader.read_exact(&mut size)?;
+    Ok(read_u64_le(&size, 0))
 }
 
 /// De-interleave non-interleaved PCM data into separate channel buffers
@@ -113,3 +292,33 @@ pub fn deinterleave_pcm_interleaved(
         }
     }
 }
+
+/// Parse a time given as plain seconds or as `[[hh:]mm:]ss[.mmm]`
+///
+/// Examples: `45`, `2.5`, `01:30`, `00:01:30.250`.
+pub fn parse_time(text: &str) -> UtilResult<Duration> {
+    let invalid = || UtilError::ValidationError(format!("Invalid time: {}", text));
+
+    let fields: Vec<&str> = text.split(':').collect();
+    if fields.len() > 3 {
+        return Err(invalid());
+    }
+
+    let (seconds, minutes_hours) = fields.split_last().ok_or_else(invalid)?;
+    let seconds: f64 = seconds.parse().map_err(|_| invalid())?;
+    if !seconds.is_finite() || seconds < 0.0 || (!minutes_hours.is_empty() && seconds >= 60.0) {
+        return Err(invalid());
+    }
+
+    let mut total = 0u64;
+    for (i, field) in minutes_hours.iter().enumerate() {
+        let value: u64 = field.parse().map_err(|_| invalid())?;
+        // Minutes are limited to 0..59 when hours are given
+        if i > 0 && value >= 60 {
+            return Err(invalid());
+        }
+        total = total * 60 + value;
+    }
+
+    Ok(Duration::from_secs(total * 60) + Duration::from_secs_f64(seconds))
+}
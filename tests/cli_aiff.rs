@@ -0,0 +1,100 @@
+//! CLI AIFF Tests
+//!
+//! Checks that AIFF and AIFF-C (`sowt`) copies of a WAV file encode to the
+//! same MP3 stream as the WAV file itself.
+
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use shine_rs_cli::util::read_wav_file;
+use std::fs;
+use std::path::Path;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-15s.wav";
+
+/// AIFF (big-endian) or AIFF-C `sowt` (little-endian) stream of `samples`
+fn aiff(samples: &[i16], sample_rate: u32, channels: u16, sowt: bool) -> Vec<u8> {
+    // 80-bit extended sample rate
+    let exponent = 31 - sample_rate.leading_zeros();
+    let mut comm = channels.to_be_bytes().to_vec();
+    comm.extend_from_slice(&(samples.len() as u32 / channels as u32).to_be_bytes());
+    comm.extend_from_slice(&16u16.to_be_bytes());
+    comm.extend_from_slice(&(16383 + exponent as u16).to_be_bytes());
+    comm.extend_from_slice(&((sample_rate as u64) << (63 - exponent)).to_be_bytes());
+    if sowt {
+        comm.extend_from_slice(b"sowt\x00\x00");
+    }
+
+    let mut ssnd = vec![0u8; 8];
+    for sample in samples {
+        if sowt {
+            ssnd.extend_from_slice(&sample.to_le_bytes());
+        } else {
+            ssnd.extend_from_slice(&sample.to_be_bytes());
+        }
+    }
+
+    let mut body = if sowt {
+        b"AIFC".to_vec()
+    } else {
+        b"AIFF".to_vec()
+    };
+    for (id, data) in [(b"COMM", comm), (b"SSND", ssnd)] {
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(&data);
+    }
+    let mut form = b"FORM".to_vec();
+    form.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    form.extend_from_slice(&body);
+    form
+}
+
+fn encode(input: &str, output: &Path) -> Vec<u8> {
+    // A range in the middle also exercises skipping through the samples
+    let args: Vec<String> = [
+        "shineenc",
+        "-q",
+        "-f",
+        "--start",
+        "5",
+        "--duration",
+        "2",
+        input,
+        output.to_str().unwrap(),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let mut stdout = Vec::new();
+    convert_wav_to_mp3(&Args::parse_from(&args).unwrap(), &mut stdout).expect("conversion failed");
+    let data = fs::read(output).unwrap();
+    let _ = fs::remove_file(output);
+    data
+}
+
+#[test]
+fn test_aiff_encodes_like_wav() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let (samples, sample_rate, channels) = read_wav_file(WAV_FILE).unwrap();
+    let dir = std::env::temp_dir();
+    let expected = encode(WAV_FILE, &dir.join("shine_rs_cli_aiff_wav.mp3"));
+
+    for (sowt, name) in [
+        (false, "shine_rs_cli.aiff"),
+        (true, "shine_rs_cli_sowt.aifc"),
+    ] {
+        let input = dir.join(name);
+        fs::write(
+            &input,
+            aiff(&samples, sample_rate as u32, channels as u16, sowt),
+        )
+        .unwrap();
+        let mp3 = encode(input.to_str().unwrap(), &dir.join(format!("{}.mp3", name)));
+        let _ = fs::remove_file(&input);
+        assert!(mp3 == expected, "{} differs from the WAV encode", name);
+    }
+}
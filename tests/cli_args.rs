@@ -0,0 +1,312 @@
+//! CLI Argument Tests
+//!
+//! Table-driven checks of the argument parser (short, long, bundled and
+//! attached options, error cases), output name derivation and overwrite
+//! protection of the command line front end.
+
+use shine_rs::StereoMode;
+use shine_rs_cli::args::{Args, ArgsError};
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use std::fs;
+use std::path::Path;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-3s.wav";
+
+fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+    let args: Vec<String> = std::iter::once("shineenc")
+        .chain(args.iter().copied())
+        .map(String::from)
+        .collect();
+    Args::parse_from(&args)
+}
+
+/// One-line description of the parsed arguments, for comparing in tables
+fn summary(args: &Args) -> String {
+    let mut flags = String::new();
+    for (set, letter) in [
+        (args.force_mono, 'm'),
+        (args.copyright, 'c'),
+        (args.force, 'f'),
+        (args.quiet, 'q'),
+        (args.verbose, 'v'),
+        (args.replaygain, 'r'),
+        (args.info, 'i'),
+        (args.json, 'J'),
+    ] {
+        if set {
+            flags.push(letter);
+        }
+    }
+    let mode = match args.stereo_mode {
+        StereoMode::Stereo => "stereo",
+        StereoMode::JointStereo => "joint",
+        StereoMode::DualChannel => "dual",
+        StereoMode::Mono => "mono",
+    };
+    format!(
+        "{} {} {} {} [{}] {:?} {:?}",
+        args.input_file,
+        args.output_file,
+        args.bitrate,
+        mode,
+        flags,
+        args.start.map(|t| t.as_millis()),
+        args.duration.map(|t| t.as_millis())
+    )
+}
+
+fn unknown(option: &str, suggestion: Option<&str>) -> ArgsError {
+    ArgsError::UnknownOption {
+        option: option.to_string(),
+        suggestion: suggestion.map(String::from),
+    }
+}
+
+fn invalid(option: &str, value: &str, reason: &str) -> ArgsError {
+    ArgsError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_table() {
+    let cases: Vec<(&[&str], Result<&str, ArgsError>)> = vec![
+        // Plain shine invocations
+        (
+            &["in.wav", "out.mp3"],
+            Ok("in.wav out.mp3 128 stereo [] None None"),
+        ),
+        (
+            &["-b", "192", "in.wav", "out.mp3"],
+            Ok("in.wav out.mp3 192 stereo [] None None"),
+        ),
+        (
+            &["-m", "-c", "-j", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 joint [mc] None None"),
+        ),
+        (
+            &["-d", "-q", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 dual [q] None None"),
+        ),
+        (
+            &["-q", "-v", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 stereo [v] None None"),
+        ),
+        (
+            &["-v", "-q", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 stereo [q] None None"),
+        ),
+        (&["-", "-"], Ok("- - 128 stereo [] None None")),
+        // Attached values and long options
+        (
+            &["-b192", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 192 stereo [] None None"),
+        ),
+        (
+            &["--bitrate", "64", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 64 stereo [] None None"),
+        ),
+        (
+            &["--bitrate=320", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 320 stereo [] None None"),
+        ),
+        (
+            &[
+                "--mono",
+                "--copyright",
+                "--force",
+                "--verbose",
+                "in.wav",
+                "o.mp3",
+            ],
+            Ok("in.wav o.mp3 128 stereo [mcfv] None None"),
+        ),
+        (
+            &["--joint-stereo", "--dual-channel", "i.wav", "o.mp3"],
+            Ok("i.wav o.mp3 128 dual [] None None"),
+        ),
+        (
+            &["--replaygain", "-q", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 stereo [qr] None None"),
+        ),
+        (
+            &["--start=1.5", "--duration", "2", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 stereo [] Some(1500) Some(2000)"),
+        ),
+        // Bundles
+        (
+            &["-qj", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 joint [q] None None"),
+        ),
+        (
+            &["-mcf", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 128 stereo [mcf] None None"),
+        ),
+        (
+            &["-qb", "64", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 64 stereo [q] None None"),
+        ),
+        (
+            &["-qb64", "in.wav", "o.mp3"],
+            Ok("in.wav o.mp3 64 stereo [q] None None"),
+        ),
+        // Option placement, "--" and derived output
+        (
+            &["in.wav", "o.mp3", "--verbose"],
+            Ok("in.wav o.mp3 128 stereo [v] None None"),
+        ),
+        (
+            &["--", "-in.wav", "o.mp3"],
+            Ok("-in.wav o.mp3 128 stereo [] None None"),
+        ),
+        (
+            &["-q", "dir/song.wav"],
+            Ok("dir/song.wav dir/song.mp3 128 stereo [q] None None"),
+        ),
+        (
+            &["--info", "--json", "x.mp3"],
+            Ok("x.mp3  128 stereo [iJ] None None"),
+        ),
+        // Cases that used to print the usage
+        (&[], Err(ArgsError::MissingInput)),
+        (&["-q"], Err(ArgsError::MissingInput)),
+        (&["-h", "in.wav", "o.mp3"], Err(ArgsError::Help)),
+        (&["--help"], Err(ArgsError::Help)),
+        (&["-b"], Err(ArgsError::MissingValue("-b".to_string()))),
+        (
+            &["in.wav", "--bitrate"],
+            Err(ArgsError::MissingValue("--bitrate".to_string())),
+        ),
+        // Unknown options with and without a suggestion
+        (&["-x", "in.wav", "o.mp3"], Err(unknown("-x", None))),
+        (
+            &["--bitrat=128", "in.wav"],
+            Err(unknown("--bitrat", Some("--bitrate"))),
+        ),
+        (
+            &["--qiuet", "in.wav"],
+            Err(unknown("--qiuet", Some("--quiet"))),
+        ),
+        (&["-quiet", "in.wav"], Err(unknown("-u", Some("--quiet")))),
+        (
+            &["--frobnicate", "in.wav"],
+            Err(unknown("--frobnicate", None)),
+        ),
+        // Bad values and extra arguments
+        (
+            &["-b", "100", "in.wav"],
+            Err(invalid("-b", "100", "supported bitrates are 8-320 kbps")),
+        ),
+        (&["-bx", "in.wav"], Err(invalid("-b", "x", "not a number"))),
+        (
+            &["--quiet=yes", "in.wav"],
+            Err(ArgsError::UnexpectedValue("--quiet".to_string())),
+        ),
+        (
+            &["in.wav", "o.mp3", "extra"],
+            Err(ArgsError::UnexpectedArgument("extra".to_string())),
+        ),
+        (
+            &["--info", "a.wav", "b.wav"],
+            Err(ArgsError::UnexpectedArgument("b.wav".to_string())),
+        ),
+    ];
+
+    for (args, expected) in cases {
+        let actual = parse(args);
+        match (&actual, &expected) {
+            (Ok(parsed), Ok(expected)) => assert_eq!(summary(parsed), *expected, "{:?}", args),
+            (Err(err), Err(expected)) => assert_eq!(err, expected, "{:?}", args),
+            _ => panic!("{:?}: expected {:?}, got {:?}", args, expected, actual),
+        }
+    }
+}
+
+#[test]
+fn test_invalid_time_value() {
+    let err = parse(&["--start", "abc", "in.wav"]).unwrap_err();
+    assert!(matches!(err, ArgsError::InvalidValue { ref option, .. } if option == "--start"));
+    assert!(!err.shows_usage());
+    assert!(ArgsError::MissingInput.shows_usage());
+}
+
+#[test]
+fn test_ms_threshold_option() {
+    assert_eq!(parse(&["-j", "in.wav"]).unwrap().ms_threshold, None);
+    assert_eq!(
+        parse(&["-j", "--ms-threshold=0.8", "in.wav"])
+            .unwrap()
+            .ms_threshold,
+        Some(0.8)
+    );
+    assert_eq!(
+        parse(&["--ms-threshold", "1.5", "in.wav"]).unwrap_err(),
+        invalid("--ms-threshold", "1.5", "expected a value between 0 and 1")
+    );
+}
+
+#[test]
+fn test_output_name_derived_from_input() {
+    let args = parse(&["-b", "192", "music/song.wav"]).unwrap();
+    assert_eq!(args.input_file, "music/song.wav");
+    assert_eq!(args.output_file, "music/song.mp3");
+    assert_eq!(args.bitrate, 192);
+
+    assert_eq!(
+        parse(&["take.one.WAV"]).unwrap().output_file,
+        "take.one.mp3"
+    );
+    assert_eq!(parse(&["noext"]).unwrap().output_file, "noext.mp3");
+
+    // An explicit output is kept as given
+    let args = parse(&["in.wav", "out.mp3"]).unwrap();
+    assert_eq!(args.output_file, "out.mp3");
+    assert!(!args.force);
+}
+
+#[test]
+fn test_output_name_collision_rejected() {
+    let err = parse(&["already.mp3"]).unwrap_err();
+    assert!(err.to_string().contains("overwrite the input"), "{}", err);
+
+    // Standard input has no name to derive from
+    let err = parse(&["-"]).unwrap_err();
+    assert!(err.to_string().contains("standard input"), "{}", err);
+}
+
+#[test]
+fn test_force_flags() {
+    assert!(parse(&["-f", "in.wav", "out.mp3"]).unwrap().force);
+    assert!(parse(&["--force", "in.wav"]).unwrap().force);
+    assert!(!parse(&["in.wav", "-"]).unwrap().force);
+}
+
+#[test]
+fn test_existing_output_needs_force() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let output = std::env::temp_dir().join("shine_rs_cli_args_overwrite.mp3");
+    let output_str = output.to_str().unwrap();
+    fs::write(&output, b"keep me").unwrap();
+
+    let mut stdout = Vec::new();
+    let err = convert_wav_to_mp3(&parse(&["-q", WAV_FILE, output_str]).unwrap(), &mut stdout)
+        .unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{}", err);
+    assert_eq!(fs::read(&output).unwrap(), b"keep me");
+
+    convert_wav_to_mp3(
+        &parse(&["-q", "-f", WAV_FILE, output_str]).unwrap(),
+        &mut stdout,
+    )
+    .unwrap();
+    assert_ne!(fs::read(&output).unwrap(), b"keep me");
+    assert!(stdout.is_empty());
+
+    let _ = fs::remove_file(&output);
+}
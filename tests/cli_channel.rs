@@ -0,0 +1,93 @@
+//! CLI Channel Selection Tests
+//!
+//! Checks that `--channel` picks which channel of stereo input is encoded
+//! with `--mono`, using a file with a tone on the left and silence on the
+//! right.
+
+use shine_rs::frame::iter_frames;
+use shine_rs::MonoSource;
+use shine_rs_cli::args::{Args, ArgsError};
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use std::fs;
+
+/// Two seconds of 44.1 kHz stereo, a 440 Hz tone left and silence right
+fn left_tone_wav() -> Vec<u8> {
+    let frames = 2 * 44100;
+    let mut samples = Vec::with_capacity(frames * 2);
+    for i in 0..frames {
+        let t = i as f64 / 44100.0;
+        samples.push(((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 16000.0) as i16);
+        samples.push(0);
+    }
+    let mut wav = Vec::new();
+    shine_rs::wav::write_wav(&mut wav, &samples, 44100, 2).unwrap();
+    wav
+}
+
+fn args(options: &[&str]) -> Result<Args, ArgsError> {
+    let args: Vec<String> = ["shineenc"]
+        .iter()
+        .chain(options)
+        .map(|s| s.to_string())
+        .collect();
+    Args::parse_from(&args)
+}
+
+/// Encode the test file with the extra options
+fn encode(name: &str, options: &[&str]) -> Vec<u8> {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("shine_rs_cli_channel_{}.wav", name));
+    let output = dir.join(format!("shine_rs_cli_channel_{}.mp3", name));
+    fs::write(&input, left_tone_wav()).unwrap();
+
+    let mut options = [&["-q", "-f"], options].concat();
+    options.extend([input.to_str().unwrap(), output.to_str().unwrap()]);
+    convert_wav_to_mp3(&args(&options).unwrap(), &mut Vec::new()).unwrap();
+    let mp3 = fs::read(&output).unwrap();
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+    mp3
+}
+
+/// Sum of `big_values` over every granule, zero for digital silence
+fn spectral_content(mp3: &[u8]) -> u32 {
+    let mut total = 0;
+    for frame in iter_frames(mp3) {
+        assert_eq!(frame.header.channels(), 1);
+        let side_info = frame.side_info().unwrap();
+        total += side_info.gr.iter().map(|gr| gr[0].big_values).sum::<u32>();
+    }
+    total
+}
+
+#[test]
+fn test_channel_option_parsing() {
+    assert_eq!(args(&["in.wav"]).unwrap().mono_source, MonoSource::Mix);
+    for (value, source) in [
+        ("left", MonoSource::Left),
+        ("right", MonoSource::Right),
+        ("mix", MonoSource::Mix),
+    ] {
+        let parsed = args(&["-m", "--channel", value, "in.wav"]).unwrap();
+        assert_eq!(parsed.mono_source, source);
+        let parsed = args(&[&format!("--channel={}", value), "in.wav"]).unwrap();
+        assert_eq!(parsed.mono_source, source);
+    }
+    assert!(matches!(
+        args(&["--channel", "centre", "in.wav"]),
+        Err(ArgsError::InvalidValue { ref option, .. }) if option == "--channel"
+    ));
+}
+
+#[test]
+fn test_right_channel_of_left_tone_is_silent() {
+    let right = encode("right", &["-m", "--channel", "right"]);
+    let left = encode("left", &["-m", "--channel", "left"]);
+    let mix = encode("mix", &["-m"]);
+
+    assert_eq!(spectral_content(&right), 0);
+    assert!(spectral_content(&left) > 0);
+    assert!(spectral_content(&mix) > 0);
+    assert_ne!(left, mix);
+    assert_eq!(mix, encode("mix_explicit", &["-m", "--channel", "mix"]));
+}
@@ -0,0 +1,71 @@
+//! CLI G.711 Tests
+//!
+//! Checks that 8 kHz µ-law WAV files are decoded and encoded as MPEG-2.5 at
+//! the highest bitrate the sample rate allows.
+
+use shine_rs::frame::iter_frames;
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use shine_rs_cli::info::{probe_file, StreamInfo};
+use std::fs;
+
+/// Mono 8 kHz µ-law WAV file of `codes`
+fn mulaw_wav(codes: &[u8]) -> Vec<u8> {
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(4 + 8 + 16 + 8 + codes.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt \x10\x00\x00\x00");
+    wav.extend_from_slice(&7u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(codes.len() as u32).to_le_bytes());
+    wav.extend_from_slice(codes);
+    wav
+}
+
+#[test]
+fn test_mulaw_input_encodes_at_8khz() {
+    let dir = std::env::temp_dir();
+    let input = dir.join("shine_rs_cli_mulaw.wav");
+    let output = dir.join("shine_rs_cli_mulaw.mp3");
+    // Half a second of a 400 Hz tone around the µ-law zero codeword
+    let codes: Vec<u8> = (0..4000)
+        .map(|i| if (i / 10) % 2 == 0 { 0xA0 } else { 0x20 })
+        .collect();
+    fs::write(&input, mulaw_wav(&codes)).unwrap();
+
+    match probe_file(input.to_str().unwrap()).unwrap() {
+        StreamInfo::Wav(info) => {
+            assert_eq!(info.format, "mu-law");
+            assert_eq!(info.samples_per_channel, 4000);
+        }
+        other => panic!("not a WAV file: {:?}", other),
+    }
+
+    // The default 128 kbps does not exist at 8 kHz; 56 kbps is the highest
+    // rate whose mono granules fit
+    let args: Vec<String> = ["shineenc", "-q", "-f"]
+        .iter()
+        .copied()
+        .chain([input.to_str().unwrap(), output.to_str().unwrap()])
+        .map(String::from)
+        .collect();
+    let mut stdout = Vec::new();
+    convert_wav_to_mp3(&Args::parse_from(&args).unwrap(), &mut stdout).expect("conversion failed");
+    let mp3 = fs::read(&output).unwrap();
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+
+    let frames: Vec<_> = iter_frames(&mp3).collect();
+    // 4000 samples fill seven frames of 576, the last one possibly cut short
+    assert!(frames.len() == 6 || frames.len() == 7);
+    for frame in &frames {
+        assert_eq!(frame.header.version_name(), "MPEG-2.5");
+        assert_eq!(frame.header.sample_rate(), 8000);
+        assert_eq!(frame.header.bitrate(), 56);
+        assert_eq!(frame.header.channels(), 1);
+    }
+}
@@ -0,0 +1,236 @@
+//! CLI --info Mode Tests
+//!
+//! Checks the stream details printed for the WAV and MP3 fixtures, both as
+//! text and as JSON, the MP3 tag detection of the inspection module, the
+//! chunk walk over odd-sized chunks, and reading RF64 files whose declared
+//! size runs past their end.
+
+use serde_json::Value;
+use shine_rs_cli::info::{probe_mp3, probe_wav, LengthMismatch, StreamInfo};
+use shine_rs_cli::util::{read_wav_file, read_wav_file_range, walk_riff_chunks, UtilError};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-3s.wav";
+const MP3_FILE: &str = "tests/audio/inputs/reference/sample-3s.mp3";
+
+/// Run the CLI in --info mode and return its standard output
+fn run_info(args: &[&str]) -> Result<String, String> {
+    let mut cmd_args = vec!["run", "--", "--info"];
+    cmd_args.extend_from_slice(args);
+
+    let result = Command::new("cargo")
+        .args(&cmd_args)
+        .output()
+        .map_err(|e| format!("Failed to run Rust encoder: {}", e))?;
+
+    if !result.status.success() {
+        return Err(format!(
+            "--info failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&result.stdout).into_owned())
+}
+
+#[test]
+fn test_info_wav_text() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let output = run_info(&[WAV_FILE]).expect("--info failed");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "file: tests/audio/inputs/basic/sample-3s.wav",
+            "type: wav",
+            "format: PCM",
+            "channels: 2",
+            "sample_rate: 44100",
+            "bits_per_sample: 16",
+            "samples_per_channel: 140928",
+            "duration: 3.196",
+            "peak_dbfs: -5.42",
+            "rms_dbfs: -20.85",
+            "dc_offset: -0.008747",
+        ]
+    );
+}
+
+#[test]
+fn test_info_mp3_text() {
+    if !Path::new(MP3_FILE).exists() {
+        println!("Skipping test - input file not found: {}", MP3_FILE);
+        return;
+    }
+
+    let output = run_info(&[MP3_FILE]).expect("--info failed");
+    assert!(output.contains("type: mp3\n"));
+    assert!(output.contains("format: MPEG-1 Layer III\n"));
+    assert!(output.contains("sample_rate: 44100\n"));
+    assert!(output.contains("bitrate_mode: CBR\n"));
+    assert!(output.contains("bitrate: 128\n"));
+    assert!(output.contains("xing: no\n"));
+    assert!(output.contains("id3v2: no\n"));
+}
+
+#[test]
+fn test_info_json_schema() {
+    for file in [WAV_FILE, MP3_FILE] {
+        if !Path::new(file).exists() {
+            println!("Skipping {} - file not found", file);
+            continue;
+        }
+
+        let output = run_info(&["--json", file]).expect("--info --json failed");
+        let json: Value = serde_json::from_str(&output).expect("Output should be valid JSON");
+        let object = json.as_object().expect("Output should be a JSON object");
+
+        assert_eq!(object["file"], file);
+        assert!(object["channels"].is_u64());
+        assert!(object["sample_rate"].is_u64());
+        assert!(object["duration"].is_f64());
+        assert!(object["format"].is_string());
+
+        match object["type"].as_str() {
+            Some("wav") => {
+                assert_eq!(object.len(), 11);
+                assert!(object["bits_per_sample"].is_u64());
+                assert!(object["peak_dbfs"].as_f64().unwrap() <= 0.0);
+                assert!(
+                    object["rms_dbfs"].as_f64().unwrap() < object["peak_dbfs"].as_f64().unwrap()
+                );
+                assert!(object["dc_offset"].is_f64());
+                assert!(object["samples_per_channel"].is_u64());
+            }
+            Some("mp3") => {
+                assert_eq!(object.len(), 14);
+                assert!(object["frames"].as_u64().unwrap() > 0);
+                assert!(object["bitrate"].is_u64());
+                assert!(object["trailing_bytes"].is_u64());
+                assert!(matches!(
+                    object["bitrate_mode"].as_str(),
+                    Some("CBR" | "VBR")
+                ));
+                for flag in ["xing", "id3v2", "id3v1"] {
+                    assert!(object[flag].is_boolean(), "{} should be a boolean", flag);
+                }
+            }
+            other => panic!("Unexpected type {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_info_detects_id3_tags() {
+    if !Path::new(MP3_FILE).exists() {
+        println!("Skipping test - input file not found: {}", MP3_FILE);
+        return;
+    }
+    let mp3 = fs::read(MP3_FILE).unwrap();
+    let plain = probe_mp3(&mp3).unwrap();
+
+    // ID3v2 header with a 20-byte (syncsafe) body, then the stream, then ID3v1
+    let mut tagged = b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec();
+    tagged.extend_from_slice(&[0u8; 20]);
+    tagged.extend_from_slice(&mp3);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(128, 0);
+    tagged.extend_from_slice(&id3v1);
+
+    let info = probe_mp3(&tagged).unwrap();
+    assert!(info.id3v2);
+    assert!(info.id3v1);
+    assert_eq!(info.frames, plain.frames);
+    assert_eq!(info.bitrate, plain.bitrate);
+    assert!(matches!(
+        StreamInfo::Mp3(info).to_json(MP3_FILE)["id3v2"],
+        Value::Bool(true)
+    ));
+}
+
+#[test]
+fn test_odd_chunk_pad_byte_keeps_chunks_aligned() {
+    // fmt, a 5-byte LIST chunk plus its pad byte, then data
+    let samples: Vec<i16> = (0..64).map(|s| s * 300 - 9000).collect();
+    let mut body = b"WAVEfmt \x10\x00\x00\x00".to_vec();
+    body.extend_from_slice(&[1, 0, 1, 0]);
+    body.extend_from_slice(&22050u32.to_le_bytes());
+    body.extend_from_slice(&(22050u32 * 2).to_le_bytes());
+    body.extend_from_slice(&[2, 0, 16, 0]);
+    body.extend_from_slice(b"LIST\x05\x00\x00\x00INFO!\x00");
+    body.extend_from_slice(b"data\x80\x00\x00\x00");
+    body.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+
+    let chunks = walk_riff_chunks(&mut Cursor::new(&wav)).unwrap();
+    let ids: Vec<&[u8; 4]> = chunks.iter().map(|chunk| &chunk.id).collect();
+    assert_eq!(ids, [b"fmt ", b"LIST", b"data"]);
+    assert_eq!((chunks[2].offset, chunks[2].size), (58, 128));
+    let info = probe_wav(&mut Cursor::new(&wav)).unwrap();
+    assert_eq!(info.samples_per_channel, 64);
+    assert!(info.mismatches.is_empty());
+
+    let path = std::env::temp_dir().join("shine_rs_cli_odd_chunk.wav");
+    fs::write(&path, &wav).unwrap();
+    let read = read_wav_file(path.to_str().unwrap());
+    let _ = fs::remove_file(&path);
+    assert_eq!(read.unwrap(), (samples, 22050, 1));
+}
+
+/// RF64 file whose ds64 declares a 6 GiB data chunk holding 16 stereo samples
+fn rf64_wav() -> Vec<u8> {
+    let mut wav = b"RF64\xff\xff\xff\xffWAVEds64\x1c\x00\x00\x00".to_vec();
+    wav.extend_from_slice(&0u64.to_le_bytes());
+    wav.extend_from_slice(&(6u64 << 30).to_le_bytes());
+    wav.extend_from_slice(&0u64.to_le_bytes());
+    wav.extend_from_slice(&0u32.to_le_bytes());
+    wav.extend_from_slice(b"fmt \x10\x00\x00\x00");
+    wav.extend_from_slice(&[1, 0, 2, 0]);
+    wav.extend_from_slice(&44100u32.to_le_bytes());
+    wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+    wav.extend_from_slice(&[4, 0, 16, 0]);
+    wav.extend_from_slice(b"data\xff\xff\xff\xff");
+    wav.extend((0..32i16).flat_map(|s| (s * 100).to_le_bytes()));
+    wav
+}
+
+#[test]
+fn test_info_rf64_uses_ds64_data_size() {
+    // Only a few samples of the declared data are present
+    let info = probe_wav(&mut Cursor::new(rf64_wav())).unwrap();
+    assert_eq!(info.format, "PCM");
+    assert_eq!(info.samples_per_channel, 16);
+    assert_eq!(
+        info.mismatches,
+        [LengthMismatch::DataTruncated {
+            declared: (6 << 30) / 4,
+            available: 16
+        }]
+    );
+}
+
+#[test]
+fn test_rf64_read_stops_at_eof() {
+    let path = std::env::temp_dir().join("shine_rs_cli_rf64.wav");
+    fs::write(&path, rf64_wav()).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (samples, sample_rate, channels) = read_wav_file(path).unwrap();
+    assert_eq!((sample_rate, channels), (44100, 2));
+    assert_eq!(samples, (0..32i16).map(|s| s * 100).collect::<Vec<_>>());
+
+    // A range inside the declared size but past the samples is empty
+    let result = read_wav_file_range(path, Some(Duration::from_secs(60)), None);
+    let _ = fs::remove_file(path);
+    assert!(matches!(result, Err(UtilError::ValidationError(_))));
+}
@@ -0,0 +1,87 @@
+//! Memory-Mapped Input Tests
+//!
+//! Checks that encoding from a memory-mapped WAV file gives exactly the same
+//! MP3 stream as the in-memory read path. Only built with the `mmap` feature.
+
+#![cfg(feature = "mmap")]
+
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::encode_input;
+use shine_rs_cli::input::PcmInput;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Write a 16-bit stereo WAV with a LIST chunk between `fmt ` and `data`
+fn write_test_wav(path: &Path, frames: usize) {
+    let mut data = Vec::with_capacity(frames * 4);
+    for i in 0..frames {
+        let t = i as f64 / 44100.0;
+        let left = ((t * 440.0 * std::f64::consts::TAU).sin() * 12000.0) as i16;
+        let right = ((t * 660.0 * std::f64::consts::TAU).sin() * 9000.0) as i16;
+        data.extend_from_slice(&left.to_le_bytes());
+        data.extend_from_slice(&right.to_le_bytes());
+    }
+
+    let list = b"INFOISFT\x06\x00\x00\x00shine\x00";
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&((4 + 24 + 8 + list.len() + 8 + data.len()) as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&44100u32.to_le_bytes());
+    wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"LIST");
+    wav.extend_from_slice(&(list.len() as u32).to_le_bytes());
+    wav.extend_from_slice(list);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    fs::write(path, wav).unwrap();
+}
+
+fn encode(input: &PcmInput) -> Vec<u8> {
+    let args: Vec<String> = ["shineenc", "-q", "in.wav", "-"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut output = Vec::new();
+    encode_input(&Args::parse_from(&args).unwrap(), input, &mut output).unwrap();
+    output
+}
+
+#[test]
+fn test_mapped_input_matches_memory_path() {
+    let path = std::env::temp_dir().join("shine_rs_cli_mmap_test.wav");
+    write_test_wav(&path, 44100 * 2 + 321);
+    let path_str = path.to_str().unwrap();
+
+    let ranges = [
+        (None, None),
+        (
+            Some(Duration::from_millis(500)),
+            Some(Duration::from_secs(1)),
+        ),
+    ];
+    for (start, duration) in ranges {
+        let mapped = PcmInput::map(path_str, start, duration).unwrap();
+        let memory = PcmInput::read(path_str, start, duration).unwrap();
+        assert!(mapped.is_mapped());
+        assert!(!memory.is_mapped());
+        assert_eq!(mapped.len(), memory.len());
+        assert!(mapped.samples().eq(memory.samples()));
+
+        let mapped_mp3 = encode(&mapped);
+        assert!(!mapped_mp3.is_empty());
+        assert_eq!(mapped_mp3, encode(&memory));
+    }
+
+    // Starting past the end is rejected like the read path does
+    assert!(PcmInput::map(path_str, Some(Duration::from_secs(10)), None).is_err());
+
+    let _ = fs::remove_file(&path);
+}
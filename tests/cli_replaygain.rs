@@ -0,0 +1,67 @@
+//! CLI ReplayGain Tests
+//!
+//! Checks that --replaygain puts an ID3v2 tag with the track gain and peak
+//! in front of the unchanged MP3 stream.
+
+use shine_rs::id3::REPLAYGAIN_TAG_LEN;
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use std::fs;
+use std::path::Path;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-3s.wav";
+
+fn encode(extra: &[&str], output: &Path) -> Vec<u8> {
+    let args: Vec<String> = ["shineenc", "-q", "-f"]
+        .iter()
+        .chain(extra)
+        .copied()
+        .chain([WAV_FILE, output.to_str().unwrap()])
+        .map(String::from)
+        .collect();
+    let mut stdout = Vec::new();
+    convert_wav_to_mp3(&Args::parse_from(&args).unwrap(), &mut stdout).expect("conversion failed");
+    let data = fs::read(output).unwrap();
+    let _ = fs::remove_file(output);
+    data
+}
+
+/// Value of the TXXX frame with `description` in an ID3v2.3 tag
+fn txxx_value(tag: &[u8], description: &str) -> Option<String> {
+    let mut pos = 10;
+    while pos + 10 <= tag.len() && &tag[pos..pos + 4] == b"TXXX" {
+        let size = u32::from_be_bytes(tag[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body = &tag[pos + 11..pos + 10 + size];
+        let split = body.iter().position(|&b| b == 0)?;
+        if &body[..split] == description.as_bytes() {
+            return Some(String::from_utf8_lossy(&body[split + 1..]).into_owned());
+        }
+        pos += 10 + size;
+    }
+    None
+}
+
+#[test]
+fn test_replaygain_tag_precedes_stream() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let dir = std::env::temp_dir();
+    let plain = encode(&[], &dir.join("shine_rs_cli_rg_plain.mp3"));
+    let tagged = encode(&["--replaygain"], &dir.join("shine_rs_cli_rg_tagged.mp3"));
+
+    assert_eq!(&tagged[..3], b"ID3");
+    assert_eq!(&tagged[REPLAYGAIN_TAG_LEN..], &plain[..]);
+
+    let tag = &tagged[..REPLAYGAIN_TAG_LEN];
+    let gain = txxx_value(tag, "REPLAYGAIN_TRACK_GAIN").expect("gain frame");
+    assert!(gain.ends_with(" dB"), "gain {:?}", gain);
+    gain.trim_end_matches(" dB").parse::<f64>().unwrap();
+    let peak: f64 = txxx_value(tag, "REPLAYGAIN_TRACK_PEAK")
+        .expect("peak frame")
+        .parse()
+        .unwrap();
+    assert!(peak > 0.0 && peak <= 1.0);
+}
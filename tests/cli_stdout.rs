@@ -0,0 +1,61 @@
+//! CLI Standard Output Tests
+//!
+//! Checks that encoding to "-" writes nothing but MP3 frames to stdout, even
+//! in verbose mode.
+
+use shine_rs::frame::iter_frames;
+use shine_rs::FrameError;
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use std::fs;
+use std::path::Path;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-3s.wav";
+
+fn parse_args(args: &[&str]) -> Args {
+    let args: Vec<String> = std::iter::once("shineenc")
+        .chain(args.iter().copied())
+        .map(String::from)
+        .collect();
+    Args::parse_from(&args).expect("arguments should parse")
+}
+
+#[test]
+fn test_stdout_output_is_pure_mp3() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let mut stdout = Vec::new();
+    convert_wav_to_mp3(&parse_args(&["-v", WAV_FILE, "-"]), &mut stdout)
+        .expect("conversion to stdout failed");
+
+    // Frames start at byte 0 and run back to back; only the final frame may
+    // be cut short by the flush
+    let mut frames = iter_frames(&stdout);
+    let first = frames
+        .next()
+        .expect("stdout should start with an MP3 frame");
+    assert_eq!(first.offset, 0);
+    assert!(frames.by_ref().count() > 100);
+    assert!(matches!(
+        frames.error(),
+        None | Some(FrameError::Truncated { .. })
+    ));
+
+    // Same bytes as encoding to a file
+    let output = std::env::temp_dir().join("shine_rs_cli_stdout_test.mp3");
+    let output_str = output.to_str().unwrap();
+    let mut unused = Vec::new();
+    convert_wav_to_mp3(
+        &parse_args(&["-q", "-f", WAV_FILE, output_str]),
+        &mut unused,
+    )
+    .expect("conversion to file failed");
+    let file_data = fs::read(&output).unwrap();
+    let _ = fs::remove_file(&output);
+
+    assert!(unused.is_empty());
+    assert_eq!(stdout, file_data);
+}
@@ -0,0 +1,80 @@
+//! CLI Thread Tests
+//!
+//! Checks that --threads encodes segments in parallel into a stream with the
+//! same frames and duration as the serial encoder.
+
+use shine_rs::frame::iter_frames;
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use shine_rs_cli::info::probe_mp3;
+use std::fs;
+use std::path::Path;
+
+const WAV_FILE: &str = "tests/audio/inputs/basic/sample-15s.wav";
+
+fn encode(extra: &[&str], output: &Path) -> Vec<u8> {
+    let args: Vec<String> = ["shineenc", "-q", "-f", "-b", "192"]
+        .iter()
+        .chain(extra)
+        .copied()
+        .chain([WAV_FILE, output.to_str().unwrap()])
+        .map(String::from)
+        .collect();
+    let mut stdout = Vec::new();
+    convert_wav_to_mp3(&Args::parse_from(&args).unwrap(), &mut stdout).expect("conversion failed");
+    let data = fs::read(output).unwrap();
+    let _ = fs::remove_file(output);
+    data
+}
+
+#[test]
+fn test_threads_keep_frames_and_duration() {
+    if !Path::new(WAV_FILE).exists() {
+        println!("Skipping test - input file not found: {}", WAV_FILE);
+        return;
+    }
+
+    let dir = std::env::temp_dir();
+    let serial = encode(&["--threads", "1"], &dir.join("shine_rs_cli_threads_1.mp3"));
+    let parallel = encode(&["--threads=4"], &dir.join("shine_rs_cli_threads_4.mp3"));
+    assert_eq!(
+        serial,
+        encode(&[], &dir.join("shine_rs_cli_threads_default.mp3"))
+    );
+    assert_ne!(serial, parallel);
+
+    let serial_info = probe_mp3(&serial).unwrap();
+    let parallel_info = probe_mp3(&parallel).unwrap();
+    assert_eq!(parallel_info.frames, serial_info.frames);
+    assert_eq!(parallel_info.duration, serial_info.duration);
+    assert_eq!(parallel_info.bitrate_mode, "CBR");
+
+    // Only the end of the stream may be cut short, as in the serial stream
+    let frame_bytes = parallel.len() / (parallel_info.frames + 1);
+    assert!(serial_info.trailing_bytes < frame_bytes);
+    assert!(parallel_info.trailing_bytes < frame_bytes);
+
+    // Every frame header matches the serial stream except for padding, whose
+    // rhythm restarts with each segment
+    let header_fields = |data: &[u8]| -> Vec<_> {
+        iter_frames(data)
+            .map(|frame| {
+                let mut header = frame.header;
+                header.padding = false;
+                header
+            })
+            .collect()
+    };
+    assert_eq!(header_fields(&parallel), header_fields(&serial));
+}
+
+#[test]
+fn test_threads_option_values() {
+    let parse = |value: &str| {
+        Args::parse_from(&["shineenc", "--threads", value, "in.wav"].map(String::from))
+    };
+    assert_eq!(parse("0").unwrap().threads, 0);
+    assert_eq!(parse("8").unwrap().threads, 8);
+    assert!(parse("-1").is_err());
+    assert!(parse("two").is_err());
+}
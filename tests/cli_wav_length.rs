@@ -0,0 +1,133 @@
+//! CLI WAV Length Tests
+//!
+//! Checks that WAV headers whose data size or `fact` count disagree with the
+//! file are reported by `--info`, encoded up to the end of the file with a
+//! warning, and rejected with `--strict-input`.
+
+use shine_rs_cli::args::Args;
+use shine_rs_cli::cli::convert_wav_to_mp3;
+use shine_rs_cli::info::{probe_wav, LengthMismatch, StreamInfo};
+use std::fs;
+use std::io::Cursor;
+
+/// Stereo 44.1 kHz WAV file of `frames` frames of a tone, with an optional
+/// `fact` chunk; the data chunk declares `declared_frames`
+fn wav(frames: u32, declared_frames: u32, fact: Option<u32>) -> Vec<u8> {
+    let mut body = b"WAVEfmt \x10\x00\x00\x00".to_vec();
+    body.extend_from_slice(&[1, 0, 2, 0]);
+    body.extend_from_slice(&44100u32.to_le_bytes());
+    body.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+    body.extend_from_slice(&[4, 0, 16, 0]);
+    if let Some(fact) = fact {
+        body.extend_from_slice(b"fact\x04\x00\x00\x00");
+        body.extend_from_slice(&fact.to_le_bytes());
+    }
+    body.extend_from_slice(b"data");
+    body.extend_from_slice(&(declared_frames * 4).to_le_bytes());
+    body.extend(
+        (0..frames * 2)
+            .flat_map(|i| { (((i / 2) as f64 * 0.05).sin() * 8000.0) as i16 }.to_le_bytes()),
+    );
+
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+    wav
+}
+
+/// Encode `wav` with the extra options, returning the MP3 or the error
+fn encode(name: &str, wav: &[u8], options: &[&str]) -> Result<Vec<u8>, String> {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("shine_rs_cli_{}.wav", name));
+    let output = dir.join(format!("shine_rs_cli_{}.mp3", name));
+    fs::write(&input, wav).unwrap();
+
+    let args: Vec<String> = ["shineenc", "-q", "-f"]
+        .iter()
+        .chain(options)
+        .copied()
+        .chain([input.to_str().unwrap(), output.to_str().unwrap()])
+        .map(String::from)
+        .collect();
+    let result = convert_wav_to_mp3(&Args::parse_from(&args).unwrap(), &mut Vec::new())
+        .map_err(|e| e.to_string())
+        .map(|()| fs::read(&output).unwrap());
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+    result
+}
+
+#[test]
+fn test_consistent_header_has_no_mismatches() {
+    let info = probe_wav(&mut Cursor::new(wav(4608, 4608, Some(4608)))).unwrap();
+    assert_eq!(info.samples_per_channel, 4608);
+    assert_eq!(info.fact_samples, Some(4608));
+    assert!(info.mismatches.is_empty());
+    assert!(StreamInfo::Wav(info)
+        .to_json("x.wav")
+        .get("warnings")
+        .is_none());
+}
+
+#[test]
+fn test_truncated_data_is_encoded_up_to_the_end() {
+    // A 4608-frame file cut after 2304 frames, as by an interrupted upload
+    let full = wav(4608, 4608, Some(4608));
+    let truncated = &full[..full.len() - 2304 * 4];
+    let info = probe_wav(&mut Cursor::new(truncated)).unwrap();
+    assert_eq!(info.samples_per_channel, 2304);
+    assert_eq!(
+        info.mismatches,
+        [
+            LengthMismatch::DataTruncated {
+                declared: 4608,
+                available: 2304
+            },
+            LengthMismatch::FactCount {
+                fact: 4608,
+                data: 2304
+            },
+        ]
+    );
+    let text = StreamInfo::Wav(info).to_text("x.wav");
+    assert!(text.contains("warnings: data chunk declares 4608 samples per channel"));
+
+    // The same audio as the intact first half
+    let mp3 = encode("truncated", truncated, &[]).expect("truncated input rejected");
+    let expected = encode("truncated_ref", &wav(2304, 2304, None), &[]).unwrap();
+    assert_eq!(mp3, expected);
+
+    let err = encode("truncated_strict", truncated, &["--strict-input"]).unwrap_err();
+    assert!(err.contains("data chunk declares 4608"), "{}", err);
+}
+
+#[test]
+fn test_oversized_declared_size_and_wrong_fact() {
+    // A streaming writer's placeholder size, never fixed
+    let oversized = wav(2304, 0x0FFF_FFFF, None);
+    let info = probe_wav(&mut Cursor::new(&oversized)).unwrap();
+    assert_eq!(info.samples_per_channel, 2304);
+    assert_eq!(
+        info.mismatches,
+        [LengthMismatch::DataTruncated {
+            declared: 0x0FFF_FFFF,
+            available: 2304
+        }]
+    );
+    let expected = encode("oversized_ref", &wav(2304, 2304, None), &[]).unwrap();
+    assert_eq!(encode("oversized", &oversized, &[]).unwrap(), expected);
+    assert!(encode("oversized_strict", &oversized, &["--strict-input"]).is_err());
+
+    // Only the fact count is wrong: the data chunk is trusted
+    let wrong_fact = wav(2304, 2304, Some(1000));
+    let info = probe_wav(&mut Cursor::new(&wrong_fact)).unwrap();
+    assert_eq!(
+        info.mismatches,
+        [LengthMismatch::FactCount {
+            fact: 1000,
+            data: 2304
+        }]
+    );
+    assert_eq!(encode("wrong_fact", &wrong_fact, &[]).unwrap(), expected);
+    assert!(encode("wrong_fact_strict", &wrong_fact, &["--strict-input"]).is_err());
+}
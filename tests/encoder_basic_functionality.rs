@@ -3,9 +3,12 @@
 //! This test suite validates the core functionality of the Rust MP3 encoder
 //! using only the CLI interface, without complex API assumptions.
 
+use shine_rs::frame::iter_frames;
+use shine_rs_cli::util::parse_time;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 
 /// Run Rust encoder with specified parameters
 fn run_rust_encoder(input_file: &str, output_file: &str, args: &[&str]) -> Result<(), String> {
@@ -13,10 +16,12 @@ fn run_rust_encoder(input_file: &str, output_file: &str, args: &[&str]) -> Resul
         return Err(format!("Input file not found: {}", input_file));
     }
 
-    let mut cmd_args = vec!["run", "--"];
+    // Options come before the file names, as in shine; test outputs are
+    // rewritten on every run
+    let mut cmd_args = vec!["run", "--", "-f"];
+    cmd_args.extend_from_slice(args);
     cmd_args.push(input_file);
     cmd_args.push(output_file);
-    cmd_args.extend_from_slice(args);
 
     let result = Command::new("cargo")
         .args(&cmd_args)
@@ -156,3 +161,54 @@ fn test_error_handling() {
     // Clean up
     let _ = fs::remove_file(output_file);
 }
+
+#[test]
+fn test_time_range_encoding() {
+    let input_file = "tests/audio/inputs/basic/sample-3s.wav";
+    let output_file = "test_time_range.mp3";
+
+    if !Path::new(input_file).exists() {
+        println!("Skipping test - input file not found: {}", input_file);
+        return;
+    }
+
+    run_rust_encoder(
+        input_file,
+        output_file,
+        &["-q", "--start", "00:00:00.500", "--duration", "2.5"],
+    )
+    .expect("Range encoding failed");
+    let data = fs::read(output_file).expect("Failed to read output file");
+    let _ = fs::remove_file(output_file);
+
+    // 2.5 s at 44.1 kHz is 110250 samples, 96 frames of 1152 with the last one padded.
+    // shine's flush may cut the last frame short, so count a truncated tail as well.
+    let mut frames = iter_frames(&data);
+    let complete = frames.by_ref().count();
+    let truncated = frames.error().is_some() as usize;
+    assert_eq!(complete + truncated, 96);
+
+    // A start past the end of the file is an error
+    assert!(run_rust_encoder(input_file, output_file, &["-q", "--start", "60"]).is_err());
+    let _ = fs::remove_file(output_file);
+}
+
+#[test]
+fn test_parse_time() {
+    assert_eq!(parse_time("45").unwrap(), Duration::from_secs(45));
+    assert_eq!(parse_time("2.5").unwrap(), Duration::from_millis(2500));
+    assert_eq!(parse_time("01:30").unwrap(), Duration::from_secs(90));
+    assert_eq!(
+        parse_time("00:01:30.250").unwrap(),
+        Duration::from_millis(90250)
+    );
+    assert_eq!(parse_time("1:00:00").unwrap(), Duration::from_secs(3600));
+
+    for invalid in ["", "abc", "-1", "1:2:3:4", "00:75:00", "01:60", "inf"] {
+        assert!(
+            parse_time(invalid).is_err(),
+            "{:?} should be rejected",
+            invalid
+        );
+    }
+}
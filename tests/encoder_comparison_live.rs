@@ -35,7 +35,7 @@ fn run_rust_encoder(
     }
 
     let mut cmd = Command::new("cargo");
-    let mut args = vec!["run", "--"];
+    let mut args = vec!["run", "--", "-f"];
 
     let bitrate_str;
     if let Some(br) = bitrate {
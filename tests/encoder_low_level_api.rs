@@ -4,8 +4,8 @@
 //! that directly mirror the C implementation.
 
 use shine_rs::{
-    shine_close, shine_encode_buffer_interleaved, shine_flush, shine_initialise,
-    shine_set_config_mpeg_defaults, ShineConfig,
+    shine_close, shine_encode_buffer_interleaved, shine_encode_buffer_interleaved_safe,
+    shine_flush, shine_initialise, shine_set_config_mpeg_defaults, EncodingError, ShineConfig,
 };
 
 #[test]
@@ -174,6 +174,60 @@ fn test_multiple_frames() {
     shine_close(encoder);
 }
 
+#[test]
+fn test_safe_interleaved_matches_unsafe() {
+    let mut config = ShineConfig::default();
+    shine_set_config_mpeg_defaults(&mut config.mpeg);
+
+    let mut safe_encoder = shine_initialise(&config).expect("Failed to initialize encoder");
+    let mut unsafe_encoder = shine_initialise(&config).expect("Failed to initialize encoder");
+    let mut safe_output = Vec::new();
+    let mut unsafe_output = Vec::new();
+
+    for frame_num in 0..8 {
+        let data: Vec<i16> = (0..1152 * 2)
+            .map(|i| ((i * 31 + frame_num * 977) % 20000 - 10000) as i16)
+            .collect();
+
+        let (frame, written) = shine_encode_buffer_interleaved_safe(&mut safe_encoder, &data)
+            .expect("safe encoding failed");
+        safe_output.extend_from_slice(&frame[..written]);
+
+        let (frame, written) =
+            unsafe { shine_encode_buffer_interleaved(&mut unsafe_encoder, data.as_ptr()) }
+                .expect("unsafe encoding failed");
+        unsafe_output.extend_from_slice(&frame[..written]);
+    }
+    safe_output.extend_from_slice(shine_flush(&mut safe_encoder).0);
+    unsafe_output.extend_from_slice(shine_flush(&mut unsafe_encoder).0);
+
+    assert!(!safe_output.is_empty());
+    assert_eq!(safe_output, unsafe_output);
+
+    shine_close(safe_encoder);
+    shine_close(unsafe_encoder);
+}
+
+#[test]
+fn test_safe_interleaved_rejects_short_input() {
+    let mut config = ShineConfig::default();
+    shine_set_config_mpeg_defaults(&mut config.mpeg);
+    let mut encoder = shine_initialise(&config).expect("Failed to initialize encoder");
+
+    let short = vec![0i16; 1152 * 2 - 1];
+    match shine_encode_buffer_interleaved_safe(&mut encoder, &short) {
+        Err(EncodingError::InvalidInputLength { expected, actual }) => {
+            assert_eq!((expected, actual), (1152 * 2, 1152 * 2 - 1));
+        }
+        other => panic!(
+            "Expected InvalidInputLength, got {:?}",
+            other.map(|(_, n)| n)
+        ),
+    }
+
+    shine_close(encoder);
+}
+
 #[cfg(test)]
 mod property_tests {
     use super::*;
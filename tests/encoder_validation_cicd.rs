@@ -9,6 +9,7 @@
 //! - Standard test files
 
 use sha2::{Digest, Sha256};
+use shine_rs::frame::iter_frames;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -76,12 +77,23 @@ fn calculate_sha256(file_path: &str) -> String {
 
 /// Run Rust encoder
 fn run_rust_encoder(input_file: &str, output_file: &str) -> Result<(), String> {
+    run_rust_encoder_with(&[], input_file, output_file)
+}
+
+/// Run Rust encoder with extra options
+fn run_rust_encoder_with(
+    options: &[&str],
+    input_file: &str,
+    output_file: &str,
+) -> Result<(), String> {
     if !Path::new(input_file).exists() {
         return Err(format!("Input file not found: {}", input_file));
     }
 
     let result = Command::new("cargo")
-        .args(["run", "--", input_file, output_file])
+        .args(["run", "--", "-f"])
+        .args(options)
+        .args([input_file, output_file])
         .output()
         .map_err(|e| format!("Failed to run Rust encoder: {}", e))?;
 
@@ -94,6 +106,7 @@ fn run_rust_encoder(input_file: &str, output_file: &str) -> Result<(), String> {
 
     Ok(())
 }
+
 /// Validate a single configuration
 fn validate_configuration(config_name: &str, config: &ReferenceConfig) -> Result<(), String> {
     let output_file = format!("test_cicd_{}.mp3", config_name);
@@ -186,6 +199,33 @@ fn test_standard_configurations() {
     }
 }
 
+#[test]
+fn test_joint_stereo_matches_reference() {
+    // Like shine, -j only changes the header mode: without --ms-threshold
+    // every frame is coded left/right with mode_ext 0
+    let configs = load_reference_manifest();
+    let config = &configs["sample-3s"];
+    let output_file = "test_cicd_joint_stereo.mp3";
+
+    run_rust_encoder_with(&["-j"], &config.input_file, output_file).unwrap();
+    let joint = fs::read(output_file).expect("Failed to read output");
+    let _ = fs::remove_file(output_file);
+    let reference = fs::read(&config.reference_file).expect("Failed to read reference");
+
+    let mut frames = 0;
+    for (joint, stereo) in iter_frames(&joint).zip(iter_frames(&reference)) {
+        assert_eq!(joint.header.mode, 1, "frame {}", joint.index);
+        assert_eq!(joint.header.mode_ext, 0, "frame {}", joint.index);
+        let mut header = joint.header;
+        header.mode = stereo.header.mode;
+        assert_eq!(header, stereo.header, "frame {}", joint.index);
+        assert_eq!(joint.data[4..], stereo.data[4..], "frame {}", joint.index);
+        frames += 1;
+    }
+    assert_eq!(frames, iter_frames(&reference).count());
+    assert_eq!(joint.len(), reference.len());
+}
+
 #[test]
 fn test_reference_file_integrity() {
     let configs = load_reference_manifest();